@@ -0,0 +1,104 @@
+//! Tail latency of the analysis of a project with one file much bigger than the others.
+//!
+//! The run with two threads is compared with its lower bound: the analysis of the
+//! biggest file alone, or half of the run with one thread when it is longer.
+//! The big file is named so that it comes last in folder order, the worst case
+//! of splitting the files in order.
+//!
+//! Run it with `cargo bench --bench tail_latency`.
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use weighted_code_coverage::files::get_metrics_concurrent;
+use weighted_code_coverage::utility::{AnalysisOptions, Complexity, Sort};
+
+const SMALL_FILES: usize = 40;
+const SMALL_FUNCTIONS: usize = 60;
+const BIG_FUNCTIONS: usize = 2400;
+const RUNS: usize = 5;
+
+// Source of a file with the given number of functions, each one with a few branches
+fn get_source(functions: usize) -> String {
+    (0..functions)
+        .map(|i| {
+            format!(
+                "pub fn f{i}(x: u32) -> u32 {{\n    if x > {i} {{\n        x * 2\n    }} else if x > 1 {{\n        x + 1\n    }} else {{\n        x\n    }}\n}}\n"
+            )
+        })
+        .collect()
+}
+
+// Write the source files of the project and a coveralls file with all their lines covered
+fn write_project(project: &Path, files: &[(String, usize)]) -> std::io::Result<()> {
+    fs::create_dir_all(project.join("src"))?;
+    let source_files = files
+        .iter()
+        .map(|(name, functions)| -> std::io::Result<serde_json::Value> {
+            let source = get_source(*functions);
+            fs::write(project.join("src").join(name), &source)?;
+            Ok(json!({
+                "name": format!("src/{name}"),
+                "coverage": vec![1; source.lines().count()],
+            }))
+        })
+        .collect::<std::io::Result<Vec<serde_json::Value>>>()?;
+    let coveralls = json!({ "source_files": source_files });
+    fs::write(project.join("coveralls.json"), coveralls.to_string())
+}
+
+// Minimum wall time of the analysis of the project with the given number of threads
+fn time_analysis(project: &Path, n_threads: usize) -> Duration {
+    let files_path = format!("{}/", project.display());
+    let json = project.join("coveralls.json");
+    (0..=RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            get_metrics_concurrent(
+                &files_path,
+                &json,
+                Complexity::Cyclomatic,
+                n_threads,
+                &[30., 1.5, 35., 30.],
+                Sort::WccPlain,
+                &AnalysisOptions::default(),
+            )
+            .expect("the analysis of the benchmark project failed");
+            start.elapsed()
+        })
+        // The first run warms up the caches
+        .skip(1)
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() -> std::io::Result<()> {
+    let root = std::env::temp_dir().join(format!("wcc-tail-latency-{}", std::process::id()));
+    let big_file = ("zz_big.rs".to_string(), BIG_FUNCTIONS);
+    let mut files = (0..SMALL_FILES)
+        .map(|i| (format!("small_{i:02}.rs"), SMALL_FUNCTIONS))
+        .collect::<Vec<(String, usize)>>();
+    files.push(big_file.clone());
+    let project = root.join("project");
+    let big_project = root.join("big");
+    write_project(&project, &files)?;
+    write_project(&big_project, &[big_file])?;
+
+    let one_thread = time_analysis(&project, 1);
+    let big_alone = time_analysis(&big_project, 1);
+    let two_threads = time_analysis(&project, 2);
+    fs::remove_dir_all(&root)?;
+
+    let lower_bound = big_alone.max(one_thread / 2);
+    println!("1 thread:          {one_thread:?}");
+    println!("big file alone:    {big_alone:?}");
+    println!("2 threads:         {two_threads:?}");
+    println!("lower bound:       {lower_bound:?}");
+    println!(
+        "tail overhead:     {:.1}%",
+        (two_threads.as_secs_f64() / lower_bound.as_secs_f64() - 1.) * 100.
+    );
+    Ok(())
+}
@@ -0,0 +1,25 @@
+pub fn always(a: u32) -> u32 {
+    a + 1
+}
+
+#[cfg(feature = "fast")]
+pub fn fast(a: u32) -> u32 {
+    a * 2
+}
+
+#[cfg(all(feature = "std", not(feature = "fast")))]
+// Only with the standard library
+pub fn slow(a: u32) -> u32 {
+    a * 3
+}
+
+#[cfg(unix)]
+pub fn platform() -> u32 {
+    1
+}
+
+pub fn body(a: u32) -> u32 {
+    #[cfg(feature = "fast")]
+    let a = a * 2;
+    a
+}
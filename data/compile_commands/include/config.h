@@ -0,0 +1,10 @@
+#ifndef CONFIG_H
+#define CONFIG_H
+
+#if defined(__GNUC__) || FORCE_VISIBILITY
+#define API_EXPORT __attribute__((visibility("default")))
+#else
+#define API_EXPORT
+#endif
+
+#endif
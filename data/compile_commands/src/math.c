@@ -0,0 +1,16 @@
+#include "config.h"
+
+API_EXPORT int add(int a, int b) {
+    return a + b;
+}
+
+int scale(int a, int factor) {
+    if (factor > 1) {
+        a = a * factor;
+    }
+    return a;
+}
+
+INLINE_HINT int sub(int a, int b) {
+    return a - b;
+}
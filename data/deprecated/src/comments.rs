@@ -0,0 +1,6 @@
+#[deprecated]
+// Kept for the old callers
+/* and for the old tests */
+pub fn old(value: u32) -> u32 {
+    value
+}
@@ -0,0 +1,37 @@
+pub struct Legacy;
+
+impl Legacy {
+    /// Old entry point
+    #[deprecated(since = "0.2.0", note = "use run")]
+    #[inline]
+    pub fn start(value: u32) -> u32 {
+        if value > 10 {
+            value * 2
+        } else if value > 5 {
+            value + 1
+        } else {
+            value
+        }
+    }
+
+    pub fn run(value: u32) -> u32 {
+        if value > 10 {
+            value * 2
+        } else if value > 5 {
+            value + 1
+        } else {
+            value
+        }
+    }
+}
+
+#[deprecated]
+pub mod old {
+    pub fn helper(value: u32) -> u32 {
+        if value > 1 {
+            value
+        } else {
+            0
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub const NAME: &str = "empty_coverage";
+
+pub const VERSION: u32 = 1;
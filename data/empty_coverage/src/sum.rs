@@ -0,0 +1,7 @@
+pub fn sum(a: u32, b: u32) -> u32 {
+    if a > b {
+        a + b
+    } else {
+        b + a
+    }
+}
@@ -0,0 +1,12 @@
+pub fn half(value: u32) -> u32 {
+    if value > 10 {
+        value / 2
+    } else {
+        value
+    }
+}
+
+pub fn broken(value: u32) -> u32 {
+    let result = value +;
+    result
+}
@@ -0,0 +1,7 @@
+pub fn double(value: u32) -> u32 {
+    if value > 10 {
+        value * 2
+    } else {
+        value
+    }
+}
@@ -1,14 +1,33 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Mutex;
+#[cfg(feature = "otlp")]
+use std::time::SystemTime;
+use std::time::{Duration, Instant};
 
 use clap::builder::{PossibleValuesParser, TypedValueParser};
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use tracing::{debug, warn};
 use tracing_subscriber::EnvFilter;
 
+use weighted_code_coverage::compile_commands::CompileCommands;
+use weighted_code_coverage::config::{read_config, Config, PartialConfig, CONFIG_FILE};
 use weighted_code_coverage::error::*;
+use weighted_code_coverage::expression::{ThresholdExpression, ThresholdExpressions};
 use weighted_code_coverage::files::*;
 use weighted_code_coverage::functions::*;
+use weighted_code_coverage::modes::{check_modes, Analysis};
+#[cfg(feature = "otlp")]
+use weighted_code_coverage::otlp::{emit_otlp_metrics, ModeRun};
 use weighted_code_coverage::output::*;
-use weighted_code_coverage::utility::{Complexity, JsonFormat, Mode, Sort};
+#[cfg(feature = "statsd")]
+use weighted_code_coverage::statsd::emit_statsd_metrics;
+use weighted_code_coverage::utility::{
+    get_ast_metrics, get_exclude_globs, read_files_from, AnalysisOptions, CategoryThreshold,
+    Complexity, CoverageInput, DeprecatedPolicy, EmptyCoverage, FileCategories, JsonFormat,
+    MergeConflict, Mode, OwnersMatch, Sort, MAX_METRIC_VALUE,
+};
 
 const fn thresholds_long_help() -> &'static str {
     "Set four  thresholds in this order: -t WCC_PLAIN, WCC_QUANTIZED, CRAP, SKUNK\n
@@ -17,7 +36,8 @@ const fn thresholds_long_help() -> &'static str {
     WCC PLAIN has a max threshold of COMP*SLOC/PLOC\n
     WCC QUANTIZED has a max threshold of 2*SLOC/PLOC\n
     CRAP has a max threshold of COMP^2 +COMP\n
-    SKUNK has a max threshold of COMP/25\n"
+    SKUNK has a max threshold of COMP/25*100\n
+    A warning is printed for each threshold that no file can exceed\n"
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -35,28 +55,219 @@ impl std::str::FromStr for Thresholds {
     }
 }
 
-fn run_functions(args: &Args) -> Result<()> {
-    let metric_to_use = args.complexity;
-    let thresholds = &args.thresholds.0;
-    let sort_by = args.sort;
-    let (metrics, files_ignored, complex_files, project_coverage) = match args.json_format {
-        JsonFormat::Covdir => get_functions_metrics_concurrent_covdir(
+// Get all the coverage files to analyze, given with --coverage or with -j and -f
+fn get_coverage_inputs(args: &Args) -> Result<Vec<CoverageInput>> {
+    if !args.coverage.is_empty() {
+        return Ok(args.coverage.clone());
+    }
+    let path_json = args.path_json.clone().ok_or(Error::PathConversionError())?;
+    if path_json.is_dir() {
+        return get_coverage_dir_inputs(path_json);
+    }
+    Ok(vec![CoverageInput::new(args.json_format, path_json)])
+}
+
+// Fail if files have been skipped because the max runtime has been reached
+fn check_deadline(options: &AnalysisOptions) -> Result<()> {
+    match options.deadline.as_ref().map_or(0, Deadline::skipped) {
+        0 => Ok(()),
+        files_skipped => Err(Error::MaxRuntimeError(files_skipped)),
+    }
+}
+
+// Get the options used while analyzing the project
+fn get_analysis_options(args: &Args) -> Result<AnalysisOptions> {
+    let options = AnalysisOptions::default()
+        .merge_conflict(args.merge_conflict)
+        .empty_coverage(args.empty_coverage)
+        .deprecated_policy(args.deprecated_policy)
+        .show_target_coverage(args.show_target_coverage)
+        .show_comment_ratio(args.show_comment_ratio)
+        .max_metric_value(args.max_metric_value)
+        .strict_analysis(args.strict_analysis)
+        .line_counts(
+            args.track.is_some()
+                || args.zero_coverage.is_some()
+                || args.config_file.coverage_floors.is_some(),
+        )
+        .order_by_fanin(args.order_by_fanin);
+    let options = match &args.path_map {
+        Some(path_map) => options.path_map(PathMap::from_file(path_map)?),
+        None => options,
+    };
+    let options = match &args.compile_commands {
+        Some(compile_commands) => {
+            options.compile_commands(CompileCommands::from_file(compile_commands)?)
+        }
+        None => options,
+    };
+    let options = match &args.files_from {
+        Some(files_from) => options.files_from(read_files_from(files_from)?),
+        None => options,
+    };
+    let options = match &args.config_file.line_exclusions {
+        Some(line_exclusions) => options.line_exclusions(line_exclusions.clone()),
+        None => options,
+    };
+    let options = match &args.active_features {
+        Some(features) => options.active_features(features.iter().cloned().collect()),
+        None => options,
+    };
+    let options = match args.max_uncovered_lines {
+        Some(max_uncovered_lines) => options.max_uncovered_lines(max_uncovered_lines),
+        None => options,
+    };
+    let options = match &args.config_file.uncovered_lines_budgets {
+        Some(budgets) => options.uncovered_lines_budgets(budgets.clone()),
+        None => options,
+    };
+    let options = match &args.hotness {
+        Some(hotness) => options.hotness(Hotness::from_file(hotness)?),
+        None => options,
+    };
+    let options = match &args.tests_per_file {
+        Some(tests_per_file) => options.tests_per_file(TestCounts::from_file(tests_per_file)?),
+        None => options,
+    };
+    let options = match args.tiers {
+        Some(tiers) => options.tiers(tiers),
+        None => options,
+    };
+    let options = if args.threshold_expression.is_empty() {
+        options
+    } else {
+        options.threshold_expressions(args.threshold_expression.iter().cloned().fold(
+            ThresholdExpressions::default(),
+            ThresholdExpressions::expression,
+        ))
+    };
+    let options = match &args.coverage_by_length {
+        Some(coverage_by_length) => options.coverage_by_length(coverage_by_length.clone()),
+        None => options,
+    };
+    let options = match args.file_timeout {
+        Some(ms) => options.file_timeout(Duration::from_millis(ms)),
+        None => options,
+    };
+    let options = match args.start {
+        Some(start) => options.start(start),
+        None => options,
+    };
+    let options = match args.max_runtime {
+        Some(seconds) => options.max_runtime(Duration::from_secs(seconds)),
+        None => options,
+    };
+    let options = match args.sample {
+        Some(size) => options.sample(Sample::new(size, args.seed)),
+        None => options,
+    };
+    let options = if args.exclude.is_empty() {
+        options
+    } else {
+        options.exclude(get_exclude_globs(&args.exclude)?)
+    };
+    let options = if args.no_coverage_weight.is_empty() {
+        options
+    } else {
+        options.no_coverage_weight(get_exclude_globs(&args.no_coverage_weight)?)
+    };
+    match &args.categories {
+        Some(categories) => {
+            let categories = args
+                .category_thresholds
+                .iter()
+                .fold(FileCategories::from_file(categories)?, |categories, t| {
+                    categories.multiplier(&t.category, t.multiplier)
+                });
+            Ok(options.categories(categories))
+        }
+        None => Ok(options),
+    }
+}
+
+// Metrics, files ignored, complex functions and project coverage of a run in functions mode
+type FunctionsOutput = (
+    Vec<RootMetrics>,
+    Vec<FileIgnored>,
+    Vec<FunctionMetrics>,
+    f64,
+);
+
+// Compute the metrics of the functions with the coverage and the options of the arguments
+fn get_functions_output(args: &Args, options: &AnalysisOptions) -> Result<FunctionsOutput> {
+    let inputs = get_coverage_inputs(args)?;
+    Ok(match inputs.as_slice() {
+        [input] if input.format == JsonFormat::Covdir => get_functions_metrics_concurrent_covdir(
             &args.path_file,
-            &args.path_json,
-            metric_to_use,
+            &input.path,
+            args.complexity,
             args.n_threads.max(1),
-            thresholds,
-            sort_by,
+            &args.thresholds.0,
+            args.sort,
+            options,
         )?,
-        JsonFormat::Coveralls => get_functions_metrics_concurrent(
+        [input] if input.format == JsonFormat::Coveralls => get_functions_metrics_concurrent(
             &args.path_file,
-            &args.path_json,
-            metric_to_use,
+            &input.path,
+            args.complexity,
             args.n_threads.max(1),
-            thresholds,
-            sort_by,
+            &args.thresholds.0,
+            args.sort,
+            options,
+        )?,
+        _ => get_functions_metrics_concurrent_merged(
+            &args.path_file,
+            &inputs,
+            args.complexity,
+            args.n_threads.max(1),
+            &args.thresholds.0,
+            args.sort,
+            options,
         )?,
+    })
+}
+
+fn run_functions(args: &Args) -> Result<()> {
+    check_modes(
+        &[
+            ("--zero-coverage", args.zero_coverage.is_some()),
+            ("--coverage-debt", args.coverage_debt),
+            ("--codeowners", args.codeowners.is_some()),
+            ("--sample", args.sample.is_some()),
+            ("--active-features", args.active_features.is_some()),
+            ("--no-coverage-weight", !args.no_coverage_weight.is_empty()),
+            ("--tiers", args.tiers.is_some()),
+            (
+                "--threshold-expression",
+                !args.threshold_expression.is_empty(),
+            ),
+            (
+                "The coverage_floors table of the config file",
+                args.config_file.coverage_floors.is_some(),
+            ),
+            (
+                "The exclusions table of the config file",
+                args.config_file.line_exclusions.is_some(),
+            ),
+        ],
+        Analysis::Functions,
+    )?;
+    let sort_by = args.sort;
+    let options = get_analysis_options(args)?;
+    #[cfg(feature = "otlp")]
+    let options = options.stage_times(StageTimes::default());
+    // The sources of the complex functions are recorded by the analysis only for the html output
+    let function_sources = FunctionSources::default();
+    let options = match args.path_html {
+        Some(_) => options.function_sources(function_sources.clone()),
+        None => options,
     };
+    #[cfg(feature = "otlp")]
+    let start = SystemTime::now();
+    let (metrics, files_ignored, complex_files, project_coverage) =
+        get_functions_output(args, &options)?;
+    #[cfg(feature = "otlp")]
+    let analyzed = SystemTime::now();
     if let Some(csv) = &args.path_csv {
         print_metrics_to_csv_function(&metrics, &files_ignored, csv, project_coverage, sort_by)?;
     }
@@ -70,42 +281,214 @@ fn run_functions(args: &Args) -> Result<()> {
             sort_by,
         )?;
     };
+    if let Some(gutter) = &args.gutter {
+        print_metrics_to_gutter(&metrics, gutter)?;
+    }
+    if let Some(sidecars) = &args.sidecars {
+        print_metrics_to_sidecars_function(&metrics, sidecars)?;
+    }
+    if let Some(ndjson) = &args.ndjson {
+        print_metrics_to_ndjson_function(&metrics, ndjson, &args.path_file, project_coverage)?;
+    }
+    if let Some(influx) = &args.influx {
+        print_metrics_to_influx_function(
+            &metrics,
+            influx,
+            args.complexity,
+            project_coverage,
+            complex_files.len(),
+            args.influx_per_file,
+        )?;
+    }
     if let Some(html) = &args.path_html {
+        let function_sources = get_function_sources(&metrics, &function_sources)?;
         print_metrics_to_html_function(
             &metrics,
             &files_ignored,
+            &function_sources,
             &html,
             &&args.path_file,
             project_coverage,
             sort_by,
         )?;
     };
-    get_metrics_output_function(&metrics, &files_ignored, &complex_files);
+    if args.summary_json {
+        print_summary_json_function(
+            &metrics,
+            &files_ignored,
+            &complex_files,
+            &args.path_file,
+            project_coverage,
+        )?;
+    } else {
+        get_metrics_output_function(
+            &metrics,
+            &files_ignored,
+            &complex_files,
+            args.worst_module_by,
+        );
+    }
+    let files_skipped = options.deadline.as_ref().map_or(0, Deadline::skipped);
+    if files_skipped > 0 && !args.summary_json {
+        print_incomplete(files_skipped);
+    }
+    #[cfg(feature = "statsd")]
+    if let Some(endpoint) = &args.statsd {
+        emit_statsd_metrics(endpoint, project_coverage, complex_files.len());
+    }
+    #[cfg(feature = "otlp")]
+    if let Ok(mut runs) = args.otlp_runs.lock() {
+        runs.push(ModeRun::new(
+            "functions",
+            project_coverage,
+            complex_files.len(),
+            options.stage_times.as_ref(),
+            start,
+            analyzed,
+        ));
+    }
+    match args.fail_under_coverage {
+        _ if files_skipped > 0 => Err(Error::MaxRuntimeError(files_skipped)),
+        Some(min) if project_coverage < min => Err(Error::CoverageError(project_coverage, min)),
+        _ if !complex_files.is_empty() => Err(Error::ThresholdsBreachError(complex_files.len())),
+        _ => Ok(()),
+    }
+}
+
+fn run_function_breakdown(args: &Args, function: &str) -> Result<()> {
+    let inputs = get_coverage_inputs(args)?;
+    let options = get_analysis_options(args)?;
+    let breakdown = get_function_breakdown(
+        &args.path_file,
+        &inputs,
+        function,
+        args.complexity,
+        &args.thresholds.0,
+        &options,
+    )?;
+    print_function_breakdown(&breakdown);
     Ok(())
 }
 
+// Print only the functions over the thresholds changed since the git reference
+fn run_new_breaches(args: &Args, git_ref: &str) -> Result<()> {
+    let options = get_analysis_options(args)?;
+    let (metrics, _, _, _) = get_functions_output(args, &options)?;
+    let breaches = get_new_breaches(&args.path_file, &metrics, git_ref)?;
+    print_new_breaches(&breaches);
+    match breaches.len() {
+        0 => Ok(()),
+        n => Err(Error::NewBreachesError(n)),
+    }
+}
+
+fn run_diff_coverage(args: &Args, git_ref: &str) -> Result<()> {
+    let inputs = get_coverage_inputs(args)?;
+    let options = get_analysis_options(args)?;
+    let (files, total, files_without_coverage, files_skipped) =
+        get_diff_coverage(&args.path_file, &inputs, git_ref, &options)?;
+    // With the summary json the standard output is only the summary
+    if !args.summary_json {
+        print_diff_coverage(&files, &total, &files_without_coverage, files_skipped);
+    }
+    check_deadline(&options)?;
+    match args.min_diff_coverage {
+        Some(min) if total.coverage < min => Err(Error::DiffCoverageError(total.coverage, min)),
+        _ => Ok(()),
+    }
+}
+
+// Compare the coverage of the covdir and the coveralls files of the same run
+fn run_cross_check(args: &Args, covdir: &Path, coveralls: &Path) -> Result<()> {
+    let options = get_analysis_options(args)?;
+    let check = get_cross_check(
+        &args.path_file,
+        covdir,
+        coveralls,
+        args.cross_check_tolerance,
+        &options,
+    )?;
+    if !args.summary_json {
+        print_cross_check(&check);
+    }
+    match check.is_consistent() {
+        true => Ok(()),
+        false => Err(Error::CrossCheckError(check.files.len())),
+    }
+}
+
+fn run_track(args: &Args, path: &Path) -> Result<()> {
+    let inputs = get_coverage_inputs(args)?;
+    let options = get_analysis_options(args)?;
+    // The state of the files mode of the run, otherwise the files are analyzed only for the track
+    let state = match args.files_track_state.lock()?.take() {
+        Some(state) => state,
+        None => {
+            let (metrics, _, _, _) = get_metrics_concurrent_merged(
+                &args.path_file,
+                &inputs,
+                args.complexity,
+                args.n_threads.max(1),
+                &args.thresholds.0,
+                Sort::Path,
+                &options,
+            )?;
+            get_track_state(&metrics)
+        }
+    };
+    // An incomplete state is not added to the history
+    check_deadline(&options)?;
+    let mut history = read_track_history(path)?;
+    let delta = history.last().map(|previous| state.delta(previous));
+    if !args.summary_json {
+        print_track_delta(&state, delta.as_ref());
+    }
+    history.push(state);
+    write_track_history(path, &history)
+}
+
 fn run_files(args: &Args) -> Result<()> {
+    check_modes(&[("--gutter", args.gutter.is_some())], Analysis::Files)?;
     let metric_to_use = args.complexity;
     let thresholds = &args.thresholds.0;
     let sort_by = args.sort;
-    let (metrics, files_ignored, complex_files, project_coverage) = match args.json_format {
-        JsonFormat::Covdir => get_metrics_concurrent_covdir(
+    let inputs = get_coverage_inputs(args)?;
+    let options = get_analysis_options(args)?;
+    #[cfg(feature = "otlp")]
+    let options = options.stage_times(StageTimes::default());
+    #[cfg(feature = "otlp")]
+    let start = SystemTime::now();
+    let (metrics, files_ignored, complex_files, project_coverage) = match inputs.as_slice() {
+        [input] if input.format == JsonFormat::Covdir => get_metrics_concurrent_covdir(
             &args.path_file,
-            &args.path_json,
+            &input.path,
             metric_to_use,
             args.n_threads.max(1),
             thresholds,
             sort_by,
+            &options,
         )?,
-        JsonFormat::Coveralls => get_metrics_concurrent(
+        [input] if input.format == JsonFormat::Coveralls => get_metrics_concurrent(
             &args.path_file,
-            &args.path_json,
+            &input.path,
             metric_to_use,
             args.n_threads.max(1),
             thresholds,
             sort_by,
+            &options,
+        )?,
+        _ => get_metrics_concurrent_merged(
+            &args.path_file,
+            &inputs,
+            metric_to_use,
+            args.n_threads.max(1),
+            thresholds,
+            sort_by,
+            &options,
         )?,
     };
+    #[cfg(feature = "otlp")]
+    let analyzed = SystemTime::now();
     if let Some(csv) = &args.path_csv {
         print_metrics_to_csv(&metrics, &files_ignored, csv, project_coverage, sort_by)?;
     }
@@ -119,6 +502,19 @@ fn run_files(args: &Args) -> Result<()> {
             sort_by,
         )?;
     };
+    if let Some(ndjson) = &args.ndjson {
+        print_metrics_to_ndjson(&metrics, ndjson, &args.path_file, project_coverage)?;
+    }
+    if let Some(influx) = &args.influx {
+        print_metrics_to_influx(
+            &metrics,
+            influx,
+            args.complexity,
+            project_coverage,
+            complex_files.len(),
+            args.influx_per_file,
+        )?;
+    }
     if let Some(html) = &args.path_html {
         print_metrics_to_html(
             &metrics,
@@ -129,8 +525,80 @@ fn run_files(args: &Args) -> Result<()> {
             sort_by,
         )?;
     };
-    get_metrics_output(&metrics, &files_ignored, &complex_files);
-    Ok(())
+    if let Some(zero_coverage) = &args.zero_coverage {
+        let files = get_zero_coverage_files(&metrics);
+        print_zero_coverage_to_json(&files, zero_coverage)?;
+    }
+    if let Some(sidecars) = &args.sidecars {
+        print_metrics_to_sidecars(&metrics, sidecars)?;
+    }
+    if args.summary_json {
+        print_summary_json(
+            &metrics,
+            &files_ignored,
+            &complex_files,
+            &args.path_file,
+            project_coverage,
+        )?;
+    } else {
+        get_metrics_output(&metrics, &files_ignored, &complex_files);
+    }
+    let files_skipped = options.deadline.as_ref().map_or(0, Deadline::skipped);
+    if files_skipped > 0 && !args.summary_json {
+        print_incomplete(files_skipped);
+    }
+    if args.track.is_some() {
+        let coverage_debt = args.coverage_debt.then(|| get_coverage_debt(&metrics));
+        *args.files_track_state.lock()? =
+            Some(get_track_state(&metrics).coverage_debt(coverage_debt));
+    }
+    if let Some(codeowners) = &args.codeowners {
+        let owners = get_owner_metrics(
+            &metrics,
+            &CodeOwners::from_file(codeowners)?,
+            args.owners_match,
+        );
+        if let Some(owners_json) = &args.owners_json {
+            print_owner_metrics_to_json(&owners, owners_json)?;
+        }
+        if !args.summary_json {
+            print_owner_metrics(&owners);
+        }
+    }
+    let directories_below = match &args.config_file.coverage_floors {
+        Some(floors) => {
+            let directories = get_directory_floors(&metrics, floors);
+            if !args.summary_json {
+                print_directory_floors(&directories);
+            }
+            directories.iter().filter(|d| d.is_below()).count()
+        }
+        None => 0,
+    };
+    #[cfg(feature = "statsd")]
+    if let Some(endpoint) = &args.statsd {
+        emit_statsd_metrics(endpoint, project_coverage, complex_files.len());
+    }
+    #[cfg(feature = "otlp")]
+    if let Ok(mut runs) = args.otlp_runs.lock() {
+        runs.push(ModeRun::new(
+            "files",
+            project_coverage,
+            complex_files.len(),
+            options.stage_times.as_ref(),
+            start,
+            analyzed,
+        ));
+    }
+    match args.fail_under_coverage {
+        _ if files_skipped > 0 => Err(Error::MaxRuntimeError(files_skipped)),
+        Some(min) if project_coverage < min => Err(Error::CoverageError(project_coverage, min)),
+        _ if args.strict_floors && directories_below > 0 => {
+            Err(Error::DirectoryCoverageError(directories_below))
+        }
+        _ if !complex_files.is_empty() => Err(Error::ThresholdsBreachError(complex_files.len())),
+        _ => Ok(()),
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -139,10 +607,127 @@ struct Args {
     /// Path to the project folder
     #[clap(short, value_hint = clap::ValueHint::DirPath)]
     path_file: PathBuf,
+    /// Path to the config file with the options of the analysis and the tables of the features,
+    /// by default the wcc.toml in the project folder
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    config: Option<PathBuf>,
+    // Config file read before the run
+    #[clap(skip)]
+    config_file: Config,
 
-    /// Path to the grcov json in coveralls/covdir format
-    #[clap(short = 'j', long = "path_json", value_hint = clap::ValueHint::DirPath)]
-    path_json: PathBuf,
+    /// Path to the grcov json in coveralls/covdir format or to the lcov file,
+    /// or to a folder of coverage jsons, e.g. one for each crate, whose formats are detected and merged
+    #[clap(short = 'j', long = "path_json", value_hint = clap::ValueHint::AnyPath, required_unless_present_any = ["coverage", "cross_check", "dump_ast_metrics"])]
+    path_json: Option<PathBuf>,
+    /// Coverage file given as FORMAT:PATH, it can be repeated to merge more coverage files
+    #[clap(long, value_name = "FORMAT:PATH", conflicts_with = "path_json")]
+    coverage: Vec<CoverageInput>,
+    /// Choose what to do when a file is present in more than one coverage file
+    #[clap(long, default_value = MergeConflict::default(), value_parser = PossibleValuesParser::new(MergeConflict::all())
+        .map(|s| s.parse::<MergeConflict>().unwrap()))]
+    merge_conflict: MergeConflict,
+    /// Path to the file with the rules rewriting the paths of the coverage files, one from=to per line,
+    /// the first matching rule replaces the start of a path
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    path_map: Option<PathBuf>,
+    /// Path to the compile_commands.json of the C/C++ files, their include directories and macros
+    /// are used to preprocess them before the analysis
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    compile_commands: Option<PathBuf>,
+    /// Choose what to do with the files without lines that can be covered
+    #[clap(long, default_value = EmptyCoverage::default(), value_parser = PossibleValuesParser::new(EmptyCoverage::all())
+        .map(|s| s.parse::<EmptyCoverage>().unwrap()))]
+    empty_coverage: EmptyCoverage,
+    /// Choose what to do with the functions marked with #[deprecated], only in functions mode
+    #[clap(long, default_value = DeprecatedPolicy::default(), value_parser = PossibleValuesParser::new(DeprecatedPolicy::all())
+        .map(|s| s.parse::<DeprecatedPolicy>().unwrap()))]
+    deprecated_policy: DeprecatedPolicy,
+    /// Show the coverage that each function above the CRAP threshold must reach to pass it
+    #[clap(long)]
+    show_target_coverage: bool,
+    /// Show the percentage of the lines of each file that are comments, only in files mode
+    #[clap(long)]
+    show_comment_ratio: bool,
+    /// Print the full breakdown of the metrics of a single function, given as path.rs::function_name
+    #[clap(long, value_name = "PATH::FUNCTION")]
+    function: Option<String>,
+    /// Compute the coverage of the lines added or modified since the given git reference
+    #[clap(long, value_name = "REF")]
+    diff_coverage: Option<String>,
+    /// Fail if the coverage of the changed lines is below this percentage
+    #[clap(long, requires = "diff_coverage")]
+    min_diff_coverage: Option<f64>,
+    /// Print only the functions over the thresholds changed since the given git reference,
+    /// failing if there is any
+    #[clap(long, value_name = "REF")]
+    new_breaches: Option<String>,
+    /// Json state file recording the line totals of each run, the delta from the previous run is printed
+    #[clap(long, value_name = "PATH")]
+    track: Option<PathBuf>,
+    /// Record in the track file the coverage debt of the files mode, the sum over the files of
+    /// complexity * (1 - coverage), for a burndown chart
+    #[clap(long, requires = "track")]
+    coverage_debt: bool,
+    /// Fail if the coverage of the whole project is below this percentage
+    #[clap(long, value_name = "PERCENTAGE")]
+    fail_under_coverage: Option<f64>,
+    /// Exit code of a run with files or functions over the thresholds or with unmet checks
+    #[clap(long, value_name = "N", default_value_t = EXIT_CODE_BREACH)]
+    exit_code_breach: u8,
+    /// Exit code of a run with a coverage below the minimum one
+    #[clap(long, value_name = "N", default_value_t = EXIT_CODE_COVERAGE)]
+    exit_code_coverage: u8,
+    /// Exit code of a run with incomplete results because it reached the max runtime
+    #[clap(long, value_name = "N", default_value_t = EXIT_CODE_INCOMPLETE)]
+    exit_code_incomplete: u8,
+    /// Exit code of a run that fails because of its inputs, e.g. a wrong option or file
+    #[clap(long, value_name = "N", default_value_t = EXIT_CODE_INPUT_ERROR)]
+    exit_code_input_error: u8,
+    /// Glob pattern of the files to exclude, relative to the project folder, it can be repeated
+    #[clap(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+    /// Glob pattern of the files whose lines are left out of the project coverage, relative to the project folder,
+    /// their metrics are still computed and they are marked as coverage exempt, it can be repeated, only in files mode
+    #[clap(long, value_name = "GLOB")]
+    no_coverage_weight: Vec<String>,
+    /// Path to a list of the files to analyze, one per line and relative to the project folder, e.g. the files of a CI shard
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    files_from: Option<PathBuf>,
+    /// Features enabled in the coverage run, the code behind the other cfg features is excluded
+    /// from the coverable lines, only in files mode
+    #[clap(long, value_name = "FEATURE,...", value_delimiter = ',')]
+    active_features: Option<Vec<String>>,
+    /// Maximum number of uncovered lines of a file, a file with more is complex whatever its coverage,
+    /// only in files mode
+    #[clap(long, value_name = "N")]
+    max_uncovered_lines: Option<usize>,
+    /// Path to the file with the call count of each function, given as path.rs::function_name COUNT,
+    /// to rank the hot functions higher, only in functions mode
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    hotness: Option<PathBuf>,
+    /// Path to the file with the number of tests exercising each file, given as path.rs COUNT,
+    /// the complex files with few tests are ranked higher and flagged, only in files mode
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    tests_per_file: Option<PathBuf>,
+    /// Multipliers of the thresholds from which a file is critical, high and medium, the complex files
+    /// are grouped by their tier in the outputs and a file below the medium one is low, only in files mode
+    #[clap(long, value_name = "CRITICAL,HIGH,MEDIUM")]
+    tiers: Option<Tiers>,
+    /// Threshold of a metric given as an expression of the sloc, ploc and comp of each file,
+    /// e.g. crap=2*sqrt(sloc), it can be repeated for each metric and the metrics without one
+    /// keep their constant threshold, only in files mode
+    #[clap(long, value_name = "METRIC=EXPRESSION")]
+    threshold_expression: Vec<ThresholdExpression>,
+    /// Minimum coverage of the functions by their SLOC, interpolated between the breakpoints,
+    /// a function less covered is complex, only in functions mode
+    #[clap(long, value_name = "SLOC:COVERAGE,...")]
+    coverage_by_length: Option<CoverageByLength>,
+    /// Path to the file mapping glob patterns of the files to their categories
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    categories: Option<PathBuf>,
+    /// Multiplier of the thresholds of a category, it can be repeated for each category
+    #[clap(long, value_name = "CATEGORY=MULTIPLIER", requires = "categories")]
+    category_thresholds: Vec<CategoryThreshold>,
     /// Path where to save the output of the csv file
     #[clap(long = "csv", value_hint = clap::ValueHint::DirPath)]
     path_csv: Option<PathBuf>,
@@ -152,6 +737,86 @@ struct Args {
     /// Path where to save the output of the json file
     #[clap(long = "json", value_hint = clap::ValueHint::DirPath)]
     json_output: Option<PathBuf>,
+    /// Print only the summary of the run as json to stdout, without the text output
+    #[clap(long)]
+    summary_json: bool,
+    /// Path where to save the metrics in NDJSON, one flat object per file or function with the metadata of the run
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    ndjson: Option<PathBuf>,
+    /// Path where to save the headline metrics in the InfluxDB line protocol, tagged by mode and complexity
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    influx: Option<PathBuf>,
+    /// Add a point for each file, or each function in functions mode, to the InfluxDB output
+    #[clap(long, requires = "influx")]
+    influx_per_file: bool,
+    /// Path where to save a lcov file highlighting the complex functions in the editors, only in functions mode
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    gutter: Option<PathBuf>,
+    /// Path where to save the files with 0% coverage, biggest first, with their complexity and SLOC, only in files mode
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    zero_coverage: Option<PathBuf>,
+    /// Directory where to save a <file>.wcc.json sidecar file with the metrics of each file,
+    /// mirroring the source tree of the project folder
+    #[clap(long, value_hint = clap::ValueHint::DirPath)]
+    sidecars: Option<PathBuf>,
+    /// Path to the CODEOWNERS file, the metrics of the files of each owner are printed, only in files mode
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    codeowners: Option<PathBuf>,
+    /// Choose the owners a file counts for when it matches more than one rule of the CODEOWNERS file
+    #[clap(long, default_value = OwnersMatch::default(), value_parser = PossibleValuesParser::new(OwnersMatch::all())
+        .map(|s| s.parse::<OwnersMatch>().unwrap()))]
+    owners_match: OwnersMatch,
+    /// Path where to save the metrics of the files of each owner in json format
+    #[clap(long, value_hint = clap::ValueHint::FilePath, requires = "codeowners")]
+    owners_json: Option<PathBuf>,
+    /// Fail when a directory is below its floor in the coverage_floors table of the config file
+    #[clap(long)]
+    strict_floors: bool,
+    /// Path where to save the merged coverage as a coveralls json, keeping the metadata of the coveralls files
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    coveralls_output: Option<PathBuf>,
+    /// Path where to save the raw metrics of every function joined with its coverage in csv format,
+    /// before any weighting, sort or threshold
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    raw_csv: Option<PathBuf>,
+    /// Path where to save the json with the matching between coverage entries and source files
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    diagnostics: Option<PathBuf>,
+    /// Paths to the covdir and the coveralls files of the same run, the coverage of each file and of the project
+    /// is computed from both and the differences over the tolerance are printed, without analyzing the project
+    #[clap(long, num_args = 2, value_names = ["COVDIR", "COVERALLS"], value_hint = clap::ValueHint::FilePath)]
+    cross_check: Option<Vec<PathBuf>>,
+    /// Path to a source file whose spaces are printed as json with all the metrics of rust-code-analysis,
+    /// without reading the coverage and analyzing the project
+    #[clap(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    dump_ast_metrics: Option<PathBuf>,
+    /// Maximum difference in percentage points between the coverages of the cross-check
+    #[clap(
+        long,
+        value_name = "PERCENTAGE",
+        default_value_t = 0.01,
+        requires = "cross_check"
+    )]
+    cross_check_tolerance: f64,
+    /// Address of the StatsD agent where to send the headline metrics as gauges
+    #[cfg(feature = "statsd")]
+    #[clap(long, alias = "metrics-endpoint", value_name = "HOST:PORT")]
+    statsd: Option<String>,
+    /// Address of the OpenTelemetry collector where to export the headline metrics and the phases
+    /// of the run over OTLP/HTTP, e.g. http://localhost:4318
+    #[cfg(feature = "otlp")]
+    #[clap(long, value_name = "URL")]
+    otlp_endpoint: Option<String>,
+    // Runs of the modes, exported together at the end of the run
+    #[cfg(feature = "otlp")]
+    #[clap(skip)]
+    otlp_runs: Mutex<Vec<ModeRun>>,
+    // Track state of the files mode, with its coverage debt when it is recorded
+    #[clap(skip)]
+    files_track_state: Mutex<Option<TrackState>>,
+    // Start of the run, set in main, the max runtime of every analysis of the run is counted from it
+    #[clap(skip)]
+    start: Option<Instant>,
     /// Choose complexity metric to use
     #[clap(long, short, default_value = Complexity::default(), value_parser = PossibleValuesParser::new(Complexity::all())
         .map(|s| s.parse::<Complexity>().unwrap()))]
@@ -160,7 +825,34 @@ struct Args {
     /// Number of threads to use for concurrency
     #[clap(default_value_t = 2)]
     n_threads: usize,
-    /// Specify the type of format used between coveralls and covdir
+
+    /// Maximum time in milliseconds for the analysis of a single file,
+    /// a file that takes longer is added to the files ignored
+    #[clap(long, value_name = "MS")]
+    file_timeout: Option<u64>,
+    /// Maximum time in seconds of the whole run, the files reached after it are skipped
+    /// and added to the files ignored, the partial results are output and the run fails as incomplete
+    #[clap(long, value_name = "SECONDS")]
+    max_runtime: Option<u64>,
+    /// Number of files with coverage to sample for a quick estimate of the project coverage,
+    /// the outputs are marked as estimates, only in files mode
+    #[clap(long, value_name = "N")]
+    sample: Option<usize>,
+    /// Seed of the sample, the same seed always samples the same files
+    #[clap(long, default_value_t = 0, requires = "sample")]
+    seed: u64,
+    /// Maximum value of CRAP and SKUNK, a greater or infinite one is saturated to it
+    /// and the file or function is flagged as complexity too high
+    #[clap(long, value_name = "VALUE", default_value_t = MAX_METRIC_VALUE)]
+    max_metric_value: f64,
+    /// Add the files with syntax errors to the files ignored instead of trusting their partial metrics
+    #[clap(long)]
+    strict_analysis: bool,
+    /// Order the complex files by the number of the files that depend on them with use or mod,
+    /// then by the sort metric, only in files mode
+    #[clap(long)]
+    order_by_fanin: bool,
+    /// Specify the type of format used between coveralls, covdir and lcov
     #[clap(long, short = 'f', default_value= JsonFormat::default(), value_parser = PossibleValuesParser::new(JsonFormat::all())
         .map(|s| s.parse::<JsonFormat>().unwrap()))]
     json_format: JsonFormat,
@@ -173,14 +865,87 @@ struct Args {
     #[clap(long, short = 'm', default_value= Mode::default(), value_parser = PossibleValuesParser::new(Mode::all())
         .map(|s| s.parse::<Mode>().unwrap()))]
     mode: Mode,
-    /// Sort complex value with the chosen metric
+    /// Sort complex value with the chosen metric, or everything by path
     #[clap(long, short, default_value = Sort::default(), value_parser = PossibleValuesParser::new(Sort::all())
         .map(|s| s.parse::<Sort>().unwrap()))]
     sort: Sort,
+    /// Metric whose average over the functions defines the worst module, only in functions mode
+    #[clap(long, default_value = "crap", value_parser = PossibleValuesParser::new(Sort::metrics())
+        .map(|s| s.parse::<Sort>().unwrap()))]
+    worst_module_by: Sort,
+}
+
+// Print the error of a failed run and get its exit code, a failure remapped to 0 is not reported
+fn get_exit_code(result: Result<()>, exit_codes: &ExitCodes) -> ExitCode {
+    let code = exit_codes.get(&result);
+    match result {
+        Err(e) if code != 0 => eprintln!("Error: {e}"),
+        _ => {}
+    }
+    ExitCode::from(code)
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+// The thresholds not given on the command line, the default ones or the ones of the config file,
+// are the same used by the files mode, warn that they are applied as-is to the functions
+fn warn_shared_thresholds(args: &Args, matches: &ArgMatches) {
+    if args.mode == Mode::Functions
+        && matches.value_source("thresholds") != Some(ValueSource::CommandLine)
+    {
+        warn!(
+            "The same thresholds are applied as-is to files and functions, \
+             but the metrics of a file sum up all its functions and have a larger scale, \
+             so they gate functions more loosely than files: give the functions ones with --thresholds"
+        );
+    }
+}
+
+// Use the values of the config file for the options not given on the command line
+fn apply_config(args: &mut Args, matches: &ArgMatches) -> Result<()> {
+    let config_path = args
+        .config
+        .clone()
+        .unwrap_or_else(|| args.path_file.join(CONFIG_FILE));
+    if args.config.is_none() && !config_path.exists() {
+        return Ok(());
+    }
+    debug!("Reading config from {:?}", config_path);
+    let config = read_config(&config_path)?;
+    let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let PartialConfig {
+        thresholds,
+        complexity,
+        mode,
+        json_format,
+        sort,
+        exclude,
+    } = config.analysis.clone();
+    if let Some(thresholds) = thresholds.filter(|_| !from_cli("thresholds")) {
+        args.thresholds = Thresholds(thresholds);
+    }
+    if let Some(complexity) = complexity.filter(|_| !from_cli("complexity")) {
+        args.complexity = complexity;
+    }
+    if let Some(mode) = mode.filter(|_| !from_cli("mode")) {
+        args.mode = mode;
+    }
+    if let Some(json_format) = json_format.filter(|_| !from_cli("json_format")) {
+        args.json_format = json_format;
+    }
+    if let Some(sort) = sort.filter(|_| !from_cli("sort")) {
+        args.sort = sort;
+    }
+    if let Some(exclude) = exclude.filter(|_| !from_cli("exclude")) {
+        args.exclude = exclude;
+    }
+    args.config_file = config;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // The max runtime counts from the start of the run, shared by all its analyses
+    args.start = Some(Instant::now());
     let filter_layer = EnvFilter::try_from_default_env()
         .or_else(|_| {
             if args.verbose {
@@ -196,8 +961,87 @@ fn main() -> Result<()> {
         .with_env_filter(filter_layer)
         .with_writer(std::io::stderr)
         .init();
-    match args.mode {
-        Mode::Functions => run_functions(&args),
-        Mode::Files => run_files(&args),
+    let exit_codes = ExitCodes {
+        breach: args.exit_code_breach,
+        coverage: args.exit_code_coverage,
+        incomplete: args.exit_code_incomplete,
+        input_error: args.exit_code_input_error,
+    };
+    get_exit_code(
+        apply_config(&mut args, &matches).and_then(|_| {
+            warn_shared_thresholds(&args, &matches);
+            run(&args)
+        }),
+        &exit_codes,
+    )
+}
+
+fn run(args: &Args) -> Result<()> {
+    let result = run_steps(args);
+    // The modes run before an error are still exported
+    #[cfg(feature = "otlp")]
+    if let (Some(endpoint), Ok(runs)) = (&args.otlp_endpoint, args.otlp_runs.lock()) {
+        emit_otlp_metrics(endpoint, &runs);
+    }
+    result
+}
+
+fn run_steps(args: &Args) -> Result<()> {
+    if let Some(path) = &args.dump_ast_metrics {
+        let options = get_analysis_options(args)?;
+        return print_ast_metrics(&get_ast_metrics(path, &options)?);
+    }
+    if let Some([covdir, coveralls]) = args.cross_check.as_deref() {
+        return run_cross_check(args, covdir, coveralls);
+    }
+    if let Some(diagnostics) = &args.diagnostics {
+        let inputs = get_coverage_inputs(args)?;
+        print_diagnostics_to_json(
+            &get_matching_diagnostics(&args.path_file, &inputs)?,
+            diagnostics,
+        )?;
+    }
+    if let Some(coveralls_output) = &args.coveralls_output {
+        let inputs = get_coverage_inputs(args)?;
+        let options = get_analysis_options(args)?;
+        print_coveralls_to_json(
+            &get_coveralls_payload(&args.path_file, &inputs, &options)?,
+            coveralls_output,
+        )?;
+        check_deadline(&options)?;
+    }
+    if let Some(raw_csv) = &args.raw_csv {
+        let inputs = get_coverage_inputs(args)?;
+        let options = get_analysis_options(args)?;
+        print_raw_functions_to_csv(
+            &get_raw_functions(&args.path_file, &inputs, &options)?,
+            raw_csv,
+        )?;
+        check_deadline(&options)?;
+    }
+    // The breaches and a coverage below the minimum do not stop the diff coverage and the track
+    let result = match run_mode(args) {
+        Err(e) if e.failure() == Failure::InputError => return Err(e),
+        result => result,
+    };
+    if let Some(git_ref) = &args.diff_coverage {
+        run_diff_coverage(args, git_ref)?;
+    }
+    if let Some(path) = &args.track {
+        run_track(args, path)?;
+    }
+    result
+}
+
+fn run_mode(args: &Args) -> Result<()> {
+    if let Some(function) = &args.function {
+        run_function_breakdown(args, function)
+    } else if let Some(git_ref) = &args.new_breaches {
+        run_new_breaches(args, git_ref)
+    } else {
+        match args.mode {
+            Mode::Functions => run_functions(args),
+            Mode::Files => run_files(args),
+        }
     }
 }
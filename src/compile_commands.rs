@@ -0,0 +1,286 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rust_code_analysis::{
+    fix_includes, get_macros, preprocess, read_file, ParserTrait, PreprocParser, PreprocResults,
+};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::*;
+
+// Entry of a compile_commands.json, the command is given either split in arguments or as a shell string
+#[derive(Deserialize)]
+struct CompileCommandEntry {
+    directory: PathBuf,
+    file: PathBuf,
+    #[serde(default)]
+    arguments: Vec<String>,
+    command: Option<String>,
+}
+
+// Flags of a compile command used by the preprocessing, their value is either attached or the next argument
+const COMPILE_FLAGS: [&str; 7] = [
+    "-isystem",
+    "-iquote",
+    "-idirafter",
+    "-include",
+    "-I",
+    "-D",
+    "-U",
+];
+
+/// Include directories and macros of the compile command of a C/C++ file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompileCommand {
+    // Directories searched for the included headers, in order
+    pub include_dirs: Vec<PathBuf>,
+    // Macros defined with -D, with their value, and undefined with -U, in order
+    pub defines: Vec<(String, Option<String>)>,
+    // Headers included before the file with -include
+    pub includes: Vec<PathBuf>,
+}
+
+impl CompileCommand {
+    // Get the include directories and the macros from the arguments of a compile command,
+    // the relative paths are resolved from the directory of the command
+    fn from_arguments(directory: &Path, arguments: &[String]) -> Self {
+        let mut command = Self::default();
+        // The first argument is the compiler
+        let mut i = 1;
+        while i < arguments.len() {
+            let argument = &arguments[i];
+            i += 1;
+            let flag = match COMPILE_FLAGS.iter().find(|f| argument.starts_with(**f)) {
+                Some(flag) => *flag,
+                None => continue,
+            };
+            let value = match &argument[flag.len()..] {
+                "" if i < arguments.len() => {
+                    i += 1;
+                    arguments[i - 1].clone()
+                }
+                "" => continue,
+                value => value.to_string(),
+            };
+            match flag {
+                "-D" => {
+                    let (name, value) = value.split_once('=').unwrap_or((&value, "1"));
+                    command.defines.push((name.into(), Some(value.into())));
+                }
+                "-U" => command.defines.push((value, None)),
+                "-include" => command.includes.push(directory.join(value)),
+                _ => command.include_dirs.push(directory.join(value)),
+            }
+        }
+        command
+    }
+
+    /// Get the preprocessor data of a C/C++ file used by rust-code-analysis to parse it:
+    /// the macros defined in the file, in the headers it includes, found next to it or
+    /// in the include directories, and the ones defined with -D, -U removes them
+    /// The macros are replaced by placeholders of the same length while parsing,
+    /// so the lines still match the coverage
+    pub fn preproc_results(&self, path: &Path) -> PreprocResults {
+        let mut results = PreprocResults::default();
+        let mut all_files: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        let mut stack = vec![path.to_path_buf()];
+        stack.extend(self.includes.iter().cloned());
+        let mut visited = HashSet::new();
+        while let Some(file) = stack.pop() {
+            if !visited.insert(normalize_path(&file)) {
+                continue;
+            }
+            // A header that can not be read is left to the best effort of the parser
+            let Ok(source) = read_file(&file) else {
+                continue;
+            };
+            let parser = PreprocParser::new(source, &file, None);
+            preprocess(&parser, &file, &mut results);
+            if let Some(name) = file.file_name() {
+                all_files
+                    .entry(name.to_string_lossy().into())
+                    .or_default()
+                    .push(file.clone());
+            }
+            let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+            stack.extend(
+                results.files[&file]
+                    .direct_includes
+                    .iter()
+                    .filter_map(|include| {
+                        std::iter::once(&dir)
+                            .chain(self.include_dirs.iter())
+                            .map(|dir| dir.join(include))
+                            .find(|header| header.is_file())
+                    }),
+            );
+        }
+        if let Some(file) = results.files.get_mut(path) {
+            file.direct_includes
+                .extend(self.includes.iter().map(|h| h.display().to_string()));
+            self.defines.iter().for_each(|(name, value)| match value {
+                Some(_) => {
+                    file.macros.insert(name.clone());
+                }
+                None => {
+                    file.macros.remove(name);
+                }
+            });
+        }
+        fix_includes(&mut results.files, &all_files);
+        debug!(
+            "Preprocessed {:?} with {} macros",
+            path,
+            get_macros(path, &results.files).len()
+        );
+        results
+    }
+}
+
+/// Compile commands of the C/C++ files of a project, read from a compile_commands.json
+/// The include directories and the macros of each file are used by rust-code-analysis to parse it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompileCommands {
+    commands: HashMap<PathBuf, CompileCommand>,
+}
+
+impl CompileCommands {
+    pub fn new(content: &str) -> Result<Self> {
+        let entries: Vec<CompileCommandEntry> = serde_json::from_str(content)
+            .map_err(|e| Error::CompileCommandsError(e.to_string()))?;
+        let mut commands = HashMap::new();
+        for entry in entries {
+            let arguments = match entry.command {
+                Some(command) if entry.arguments.is_empty() => split_command(&command),
+                _ => entry.arguments,
+            };
+            if arguments.is_empty() {
+                return Err(Error::CompileCommandsError(format!(
+                    "{} has no command",
+                    entry.file.display()
+                )));
+            }
+            commands.insert(
+                normalize_path(&entry.directory.join(&entry.file)),
+                CompileCommand::from_arguments(&entry.directory, &arguments),
+            );
+        }
+        Ok(Self { commands })
+    }
+
+    pub fn from_file<A: AsRef<Path>>(path: A) -> Result<Self> {
+        Self::new(&fs::read_to_string(path)?)
+    }
+
+    /// Get the compile command of a file, None if the file is not compiled by any command
+    pub fn command<A: AsRef<Path>>(&self, path: A) -> Option<&CompileCommand> {
+        self.commands.get(&normalize_path(path.as_ref()))
+    }
+}
+
+// Path used to match a file with its compile command, the absolute one when the file exists
+fn normalize_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// Split a shell command in its arguments, handling the quotes and the escaped characters
+fn split_command(command: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut argument = String::new();
+    let mut in_argument = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None | Some('"'), '\\') => {
+                argument.extend(chars.next());
+                in_argument = true;
+            }
+            (Some(_), c) => argument.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_argument = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_argument {
+                    arguments.push(std::mem::take(&mut argument));
+                    in_argument = false;
+                }
+            }
+            (None, c) => {
+                argument.push(c);
+                in_argument = true;
+            }
+        }
+    }
+    if in_argument {
+        arguments.push(argument);
+    }
+    arguments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utility::{get_root, get_syntax_errors};
+
+    const MATH: &str = "./data/compile_commands/src/math.c";
+
+    #[test]
+    fn test_compile_commands() {
+        let commands =
+            CompileCommands::from_file("./data/compile_commands/compile_commands.json").unwrap();
+        let command = commands.command(MATH).unwrap();
+        assert_eq!(
+            command.include_dirs,
+            vec![PathBuf::from("./data/compile_commands/include")]
+        );
+        assert_eq!(
+            command.defines,
+            vec![
+                ("FAST_MATH".to_string(), Some("1".to_string())),
+                ("INLINE_HINT".to_string(), Some("static inline".to_string())),
+            ]
+        );
+        assert!(commands
+            .command("./data/compile_commands/src/other.c")
+            .is_none());
+        // The macros of the command and of the included header are known to the parser
+        let results = command.preproc_results(Path::new(MATH));
+        let macros = get_macros(Path::new(MATH), &results.files);
+        assert_eq!(
+            macros,
+            HashSet::from(["API_EXPORT", "CONFIG_H", "FAST_MATH", "INLINE_HINT"].map(String::from))
+        );
+        // so they are replaced by placeholders instead of being parsed as types
+        let lines = |errors: Vec<(usize, String)>| {
+            errors
+                .into_iter()
+                .map(|(line, error)| (line, error.contains('$')))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lines(get_syntax_errors(MATH, None).unwrap()),
+            vec![(3, false), (14, false)]
+        );
+        assert_eq!(
+            lines(get_syntax_errors(MATH, Some(command)).unwrap()),
+            vec![(3, true), (14, true)]
+        );
+        let root = get_root(MATH, Some(command)).unwrap();
+        assert_eq!(root.spaces.len(), 3);
+        assert_eq!(root.metrics.cyclomatic.cyclomatic_sum(), 5.);
+        assert_eq!(
+            split_command(r#"cc -DNAME="a \"b\"" 'c d' e\ f"#),
+            vec!["cc", "-DNAME=a \"b\"", "c d", "e f"]
+        );
+        assert!(matches!(
+            CompileCommands::new(r#"[{"directory": ".", "file": "a.c"}]"#),
+            Err(Error::CompileCommandsError(_))
+        ));
+    }
+}
@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use globset::{Glob, GlobMatcher};
+
+use crate::contract::{Contracts, CONTRACT_TABLE};
+use crate::error::*;
+use crate::utility::{Complexity, JsonFormat, Mode, Sort};
+
+/// Default name of the config file
+pub const CONFIG_FILE: &str = "wcc.toml";
+
+/// Table of Cargo.toml holding the configuration
+pub const CARGO_METADATA_TABLE: &str = "package.metadata.wcc";
+
+// Keys of the analysis read from the config file and the table of Cargo.toml, the others are ignored
+const CONFIG_KEYS: [&str; 6] = [
+    "thresholds",
+    "complexity",
+    "mode",
+    "json_format",
+    "sort",
+    "exclude",
+];
+
+/// Table of the config file mapping the files to their excluded line ranges
+pub const LINE_EXCLUSIONS_TABLE: &str = "exclusions";
+
+/// Table of the config file mapping glob patterns of the files to their uncovered lines budget
+pub const UNCOVERED_LINES_TABLE: &str = "uncovered_lines";
+
+/// Table of the config file mapping glob patterns of the directories to their minimum coverage
+pub const COVERAGE_FLOORS_TABLE: &str = "coverage_floors";
+
+/// Default thresholds in this order: WCC_PLAIN, WCC_QUANTIZED, CRAP, SKUNK
+pub const DEFAULT_THRESHOLDS: [f64; 4] = [35.0, 1.5, 35.0, 30.0];
+
+/// Configuration of an analysis stored in the config file
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigFile {
+    pub thresholds: Vec<f64>,
+    pub complexity: String,
+    pub mode: String,
+    pub json_format: String,
+    pub sort: String,
+    pub exclude: Vec<String>,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self {
+            thresholds: DEFAULT_THRESHOLDS.to_vec(),
+            complexity: Complexity::default().into(),
+            mode: Mode::default().into(),
+            json_format: JsonFormat::default().into(),
+            sort: Sort::default().into(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Render the configuration as a commented toml file
+    pub fn to_template(&self) -> String {
+        let thresholds = self
+            .thresholds
+            .iter()
+            .map(|t| format!("{t:?}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let exclude = self
+            .exclude
+            .iter()
+            .map(|e| format!("{e:?}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(
+            "# Weighted Code Coverage configuration\n\
+             \n\
+             # Thresholds in this order: WCC_PLAIN, WCC_QUANTIZED, CRAP, SKUNK\n\
+             # 0 means no threshold at all\n\
+             thresholds = [{thresholds}]\n\
+             \n\
+             # Complexity metric to use: {complexity_all}\n\
+             complexity = {complexity:?}\n\
+             \n\
+             # Mode to use for analysis: {mode_all}\n\
+             mode = {mode:?}\n\
+             \n\
+             # Format of the coverage file: {json_format_all}\n\
+             json_format = {json_format:?}\n\
+             \n\
+             # Metric used to sort the complex files: {sort_all}\n\
+             sort = {sort:?}\n\
+             \n\
+             # Glob patterns of the files to exclude from the analysis, e.g.\n\
+             # exclude = [\"tests/**\", \"benches/**\", \"**/generated/*.rs\"]\n\
+             exclude = [{exclude}]\n\
+             \n\
+             # Line ranges of the files excluded from their coverable lines, only in files mode\n\
+             # [{LINE_EXCLUSIONS_TABLE}]\n\
+             # \"src/platform.rs\" = [[10, 20], [35, 35]]\n\
+             \n\
+             # Uncovered lines budget of the files, the last matching pattern wins, only in files mode\n\
+             # [{UNCOVERED_LINES_TABLE}]\n\
+             # \"**\" = 50\n\
+             # \"src/generated/**\" = 200\n\
+             \n\
+             # Minimum coverage of the directories, the last matching pattern wins, only in files mode\n\
+             # [{COVERAGE_FLOORS_TABLE}]\n\
+             # \"src/**\" = 60\n\
+             # \"src/parser\" = 80\n\
+             \n\
+             # Contract of each directory checked by cargo wcc verify\n\
+             # [{CONTRACT_TABLE}.\"src/metrics\"]\n\
+             # max_crap = 30\n\
+             # min_coverage = 80\n",
+            complexity_all = Complexity::all().join(", "),
+            complexity = self.complexity,
+            mode_all = Mode::all().join(", "),
+            mode = self.mode,
+            json_format_all = JsonFormat::all().join(", "),
+            json_format = self.json_format,
+            sort_all = Sort::all().join(", "),
+            sort = self.sort,
+        )
+    }
+}
+
+/// Write the default configuration as a commented toml file at the given path
+/// An existing file is overwritten only if force is true
+pub fn write_config_template<A: AsRef<Path>>(path: A, force: bool) -> Result<()> {
+    let path = path.as_ref();
+    if path.exists() && !force {
+        return Err(Error::ConfigExistsError(path.display().to_string()));
+    }
+    fs::write(path, ConfigFile::default().to_template())?;
+    Ok(())
+}
+
+/// Configuration given in a config source, the values not present are None
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PartialConfig {
+    pub thresholds: Option<Vec<f64>>,
+    pub complexity: Option<Complexity>,
+    pub mode: Option<Mode>,
+    pub json_format: Option<JsonFormat>,
+    pub sort: Option<Sort>,
+    pub exclude: Option<Vec<String>>,
+}
+
+// Value of a toml key, dates and times are not supported
+// The tables are flattened into dotted keys by parse_toml_table
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TomlValue {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Array(Vec<TomlValue>),
+    Table(Vec<(String, TomlValue)>),
+}
+
+impl TomlValue {
+    fn new(key: &str, value: toml::Value) -> Result<Self> {
+        Ok(match value {
+            toml::Value::String(s) => TomlValue::String(s),
+            toml::Value::Integer(n) => TomlValue::Number(n as f64),
+            toml::Value::Float(n) => TomlValue::Number(n),
+            toml::Value::Boolean(b) => TomlValue::Bool(b),
+            toml::Value::Array(a) => TomlValue::Array(
+                a.into_iter()
+                    .map(|v| TomlValue::new(key, v))
+                    .collect::<Result<Vec<TomlValue>>>()?,
+            ),
+            toml::Value::Table(t) => TomlValue::Table(
+                t.into_iter()
+                    .map(|(k, v)| Ok((k, TomlValue::new(key, v)?)))
+                    .collect::<Result<Vec<(String, TomlValue)>>>()?,
+            ),
+            toml::Value::Datetime(d) => {
+                return Err(Error::ConfigError(format!(
+                    "{key}: dates and times like {d} are not supported"
+                )))
+            }
+        })
+    }
+
+    fn into_string(self, key: &str) -> Result<String> {
+        match self {
+            TomlValue::String(s) => Ok(s),
+            _ => Err(Error::ConfigError(format!("{key} must be a string"))),
+        }
+    }
+
+    fn into_array(self, key: &str) -> Result<Vec<TomlValue>> {
+        match self {
+            TomlValue::Array(a) => Ok(a),
+            _ => Err(Error::ConfigError(format!("{key} must be an array"))),
+        }
+    }
+}
+
+// Add the values of a table to the values, a nested table is flattened into a key for each of its values
+fn push_toml_values(
+    values: &mut Vec<(Vec<String>, toml::Value)>,
+    key: &[String],
+    table: toml::value::Table,
+) {
+    table.into_iter().for_each(|(k, v)| {
+        let key = key.iter().cloned().chain([k]).collect::<Vec<String>>();
+        match v {
+            toml::Value::Table(table) => push_toml_values(values, &key, table),
+            v => values.push((key, v)),
+        }
+    });
+}
+
+// Get the values of the keys of a table of a toml file accepted by is_key, in the order of the file
+// Keys can be given in the table, in its subtables, as dotted keys in a parent table
+// or in inline tables, the returned keys are relative to the table and joined by dots
+// The whole content is parsed, so a file that is not valid toml is an error
+pub(crate) fn parse_toml_table<F: Fn(&str) -> bool>(
+    content: &str,
+    table: &str,
+    is_key: F,
+) -> Result<Vec<(String, TomlValue)>> {
+    // An empty table is the root one, with all the keys of the file
+    let table = table
+        .split('.')
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<&str>>();
+    let root = toml::from_str::<toml::value::Table>(content)
+        .map_err(|e| Error::ConfigError(e.to_string()))?;
+    let mut values = Vec::new();
+    push_toml_values(&mut values, &[], root);
+    values
+        .into_iter()
+        .filter(|(key, _)| key.len() > table.len() && key.iter().zip(&table).all(|(k, t)| k == t))
+        .map(|(key, value)| (key[table.len()..].join("."), value))
+        .filter(|(key, _)| is_key(key))
+        .map(|(key, value)| Ok((key.clone(), TomlValue::new(&key, value)?)))
+        .collect()
+}
+
+// Get the configuration of the analysis from a table of a toml file
+fn parse_partial_config(content: &str, table: &str) -> Result<PartialConfig> {
+    let mut config = PartialConfig::default();
+    for (key, value) in parse_toml_table(content, table, |key| {
+        key.split('.')
+            .next()
+            .is_some_and(|key| CONFIG_KEYS.contains(&key))
+    })? {
+        match key.as_str() {
+            // A known key given as a table, e.g. with an inline table
+            _ if key.contains('.') => {
+                return Err(Error::ConfigError(format!("{key} is not supported")))
+            }
+            "thresholds" => {
+                let thresholds = value
+                    .into_array(&key)?
+                    .into_iter()
+                    .map(|t| match t {
+                        TomlValue::Number(t) => Ok(t),
+                        _ => Err(Error::ConfigError("thresholds must be numbers".into())),
+                    })
+                    .collect::<Result<Vec<f64>>>()?;
+                config.thresholds = Some(thresholds);
+            }
+            "complexity" => {
+                let complexity = value.into_string(&key)?;
+                config.complexity = Some(
+                    complexity
+                        .parse::<Complexity>()
+                        .map_err(Error::ConfigError)?,
+                );
+            }
+            "mode" => {
+                let mode = value.into_string(&key)?;
+                config.mode = Some(mode.parse::<Mode>().map_err(Error::ConfigError)?);
+            }
+            "json_format" => {
+                let json_format = value.into_string(&key)?;
+                config.json_format = Some(
+                    json_format
+                        .parse::<JsonFormat>()
+                        .map_err(Error::ConfigError)?,
+                );
+            }
+            "sort" => {
+                let sort = value.into_string(&key)?;
+                config.sort = Some(sort.parse::<Sort>().map_err(Error::ConfigError)?);
+            }
+            _ => {
+                let exclude = value
+                    .into_array(&key)?
+                    .into_iter()
+                    .map(|e| e.into_string("exclude"))
+                    .collect::<Result<Vec<String>>>()?;
+                config.exclude = Some(exclude);
+            }
+        }
+    }
+    Ok(config)
+}
+
+// Get the configuration from the content of a Cargo.toml
+fn parse_cargo_metadata(manifest: &str) -> Result<PartialConfig> {
+    parse_partial_config(manifest, CARGO_METADATA_TABLE)
+}
+
+/// Read the configuration from the [package.metadata.wcc] table of a Cargo.toml
+/// The table supports the thresholds, complexity, mode, json_format, sort and exclude keys,
+/// every missing key is None
+pub fn read_cargo_metadata<A: AsRef<Path>>(manifest_path: A) -> Result<PartialConfig> {
+    parse_cargo_metadata(&fs::read_to_string(manifest_path)?)
+}
+
+/// Configuration read from the config file, the single source of the configuration of a project
+/// The keys of the analysis are at the top of the file, the ones of each feature in its own table,
+/// and a missing table is None
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub analysis: PartialConfig,
+    pub line_exclusions: Option<LineExclusions>,
+    pub uncovered_lines_budgets: Option<UncoveredLinesBudgets>,
+    pub coverage_floors: Option<CoverageFloors>,
+    pub contracts: Option<Contracts>,
+}
+
+impl Config {
+    pub fn new(content: &str) -> Result<Self> {
+        let has_table = |table: &str| -> Result<bool> {
+            Ok(!parse_toml_table(content, table, |_| true)?.is_empty())
+        };
+        Ok(Self {
+            analysis: parse_partial_config(content, "")?,
+            line_exclusions: match has_table(LINE_EXCLUSIONS_TABLE)? {
+                true => Some(LineExclusions::new(content)?),
+                false => None,
+            },
+            uncovered_lines_budgets: match has_table(UNCOVERED_LINES_TABLE)? {
+                true => Some(UncoveredLinesBudgets::new(content)?),
+                false => None,
+            },
+            coverage_floors: match has_table(COVERAGE_FLOORS_TABLE)? {
+                true => Some(CoverageFloors::new(content)?),
+                false => None,
+            },
+            contracts: match has_table(CONTRACT_TABLE)? {
+                true => Some(Contracts::new(content)?),
+                false => None,
+            },
+        })
+    }
+}
+
+/// Read the config file, e.g. the wcc.toml written by cargo wcc init
+pub fn read_config<A: AsRef<Path>>(path: A) -> Result<Config> {
+    Config::new(&fs::read_to_string(path)?)
+}
+
+/// Line ranges excluded from the coverable lines of the files, e.g. platform-specific blocks
+/// They are read from the [exclusions] table of the config file, mapping the path of each file
+/// relative to the project folder to its 1-based inclusive line ranges:
+/// "src/platform.rs" = [[10, 20], [35, 35]]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineExclusions {
+    ranges: HashMap<String, Vec<(usize, usize)>>,
+}
+
+impl LineExclusions {
+    pub fn new(content: &str) -> Result<Self> {
+        let mut ranges = HashMap::new();
+        for (file, value) in parse_toml_table(content, LINE_EXCLUSIONS_TABLE, |_| true)? {
+            let file_ranges = value
+                .into_array(&file)
+                .and_then(|values| values.into_iter().map(get_line_range).collect())
+                .map_err(|e| match e {
+                    Error::ConfigError(e) => Error::LineExclusionsError(format!("{file}: {e}")),
+                    e => e,
+                })?;
+            ranges.insert(file, file_ranges);
+        }
+        Ok(Self { ranges })
+    }
+
+    /// Get the line ranges excluded from a file given its path relative to the project folder
+    pub fn ranges(&self, file_path: &str) -> Option<&[(usize, usize)]> {
+        self.ranges
+            .get(file_path.trim_start_matches('/'))
+            .map(|r| r.as_slice())
+    }
+}
+
+/// Maximum number of uncovered lines of the files, whatever their coverage percentage
+/// They are read from the [uncovered_lines] table of the config file, mapping glob patterns
+/// of the files relative to the project folder to their budget, the last matching pattern wins:
+/// "**" = 50
+/// "src/generated/**" = 200
+#[derive(Clone, Debug, Default)]
+pub struct UncoveredLinesBudgets {
+    budgets: Vec<(GlobMatcher, usize)>,
+}
+
+impl UncoveredLinesBudgets {
+    pub fn new(content: &str) -> Result<Self> {
+        let budgets = parse_toml_table(content, UNCOVERED_LINES_TABLE, |_| true)?
+            .into_iter()
+            .map(|(glob, value)| match value {
+                TomlValue::Number(n) if n >= 0. && n.fract() == 0. => {
+                    Ok((Glob::new(&glob)?.compile_matcher(), n as usize))
+                }
+                _ => Err(Error::UncoveredLinesError(format!(
+                    "{glob} must be a number of lines"
+                ))),
+            })
+            .collect::<Result<Vec<(GlobMatcher, usize)>>>()?;
+        Ok(Self { budgets })
+    }
+
+    /// Get the budget of a file given its path relative to the project folder
+    pub fn budget(&self, file_path: &str) -> Option<usize> {
+        let file_path = file_path.trim_start_matches('/');
+        self.budgets
+            .iter()
+            .rev()
+            .find(|(glob, _)| glob.is_match(file_path))
+            .map(|(_, budget)| *budget)
+    }
+}
+
+/// Minimum coverage percentage of the directories, computed over the files directly inside each one
+/// They are read from the [coverage_floors] table of the config file, mapping glob patterns
+/// of the directories relative to the project folder to their floor, the last matching pattern wins:
+/// "src/**" = 60
+/// "src/parser" = 80
+#[derive(Clone, Debug, Default)]
+pub struct CoverageFloors {
+    floors: Vec<(GlobMatcher, f64)>,
+}
+
+impl CoverageFloors {
+    pub fn new(content: &str) -> Result<Self> {
+        let floors = parse_toml_table(content, COVERAGE_FLOORS_TABLE, |_| true)?
+            .into_iter()
+            .map(|(glob, value)| match value {
+                TomlValue::Number(n) if (0. ..=100.).contains(&n) => {
+                    Ok((Glob::new(&glob)?.compile_matcher(), n))
+                }
+                _ => Err(Error::CoverageFloorsError(format!(
+                    "{glob} must be a percentage between 0 and 100"
+                ))),
+            })
+            .collect::<Result<Vec<(GlobMatcher, f64)>>>()?;
+        Ok(Self { floors })
+    }
+
+    /// Get the floor of a directory given its path relative to the project folder
+    pub fn floor(&self, directory: &str) -> Option<f64> {
+        let directory = directory.trim_start_matches('/');
+        self.floors
+            .iter()
+            .rev()
+            .find(|(glob, _)| glob.is_match(directory))
+            .map(|(_, floor)| *floor)
+    }
+}
+
+// Get a line range given as an array with its first and last line
+fn get_line_range(value: TomlValue) -> Result<(usize, usize)> {
+    let line = |value: &TomlValue| match value {
+        TomlValue::Number(n) if *n >= 1. && n.fract() == 0. => Some(*n as usize),
+        _ => None,
+    };
+    match value.into_array("range")?.as_slice() {
+        [start, end] => match (line(start), line(end)) {
+            (Some(start), Some(end)) if start <= end => Ok((start, end)),
+            _ => Err(Error::ConfigError(format!(
+                "{start:?}-{end:?} is not a range of lines"
+            ))),
+        },
+        _ => Err(Error::ConfigError(
+            "a range must be given as [first_line, last_line]".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_config_template() {
+        let path = Path::new("./data/wcc_init.toml");
+        write_config_template(path, false).unwrap();
+        let template = fs::read_to_string(path).unwrap();
+        assert!(template.contains("thresholds = [35.0, 1.5, 35.0, 30.0]"));
+        assert!(template.contains("complexity = \"cyclomatic\""));
+        assert!(template.contains("mode = \"files\""));
+        assert!(matches!(
+            write_config_template(path, false),
+            Err(Error::ConfigExistsError(_))
+        ));
+        write_config_template(path, true).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_cargo_metadata() {
+        let manifest = r#"
+[package]
+name = "example"
+license = ""
+description = """
+A [multi-line] = description
+"""
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+
+[[bin]]
+name = 'example'
+test = false
+
+[package.metadata.wcc]
+# Stricter CRAP threshold
+thresholds = [
+    35.0, 1.5,
+    20, 30.0, # SKUNK
+]
+complexity = "cognitive"
+exclude = ["tests/**", 'benches/**']
+unknown = true
+
+[package.metadata.other]
+mode = "functions"
+"#;
+        let config = parse_cargo_metadata(manifest).unwrap();
+        assert_eq!(
+            config,
+            PartialConfig {
+                thresholds: Some(vec![35.0, 1.5, 20.0, 30.0]),
+                complexity: Some(Complexity::Cognitive),
+                mode: None,
+                exclude: Some(vec!["tests/**".into(), "benches/**".into()]),
+                ..Default::default()
+            }
+        );
+        let config =
+            parse_cargo_metadata("[package.metadata]\nwcc.mode = \"functions\"\n").unwrap();
+        assert_eq!(config.mode, Some(Mode::Functions));
+        assert_eq!(
+            parse_cargo_metadata("[package]\n").unwrap(),
+            PartialConfig::default()
+        );
+        assert!(matches!(
+            parse_cargo_metadata("[package.metadata.wcc]\nmode = \"lines\"\n"),
+            Err(Error::ConfigError(_))
+        ));
+        let config =
+            parse_cargo_metadata("[package.metadata]\nwcc = { complexity = 'cognitive' }\n")
+                .unwrap();
+        assert_eq!(config.complexity, Some(Complexity::Cognitive));
+        // The manifest of this crate has no wcc table
+        assert_eq!(
+            read_cargo_metadata("Cargo.toml").unwrap(),
+            PartialConfig::default()
+        );
+        // A date is only an error for a key of the table
+        assert_eq!(
+            parse_cargo_metadata("[package]\nreleased = 1979-05-27\n").unwrap(),
+            PartialConfig::default()
+        );
+        // A manifest that is not valid toml is an error instead of being skipped
+        for manifest in [
+            "[package.metadata.wcc]\nmode = 1979-05-27\n",
+            "[package]\nname = \"example\" version = \"0.1.0\"\n",
+            "[package\nname = \"example\"\n",
+            "[package]\nname = \"example\n",
+            "[package]\nname = \n",
+            "[package]\nname = \"example\"\nname = \"other\"\n",
+            "[package]\n[package]\n",
+            "[package.metadata.wcc]\nthresholds = { crap = 20 }\n",
+        ] {
+            assert!(
+                matches!(parse_cargo_metadata(manifest), Err(Error::ConfigError(_))),
+                "{manifest}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        let config = Config::new(
+            r#"
+thresholds = [35.0, 1.5, 20.0, 30.0]
+json_format = "covdir"
+sort = "crap"
+
+[exclusions]
+"src/platform.rs" = [[10, 20]]
+
+[coverage_floors]
+"src/**" = 60
+
+[contract."."]
+max_complex_files = 10
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.analysis,
+            PartialConfig {
+                thresholds: Some(vec![35.0, 1.5, 20.0, 30.0]),
+                json_format: Some(JsonFormat::Covdir),
+                sort: Some(Sort::Crap),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            config.line_exclusions.unwrap().ranges("src/platform.rs"),
+            Some(&[(10, 20)][..])
+        );
+        assert!(config.uncovered_lines_budgets.is_none());
+        assert!(config.coverage_floors.is_some());
+        // The keys keep the order of the file, the last matching pattern wins even if sorted first
+        let budgets =
+            UncoveredLinesBudgets::new("[uncovered_lines]\n\"src/gen/**\" = 200\n\"**\" = 50\n")
+                .unwrap();
+        assert_eq!(budgets.budget("src/gen/parser.rs"), Some(50));
+        assert!(config.contracts.is_some());
+        // The template written by cargo wcc init is a valid config file without tables
+        let path = Path::new("./data/wcc_config.toml");
+        write_config_template(path, false).unwrap();
+        let config = read_config(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(config.analysis.mode, Some(Mode::Files));
+        assert!(config.line_exclusions.is_none() && config.contracts.is_none());
+    }
+
+    #[test]
+    fn test_line_exclusions() {
+        let exclusions = LineExclusions::new(
+            r#"
+            # Platform-specific blocks
+            [exclusions]
+            "src/platform.rs" = [[10, 20], [35, 35]]
+            "src/lib.rs" = []
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            exclusions.ranges("/src/platform.rs"),
+            Some(&[(10, 20), (35, 35)][..])
+        );
+        assert_eq!(exclusions.ranges("src/lib.rs"), Some(&[][..]));
+        assert_eq!(exclusions.ranges("src/main.rs"), None);
+        [
+            "[[20, 10]]",
+            "[[0, 1]]",
+            "[[1.5, 2]]",
+            "[[1, 2, 3]]",
+            "[1, 2]",
+            "\"1-2\"",
+        ]
+        .iter()
+        .for_each(|ranges| {
+            let content = format!("[exclusions]\n\"src/lib.rs\" = {ranges}\n");
+            assert!(matches!(
+                LineExclusions::new(&content),
+                Err(Error::LineExclusionsError(_))
+            ));
+        });
+    }
+}
@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::{parse_toml_table, TomlValue};
+use crate::error::*;
+use crate::files::FileMetrics;
+
+/// Name of the table of the contract file with the clauses of each directory
+pub const CONTRACT_TABLE: &str = "contract";
+
+/// Clauses accepted in a contract, the max ones are checked on each file of the directory,
+/// min_coverage too, while max_complex_files is checked on the number of complex files
+pub const CONTRACT_CLAUSES: [&str; 6] = [
+    "max_wcc_plain",
+    "max_wcc_quantized",
+    "max_crap",
+    "max_skunk",
+    "min_coverage",
+    "max_complex_files",
+];
+
+/// Minimum acceptable metrics of a directory, relative to the project folder
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Contract {
+    pub dir: String,
+    pub clauses: Vec<(String, f64)>,
+}
+
+/// Contracts of the directories of a project, read from the [contract] table of the config file
+/// with a table for each directory, "." for the whole project:
+/// [contract."src/metrics"]
+/// max_crap = 30
+/// min_coverage = 80
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Contracts {
+    contracts: Vec<Contract>,
+}
+
+impl Contracts {
+    pub fn new(content: &str) -> Result<Self> {
+        let mut contracts: Vec<Contract> = Vec::new();
+        for (key, value) in parse_toml_table(content, CONTRACT_TABLE, |_| true)? {
+            // The directory can contain dots, the clause is the last dotted key
+            let (dir, clause) = key
+                .rsplit_once('.')
+                .filter(|(_, clause)| CONTRACT_CLAUSES.contains(clause))
+                .ok_or_else(|| {
+                    Error::ContractError(format!(
+                        "{key} is not a clause, it must be one of {}",
+                        CONTRACT_CLAUSES.join(", ")
+                    ))
+                })?;
+            let limit = match value {
+                TomlValue::Number(limit) => limit,
+                _ => return Err(Error::ContractError(format!("{key} must be a number"))),
+            };
+            let dir = dir.trim_start_matches("./").trim_matches('/').to_string();
+            match contracts.iter_mut().find(|c| c.dir == dir) {
+                Some(contract) => contract.clauses.push((clause.into(), limit)),
+                None => contracts.push(Contract {
+                    dir,
+                    clauses: vec![(clause.into(), limit)],
+                }),
+            }
+        }
+        Ok(Self { contracts })
+    }
+
+    pub fn contracts(&self) -> &[Contract] {
+        &self.contracts
+    }
+}
+
+/// Result of a clause of a contract, with the value reached by the directory
+/// and the files that do not satisfy it
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClauseResult {
+    pub clause: String,
+    pub limit: f64,
+    pub value: f64,
+    pub passed: bool,
+    pub files: Vec<String>,
+}
+
+/// Result of the contract of a directory, it passes when it has files and all its clauses pass
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ContractResult {
+    pub dir: String,
+    pub number_of_files: usize,
+    pub passed: bool,
+    pub clauses: Vec<ClauseResult>,
+}
+
+// Check if a file, given with its path relative to the project folder, is in the directory
+fn is_in_dir(file_path: &str, dir: &str) -> bool {
+    let file_path = file_path.trim_start_matches('/');
+    dir.is_empty()
+        || dir == "."
+        || file_path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Evaluate a clause on the files of a directory
+fn verify_clause(clause: &str, limit: f64, files: &[&FileMetrics]) -> ClauseResult {
+    let metric = |m: &FileMetrics| match clause {
+        "max_wcc_plain" => m.metrics.wcc_plain,
+        "max_wcc_quantized" => m.metrics.wcc_quantized,
+        "max_crap" => m.metrics.crap,
+        "max_skunk" => m.metrics.skunk,
+        _ => m.metrics.coverage,
+    };
+    let (value, failed) = match clause {
+        "max_complex_files" => {
+            let complex = files
+                .iter()
+                .filter(|m| m.metrics.is_complex)
+                .map(|m| m.file_path.clone())
+                .collect::<Vec<String>>();
+            let value = complex.len() as f64;
+            (value, if value > limit { complex } else { Vec::new() })
+        }
+        // The files without lines that can be covered have no coverage to check
+        "min_coverage" => {
+            let files = files
+                .iter()
+                .filter(|m| !m.no_coverable_lines)
+                .collect::<Vec<_>>();
+            let value = files.iter().map(|m| metric(m)).fold(100., f64::min);
+            let failed = files
+                .iter()
+                .filter(|m| metric(m) < limit)
+                .map(|m| m.file_path.clone())
+                .collect();
+            (value, failed)
+        }
+        _ => {
+            let value = files.iter().map(|m| metric(m)).fold(0., f64::max);
+            let failed = files
+                .iter()
+                .filter(|m| metric(m) > limit)
+                .map(|m| m.file_path.clone())
+                .collect();
+            (value, failed)
+        }
+    };
+    ClauseResult {
+        clause: clause.into(),
+        limit,
+        value,
+        passed: failed.is_empty(),
+        files: failed,
+    }
+}
+
+/// Verify the contracts on the metrics of the files of a run in files mode
+/// It returns the result of each contract, in the order of the contract file
+pub fn verify_contracts(contracts: &Contracts, metrics: &[FileMetrics]) -> Vec<ContractResult> {
+    contracts
+        .contracts()
+        .iter()
+        .map(|contract| {
+            let files = metrics
+                .iter()
+                .filter(|m| m.file_path != "-" && is_in_dir(&m.file_path, &contract.dir))
+                .collect::<Vec<&FileMetrics>>();
+            let clauses = contract
+                .clauses
+                .iter()
+                .map(|(clause, limit)| verify_clause(clause, *limit, &files))
+                .collect::<Vec<ClauseResult>>();
+            ContractResult {
+                dir: contract.dir.clone(),
+                number_of_files: files.len(),
+                passed: !files.is_empty() && clauses.iter().all(|c| c.passed),
+                clauses,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::{get_metrics_concurrent, Metrics};
+    use crate::utility::{compare_float, AnalysisOptions, Complexity, Sort};
+
+    #[test]
+    fn test_verify_contracts() {
+        let contracts = Contracts::new(
+            r#"
+            [contract."."]
+            max_complex_files = 10
+
+            [contract."src"]
+            max_crap = 300
+            min_coverage = 80
+
+            [contract."examples/"]
+            min_coverage = 50
+
+            [contract."src/missing"]
+            max_skunk = 30
+            "#,
+        )
+        .unwrap();
+        assert_eq!(contracts.contracts().len(), 4);
+        assert_eq!(contracts.contracts()[2].dir, "examples");
+        let (metrics, _, _, _) = get_metrics_concurrent(
+            "./data/seahorse/",
+            "./data/seahorse/seahorse.json",
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let results = verify_contracts(&contracts, &metrics);
+        assert!(results[0].passed);
+        assert_eq!(results[0].clauses[0].value, 5.);
+        let src = &results[1];
+        assert!(!src.passed);
+        // Only the unmet clause lists the files that do not satisfy it
+        assert!(src.clauses[0].passed);
+        assert!(src.clauses[0].files.is_empty());
+        assert!(compare_float(src.clauses[0].value, 257.94117647058823));
+        assert!(!src.clauses[1].passed);
+        assert!(src.clauses[1].files.contains(&"src/error.rs".to_string()));
+        assert!(src.clauses[1].value < 80.);
+        // The examples are not tested at all
+        assert!(!results[2].passed);
+        assert_eq!(results[2].clauses[0].value, 0.);
+        // A directory without files does not pass
+        assert_eq!(results[3].number_of_files, 0);
+        assert!(!results[3].passed);
+        assert!(is_in_dir("/src/app.rs", "src"));
+        assert!(!is_in_dir("src2/app.rs", "src"));
+        let file = FileMetrics::new(
+            Metrics::default().coverage(100.),
+            "lib.rs".into(),
+            "src/lib.rs".into(),
+        );
+        assert!(verify_contracts(&contracts, &[file])[1].passed);
+        [
+            "[contract.src]\nmax_lines = 1\n",
+            "[contract.src]\nmax_crap = \"1\"\n",
+        ]
+        .iter()
+        .for_each(|content| {
+            assert!(matches!(
+                Contracts::new(content),
+                Err(Error::ContractError(_))
+            ));
+        });
+    }
+}
@@ -20,14 +20,26 @@ pub enum Error {
     ReadingJSONError(),
     #[error("Error while computing Metrics")]
     MetricsError(),
+    #[error("The analysis of {0} did not finish in {1} ms")]
+    FileTimeoutError(String, u128),
     #[error("Error while guessing language")]
     LanguageError(),
     #[error("Error while writing on csv")]
     WritingError(#[from] csv::Error),
     #[error("Error during concurrency")]
     ConcurrentError(),
-    #[error("Json Type is not supported! Only coveralls and covdir are supported.")]
+    #[error("Json Type is not supported! Only coveralls, covdir, lcov and codecov are supported.")]
     TypeError(),
+    #[error("Error while reading lcov file")]
+    ReadingLcovError(),
+    #[error("Error while reading Codecov file")]
+    ReadingCodecovError(),
+    #[error("{0} is not a coverage json in coveralls, covdir or codecov format")]
+    CoverageFormatError(String),
+    #[error("No coverage json found in {0}")]
+    EmptyCoverageDirError(String),
+    #[error("File {0} is present in more than one coverage file")]
+    CoverageConflictError(String),
     #[error("Error while converting path to string")]
     PathConversionError(),
     #[error("Error while locking mutex")]
@@ -36,12 +48,147 @@ pub enum Error {
     ThresholdsError(),
     #[error("Error while sending job via sender")]
     SenderError(),
+    #[error("Config file {0} already exists, use --force to overwrite it")]
+    ConfigExistsError(String),
+    #[error("Wrong config: {0}")]
+    ConfigError(String),
+    #[error("Error while running git: {0}")]
+    GitError(String),
+    #[error("Diff coverage {0:.2}% is below the minimum of {1:.2}%")]
+    DiffCoverageError(f64, f64),
+    #[error("Project coverage {0:.2}% is below the minimum of {1:.2}%")]
+    CoverageError(f64, f64),
+    #[error("{0} directories are below their coverage floor")]
+    DirectoryCoverageError(usize),
+    #[error("Wrong line in categories file: {0}")]
+    CategoriesError(String),
+    #[error("Wrong line exclusion: {0}")]
+    LineExclusionsError(String),
+    #[error("Wrong uncovered lines budget: {0}")]
+    UncoveredLinesError(String),
+    #[error("Wrong coverage floor: {0}")]
+    CoverageFloorsError(String),
+    #[error("Wrong line in hotness file: {0}")]
+    HotnessError(String),
+    #[error("Wrong rule in path map file: {0}, it must be given as from=to")]
+    PathMapError(String),
+    #[error("Wrong line in tests per file map: {0}")]
+    TestCountsError(String),
+    #[error("Wrong compile commands file: {0}")]
+    CompileCommandsError(String),
+    #[error("Wrong glob pattern")]
+    GlobError(#[from] globset::Error),
+    #[error("{0} can be used only in {}", crate::modes::get_supported_modes(.0))]
+    ModeError(&'static str),
+    #[error("Function {0} must be given as path.rs::function_name")]
+    FunctionSelectorError(String),
+    #[error("Function {0} not found in the files with coverage")]
+    FunctionNotFoundError(String),
+    #[error("Track file {0} is not a valid state: {1}")]
+    TrackStateError(String, String),
+    #[error("OTLP export failed: {0}")]
+    OtlpError(String),
+    #[error("Contract error: {0}")]
+    ContractError(String),
+    #[error("The benchmark needs at least one run and the numbers of threads must be positive")]
+    BenchError(),
+    #[error("{0} is not a json report of a run in files mode")]
+    AggregateReportError(String),
+    #[error("{0} contracts are not met")]
+    ContractNotMetError(usize),
+    #[error("{0} values of the json report differ from a fresh run")]
+    ReportMismatchError(usize),
+    #[error("The covdir and coveralls files differ by more than the tolerance, in {0} files")]
+    CrossCheckError(usize),
+    #[error("{0} functions changed since the git reference are over the thresholds")]
+    NewBreachesError(usize),
+    #[error("{0} files or functions are over the thresholds")]
+    ThresholdsBreachError(usize),
+    #[error("The max runtime has been reached, {0} files have been skipped and the results are incomplete")]
+    MaxRuntimeError(usize),
     #[error("Error while creating HTML file")]
     HTMLError(#[from] tera::Error),
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+/// Default exit code of a run with files or functions over the thresholds or with unmet checks
+pub const EXIT_CODE_BREACH: u8 = 1;
+
+/// Default exit code of a run that fails because of its inputs, e.g. a wrong option or file
+pub const EXIT_CODE_INPUT_ERROR: u8 = 2;
+
+/// Default exit code of a run with a coverage below the minimum one
+pub const EXIT_CODE_COVERAGE: u8 = 3;
+
+/// Default exit code of a run with incomplete results because it reached the max runtime
+pub const EXIT_CODE_INCOMPLETE: u8 = 4;
+
+/// Kind of failure of a run, each one with its own exit code
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// Files or functions over the thresholds, unmet contracts, an outdated json report
+    /// or coverage files that disagree in a cross-check.
+    Breach,
+    /// Project or diff coverage below the minimum one.
+    Coverage,
+    /// Files skipped because the max runtime has been reached.
+    Incomplete,
+    /// Any other error, e.g. a wrong option or a missing file.
+    InputError,
+}
+
+impl Error {
+    /// Get the kind of failure of the error
+    pub fn failure(&self) -> Failure {
+        match self {
+            Error::ThresholdsBreachError(_)
+            | Error::NewBreachesError(_)
+            | Error::ContractNotMetError(_)
+            | Error::ReportMismatchError(_)
+            | Error::CrossCheckError(_) => Failure::Breach,
+            Error::CoverageError(_, _)
+            | Error::DiffCoverageError(_, _)
+            | Error::DirectoryCoverageError(_) => Failure::Coverage,
+            Error::MaxRuntimeError(_) => Failure::Incomplete,
+            _ => Failure::InputError,
+        }
+    }
+}
+
+/// Exit codes of the kinds of failure of a run, a successful run exits with 0
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+pub struct ExitCodes {
+    pub breach: u8,
+    pub coverage: u8,
+    pub incomplete: u8,
+    pub input_error: u8,
+}
+
+impl Default for ExitCodes {
+    fn default() -> Self {
+        Self {
+            breach: EXIT_CODE_BREACH,
+            coverage: EXIT_CODE_COVERAGE,
+            incomplete: EXIT_CODE_INCOMPLETE,
+            input_error: EXIT_CODE_INPUT_ERROR,
+        }
+    }
+}
+
+impl ExitCodes {
+    /// Get the exit code of the result of a run
+    pub fn get<T>(&self, result: &Result<T>) -> u8 {
+        match result.as_ref().map_err(Error::failure) {
+            Ok(_) => 0,
+            Err(Failure::Breach) => self.breach,
+            Err(Failure::Coverage) => self.coverage,
+            Err(Failure::Incomplete) => self.incomplete,
+            Err(Failure::InputError) => self.input_error,
+        }
+    }
+}
+
 impl<T> From<PoisonError<T>> for Error {
     fn from(_item: PoisonError<T>) -> Self {
         Error::MutexError()
@@ -53,3 +200,28 @@ impl From<Box<dyn std::any::Any + Send>> for Error {
         Error::ConcurrentError()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let codes = ExitCodes::default();
+        assert_eq!(codes.get(&Ok(())), 0);
+        assert_eq!(codes.get::<()>(&Err(Error::ThresholdsBreachError(3))), 1);
+        assert_eq!(codes.get::<()>(&Err(Error::ContractNotMetError(1))), 1);
+        assert_eq!(codes.get::<()>(&Err(Error::ThresholdsError())), 2);
+        assert_eq!(codes.get::<()>(&Err(Error::CoverageError(50., 80.))), 3);
+        assert_eq!(codes.get::<()>(&Err(Error::DiffCoverageError(50., 80.))), 3);
+        assert_eq!(codes.get::<()>(&Err(Error::DirectoryCoverageError(2))), 3);
+        assert_eq!(codes.get::<()>(&Err(Error::MaxRuntimeError(4))), 4);
+        // A breach can be remapped, e.g. to not fail on it
+        let codes = ExitCodes {
+            breach: 0,
+            ..ExitCodes::default()
+        };
+        assert_eq!(codes.get::<()>(&Err(Error::NewBreachesError(2))), 0);
+        assert_eq!(codes.get::<()>(&Err(Error::MetricsError())), 2);
+    }
+}
@@ -0,0 +1,336 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use tracing::warn;
+
+/// Variables of an expression, the metrics of the file it is evaluated on
+pub const EXPRESSION_VARIABLES: [&str; 3] = ["sloc", "ploc", "comp"];
+
+/// Functions of an expression with their number of arguments
+pub const EXPRESSION_FUNCTIONS: [(&str, usize); 7] = [
+    ("sqrt", 1),
+    ("ln", 1),
+    ("log2", 1),
+    ("log10", 1),
+    ("abs", 1),
+    ("min", 2),
+    ("max", 2),
+];
+
+/// Metrics whose threshold can be given as an expression, in the order of the thresholds
+pub const THRESHOLD_METRICS: [&str; 4] = ["wcc_plain", "wcc_quantized", "crap", "skunk"];
+
+// Node of the tree of a parsed expression
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f64),
+    // Index of the variable in EXPRESSION_VARIABLES
+    Variable(usize),
+    Neg(Box<Node>),
+    Binary(char, Box<Node>, Box<Node>),
+    Call(&'static str, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, variables: &[f64; 3]) -> f64 {
+        match self {
+            Node::Number(n) => *n,
+            Node::Variable(i) => variables[*i],
+            Node::Neg(node) => -node.eval(variables),
+            Node::Binary(op, left, right) => {
+                let (left, right) = (left.eval(variables), right.eval(variables));
+                match op {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    '/' => left / right,
+                    _ => left.powf(right),
+                }
+            }
+            Node::Call(function, args) => {
+                let args = args.iter().map(|a| a.eval(variables)).collect::<Vec<f64>>();
+                match *function {
+                    "sqrt" => args[0].sqrt(),
+                    "ln" => args[0].ln(),
+                    "log2" => args[0].log2(),
+                    "log10" => args[0].log10(),
+                    "abs" => args[0].abs(),
+                    "min" => args[0].min(args[1]),
+                    _ => args[0].max(args[1]),
+                }
+            }
+        }
+    }
+}
+
+// Recursive descent parser of an expression, one method for each precedence level:
+// expr := term (('+' | '-') term)*
+// term := unary (('*' | '/') unary)*
+// unary := '-' unary | power
+// power := atom ('^' unary)?
+// atom := number | variable | function '(' expr (',' expr)* ')' | '(' expr ')'
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespaces(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    // Consume the next character if it is one of the given ones
+    fn next_if_in(&mut self, chars: &[char]) -> Option<char> {
+        self.skip_whitespaces();
+        self.chars.next_if(|c| chars.contains(c))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next_if_in(&[expected]) {
+            Some(_) => Ok(()),
+            None => Err(match self.chars.peek() {
+                Some(c) => format!("expected {expected:?} instead of {c:?}"),
+                None => format!("expected {expected:?} at the end"),
+            }),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        while let Some(op) = self.next_if_in(&['+', '-']) {
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while let Some(op) = self.next_if_in(&['*', '/']) {
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.next_if_in(&['-']) {
+            Some(_) => Ok(Node::Neg(Box::new(self.unary()?))),
+            None => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Node, String> {
+        let node = self.atom()?;
+        match self.next_if_in(&['^']) {
+            // The power is right associative and binds tighter than the unary minus on its left
+            Some(op) => Ok(Node::Binary(op, Box::new(node), Box::new(self.unary()?))),
+            None => Ok(node),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        self.skip_whitespaces();
+        match self.chars.peek().copied() {
+            Some('(') => {
+                self.chars.next();
+                let node = self.expr()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                number
+                    .parse::<f64>()
+                    .map(Node::Number)
+                    .map_err(|_| format!("{number:?} is not a valid number"))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut name = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphanumeric()) {
+                    name.push(c);
+                }
+                if let Some(i) = EXPRESSION_VARIABLES.iter().position(|v| *v == name) {
+                    return Ok(Node::Variable(i));
+                }
+                let (function, arity) = EXPRESSION_FUNCTIONS
+                    .iter()
+                    .find(|(f, _)| *f == name)
+                    .ok_or(format!(
+                        "{name:?} is not a variable or a function, the variables are {}",
+                        EXPRESSION_VARIABLES.join(", ")
+                    ))?;
+                self.expect('(')?;
+                let mut args = vec![self.expr()?];
+                while self.next_if_in(&[',']).is_some() {
+                    args.push(self.expr()?);
+                }
+                self.expect(')')?;
+                if args.len() != *arity {
+                    return Err(format!(
+                        "{function} takes {arity} arguments instead of {}",
+                        args.len()
+                    ));
+                }
+                Ok(Node::Call(function, args))
+            }
+            Some(c) => Err(format!("unexpected {c:?}")),
+            None => Err("unexpected end".into()),
+        }
+    }
+}
+
+/// Arithmetic expression of the metrics of a file, e.g. 2*sqrt(sloc)
+/// An expression has numbers, the variables sloc, ploc and comp, the operators + - * / ^,
+/// parentheses and the functions sqrt, ln, log2, log10, abs, min and max
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(expression: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: expression.chars().peekable(),
+        };
+        let root = parser
+            .expr()
+            .and_then(|root| {
+                parser.skip_whitespaces();
+                match parser.chars.peek() {
+                    Some(c) => Err(format!("unexpected {c:?}")),
+                    None => Ok(root),
+                }
+            })
+            .map_err(|e| format!("{expression:?} is not a valid expression: {e}."))?;
+        Ok(Self {
+            source: expression.trim().into(),
+            root,
+        })
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl Expression {
+    /// Evaluate the expression on the SLOC, PLOC and complexity of a file
+    pub fn eval(&self, sloc: f64, ploc: f64, comp: f64) -> f64 {
+        self.root.eval(&[sloc, ploc, comp])
+    }
+}
+
+/// Threshold of a metric given as METRIC=EXPRESSION, e.g. crap=2*sqrt(sloc)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThresholdExpression {
+    // Index of the metric in the thresholds
+    pub metric: usize,
+    pub expression: Expression,
+}
+
+impl FromStr for ThresholdExpression {
+    type Err = String;
+
+    fn from_str(threshold_expression: &str) -> std::result::Result<Self, Self::Err> {
+        let (metric, expression) = threshold_expression.split_once('=').ok_or(format!(
+            "{threshold_expression:?} is not in the METRIC=EXPRESSION form."
+        ))?;
+        let metric = THRESHOLD_METRICS
+            .iter()
+            .position(|m| *m == metric.trim())
+            .ok_or(format!(
+                "{metric:?} is not a metric, it must be one of {}.",
+                THRESHOLD_METRICS.join(", ")
+            ))?;
+        Ok(Self {
+            metric,
+            expression: expression.parse()?,
+        })
+    }
+}
+
+/// Thresholds of the metrics computed for each file by their expressions,
+/// the metrics without an expression keep their constant threshold
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ThresholdExpressions([Option<Expression>; 4]);
+
+impl ThresholdExpressions {
+    /// Set the expression of the threshold of a metric, it replaces a previous one
+    pub fn expression(mut self, threshold_expression: ThresholdExpression) -> Self {
+        self.0[threshold_expression.metric] = Some(threshold_expression.expression);
+        self
+    }
+
+    /// Get the thresholds of a file given its SLOC, PLOC and complexity
+    /// An expression evaluated to a negative or not finite value falls back to the constant threshold
+    pub fn thresholds(&self, thresholds: &[f64], sloc: f64, ploc: f64, comp: f64) -> Vec<f64> {
+        thresholds
+            .iter()
+            .zip(&self.0)
+            .zip(THRESHOLD_METRICS)
+            .map(|((threshold, expression), metric)| match expression {
+                Some(expression) => {
+                    let value = expression.eval(sloc, ploc, comp);
+                    if value.is_finite() && value >= 0. {
+                        value
+                    } else {
+                        warn!(
+                            "The {} threshold {} is {} with sloc {}, ploc {}, comp {}, the constant one is used",
+                            metric, expression, value, sloc, ploc, comp
+                        );
+                        *threshold
+                    }
+                }
+                None => *threshold,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utility::compare_float;
+
+    #[test]
+    fn test_expressions() {
+        let eval = |e: &str| e.parse::<Expression>().unwrap().eval(16., 9., 4.);
+        assert!(compare_float(eval("2*sqrt(sloc)"), 8.));
+        assert!(compare_float(eval("1 + 2 * 3 - 4 / 2"), 5.));
+        assert!(compare_float(eval("(1 + 2) * 3"), 9.));
+        assert!(compare_float(eval("2 ^ 3 ^ 2"), 512.));
+        assert!(compare_float(eval("-comp ^ 2"), -16.));
+        assert!(compare_float(eval("max(ploc, comp * 3) + min(1, -2)"), 10.));
+        assert!(compare_float(eval("log2(sloc) + abs(-0.5)"), 4.5));
+        for invalid in [
+            "",
+            "2 *",
+            "lines",
+            "sqrt(1, 2)",
+            "max(1)",
+            "(1 + 2",
+            "1 2",
+            "1.2.3",
+        ] {
+            assert!(invalid.parse::<Expression>().is_err(), "{invalid}");
+        }
+        // The metrics without an expression and the invalid values keep the constant thresholds
+        let expressions = ThresholdExpressions::default()
+            .expression("crap=2*sqrt(sloc)".parse().unwrap())
+            .expression("skunk = ln(comp - 4)".parse().unwrap());
+        assert_eq!(
+            expressions.thresholds(&[30., 1.5, 35., 30.], 16., 9., 4.),
+            vec![30., 1.5, 8., 30.]
+        );
+        assert!("cyclomatic=sloc".parse::<ThresholdExpression>().is_err());
+        assert!("crap".parse::<ThresholdExpression>().is_err());
+    }
+}
@@ -1,16 +1,29 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::path::*;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Instant, SystemTime};
 
+use chrono::{DateTime, Utc};
 use crossbeam::channel::{unbounded, Receiver, Sender};
+use globset::{GlobBuilder, GlobMatcher};
+use rust_code_analysis::read_file;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use tracing::debug;
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
 
+use crate::config::CoverageFloors;
 use crate::error::*;
+use crate::expression::THRESHOLD_METRICS;
+use crate::metrics::provider::{from_checked_root, ComplexityMetrics};
+use crate::modes::*;
 use crate::utility::*;
 
 /// Struct containing all the metrics
@@ -22,6 +35,9 @@ pub struct Metrics {
     pub skunk: f64,
     pub is_complex: bool,
     pub coverage: f64,
+    // CRAP or SKUNK have been saturated to the max metric value
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub complexity_too_high: bool,
 }
 
 impl Metrics {
@@ -40,6 +56,19 @@ impl Metrics {
             skunk,
             is_complex,
             coverage,
+            complexity_too_high: false,
+        }
+    }
+
+    // Compare two metrics by the chosen one, the greater value first
+    // Path is not a metric, so they compare as equal
+    pub(crate) fn compare(&self, other: &Self, sort_by: Sort) -> Ordering {
+        match sort_by {
+            Sort::WccPlain => other.wcc_plain.total_cmp(&self.wcc_plain),
+            Sort::WccQuantized => other.wcc_quantized.total_cmp(&self.wcc_quantized),
+            Sort::Crap => other.crap.total_cmp(&self.crap),
+            Sort::Skunk => other.skunk.total_cmp(&self.skunk),
+            Sort::Path => Ordering::Equal,
         }
     }
 
@@ -51,6 +80,7 @@ impl Metrics {
             skunk: f64::MAX,
             is_complex: false,
             coverage: 100.0,
+            complexity_too_high: false,
         }
     }
 
@@ -78,6 +108,10 @@ impl Metrics {
         self.coverage = coverage;
         self
     }
+    pub fn complexity_too_high(mut self, complexity_too_high: bool) -> Self {
+        self.complexity_too_high = complexity_too_high;
+        self
+    }
 }
 
 /// Struct with all the metrics computed for a single file
@@ -87,6 +121,74 @@ pub struct FileMetrics {
     pub metrics: Metrics,
     pub file: String,
     pub file_path: String,
+    // The file has no lines that can be covered
+    #[serde(default)]
+    pub no_coverable_lines: bool,
+    // Category of the file, only when the categories are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    // Coverable lines excluded from the file, only when the line exclusions are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excluded_lines: Option<usize>,
+    // Coverable lines of the items behind inactive cfg features, only when the active features are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfg_excluded_lines: Option<usize>,
+    // Percentage of the lines of the file that are comments, only when it is requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment_ratio: Option<f64>,
+    // Number of the other files that depend on the file, only when the complex files are ordered by it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fan_in: Option<usize>,
+    // Coverable lines of the file that are not covered, only when an uncovered lines budget is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uncovered_lines: Option<usize>,
+    // Percentage of the covered branches, only with covdir files with branch coverage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_coverage: Option<f64>,
+    // Number of tests exercising the file in the tests per file map, only with a tests per file map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tests: Option<usize>,
+    // CRAP weighted by the number of tests of the file, only with a tests per file map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+    // Severity tier of the file by its distance over the thresholds, only when the tiers are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<Tier>,
+    // The file is left out of the project coverage, only when the no coverage weight globs are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage_exempt: Option<bool>,
+    // Sample the PROJECT row has been estimated from, only when the files are sampled,
+    // in the PROJECT row and in the sidecar files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<Estimate>,
+    // SLOC of the file, only when the lines are counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sloc: Option<usize>,
+    // Complexity of the file with the chosen metric, the sum over its spaces,
+    // only when the lines are counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<f64>,
+    // Covered lines of the file, or of the project in the PROJECT row, only when the lines are counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub covered_lines: Option<usize>,
+    // Coverable lines of the file, only when the lines are counted, always in the PROJECT row
+    // to weight the coverage of the aggregate reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverable_lines: Option<usize>,
+    // Complexity of the file left uncovered, complexity * (1 - coverage), only when the lines are counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage_debt: Option<f64>,
+    // Files skipped because the max runtime has been reached, only in the PROJECT row of an incomplete run
+    // and in its sidecar files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_skipped: Option<usize>,
+    // Rules of the path map with the number of paths each one has rewritten, only in the PROJECT row
+    // of a run with a path map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_map: Option<Vec<PathMapRule>>,
+    // Thresholds the file has been checked against, only when the categories or the expressions change them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thresholds: Option<Vec<f64>>,
 }
 
 impl FileMetrics {
@@ -95,14 +197,126 @@ impl FileMetrics {
             metrics,
             file,
             file_path,
+            ..Self::default()
         }
     }
 
+    pub fn no_coverable_lines(mut self, no_coverable_lines: bool) -> Self {
+        self.no_coverable_lines = no_coverable_lines;
+        self
+    }
+
+    pub fn category(mut self, category: Option<String>) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn excluded_lines(mut self, excluded_lines: Option<usize>) -> Self {
+        self.excluded_lines = excluded_lines;
+        self
+    }
+
+    pub fn cfg_excluded_lines(mut self, cfg_excluded_lines: Option<usize>) -> Self {
+        self.cfg_excluded_lines = cfg_excluded_lines;
+        self
+    }
+
+    pub fn comment_ratio(mut self, comment_ratio: Option<f64>) -> Self {
+        self.comment_ratio = comment_ratio;
+        self
+    }
+
+    pub fn fan_in(mut self, fan_in: Option<usize>) -> Self {
+        self.fan_in = fan_in;
+        self
+    }
+
+    pub fn uncovered_lines(mut self, uncovered_lines: Option<usize>) -> Self {
+        self.uncovered_lines = uncovered_lines;
+        self
+    }
+
+    pub fn branch_coverage(mut self, branch_coverage: Option<f64>) -> Self {
+        self.branch_coverage = branch_coverage;
+        self
+    }
+
+    pub fn tests(mut self, tests: Option<usize>) -> Self {
+        self.tests = tests;
+        self
+    }
+
+    pub fn priority(mut self, priority: Option<f64>) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn tier(mut self, tier: Option<Tier>) -> Self {
+        self.tier = tier;
+        self
+    }
+
+    pub fn coverage_exempt(mut self, coverage_exempt: Option<bool>) -> Self {
+        self.coverage_exempt = coverage_exempt;
+        self
+    }
+
+    pub fn estimate(mut self, estimate: Option<Estimate>) -> Self {
+        self.estimate = estimate;
+        self
+    }
+
+    pub fn sloc(mut self, sloc: Option<usize>) -> Self {
+        self.sloc = sloc;
+        self
+    }
+
+    pub fn complexity(mut self, complexity: Option<f64>) -> Self {
+        self.complexity = complexity;
+        self
+    }
+
+    pub fn covered_lines(mut self, covered_lines: Option<usize>) -> Self {
+        self.covered_lines = covered_lines;
+        self
+    }
+
+    pub fn coverable_lines(mut self, coverable_lines: Option<usize>) -> Self {
+        self.coverable_lines = coverable_lines;
+        self
+    }
+
+    pub fn coverage_debt(mut self, coverage_debt: Option<f64>) -> Self {
+        self.coverage_debt = coverage_debt;
+        self
+    }
+
+    pub fn files_skipped(mut self, files_skipped: Option<usize>) -> Self {
+        self.files_skipped = files_skipped;
+        self
+    }
+
+    pub fn path_map(mut self, path_map: Option<Vec<PathMapRule>>) -> Self {
+        self.path_map = path_map;
+        self
+    }
+
+    pub fn thresholds(mut self, thresholds: Option<Vec<f64>>) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Get the thresholds the file has been checked against, the given ones if they have not been changed
+    pub fn file_thresholds<'a>(&'a self, thresholds: &'a [f64]) -> &'a [f64] {
+        self.thresholds.as_deref().unwrap_or(thresholds)
+    }
+
     pub fn avg(m: Metrics) -> Self {
         Self {
             metrics: m,
             file: "AVG".into(),
             file_path: "-".into(),
+            ..Self::default()
         }
     }
 
@@ -111,6 +325,7 @@ impl FileMetrics {
             metrics: m,
             file: "MIN".into(),
             file_path: "-".into(),
+            ..Self::default()
         }
     }
 
@@ -119,35 +334,369 @@ impl FileMetrics {
             metrics: m,
             file: "MAX".into(),
             file_path: "-".into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Number of tests exercising each file of a project, e.g. obtained from the test metadata,
+/// used to rank higher the complex files exercised by few tests
+#[derive(Clone, Debug, Default)]
+pub struct TestCounts {
+    tests: Vec<(PathBuf, usize)>,
+    median: f64,
+}
+
+impl TestCounts {
+    /// Read the tests per file map, each line has a file, with the path relative to the project folder,
+    /// and the number of tests exercising it separated by whitespaces
+    /// Empty lines and lines starting with # are skipped
+    pub fn new(map: &str) -> Result<Self> {
+        let mut tests = map
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| -> Result<(PathBuf, usize)> {
+                let (file, count) = line
+                    .rsplit_once(char::is_whitespace)
+                    .ok_or(Error::TestCountsError(line.into()))?;
+                let count = count
+                    .parse::<usize>()
+                    .map_err(|_| Error::TestCountsError(line.into()))?;
+                let file = file.trim().trim_start_matches("./").trim_start_matches('/');
+                Ok((PathBuf::from(file), count))
+            })
+            .collect::<Result<Vec<(PathBuf, usize)>>>()?;
+        tests.sort_by_key(|t| t.1);
+        let median = tests.get(tests.len() / 2).map_or(0., |t| t.1 as f64);
+        Ok(Self { tests, median })
+    }
+
+    pub fn from_file<A: AsRef<Path>>(path: A) -> Result<Self> {
+        Self::new(&fs::read_to_string(path)?)
+    }
+
+    /// Get the number of tests exercising a file given its path relative to the project folder
+    pub fn tests(&self, file_path: &str) -> Option<usize> {
+        let file_path = Path::new(file_path.trim_start_matches('/'));
+        self.tests
+            .iter()
+            .find(|(file, _)| file_path == file)
+            .map(|(_, tests)| *tests)
+    }
+
+    /// Get the weight of a number of tests, ln(1 + median) / ln(1 + tests) with the median of the map:
+    /// 1 for the median and for the files absent from the map, more for the files with fewer tests
+    /// and infinite for the files without tests
+    pub fn weight(&self, tests: Option<usize>) -> f64 {
+        tests.map_or(1., |tests| get_log_ratio(self.median, tests as f64))
+    }
+}
+
+/// Severity tier of a file, from the most to the least severe
+#[derive(Copy, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    Critical,
+    High,
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Tier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Tier::Critical => "critical",
+            Tier::High => "high",
+            Tier::Medium => "medium",
+            Tier::Low => "low",
+        };
+        s.fmt(f)
+    }
+}
+
+/// Boundaries of the severity tiers, given as the multipliers of the thresholds
+/// above which a file is critical, high and medium separated by commas, e.g. 3,2,1
+/// A file up to the medium multiplier is low
+#[derive(Copy, Debug, Clone, PartialEq)]
+pub struct Tiers([f64; 3]);
+
+impl FromStr for Tiers {
+    type Err = String;
+
+    fn from_str(tiers: &str) -> std::result::Result<Self, Self::Err> {
+        let multipliers = tiers
+            .split(',')
+            .map(|m| {
+                m.trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|m| *m >= 0.)
+                    .ok_or(format!("{m:?} is not a valid multiplier."))
+            })
+            .collect::<std::result::Result<Vec<f64>, Self::Err>>()?;
+        match multipliers.as_slice() {
+            [critical, high, medium] if critical >= high && high >= medium => {
+                Ok(Self([*critical, *high, *medium]))
+            }
+            _ => Err(format!(
+                "{tiers:?} must be three decreasing multipliers for critical, high and medium."
+            )),
+        }
+    }
+}
+
+impl Tiers {
+    /// Get the tier of a file given its distance over the thresholds,
+    /// the highest ratio of its metrics to their thresholds
+    /// As for the thresholds, a file in a tier is strictly above its multiplier
+    pub fn tier(&self, ratio: f64) -> Tier {
+        let [critical, high, medium] = self.0;
+        if ratio > critical {
+            Tier::Critical
+        } else if ratio > high {
+            Tier::High
+        } else if ratio > medium {
+            Tier::Medium
+        } else {
+            Tier::Low
+        }
+    }
+}
+
+/// Deadline of the whole analysis, shared by the consumer threads
+/// The files reached after it are skipped, so a run over its max runtime still outputs
+/// the metrics of the files analyzed so far, marked as incomplete
+#[derive(Clone, Debug)]
+pub struct Deadline {
+    at: Instant,
+    skipped: Arc<AtomicUsize>,
+}
+
+impl Deadline {
+    /// Create a deadline expiring at the given instant, e.g. the start of the run plus its max runtime
+    pub fn new(at: Instant) -> Self {
+        Self {
+            at,
+            skipped: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Check if the deadline has expired
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    // Count a file as skipped if the deadline has expired
+    pub(crate) fn skip(&self) -> bool {
+        let expired = self.is_expired();
+        if expired {
+            self.skipped.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+        expired
+    }
+
+    /// Get the number of files skipped because of the deadline
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(AtomicOrdering::Relaxed)
+    }
+}
+
+/// Name, start and end of a stage of the analysis
+pub type Stage = (&'static str, SystemTime, SystemTime);
+
+/// Start and end of the stages of the analysis, e.g. the reading of the coverage,
+/// shared by the clones of the options so they can be exported as spans after the run
+#[derive(Clone, Debug, Default)]
+pub struct StageTimes(Arc<Mutex<Vec<Stage>>>);
+
+impl StageTimes {
+    // Run a stage of the analysis and record its start and end
+    pub(crate) fn record<T>(&self, name: &'static str, stage: impl FnOnce() -> T) -> T {
+        let start = SystemTime::now();
+        let res = stage();
+        if let Ok(mut stages) = self.0.lock() {
+            stages.push((name, start, SystemTime::now()));
+        }
+        res
+    }
+
+    /// Get the stages recorded so far, in the order they ended
+    pub fn get(&self) -> Vec<Stage> {
+        self.0
+            .lock()
+            .map_or_else(|_| Vec::new(), |stages| stages.clone())
+    }
+}
+
+/// Number of files with coverage to analyze, sampled for a quick estimate of the project coverage
+/// The same seed always gives the same sample of a project
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub size: usize,
+    pub seed: u64,
+}
+
+impl Sample {
+    pub fn new(size: usize, seed: u64) -> Self {
+        Self { size, seed }
+    }
+}
+
+/// Sample of the files the metrics of the PROJECT row have been estimated from
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Estimate {
+    pub sampled_files: usize,
+    pub total_files: usize,
+    pub seed: u64,
+}
+
+// 64-bit FNV-1a hash, unlike the DefaultHasher it is the same with every Rust version and platform
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// Get the rank of a file in a sample, the FNV-1a hash of the seed and of its relative path with / separators
+fn get_sample_rank(seed: u64, file_path: &str) -> u64 {
+    fnv1a(
+        &[
+            &seed.to_le_bytes()[..],
+            file_path.replace('\\', "/").as_bytes(),
+        ]
+        .concat(),
+    )
+}
+
+// Sample the source files with coverage when the sample of the options is smaller than them,
+// ranking the files by the hash of the seed and of their path relative to the project folder,
+// so that the sample depends neither on the order nor on the location of the files
+fn sample_files<V>(
+    files: Vec<String>,
+    covs: &HashMap<String, V>,
+    prefix: &str,
+    options: &AnalysisOptions,
+) -> (Vec<String>, Option<Estimate>) {
+    let sample = match options.sample {
+        Some(sample) => sample,
+        None => return (files, None),
+    };
+    let mut files = files
+        .into_iter()
+        .filter(|file| covs.contains_key(file))
+        .map(|file| {
+            (
+                get_sample_rank(sample.seed, file.strip_prefix(prefix).unwrap_or(&file)),
+                file,
+            )
+        })
+        .collect::<Vec<(u64, String)>>();
+    let total_files = files.len();
+    if sample.size >= total_files {
+        debug!(
+            "The sample of {} files has all the {} files",
+            sample.size, total_files
+        );
+        return (files.into_iter().map(|(_, file)| file).collect(), None);
+    }
+    files.sort();
+    files.truncate(sample.size);
+    debug!("Sampled {} files of {}", sample.size, total_files);
+    let estimate = Estimate {
+        sampled_files: sample.size,
+        total_files,
+        seed: sample.seed,
+    };
+    (
+        files.into_iter().map(|(_, file)| file).collect(),
+        Some(estimate),
+    )
+}
+
+// Get the files skipped because the deadline has expired, None if the results are complete
+pub(crate) fn get_files_skipped(composer: &JobComposer) -> Option<usize> {
+    (composer.files_skipped > 0).then_some(composer.files_skipped)
+}
+
+// Check if a file must be skipped because the deadline of the analysis has expired
+pub(crate) fn is_past_deadline(options: &AnalysisOptions) -> bool {
+    options.deadline.as_ref().is_some_and(Deadline::skip)
+}
+
+/// Reason why a file is left out of the metrics
+#[derive(Copy, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IgnoreReason {
+    /// The file has no entry in the coverage files
+    NoCoverage,
+    /// The analysis of the file took more than the file timeout
+    Timeout,
+    /// The file has syntax errors and the analysis is strict
+    SyntaxErrors,
+    /// The file has no coverable lines and the files without them are ignored
+    NoCoverableLines,
+}
+
+impl fmt::Display for IgnoreReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            IgnoreReason::NoCoverage => "no coverage",
+            IgnoreReason::Timeout => "timeout",
+            IgnoreReason::SyntaxErrors => "syntax errors",
+            IgnoreReason::NoCoverableLines => "no coverable lines",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// File left out of the metrics, with the reason why
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FileIgnored {
+    pub file: String,
+    pub reason: IgnoreReason,
+}
+
+impl FileIgnored {
+    pub(crate) fn new(file: impl Into<String>, reason: IgnoreReason) -> Self {
+        Self {
+            file: file.into(),
+            reason,
         }
     }
 }
 
-type Output = (Vec<FileMetrics>, Vec<String>, Vec<FileMetrics>, f64);
+pub(crate) type Output = (Vec<FileMetrics>, Vec<FileIgnored>, Vec<FileMetrics>, f64);
 
 // Job received by the consumer threads
 #[derive(Clone)]
 struct JobItem {
     chunk: Vec<String>,
-    covs: HashMap<String, Vec<Value>>,
+    covs: Arc<CoverageData>,
     metric: Complexity,
-    prefix: usize,
+    files_path: PathBuf,
     thresholds: Vec<f64>,
+    options: AnalysisOptions,
 }
 impl JobItem {
     fn new(
         chunk: Vec<String>,
-        covs: HashMap<String, Vec<Value>>,
+        covs: Arc<CoverageData>,
         metric: Complexity,
-        prefix: usize,
+        files_path: PathBuf,
         thresholds: Vec<f64>,
+        options: AnalysisOptions,
     ) -> Self {
         Self {
             chunk,
             covs,
             metric,
-            prefix,
+            files_path,
             thresholds,
+            options,
         }
     }
 }
@@ -156,13 +705,13 @@ impl fmt::Debug for JobItem {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Job: chunks:{:?}, metric:{}, prefix:{:?}, thresholds: {:?}",
-            self.chunk, self.metric, self.prefix, self.thresholds
+            "Job: chunks:{:?}, metric:{}, files_path:{:?}, thresholds: {:?}",
+            self.chunk, self.metric, self.files_path, self.thresholds
         )
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub(crate) struct JobComposer {
     pub(crate) covered_lines: f64,
     pub(crate) total_lines: f64,
@@ -170,7 +719,38 @@ pub(crate) struct JobComposer {
     pub(crate) wcc_quantized_sum: f64,
     pub(crate) ploc_sum: f64,
     pub(crate) comp_sum: f64,
+    pub(crate) max_reachable: [f64; 4],
+    // Files skipped because the deadline has expired
+    pub(crate) files_skipped: usize,
+}
+
+impl JobComposer {
+    // Add the values of another composer, the max reachable values are the highest ones
+    pub(crate) fn add(&mut self, other: &JobComposer) {
+        self.covered_lines += other.covered_lines;
+        self.total_lines += other.total_lines;
+        self.wcc_plain_sum += other.wcc_plain_sum;
+        self.wcc_quantized_sum += other.wcc_quantized_sum;
+        self.ploc_sum += other.ploc_sum;
+        self.comp_sum += other.comp_sum;
+        self.files_skipped += other.files_skipped;
+        self.max_reachable
+            .iter_mut()
+            .zip(other.max_reachable)
+            .for_each(|(current, m)| *current = current.max(m));
+    }
+
+    // Update the highest values a file could reach for each metric
+    // The order is the same of the thresholds: WCC PLAIN, WCC QUANTIZED, CRAP, SKUNK
+    fn update_max_reachable(&mut self, comp: f64, sloc: f64, ploc: f64) {
+        let max = get_max_reachable_values(comp, sloc, ploc);
+        self.max_reachable
+            .iter_mut()
+            .zip(max)
+            .for_each(|(current, m)| *current = current.max(m));
+    }
 }
+
 pub(crate) type ComposerReceiver = Receiver<Option<JobComposer>>;
 pub(crate) type ComposerSender = Sender<Option<JobComposer>>;
 
@@ -181,6 +761,8 @@ pub(crate) fn composer(receiver: ComposerReceiver) -> Result<JobComposer> {
     let mut wcc_quantized_sum = 0.0;
     let mut ploc_sum = 0.0;
     let mut comp_sum = 0.0;
+    let mut max_reachable = [0.0; 4];
+    let mut files_skipped = 0;
     while let Ok(job) = receiver.recv() {
         if job.is_none() {
             break;
@@ -192,6 +774,11 @@ pub(crate) fn composer(receiver: ComposerReceiver) -> Result<JobComposer> {
         wcc_quantized_sum += job.wcc_quantized_sum;
         ploc_sum += job.ploc_sum;
         comp_sum += job.comp_sum;
+        files_skipped += job.files_skipped;
+        max_reachable
+            .iter_mut()
+            .zip(job.max_reachable)
+            .for_each(|(current, m): (&mut f64, f64)| *current = current.max(m));
     }
     Ok(JobComposer {
         covered_lines,
@@ -200,21 +787,36 @@ pub(crate) fn composer(receiver: ComposerReceiver) -> Result<JobComposer> {
         wcc_quantized_sum,
         ploc_sum,
         comp_sum,
+        max_reachable,
+        files_skipped,
     })
 }
 
+// Warn about the thresholds that no file of the project could ever exceed,
+// because in that case the threshold is disabled without the user knowing it
+fn warn_unreachable_thresholds(composer_output: &JobComposer, thresholds: &[f64]) {
+    get_unreachable_thresholds(&composer_output.max_reachable, thresholds)
+        .iter()
+        .for_each(|(metric, threshold, max)| {
+            warn!(
+                "The {} threshold {} can not be exceeded by any file, the maximum reachable value is {:.3}",
+                metric, threshold, max
+            );
+        });
+}
+
 // Configuration shared by all threads with all the data that must be returned
 #[derive(Clone, Default, Debug)]
 pub struct Config {
     pub(crate) res: Arc<Mutex<Vec<FileMetrics>>>,
-    pub(crate) files_ignored: Arc<Mutex<Vec<String>>>,
+    pub(crate) files_ignored: Arc<Mutex<Vec<FileIgnored>>>,
 }
 
 impl Config {
     fn new() -> Self {
         Self {
             res: Arc::new(Mutex::new(Vec::<FileMetrics>::new())),
-            files_ignored: Arc::new(Mutex::new(Vec::<String>::new())),
+            files_ignored: Arc::new(Mutex::new(Vec::<FileIgnored>::new())),
         }
     }
     fn clone(&self) -> Self {
@@ -225,9 +827,460 @@ impl Config {
     }
 }
 
+// Source files of a project folder split by whether a coverage entry matches them
+#[derive(Clone, Debug, Default, PartialEq)]
+struct SourceFiles {
+    matched: Vec<String>,
+    unmatched: Vec<String>,
+}
+
+// Hash of the coverage entries and project folder
+type SourceFilesKey = (u64, PathBuf);
+
+/// Cache of the matching between the coverage entries and the source files of a project folder,
+/// keyed by the hash of the entries and the project folder
+/// The clones of the options share the same cache, so it lasts as long as the options holding it
+#[derive(Clone, Debug, Default)]
+pub struct SourceFilesCache(Arc<Mutex<HashMap<SourceFilesKey, Arc<SourceFiles>>>>);
+
+// Key of the source files cache, the entries are sorted to not depend on the coverage order
+fn get_source_files_key<'a>(
+    files_path: &Path,
+    entries: impl Iterator<Item = &'a String>,
+) -> SourceFilesKey {
+    let mut entries = entries.collect::<Vec<&String>>();
+    entries.sort();
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    (hasher.finish(), files_path.to_path_buf())
+}
+
+// Get the source files of the project folder to match with the coverage entries,
+// only the listed ones if a list is given and without the excluded ones
+pub(crate) fn get_source_files<'a>(
+    files_path: &Path,
+    entries: impl Iterator<Item = &'a String>,
+    options: &AnalysisOptions,
+) -> Result<Vec<String>> {
+    let files = get_all_source_files(files_path, entries, options)?;
+    if options.exclude.is_none() && options.files_from.is_none() {
+        return Ok(files);
+    }
+    let prefix = &get_files_prefix(files_path);
+    Ok(files
+        .into_iter()
+        .filter(|file| {
+            let file_path = file.strip_prefix(prefix).unwrap_or(file);
+            let excluded = options.is_excluded(file_path) || !options.is_listed(file_path);
+            if excluded {
+                debug!("File {:?} excluded", file);
+            }
+            !excluded
+        })
+        .collect())
+}
+
+// Match the source files of the project folder with the coverage entries
+fn match_source_files<'a>(
+    files_path: &Path,
+    entries: impl Iterator<Item = &'a String>,
+) -> Result<SourceFiles> {
+    let entries = entries.collect::<HashSet<&String>>();
+    let (matched, unmatched) = read_files(files_path)?
+        .into_iter()
+        .partition(|file| entries.contains(file));
+    Ok(SourceFiles { matched, unmatched })
+}
+
+// Get all the source files of the project folder, the ones matching a coverage entry first
+// With a cache in the options the files are matched only once
+// for each coverage file content and project folder
+fn get_all_source_files<'a>(
+    files_path: &Path,
+    entries: impl Iterator<Item = &'a String>,
+    options: &AnalysisOptions,
+) -> Result<Vec<String>> {
+    let files = match &options.source_files_cache {
+        Some(SourceFilesCache(cache)) => {
+            let entries = entries.collect::<Vec<&String>>();
+            let key = get_source_files_key(files_path, entries.iter().copied());
+            let cached = cache.lock()?.get(&key).cloned();
+            match cached {
+                Some(files) => {
+                    debug!("Source files of {:?} found in cache", files_path);
+                    files
+                }
+                None => {
+                    let files = Arc::new(match_source_files(files_path, entries.into_iter())?);
+                    cache.lock()?.insert(key, Arc::clone(&files));
+                    files
+                }
+            }
+        }
+        None => Arc::new(match_source_files(files_path, entries)?),
+    };
+    Ok(files
+        .matched
+        .iter()
+        .chain(files.unmatched.iter())
+        .cloned()
+        .collect())
+}
+
 type JobReceiver = Receiver<Option<JobItem>>;
 
-// Consumer function run by ead independent thread
+// Check if the lines of a file are left out of the project coverage, None without no coverage weight globs
+fn is_coverage_exempt(file_path: &str, options: &AnalysisOptions) -> Option<bool> {
+    options
+        .no_coverage_weight
+        .as_ref()
+        .map(|globs| globs.is_match(file_path.trim_start_matches('/')))
+}
+
+// Get the thresholds of a file from the expressions of the options, given its SLOC, PLOC and complexity
+// Without expressions the thresholds are not changed
+fn get_file_thresholds(
+    root: &ComplexityMetrics,
+    comp: f64,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> Vec<f64> {
+    match &options.threshold_expressions {
+        Some(expressions) => expressions.thresholds(thresholds, root.sloc, root.ploc, comp),
+        None => thresholds.to_vec(),
+    }
+}
+
+// Get the complexity of a file left uncovered, a file without coverable lines has no debt
+fn get_file_coverage_debt(comp: f64, covered_lines: f64, coverable_lines: f64) -> f64 {
+    if coverable_lines == 0. {
+        0.
+    } else {
+        comp * (1. - covered_lines / coverable_lines)
+    }
+}
+
+// Get the thresholds of a file to store with its metrics, None when they are the given ones
+fn get_changed_thresholds(file_thresholds: &[f64], thresholds: &[f64]) -> Option<Vec<f64>> {
+    (file_thresholds != thresholds).then(|| file_thresholds.to_vec())
+}
+
+// Get the category of a file and its thresholds scaled by the multiplier of the category
+// Without categories the thresholds are not changed
+fn get_file_category(
+    file_path: &str,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> (Option<String>, Vec<f64>) {
+    match &options.categories {
+        Some(categories) => {
+            let category = categories.category(file_path.trim_start_matches('/'));
+            (
+                Some(category.to_string()),
+                categories.thresholds(category, thresholds),
+            )
+        }
+        None => (None, thresholds.to_vec()),
+    }
+}
+
+// Remove the line ranges excluded from a file from its coverable lines
+// The ranges must be inside the file, it returns the number of coverable lines excluded
+// or None if the file has no line exclusions
+fn exclude_lines(
+    path: &Path,
+    file_path: &str,
+    arr: &mut [Value],
+    options: &AnalysisOptions,
+) -> Result<Option<usize>> {
+    let ranges = match options
+        .line_exclusions
+        .as_ref()
+        .and_then(|e| e.ranges(file_path))
+    {
+        Some(ranges) => ranges,
+        None => return Ok(None),
+    };
+    let file_lines = String::from_utf8_lossy(&read_file(path)?).lines().count();
+    let mut excluded = 0;
+    for (start, end) in ranges {
+        if *end > file_lines {
+            return Err(Error::LineExclusionsError(format!(
+                "{file_path}: range {start}-{end} is beyond the {file_lines} lines of the file"
+            )));
+        }
+        arr.iter_mut()
+            .take(*end)
+            .skip(start - 1)
+            .filter(|line| !line.is_null() && line.as_i64() != Some(-1))
+            .for_each(|line| {
+                *line = Value::Null;
+                excluded += 1;
+            });
+    }
+    debug!("File: {:?} excluded lines: {}", file_path, excluded);
+    Ok(Some(excluded))
+}
+
+// Remove the lines of the items behind inactive cfg features from the coverable lines of a file,
+// so that the code disabled in the coverage run is not counted as uncovered
+// It returns the number of coverable lines excluded or None without active features
+fn exclude_cfg_lines(
+    path: &Path,
+    file_path: &str,
+    arr: &mut [Value],
+    options: &AnalysisOptions,
+) -> Result<Option<usize>> {
+    let features = match &options.active_features {
+        Some(features) => features,
+        None => return Ok(None),
+    };
+    let mut excluded = 0;
+    for (start, end) in get_cfg_excluded_ranges(path, features)? {
+        arr.iter_mut()
+            .take(end)
+            .skip(start - 1)
+            .filter(|line| !line.is_null() && line.as_i64() != Some(-1))
+            .for_each(|line| {
+                *line = Value::Null;
+                excluded += 1;
+            });
+    }
+    debug!("File: {:?} lines excluded by cfg: {}", file_path, excluded);
+    Ok(Some(excluded))
+}
+
+// Get the uncovered lines of a file and mark the file as complex when they are more than its budget,
+// the one of the last matching pattern of the budgets or the global one
+// The uncovered lines are None without any budget
+fn apply_uncovered_lines_budget(
+    m: Metrics,
+    file_path: &str,
+    arr: &[Value],
+    root: &ComplexityMetrics,
+    options: &AnalysisOptions,
+) -> Result<(Metrics, Option<usize>)> {
+    if options.max_uncovered_lines.is_none() && options.uncovered_lines_budgets.is_none() {
+        return Ok((m, None));
+    }
+    let (covered_lines, tot_lines) = get_covered_lines(arr, root.start_line, root.end_line)?;
+    let uncovered_lines = (tot_lines - covered_lines) as usize;
+    let budget = options
+        .uncovered_lines_budgets
+        .as_ref()
+        .and_then(|budgets| budgets.budget(file_path))
+        .or(options.max_uncovered_lines);
+    let is_complex = m.is_complex || budget.is_some_and(|budget| uncovered_lines > budget);
+    Ok((m.is_complex(is_complex), Some(uncovered_lines)))
+}
+
+// Get the number of tests and the priority, the CRAP weighted by the tests, of a file
+// and mark the file as complex when its priority is above the CRAP threshold,
+// so that a complex file exercised by few tests is flagged even when its coverage looks fine
+// Both are None without a tests per file map
+fn apply_test_counts(
+    m: Metrics,
+    file_path: &str,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> (Metrics, Option<usize>, Option<f64>) {
+    match &options.tests_per_file {
+        Some(test_counts) => {
+            let tests = test_counts.tests(file_path);
+            let priority =
+                get_priority(m.crap, test_counts.weight(tests), options.max_metric_value);
+            let is_complex = m.is_complex || priority > thresholds[2];
+            (m.is_complex(is_complex), tests, Some(priority))
+        }
+        None => (m, None, None),
+    }
+}
+
+// Get the distance of a file over the thresholds, the highest ratio of its metrics to their thresholds
+// The priority weighted by the tests counts as CRAP, a metric over a threshold of 0 is infinitely over it
+fn get_threshold_ratio(m: &Metrics, priority: Option<f64>, thresholds: &[f64]) -> f64 {
+    let crap = priority.map_or(m.crap, |priority| priority.max(m.crap));
+    [m.wcc_plain, m.wcc_quantized, crap, m.skunk]
+        .iter()
+        .zip(thresholds)
+        .map(|(value, threshold)| match *threshold {
+            t if t > 0. => value / t,
+            _ if *value > 0. => f64::INFINITY,
+            _ => 0.,
+        })
+        .fold(0., f64::max)
+}
+
+// Get the severity tier of a file from the tiers of the options, None without tiers
+fn get_tier(
+    m: &Metrics,
+    priority: Option<f64>,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> Option<Tier> {
+    options
+        .tiers
+        .map(|tiers| tiers.tier(get_threshold_ratio(m, priority, thresholds)))
+}
+
+// Get the percentage of the lines of a file that are comments, only when it is requested
+fn get_comment_ratio(root: &ComplexityMetrics, options: &AnalysisOptions) -> Option<f64> {
+    match options.show_comment_ratio {
+        true if root.sloc > 0. => Some(root.cloc / root.sloc * 100.),
+        true => Some(0.),
+        false => None,
+    }
+}
+
+// Get the complexity values of a file from the complexity provider of the options
+// within the file timeout of the options
+// A C/C++ file with a compile command is analyzed by rust-code-analysis with its macros
+fn get_complexity(path: &Path, options: &AnalysisOptions) -> Result<ComplexityMetrics> {
+    let provider = options.complexity_provider.clone();
+    let file = path.to_path_buf();
+    let command = options.compile_command(path).cloned();
+    analyze_with_timeout(path, options, move || match provider {
+        Some(provider) => provider.complexity(&file, &read_file(&file)?),
+        None => get_root_with_errors(&file, command.as_ref()).map(from_checked_root),
+    })
+}
+
+// Coverage of a single file, the covdir files also give the coverage of the whole file and of its branches
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FileCoverage {
+    pub(crate) arr: Vec<Value>,
+    pub(crate) coverage: Option<f64>,
+    pub(crate) branch_coverage: Option<f64>,
+}
+
+impl From<Vec<Value>> for FileCoverage {
+    fn from(arr: Vec<Value>) -> Self {
+        Self {
+            arr,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<&Covdir> for FileCoverage {
+    fn from(covdir: &Covdir) -> Self {
+        Self {
+            arr: covdir.arr.clone(),
+            coverage: Some(covdir.coverage),
+            branch_coverage: covdir.branch_coverage,
+        }
+    }
+}
+
+// Compute the metrics of a single file given its coverage, shared by the consumers of every format
+// It returns the metrics of the file and its contribution to the project metrics,
+// or the reason why the file must be added to the files ignored
+pub(crate) fn get_file_metrics(
+    file: &str,
+    coverage: FileCoverage,
+    metric: Complexity,
+    files_path: &Path,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> Result<std::result::Result<(FileMetrics, JobComposer), IgnoreReason>> {
+    let path = &*get_source_path(files_path, file);
+    let prefix = get_files_prefix(files_path).len();
+    let file_name = path
+        .file_name()
+        .ok_or(Error::PathConversionError())?
+        .to_string_lossy()
+        .into();
+    let file_path = file.to_string().split_off(prefix);
+    let mut arr = coverage.arr;
+    let excluded_lines = exclude_lines(path, &file_path, &mut arr, options)?;
+    let cfg_excluded_lines = exclude_cfg_lines(path, &file_path, &mut arr, options)?;
+    // A file whose analysis times out is added to the files ignored
+    let Some(root) = skip_on_timeout(get_complexity(path, options))? else {
+        return Ok(Err(IgnoreReason::Timeout));
+    };
+    // With the strict analysis a file parsed only partially is added to the files ignored
+    if has_syntax_errors(path, root.has_syntax_errors, options)? {
+        return Ok(Err(IgnoreReason::SyntaxErrors));
+    }
+    // A file without coverable lines is either ignored or counted as fully covered
+    let no_coverable_lines = !has_coverable_lines(&arr, root.start_line, root.end_line);
+    if no_coverable_lines && options.empty_coverage == EmptyCoverage::Ignore {
+        return Ok(Err(IgnoreReason::NoCoverableLines));
+    }
+    let (covered_lines, tot_lines) = get_covered_lines(&arr, root.start_line, root.end_line)?;
+    debug!(
+        "File: {:?} covered lines: {}  total lines: {}",
+        file, covered_lines, tot_lines
+    );
+    let ploc = root.ploc;
+    let comp = match metric {
+        Complexity::Cyclomatic => root.cyclomatic_sum,
+        Complexity::Cognitive => root.cognitive_sum,
+    };
+    let expression_thresholds = get_file_thresholds(&root, comp, thresholds, options);
+    let (category, file_thresholds) =
+        get_file_category(&file_path, &expression_thresholds, options);
+    let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) = if no_coverable_lines {
+        get_metrics_without_coverable_lines(
+            &root,
+            metric,
+            &file_thresholds,
+            options.max_metric_value,
+        )?
+    } else {
+        Tree::get_metrics_from_space(
+            &root,
+            &arr,
+            metric,
+            coverage.coverage,
+            &file_thresholds,
+            options.max_metric_value,
+        )?
+    };
+    let (m, uncovered_lines) = apply_uncovered_lines_budget(m, &file_path, &arr, &root, options)?;
+    let (m, tests, priority) = apply_test_counts(m, &file_path, &file_thresholds, options);
+    let tier = get_tier(&m, priority, &file_thresholds, options);
+    let file_lines = options.line_counts.then_some((covered_lines, tot_lines));
+    // The lines of a coverage exempt file are left out of the project coverage
+    let coverage_exempt = is_coverage_exempt(&file_path, options);
+    let (covered_lines, tot_lines) = match coverage_exempt {
+        Some(true) => (0., 0.),
+        _ => (covered_lines, tot_lines),
+    };
+    let mut composer_output = JobComposer {
+        covered_lines,
+        total_lines: tot_lines,
+        wcc_plain_sum: sp_sum,
+        wcc_quantized_sum: sq_sum,
+        ploc_sum: ploc,
+        comp_sum: comp,
+        max_reachable: [0.0; 4],
+        files_skipped: 0,
+    };
+    composer_output.update_max_reachable(comp, root.sloc, ploc);
+    let file_metrics = FileMetrics::new(m, file_name, file_path)
+        .no_coverable_lines(no_coverable_lines)
+        .category(category)
+        .excluded_lines(excluded_lines)
+        .cfg_excluded_lines(cfg_excluded_lines)
+        .comment_ratio(get_comment_ratio(&root, options))
+        .uncovered_lines(uncovered_lines)
+        .branch_coverage(coverage.branch_coverage)
+        .tests(tests)
+        .priority(priority)
+        .tier(tier)
+        .coverage_exempt(coverage_exempt)
+        .sloc(options.line_counts.then_some(root.sloc as usize))
+        .complexity(options.line_counts.then_some(comp))
+        .covered_lines(file_lines.map(|(covered, _)| covered as usize))
+        .coverable_lines(file_lines.map(|(_, coverable)| coverable as usize))
+        .coverage_debt(
+            file_lines.map(|(covered, coverable)| get_file_coverage_debt(comp, covered, coverable)),
+        )
+        .thresholds(get_changed_thresholds(&file_thresholds, thresholds));
+    Ok(Ok((file_metrics, composer_output)))
+}
+
 fn consumer(receiver: JobReceiver, sender_composer: ComposerSender, cfg: &Config) -> Result<()> {
     // Get all shared data
     let files_ignored = &cfg.files_ignored;
@@ -242,51 +1295,41 @@ fn consumer(receiver: JobReceiver, sender_composer: ComposerSender, cfg: &Config
         let chunk = job.chunk;
         let covs = job.covs;
         let metric = job.metric;
-        let prefix = job.prefix;
+        let files_path = job.files_path;
         let thresholds = job.thresholds;
+        let options = job.options;
         // For each file in the chunk received
         for file in chunk {
-            let path = Path::new(&file);
-            let file_name = path
-                .file_name()
-                .ok_or(Error::PathConversionError())?
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .into();
+            // The files reached after the deadline are skipped
+            if is_past_deadline(&options) {
+                composer_output.files_skipped += 1;
+                continue;
+            }
             // Get the coverage vector from the coveralls file
             // if not present the file will be added to the files ignored
             let arr = match covs.get(&file) {
                 Some(arr) => arr.to_vec(),
                 None => {
                     let mut f = files_ignored.lock()?;
-                    f.push(file);
+                    f.push(FileIgnored::new(file, IgnoreReason::NoCoverage));
                     continue;
                 }
             };
-            let root = get_root(path)?;
-            let (covered_lines, tot_lines) =
-                get_covered_lines(&arr, root.start_line, root.end_line)?;
-            debug!(
-                "File: {:?} covered lines: {}  total lines: {}",
-                file, covered_lines, tot_lines
-            );
-            let ploc = root.metrics.loc.ploc();
-            let comp = match metric {
-                Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-                Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-            };
-            let file_path = file.clone().split_off(prefix);
             // Upgrade all the global variables and add metrics to the result and complex_files
-            let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
-                Tree::get_metrics_from_space(&root, &arr, metric, None, &thresholds)?;
-            let mut res = res.lock()?;
-            composer_output.covered_lines += covered_lines;
-            composer_output.total_lines += tot_lines;
-            composer_output.ploc_sum += ploc;
-            composer_output.wcc_plain_sum += sp_sum;
-            composer_output.wcc_quantized_sum += sq_sum;
-            composer_output.comp_sum += comp;
-            res.push(FileMetrics::new(m, file_name, file_path));
+            match get_file_metrics(
+                &file,
+                arr.into(),
+                metric,
+                &files_path,
+                &thresholds,
+                &options,
+            )? {
+                Ok((file_metrics, file_composer)) => {
+                    composer_output.add(&file_composer);
+                    res.lock()?.push(file_metrics);
+                }
+                Err(reason) => files_ignored.lock()?.push(FileIgnored::new(file, reason)),
+            }
         }
     }
     if let Err(_e) = sender_composer.send(Some(composer_output)) {
@@ -296,14 +1339,318 @@ fn consumer(receiver: JobReceiver, sender_composer: ComposerSender, cfg: &Config
     Ok(())
 }
 
-// Chunks the vector of files in multiple chunk to be used by threads
-// It will return number of chunk with the same number of elements usually equal
-// Or very close to n_threads
-fn chunk_vector(vec: Vec<String>, n_threads: usize) -> Vec<Vec<String>> {
-    let chunks = vec.chunks((vec.len() / n_threads).max(1));
-    chunks
-        .map(|chunk| chunk.iter().map(|c| c.into()).collect::<Vec<String>>())
-        .collect::<Vec<Vec<String>>>()
+/// Rule of the path map with the number of paths of the coverage it has rewritten in a run
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PathMapRule {
+    pub from: String,
+    pub to: String,
+    pub rewritten: usize,
+}
+
+/// Rules to rewrite the paths of the coverage files before they are matched with the files
+/// of the project folder, e.g. when the coverage has been collected on another machine
+/// The number of paths rewritten by each rule is shared by the clones of the path map
+#[derive(Clone, Debug, Default)]
+pub struct PathMap {
+    rules: Vec<(String, String)>,
+    rewritten: Arc<Mutex<Vec<usize>>>,
+}
+
+impl PathMap {
+    /// Read the path map, each line has a rule given as from=to, the paths are absolute
+    /// or relative to the project folder, as written in the coverage files
+    /// Empty lines and lines starting with # are skipped, the rules are tried in order
+    /// and the first one whose from is a prefix of a path replaces it with to
+    pub fn new(map: &str) -> Result<Self> {
+        let rules = map
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| -> Result<(String, String)> {
+                let (from, to) = line
+                    .split_once('=')
+                    .filter(|(from, _)| !from.trim().is_empty())
+                    .ok_or(Error::PathMapError(line.into()))?;
+                Ok((from.trim().into(), to.trim().into()))
+            })
+            .collect::<Result<Vec<(String, String)>>>()?;
+        let rewritten = Arc::new(Mutex::new(vec![0; rules.len()]));
+        Ok(Self { rules, rewritten })
+    }
+
+    pub fn from_file<A: AsRef<Path>>(path: A) -> Result<Self> {
+        Self::new(&fs::read_to_string(path)?)
+    }
+
+    pub fn rules(&self) -> &[(String, String)] {
+        &self.rules
+    }
+
+    // Add the number of paths rewritten by each rule in a coverage file
+    fn record(&self, rewritten: &[usize]) {
+        if let Ok(mut counts) = self.rewritten.lock() {
+            counts.iter_mut().zip(rewritten).for_each(|(n, r)| *n += r);
+        }
+    }
+
+    /// Get the rules with the number of paths they have rewritten so far
+    pub fn get_rules_rewritten(&self) -> Vec<PathMapRule> {
+        let rewritten = self
+            .rewritten
+            .lock()
+            .map_or_else(|_| Vec::new(), |counts| counts.clone());
+        self.rules
+            .iter()
+            .zip(rewritten)
+            .map(|((from, to), rewritten)| PathMapRule {
+                from: from.clone(),
+                to: to.clone(),
+                rewritten,
+            })
+            .collect()
+    }
+
+    /// Rewrite a path with the first matching rule
+    /// It returns the index of the rule and the new path, None if no rule matches
+    pub fn rewrite(&self, path: &str) -> Option<(usize, String)> {
+        self.rules.iter().enumerate().find_map(|(i, (from, to))| {
+            path.strip_prefix(from.as_str())
+                .map(|rest| (i, format!("{to}{rest}")))
+        })
+    }
+}
+
+// Rewrite the paths of a coverage with the path map of the options, prefix is the project folder
+// The paths are rewritten without the project folder and joined to it again, two paths
+// rewritten to the same one are a conflict
+// It returns the coverage and the number of paths rewritten by each rule
+fn rewrite_coverage_paths<V>(
+    covs: HashMap<String, V>,
+    prefix: &str,
+    path_map: &PathMap,
+) -> Result<(HashMap<String, V>, Vec<usize>)> {
+    let mut rewritten = vec![0; path_map.rules().len()];
+    let mut res = HashMap::<String, V>::new();
+    covs.into_iter()
+        .try_for_each(|(name, value)| -> Result<()> {
+            let path = name.strip_prefix(prefix).unwrap_or(&name);
+            let name = match path_map.rewrite(path) {
+                Some((i, path)) if name != "PROJECT_ROOT" => {
+                    rewritten[i] += 1;
+                    Path::new(prefix)
+                        .join(path)
+                        .display()
+                        .to_string()
+                        .replace('\\', "/")
+                }
+                _ => name,
+            };
+            if res.contains_key(&name) {
+                return Err(Error::CoverageConflictError(name));
+            }
+            res.insert(name, value);
+            Ok(())
+        })?;
+    Ok((res, rewritten))
+}
+
+// Apply the path map of the options, if any, to the paths of a coverage
+pub(crate) fn apply_path_map<V>(
+    covs: HashMap<String, V>,
+    prefix: &str,
+    options: &AnalysisOptions,
+) -> Result<HashMap<String, V>> {
+    let path_map = match &options.path_map {
+        Some(path_map) => path_map,
+        None => return Ok(covs),
+    };
+    let (covs, rewritten) = rewrite_coverage_paths(covs, prefix, path_map)?;
+    path_map
+        .rules()
+        .iter()
+        .zip(&rewritten)
+        .for_each(|((from, to), n)| {
+            debug!("Path map rule {}={} rewrote {} paths", from, to, n);
+        });
+    path_map.record(&rewritten);
+    Ok(covs)
+}
+
+/// Find the coverage json files of a directory, e.g. one for each crate of a workspace,
+/// the format of each one is detected from its content
+/// It returns the coverage files sorted by path, an error if there is none or one is not in a known format
+pub fn get_coverage_dir_inputs<A: AsRef<Path>>(dir: A) -> Result<Vec<CoverageInput>> {
+    let dir = dir.as_ref();
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<PathBuf>>>()?;
+    paths.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+    if paths.is_empty() {
+        return Err(Error::EmptyCoverageDirError(dir.display().to_string()));
+    }
+    let inputs = paths
+        .into_iter()
+        .map(|path| {
+            let format = detect_json_format(&fs::read_to_string(&path)?)
+                .ok_or_else(|| Error::CoverageFormatError(path.display().to_string()))?;
+            debug!("Found {:?} coverage file: {:?}", format, path);
+            Ok(CoverageInput::new(format, path))
+        })
+        .collect::<Result<Vec<CoverageInput>>>()?;
+    info!(
+        "Found {} coverage files in {:?}, they are merged",
+        inputs.len(),
+        dir
+    );
+    Ok(inputs)
+}
+
+// Read all the coverage files and merge them in a single coverage
+// The paths of each coverage file are rewritten with the path map of the options
+// If a file is present in more than one coverage file its lines are summed
+// or an error is returned, following the merge conflict of the options
+pub(crate) fn get_coverage_data(
+    inputs: &[CoverageInput],
+    prefix: &str,
+    options: &AnalysisOptions,
+) -> Result<CoverageData> {
+    let mut covs = CoverageData::new();
+    inputs.iter().try_for_each(|input| -> Result<()> {
+        debug!("Reading {:?} coverage file: {:?}", input.format, input.path);
+        apply_path_map(read_coverage(input, prefix)?, prefix, options)?
+            .into_iter()
+            .try_for_each(|(name, arr)| -> Result<()> {
+                match covs.get_mut(&name) {
+                    None => {
+                        covs.insert(name, arr);
+                    }
+                    Some(current) => match options.merge_conflict {
+                        MergeConflict::Error => return Err(Error::CoverageConflictError(name)),
+                        MergeConflict::Sum => sum_lines(current, &arr),
+                    },
+                }
+                Ok(())
+            })
+    })?;
+    Ok(covs)
+}
+
+// Source digest and branches of a source file of the coveralls files
+#[derive(Default)]
+struct CoverallsSourceFile {
+    source_digest: Option<Value>,
+    // Hits of each branch, keyed by its line, block and branch numbers
+    branches: Vec<([u64; 3], u64)>,
+}
+
+impl CoverallsSourceFile {
+    // Add the flat branches array of coveralls, [line, block, branch, hits, ...],
+    // summing the hits of the branches already present
+    fn add_branches(&mut self, branches: &[Value]) {
+        branches.chunks_exact(4).for_each(|branch| {
+            let values = branch
+                .iter()
+                .map(|v| v.as_u64().unwrap_or_default())
+                .collect::<Vec<u64>>();
+            let key = [values[0], values[1], values[2]];
+            match self.branches.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, hits)) => *hits += values[3],
+                None => self.branches.push((key, values[3])),
+            }
+        });
+    }
+
+    fn branches(&self) -> Vec<u64> {
+        self.branches
+            .iter()
+            .flat_map(|(key, hits)| key.iter().copied().chain(std::iter::once(*hits)))
+            .collect()
+    }
+}
+
+/// This Function get the folder of the repo to analyzed and a list of coverage files
+/// and merges them in a single coveralls payload, following the merge conflict of the options
+/// The top-level metadata of the coveralls files, e.g. service_job_id and git, is kept unchanged,
+/// if a field is present in more than one coveralls file the value of the first one is kept
+/// The names of the source files are relative to the project folder
+/// Each source file keeps the source digest of the first coveralls file with it, or the MD5
+/// of its source when it has none, and the branches of the coveralls files, with their hits summed
+pub fn get_coveralls_payload<A: AsRef<Path>>(
+    files_path: A,
+    inputs: &[CoverageInput],
+    options: &AnalysisOptions,
+) -> Result<Value> {
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let mut payload = CoverallsMetadata::new();
+    let mut files = HashMap::<String, CoverallsSourceFile>::new();
+    inputs
+        .iter()
+        .filter(|input| input.format == JsonFormat::Coveralls)
+        .try_for_each(|input| -> Result<()> {
+            let content = fs::read_to_string(&input.path)?;
+            read_coveralls_metadata(&content)?
+                .into_iter()
+                .for_each(|(key, value)| {
+                    payload.entry(key).or_insert(value);
+                });
+            apply_path_map(read_json_entries(&content, prefix)?, prefix, options)?
+                .into_iter()
+                .for_each(|(name, entry)| {
+                    let file = files.entry(name).or_default();
+                    if file.source_digest.is_none() {
+                        file.source_digest = entry.get("source_digest").cloned();
+                    }
+                    if let Some(branches) = entry["branches"].as_array() {
+                        file.add_branches(branches);
+                    }
+                });
+            Ok(())
+        })?;
+    let mut covs = get_coverage_data(inputs, prefix, options)?
+        .into_iter()
+        .collect::<Vec<(String, Vec<Value>)>>();
+    covs.sort_by(|a, b| a.0.cmp(&b.0));
+    // The files reached after the deadline are skipped
+    let source_files = covs
+        .into_iter()
+        .filter(|_| !is_past_deadline(options))
+        .map(|(name, coverage)| -> Result<Value> {
+            let mut file = files.remove(&name).unwrap_or_default();
+            let source_digest = match file.source_digest.take() {
+                Some(source_digest) => source_digest,
+                None => match fs::read(get_source_path(files_path.as_ref(), &name)) {
+                    Ok(source) => Value::from(get_source_digest(&source)),
+                    Err(_) => Value::Null,
+                },
+            };
+            let name = name.strip_prefix(prefix).unwrap_or(&name).to_string();
+            Ok(json!({
+                "name": name,
+                "source_digest": source_digest,
+                "coverage": coverage,
+                "branches": file.branches(),
+            }))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+    payload.insert("source_files".into(), Value::Array(source_files));
+    Ok(Value::Object(payload))
+}
+
+// Sum the hits of each line of two coverage arrays
+// A line that can be covered in one of the two arrays can be covered in the result
+fn sum_lines(current: &mut Vec<Value>, other: &[Value]) {
+    if current.len() < other.len() {
+        current.resize(other.len(), Value::Null);
+    }
+    current
+        .iter_mut()
+        .zip(other)
+        .for_each(|(line, other)| match (line.as_u64(), other.as_u64()) {
+            (Some(hits), Some(other)) => *line = Value::from(hits + other),
+            (None, Some(other)) => *line = Value::from(other),
+            _ => (),
+        });
 }
 
 /// This Function get the folder of the repo to analyzed and the path to the coveralls file obtained using grcov
@@ -317,21 +1664,73 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
     n_threads: usize,
     thresholds: &[f64],
     sort_by: Sort,
+    options: &AnalysisOptions,
 ) -> Result<Output> {
     if thresholds.len() != 4 {
         return Err(Error::ThresholdsError());
     }
-    // Take all the files starting from the given project folder
-    let vec = read_files(files_path.as_ref())?;
     // Read coveralls file to string and then get all the coverage vectors
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
-    )?;
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let covs = options.record_stage("reading coverage", || {
+        let file = fs::read_to_string(json_path)?;
+        apply_path_map(read_json(file, prefix)?, prefix, options)
+    })?;
+    get_metrics_concurrent_from_covs(
+        files_path.as_ref(),
+        covs,
+        metric,
+        n_threads,
+        thresholds,
+        sort_by,
+        options,
+    )
+}
+
+/// This Function get the folder of the repo to analyzed and a list of coverage files, each one with its format
+/// All the coverage files are merged in a single coverage before computing the metrics,
+/// the files present in more than one coverage file are handled following the merge conflict of the options
+/// It also takes as arguments the complexity metrics that must be used between cognitive or cyclomatic
+/// If the a file is not found in the coverage that files will be skipped
+/// It returns the  tuple (res, files_ignored, complex_files, project_coverage)
+pub fn get_metrics_concurrent_merged<A: AsRef<Path>>(
+    files_path: A,
+    inputs: &[CoverageInput],
+    metric: Complexity,
+    n_threads: usize,
+    thresholds: &[f64],
+    sort_by: Sort,
+    options: &AnalysisOptions,
+) -> Result<Output> {
+    if thresholds.len() != 4 {
+        return Err(Error::ThresholdsError());
+    }
+    let covs = options.record_stage("reading coverage", || {
+        get_coverage_data(inputs, &get_files_prefix(files_path.as_ref()), options)
+    })?;
+    get_metrics_concurrent_from_covs(
+        files_path.as_ref(),
+        covs,
+        metric,
+        n_threads,
+        thresholds,
+        sort_by,
+        options,
+    )
+}
+
+// Compute the metrics of all the files in the project folder using the given coverage
+fn get_metrics_concurrent_from_covs(
+    files_path: &Path,
+    covs: CoverageData,
+    metric: Complexity,
+    n_threads: usize,
+    thresholds: &[f64],
+    sort_by: Sort,
+    options: &AnalysisOptions,
+) -> Result<Output> {
+    // Take all the files starting from the given project folder
+    let vec = get_source_files(files_path, covs.keys(), options)?;
+    let (vec, estimate) = sample_files(vec, &covs, &get_files_prefix(files_path), options);
     let mut handlers = vec![];
     // Create a new vonfig with  all needed mutexes
     let cfg = Config::new();
@@ -351,22 +1750,19 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
         let h = thread::spawn(move || -> Result<()> { consumer(r, s, &config) });
         handlers.push(h);
     }
-    let prefix = files_path
-        .as_ref()
-        .to_str()
-        .ok_or(Error::PathConversionError())?
-        .to_string()
-        .len();
+    // The coverage is shared by all the jobs without copying it
+    let covs = Arc::new(covs);
     // Send all chunks to the consumers
     chunks
         .iter()
         .try_for_each(|chunk: &Vec<String>| -> Result<()> {
             let job = JobItem::new(
                 chunk.to_vec(),
-                covs.clone(),
+                Arc::clone(&covs),
                 metric,
-                prefix,
+                files_path.to_path_buf(),
                 thresholds.to_vec(),
+                options.clone(),
             );
             debug!("Sending job: {:?}", job);
             if let Err(_e) = sender.send(Some(job)) {
@@ -390,29 +1786,62 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
     if let Err(_e) = sender_composer.send(None) {
         return Err(Error::SenderError());
     }
-    let mut files_ignored = cfg.files_ignored.lock()?;
-    let mut res = cfg.res.lock()?;
+    let files_ignored = cfg.files_ignored.lock()?;
+    let res = cfg.res.lock()?;
     let composer_output = composer.join()??;
+    warn_unreachable_thresholds(&composer_output, thresholds);
+    get_files_output(
+        files_path,
+        (*res).clone(),
+        (*files_ignored).clone(),
+        composer_output,
+        sort_by,
+        estimate,
+        options,
+    )
+}
+
+// Sort the metrics of the files and add the project, average, maximum and minimum rows
+// It returns the tuple (res, files_ignored, complex_files, project_coverage)
+pub(crate) fn get_files_output(
+    files_path: &Path,
+    mut res: Vec<FileMetrics>,
+    mut files_ignored: Vec<FileIgnored>,
+    composer_output: JobComposer,
+    sort_by: Sort,
+    estimate: Option<Estimate>,
+    options: &AnalysisOptions,
+) -> Result<Output> {
+    let (covered_lines, coverable_lines) = (
+        composer_output.covered_lines as usize,
+        composer_output.total_lines as usize,
+    );
+    let files_skipped = get_files_skipped(&composer_output);
     let project_metric = FileMetrics::new(
-        get_project_metrics(composer_output, None)?,
+        get_project_metrics(composer_output, None, options.max_metric_value)?,
         "PROJECT".into(),
         "-".into(),
-    );
+    )
+    .estimate(estimate)
+    .covered_lines(options.line_counts.then_some(covered_lines))
+    .coverable_lines(Some(coverable_lines))
+    .files_skipped(files_skipped)
+    .path_map(options.path_map.as_ref().map(PathMap::get_rules_rewritten));
     let project_coverage = project_metric.metrics.coverage;
     files_ignored.sort();
-    res.sort_by(|a, b| a.file.cmp(&b.file));
+    if sort_by == Sort::Path {
+        res.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    } else {
+        res.sort_by(|a, b| a.file.cmp(&b.file));
+    }
+    set_fan_in(files_path, &mut res, options)?;
     // Get AVG MIN MAX and complex files
     let mut complex_files = res
         .iter()
         .filter(|m| m.metrics.is_complex)
         .cloned()
         .collect::<Vec<FileMetrics>>();
-    complex_files.sort_by(|a, b| match sort_by {
-        Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-        Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-        Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-        Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-    });
+    sort_complex_files(&mut complex_files, sort_by);
     let m = res
         .iter()
         .map(|metric| metric.metrics)
@@ -423,8 +1852,8 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
     res.push(FileMetrics::max(max));
     res.push(FileMetrics::min(min));
     Ok((
-        (*res).clone(),
-        (*files_ignored).clone(),
+        res,
+        files_ignored,
         complex_files,
         f64::round(project_coverage * 100.) / 100.,
     ))
@@ -433,26 +1862,29 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
 // Job received by the consumer threads for the covdir version
 struct JobItemCovDir {
     chunk: Vec<String>,
-    covs: HashMap<String, Covdir>,
+    covs: Arc<HashMap<String, Covdir>>,
     metric: Complexity,
-    prefix: usize,
+    files_path: PathBuf,
     thresholds: Vec<f64>,
+    options: AnalysisOptions,
 }
 
 impl JobItemCovDir {
     fn new(
         chunk: Vec<String>,
-        covs: HashMap<String, Covdir>,
+        covs: Arc<HashMap<String, Covdir>>,
         metric: Complexity,
-        prefix: usize,
+        files_path: PathBuf,
         thresholds: Vec<f64>,
+        options: AnalysisOptions,
     ) -> Self {
         Self {
             chunk,
             covs,
             metric,
-            prefix,
+            files_path,
             thresholds,
+            options,
         }
     }
 }
@@ -460,8 +1892,8 @@ impl fmt::Debug for JobItemCovDir {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Job: chunks:{:?}, metric:{}, prefix:{:?}, thresholds: {:?}",
-            self.chunk, self.metric, self.prefix, self.thresholds
+            "Job: chunks:{:?}, metric:{}, files_path:{:?}, thresholds: {:?}",
+            self.chunk, self.metric, self.files_path, self.thresholds
         )
     }
 }
@@ -487,45 +1919,41 @@ fn consumer_covdir(
         let chunk = job.chunk;
         let covs = job.covs;
         let metric = job.metric;
-        let prefix = job.prefix;
+        let files_path = job.files_path;
         let thresholds = job.thresholds;
+        let options = job.options;
         // For each file in the chunk
         for file in chunk {
-            let path = Path::new(&file);
-            let file_name = path
-                .file_name()
-                .ok_or(Error::PathConversionError())?
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .into();
+            // The files reached after the deadline are skipped
+            if is_past_deadline(&options) {
+                composer_output.files_skipped += 1;
+                continue;
+            }
             // Get the coverage vector from the covdir file
             // If not present the file will be added to the files ignored
             let covdir = match covs.get(&file) {
                 Some(covdir) => covdir,
                 None => {
                     let mut f = files_ignored.lock()?;
-                    f.push(file);
+                    f.push(FileIgnored::new(file, IgnoreReason::NoCoverage));
                     continue;
                 }
             };
-            let arr = &covdir.arr;
-            let coverage = Some(covdir.coverage);
-            let root = get_root(path)?;
-            let ploc = root.metrics.loc.ploc();
-            let comp = match metric {
-                Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-                Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-            };
-            let file_path = file.clone().split_off(prefix);
-            let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
-                Tree::get_metrics_from_space(&root, arr, metric, coverage, &thresholds)?;
-            let mut res = res.lock()?;
             // Update all shared variables
-            composer_output.ploc_sum += ploc;
-            composer_output.wcc_plain_sum += sp_sum;
-            composer_output.wcc_quantized_sum += sq_sum;
-            composer_output.comp_sum += comp;
-            res.push(FileMetrics::new(m, file_name, file_path));
+            match get_file_metrics(
+                &file,
+                covdir.into(),
+                metric,
+                &files_path,
+                &thresholds,
+                &options,
+            )? {
+                Ok((file_metrics, file_composer)) => {
+                    composer_output.add(&file_composer);
+                    res.lock()?.push(file_metrics);
+                }
+                Err(reason) => files_ignored.lock()?.push(FileIgnored::new(file, reason)),
+            }
         }
     }
     if let Err(_e) = sender_composer.send(Some(composer_output)) {
@@ -545,21 +1973,32 @@ pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     n_threads: usize,
     thresholds: &[f64],
     sort_by: Sort,
+    options: &AnalysisOptions,
 ) -> Result<Output> {
     if thresholds.len() != 4 {
         return Err(Error::ThresholdsError());
     }
-    // Get all the files from project folder
-    let vec = read_files(files_path.as_ref())?;
-    // Read covdir json and obtain all coverage information
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json_covdir(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
+    // The coverage of a covdir file is already computed, lines cannot be excluded from it
+    check_modes(
+        &[
+            (
+                "The exclusions table of the config file",
+                options.line_exclusions.is_some(),
+            ),
+            ("--no-coverage-weight", options.no_coverage_weight.is_some()),
+            ("--active-features", options.active_features.is_some()),
+        ],
+        Analysis::FilesCovdir,
     )?;
+    // Read covdir json and obtain all coverage information
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let covs = options.record_stage("reading coverage", || {
+        let file = fs::read_to_string(json_path)?;
+        apply_path_map(read_json_covdir(file, prefix)?, prefix, options)
+    })?;
+    // Get all the files from project folder
+    let vec = get_source_files(files_path.as_ref(), covs.keys(), options)?;
+    let (vec, estimate) = sample_files(vec, &covs, prefix, options);
     let mut handlers = vec![];
     // Create a new Config all needed mutexes
     let cfg = Config::new();
@@ -580,19 +2019,16 @@ pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
         let h = thread::spawn(move || -> Result<()> { consumer_covdir(r, s, &config) });
         handlers.push(h);
     }
-    let prefix = files_path
-        .as_ref()
-        .to_str()
-        .ok_or(Error::PathConversionError())?
-        .to_string()
-        .len();
+    // The coverage is shared by all the jobs without copying it
+    let covs = Arc::new(covs);
     chunks.iter().try_for_each(|chunk| {
         let job = JobItemCovDir::new(
             chunk.to_vec(),
-            covs.clone(),
+            Arc::clone(&covs),
             metric,
-            prefix,
+            files_path.as_ref().to_path_buf(),
             thresholds.to_vec(),
+            options.clone(),
         );
         debug!("Sending job: {:?}", job);
         if let Err(_e) = sender.send(Some(job)) {
@@ -618,31 +2054,50 @@ pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     }
     let mut files_ignored = cfg.files_ignored.lock()?;
     let mut res = cfg.res.lock()?;
-    let project_coverage = covs
-        .get(&("PROJECT_ROOT".to_string()))
-        .ok_or(Error::HashMapError())?
-        .coverage;
+    // The project coverage is the one of the root of the covdir file, as in the grcov report,
+    // or the one of the files of the sample
+    let project_root = covs.get("PROJECT_ROOT").ok_or(Error::HashMapError())?;
     // Get final  metrics for all the project
     let composer_output = composer.join()??;
+    warn_unreachable_thresholds(&composer_output, thresholds);
+    let (covered_lines, coverable_lines) = (
+        composer_output.covered_lines as usize,
+        composer_output.total_lines as usize,
+    );
+    let files_skipped = get_files_skipped(&composer_output);
     let project_metric = FileMetrics::new(
-        get_project_metrics(composer_output, Some(project_coverage))?,
+        get_project_metrics(
+            composer_output,
+            estimate.is_none().then_some(project_root.coverage),
+            options.max_metric_value,
+        )?,
         "PROJECT".into(),
         "-".into(),
-    );
+    )
+    .branch_coverage(project_root.branch_coverage.filter(|_| estimate.is_none()))
+    .estimate(estimate)
+    .covered_lines(options.line_counts.then_some(covered_lines))
+    .coverable_lines(Some(coverable_lines))
+    .files_skipped(files_skipped)
+    .path_map(options.path_map.as_ref().map(PathMap::get_rules_rewritten));
+    let project_coverage = match estimate {
+        Some(_) => f64::round(project_metric.metrics.coverage * 100.) / 100.,
+        None => project_root.coverage,
+    };
     files_ignored.sort();
-    res.sort_by(|a, b| a.file.cmp(&b.file));
+    if sort_by == Sort::Path {
+        res.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    } else {
+        res.sort_by(|a, b| a.file.cmp(&b.file));
+    }
+    set_fan_in(files_path.as_ref(), &mut res, options)?;
     // Get AVG MIN MAX and complex files
     let mut complex_files = res
         .iter()
         .filter(|m| m.metrics.is_complex)
         .cloned()
         .collect::<Vec<FileMetrics>>();
-    complex_files.sort_by(|a, b| match sort_by {
-        Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-        Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-        Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-        Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-    });
+    sort_complex_files(&mut complex_files, sort_by);
     let m = res
         .iter()
         .map(|metric| metric.metrics)
@@ -660,117 +2115,1340 @@ pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     ))
 }
 
-#[cfg(test)]
-mod tests {
+// Split the items of a use tree separated by the commas outside of the braces
+fn split_use_items(tree: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    tree.char_indices().for_each(|(i, c)| match c {
+        '{' => depth += 1,
+        '}' => depth -= 1,
+        ',' if depth == 0 => {
+            items.push(&tree[start..i]);
+            start = i + 1;
+        }
+        _ => {}
+    });
+    items.push(&tree[start..]);
+    items
+}
 
-    use super::*;
-    use crate::utility::compare_float;
+// Expand a use tree, e.g. crate::{error::*, files::{self, Metrics as M}},
+// in the paths of the items it imports, each one as the list of its segments
+fn expand_use_tree(prefix: &[String], tree: &str, paths: &mut Vec<Vec<String>>) {
+    let tree = tree.trim();
+    match tree.find('{') {
+        Some(open) => {
+            let mut head = prefix.to_vec();
+            head.extend(
+                tree[..open]
+                    .split("::")
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(String::from),
+            );
+            let body = tree[open + 1..].trim_end().trim_end_matches('}');
+            split_use_items(body)
+                .iter()
+                .filter(|item| !item.trim().is_empty())
+                .for_each(|item| expand_use_tree(&head, item, paths));
+        }
+        None => {
+            let item = tree.split(" as ").next().unwrap_or_default();
+            let mut path = prefix.to_vec();
+            path.extend(
+                item.split("::")
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty() && *s != "*")
+                    .map(String::from),
+            );
+            // The self of a group is the module of the group itself
+            if path.len() > 1 && path.last().is_some_and(|s| s == "self") {
+                path.pop();
+            }
+            paths.push(path);
+        }
+    }
+}
 
-    const JSON: &str = "./data/seahorse/seahorse.json";
-    const COVDIR: &str = "./data/seahorse/covdir.json";
-    const PROJECT: &str = "./data/seahorse/";
-    const IGNORED: &str = "./data/seahorse/src/action.rs";
+// Get the use and mod statements of a Rust source, without their visibility,
+// the statements split across more lines are joined and the line comments are skipped
+fn get_import_statements(source: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current: Option<String> = None;
+    source.lines().for_each(|line| {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let statement = match current.take() {
+            Some(statement) => format!("{statement} {line}"),
+            None => {
+                let line = match line.strip_prefix("pub") {
+                    Some(rest) if rest.starts_with('(') => {
+                        rest.split_once(')').map_or(rest, |(_, rest)| rest)
+                    }
+                    Some(rest) => rest,
+                    None => line,
+                }
+                .trim_start();
+                if !line.starts_with("use ") && !line.starts_with("mod ") {
+                    return;
+                }
+                line.to_string()
+            }
+        };
+        match statement.split_once(';') {
+            Some((statement, _)) => statements.push(statement.to_string()),
+            // An inline module has no semicolon, it is not a file
+            None if statement.starts_with("mod ") => {}
+            None => current = Some(statement),
+        }
+    });
+    statements
+}
 
-    #[test]
-    fn test_metrics_coveralls_cyclomatic() {
-        let json = Path::new(JSON);
-        let project = Path::new(PROJECT);
-        let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
-            project,
-            json,
-            Complexity::Cyclomatic,
-            8,
-            &[30., 1.5, 35., 30.],
-            Sort::WccPlain,
-        )
-        .unwrap();
-        let error = &metrics[3].metrics;
-        let ma = &metrics[7].metrics;
-        let h = &metrics[5].metrics;
-        let app = &metrics[0].metrics;
-        let cont = &metrics[2].metrics;
+// Get the module path of a file relative to the root of its crate,
+// the nearest folder with a lib.rs or a main.rs, or the project folder if there is none
+fn get_module_path(files_path: &Path, path: &Path) -> (PathBuf, Vec<String>) {
+    let root = path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(files_path))
+        .find(|dir| dir.join("lib.rs").is_file() || dir.join("main.rs").is_file())
+        .unwrap_or(files_path)
+        .to_path_buf();
+    let mut module = path
+        .strip_prefix(&root)
+        .unwrap_or(path)
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+    if module.len() == 1 && (module[0] == "lib" || module[0] == "main") {
+        module.clear();
+    }
+    if module.last().is_some_and(|m| m == "mod") {
+        module.pop();
+    }
+    (root, module)
+}
 
-        assert_eq!(files_ignored.len(), 1);
-        assert!(files_ignored[0] == ignored.as_os_str().to_str().unwrap());
-        assert!(compare_float(error.wcc_plain, 0.53125));
-        assert!(compare_float(error.wcc_quantized, 0.03125));
-        assert!(compare_float(error.crap, 257.94117647058823));
-        assert!(compare_float(error.skunk, 64.00000000000001));
-        assert!(compare_float(ma.wcc_plain, 0.));
-        assert!(compare_float(ma.wcc_quantized, 0.));
-        assert!(compare_float(ma.crap, 552.));
-        assert!(compare_float(ma.skunk, 92.));
-        assert!(compare_float(h.wcc_plain, 1.5));
-        assert!(compare_float(h.wcc_quantized, 0.5));
-        assert!(compare_float(h.crap, 3.));
-        assert!(compare_float(h.skunk, 0.));
-        assert!(compare_float(app.wcc_plain, 79.21478060046189));
-        assert!(compare_float(app.wcc_quantized, 0.792147806004619));
-        assert!(compare_float(app.crap, 123.97408556537728));
-        assert!(compare_float(app.skunk, 53.53535353535352));
-        assert!(compare_float(cont.wcc_plain, 24.31578947368421));
-        assert!(compare_float(cont.wcc_quantized, 0.7368421052631579));
-        assert!(compare_float(cont.crap, 33.468144844401756));
-        assert!(compare_float(cont.skunk, 9.9622641509434));
+// Resolve the path of an import of a module to the absolute path of the imported item in its crate
+// The imports of other crates are resolved as relative to the module, so they usually match nothing
+fn resolve_import(module: &[String], path: &[String]) -> Vec<String> {
+    let mut resolved = module.to_vec();
+    let mut segments = path.iter().peekable();
+    match segments.peek().map(|s| s.as_str()) {
+        Some("crate") => {
+            resolved.clear();
+            segments.next();
+        }
+        Some("self") => {
+            segments.next();
+        }
+        _ => {}
+    }
+    while segments.peek().is_some_and(|s| *s == "super") {
+        resolved.pop();
+        segments.next();
     }
+    resolved.extend(segments.cloned());
+    resolved
+}
 
-    #[test]
-    fn test_metrics_coveralls_cognitive() {
-        let json = Path::new(JSON);
-        let project = Path::new(PROJECT);
-        let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
-            project,
-            json,
-            Complexity::Cognitive,
-            8,
-            &[30., 1.5, 35., 30.],
-            Sort::WccPlain,
-        )
-        .unwrap();
-        let error = &metrics[3].metrics;
-        let ma = &metrics[7].metrics;
-        let h = &metrics[5].metrics;
-        let app = &metrics[0].metrics;
-        let cont = &metrics[2].metrics;
+/// Get the fan-in of each Rust file, the number of the other files that depend on it
+/// with a use of one of its items or with the mod declaration of its module
+/// The import graph is built with an approximate parse of the use and mod statements,
+/// the file paths are relative to the project folder as in the metrics
+pub fn get_fan_in<A: AsRef<Path>>(
+    files_path: A,
+    file_paths: &[String],
+) -> Result<HashMap<String, usize>> {
+    let files_path = files_path.as_ref();
+    let modules = file_paths
+        .iter()
+        .filter(|file_path| file_path.ends_with(".rs"))
+        .map(|file_path| {
+            let path = files_path.join(file_path.trim_start_matches('/'));
+            let (root, module) = get_module_path(files_path, &path);
+            (file_path.as_str(), path, root, module)
+        })
+        .collect::<Vec<_>>();
+    let index = modules
+        .iter()
+        .map(|(file_path, _, root, module)| ((root, module), *file_path))
+        .collect::<HashMap<_, _>>();
+    let mut dependants = HashMap::<&str, Vec<&str>>::new();
+    for (file_path, path, root, module) in &modules {
+        let mut imports = Vec::new();
+        get_import_statements(&fs::read_to_string(path)?)
+            .iter()
+            .for_each(|statement| match statement.strip_prefix("mod ") {
+                Some(name) => imports.push(vec!["self".into(), name.trim().into()]),
+                None => expand_use_tree(&[], &statement["use ".len()..], &mut imports),
+            });
+        imports.iter().for_each(|import| {
+            let mut resolved = resolve_import(module, import);
+            // The item is in the file of the longest module of its path, if the crate has it
+            let target = loop {
+                match index.get(&(root, &resolved)) {
+                    Some(target) => break Some(*target),
+                    None if resolved.is_empty() => break None,
+                    None => {
+                        resolved.pop();
+                    }
+                }
+            };
+            if let Some(target) = target.filter(|target| target != file_path) {
+                let files = dependants.entry(target).or_default();
+                if !files.contains(file_path) {
+                    files.push(file_path);
+                }
+            }
+        });
+    }
+    Ok(file_paths
+        .iter()
+        .map(|file_path| {
+            let fan_in = dependants.get(file_path.as_str()).map_or(0, |f| f.len());
+            (file_path.clone(), fan_in)
+        })
+        .collect())
+}
 
-        assert_eq!(files_ignored.len(), 1);
-        assert!(files_ignored[0] == ignored.as_os_str().to_str().unwrap());
-        assert!(compare_float(error.wcc_plain, 0.0625));
-        assert!(compare_float(error.wcc_quantized, 0.03125));
-        assert!(compare_float(error.crap, 5.334825971911256));
-        assert!(compare_float(error.skunk, 7.529411764705883));
-        assert!(compare_float(ma.wcc_plain, 0.));
-        assert!(compare_float(ma.wcc_quantized, 0.));
-        assert!(compare_float(ma.crap, 72.));
-        assert!(compare_float(ma.skunk, 32.));
-        assert!(compare_float(h.wcc_plain, 0.));
-        assert!(compare_float(h.wcc_quantized, 0.5));
-        assert!(compare_float(h.crap, 0.));
-        assert!(compare_float(h.skunk, 0.));
-        assert!(compare_float(app.wcc_plain, 66.540415704388));
-        assert!(compare_float(app.wcc_quantized, 0.792147806004619));
-        assert!(compare_float(app.crap, 100.91611477493021));
-        assert!(compare_float(app.skunk, 44.969696969696955));
-        assert!(compare_float(cont.wcc_plain, 18.42105263157895));
-        assert!(compare_float(cont.wcc_quantized, 0.8872180451127819));
-        assert!(compare_float(cont.crap, 25.268678170570336));
-        assert!(compare_float(cont.skunk, 7.547169811320757));
+// Set the fan-in of each file of the metrics, only when the complex files are ordered by it
+fn set_fan_in(files_path: &Path, res: &mut [FileMetrics], options: &AnalysisOptions) -> Result<()> {
+    if !options.order_by_fanin {
+        return Ok(());
     }
+    let file_paths = res
+        .iter()
+        .map(|m| m.file_path.clone())
+        .collect::<Vec<String>>();
+    let fan_in = get_fan_in(files_path, &file_paths)?;
+    res.iter_mut()
+        .for_each(|m| m.fan_in = fan_in.get(&m.file_path).copied());
+    Ok(())
+}
 
-    #[test]
-    fn test_metrics_covdir_cyclomatic() {
-        let covdir = Path::new(COVDIR);
-        let project = Path::new(PROJECT);
-        let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_metrics_concurrent_covdir(
-            project,
-            covdir,
-            Complexity::Cyclomatic,
-            8,
-            &[30., 1.5, 35., 30.],
-            Sort::WccPlain,
+// Sort the complex files by the chosen metric, by the priority if there is a tests per file map,
+// by their fan-in first when it is computed, so the risky files with more dependants come first
+// With the tiers the files are grouped by their tier, from the critical ones, and sorted inside it
+pub(crate) fn sort_complex_files(complex_files: &mut [FileMetrics], sort_by: Sort) {
+    complex_files.sort_by(|a, b| {
+        a.tier
+            .cmp(&b.tier)
+            .then_with(|| b.fan_in.cmp(&a.fan_in))
+            .then_with(|| match (sort_by, a.priority, b.priority) {
+                (Sort::Path, _, _) => a.file_path.cmp(&b.file_path),
+                (_, Some(x), Some(y)) => y.total_cmp(&x),
+                _ => a.metrics.compare(&b.metrics, sort_by),
+            })
+    });
+}
+
+/// A file with no covered lines, with its complexity and its source lines of code
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ZeroCoverageFile {
+    pub file_path: String,
+    pub complexity: f64,
+    pub sloc: f64,
+}
+
+/// This Function get the metrics of the files of a run in files mode, computed with the line counts,
+/// and returns the files with a coverage of 0%, sorted from the biggest one by SLOC
+/// The files without lines that can be covered and the summary rows are skipped
+/// The complexity is the sum of the complexity of all the spaces of the file
+pub fn get_zero_coverage_files(metrics: &[FileMetrics]) -> Vec<ZeroCoverageFile> {
+    let mut files = metrics
+        .iter()
+        .filter(|m| m.file_path != "-" && !m.no_coverable_lines && m.metrics.coverage == 0.0)
+        .map(|m| ZeroCoverageFile {
+            file_path: m.file_path.clone(),
+            complexity: m.complexity.unwrap_or_default(),
+            sloc: m.sloc.unwrap_or_default() as f64,
+        })
+        .collect::<Vec<ZeroCoverageFile>>();
+    files.sort_by(|a, b| {
+        b.sloc
+            .total_cmp(&a.sloc)
+            .then_with(|| a.file_path.cmp(&b.file_path))
+    });
+    files
+}
+
+/// Owner of the files without any owner in the CODEOWNERS file
+pub const UNOWNED: &str = "(unowned)";
+
+/// Owners of the files of a project, read from a CODEOWNERS file
+#[derive(Clone, Debug, Default)]
+pub struct CodeOwners {
+    rules: Vec<(Vec<GlobMatcher>, Vec<String>)>,
+}
+
+// Get the globs matching a CODEOWNERS pattern, relative to the project folder
+// A pattern without a slash matches at any depth, one ending with a slash only directories
+fn get_codeowners_globs(pattern: &str) -> Vec<String> {
+    let (pattern, dir) = match pattern.strip_suffix('/') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let pattern = match pattern.strip_prefix('/') {
+        Some(pattern) => pattern.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{pattern}"),
+    };
+    if dir {
+        vec![format!("{pattern}/**")]
+    } else {
+        vec![format!("{pattern}/**"), pattern]
+    }
+}
+
+impl CodeOwners {
+    /// Read the CODEOWNERS file, each line has a pattern followed by its owners
+    /// separated by whitespaces, a rule without owners leaves the files unowned
+    /// Empty lines and comments starting with # are skipped
+    pub fn new(codeowners: &str) -> Result<Self> {
+        let rules = codeowners
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| -> Result<(Vec<GlobMatcher>, Vec<String>)> {
+                let mut items = line.split_whitespace();
+                let pattern = items.next().unwrap_or_default();
+                let globs = get_codeowners_globs(pattern)
+                    .iter()
+                    .map(|glob| -> Result<GlobMatcher> {
+                        Ok(GlobBuilder::new(glob)
+                            .literal_separator(true)
+                            .build()?
+                            .compile_matcher())
+                    })
+                    .collect::<Result<Vec<GlobMatcher>>>()?;
+                Ok((globs, items.map(String::from).collect()))
+            })
+            .collect::<Result<Vec<(Vec<GlobMatcher>, Vec<String>)>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn from_file<A: AsRef<Path>>(path: A) -> Result<Self> {
+        Self::new(&fs::read_to_string(path)?)
+    }
+
+    /// Get the owners of a file given its path relative to the project folder,
+    /// the ones of the last matching rule or of all of them following owners_match
+    pub fn owners(&self, file_path: &str, owners_match: OwnersMatch) -> Vec<&str> {
+        let file_path = file_path.trim_start_matches('/');
+        let mut rules = self
+            .rules
+            .iter()
+            .rev()
+            .filter(|(globs, _)| globs.iter().any(|glob| glob.is_match(file_path)));
+        let mut owners = match owners_match {
+            OwnersMatch::MostSpecific => rules
+                .next()
+                .map(|(_, owners)| owners.iter().map(String::as_str).collect())
+                .unwrap_or_default(),
+            OwnersMatch::Each => rules
+                .flat_map(|(_, owners)| owners.iter().map(String::as_str))
+                .collect::<Vec<&str>>(),
+        };
+        owners.sort();
+        owners.dedup();
+        owners
+    }
+}
+
+/// Metrics of the files of an owner, the sums of CRAP and SKUNK measure the risk it owns
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct OwnerMetrics {
+    pub owner: String,
+    pub number_of_files: usize,
+    pub number_of_complex_files: usize,
+    pub crap_sum: f64,
+    pub skunk_sum: f64,
+    pub max_crap: f64,
+    pub max_skunk: f64,
+    pub avg: Metrics,
+}
+
+/// This Function get the metrics of the files of a run in files mode and the owners of the project
+/// and returns the metrics of each owner, sorted by owner
+/// A file with more owners counts for each of them, the files without owners count for UNOWNED
+pub fn get_owner_metrics(
+    metrics: &[FileMetrics],
+    codeowners: &CodeOwners,
+    owners_match: OwnersMatch,
+) -> Vec<OwnerMetrics> {
+    let mut files = HashMap::<&str, Vec<&FileMetrics>>::new();
+    metrics.iter().filter(|m| m.file_path != "-").for_each(|m| {
+        let owners = codeowners.owners(&m.file_path, owners_match);
+        let owners = if owners.is_empty() {
+            vec![UNOWNED]
+        } else {
+            owners
+        };
+        owners
+            .into_iter()
+            .for_each(|owner| files.entry(owner).or_default().push(m));
+    });
+    let mut owners = files
+        .into_iter()
+        .map(|(owner, files)| {
+            let m = files.iter().map(|f| f.metrics).collect::<Vec<Metrics>>();
+            let (mut avg, max, _) = get_cumulative_values(&m);
+            avg.coverage /= m.len() as f64;
+            OwnerMetrics {
+                owner: owner.into(),
+                number_of_files: files.len(),
+                number_of_complex_files: m.iter().filter(|m| m.is_complex).count(),
+                crap_sum: m.iter().map(|m| m.crap).sum(),
+                skunk_sum: m.iter().map(|m| m.skunk).sum(),
+                max_crap: max.crap,
+                max_skunk: max.skunk,
+                avg,
+            }
+        })
+        .collect::<Vec<OwnerMetrics>>();
+    owners.sort_by(|a, b| a.owner.cmp(&b.owner));
+    owners
+}
+
+/// Coverage of a directory against its floor, the covered lines over the coverable lines
+/// of all the files inside it and inside its subdirectories
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DirectoryFloor {
+    pub directory: String,
+    pub number_of_files: usize,
+    pub covered_lines: usize,
+    pub coverable_lines: usize,
+    pub coverage: f64,
+    pub floor: f64,
+}
+
+impl DirectoryFloor {
+    pub fn is_below(&self) -> bool {
+        self.coverage < self.floor
+    }
+}
+
+// Get the directories containing a file, from its parent up to the project folder itself, "."
+fn get_ancestor_directories(file_path: &str) -> Vec<String> {
+    Path::new(file_path.trim_start_matches('/'))
+        .ancestors()
+        .skip(1)
+        .map(|d| match d.to_str() {
+            Some("") | None => ".".to_string(),
+            Some(d) => d.to_string(),
+        })
+        .collect()
+}
+
+/// This Function get the metrics of the files of a run in files mode, computed with the line counts,
+/// and the coverage floors and returns the coverage of each directory with a floor, sorted by directory
+/// The coverage of a directory totals the lines of its whole subtree,
+/// the directory "." is the project folder with all the files
+pub fn get_directory_floors(
+    metrics: &[FileMetrics],
+    floors: &CoverageFloors,
+) -> Vec<DirectoryFloor> {
+    let mut directories = HashMap::<String, (usize, usize, usize)>::new();
+    metrics.iter().filter(|m| m.file_path != "-").for_each(|m| {
+        get_ancestor_directories(&m.file_path)
+            .into_iter()
+            .for_each(|directory| {
+                let (files, covered, coverable) = directories.entry(directory).or_default();
+                *files += 1;
+                *covered += m.covered_lines.unwrap_or_default();
+                *coverable += m.coverable_lines.unwrap_or_default();
+            });
+    });
+    let mut directories = directories
+        .into_iter()
+        .filter_map(
+            |(directory, (number_of_files, covered_lines, coverable_lines))| {
+                let floor = floors.floor(&directory)?;
+                Some(DirectoryFloor {
+                    coverage: get_lines_coverage((covered_lines, coverable_lines)),
+                    number_of_files,
+                    covered_lines,
+                    coverable_lines,
+                    directory,
+                    floor,
+                })
+            },
+        )
+        .collect::<Vec<DirectoryFloor>>();
+    directories.sort_by(|a, b| a.directory.cmp(&b.directory));
+    directories
+}
+
+/// Result of the matching between a coverage entry and the source files of the project folder
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MatchDiagnostic {
+    pub coverage_file: String,
+    pub entry: String,
+    pub matched: bool,
+    pub source_file: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// Struct with the matching result of all the coverage entries
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MatchDiagnostics {
+    pub project_folder: String,
+    pub number_of_entries: usize,
+    pub number_of_entries_not_matched: usize,
+    pub entries: Vec<MatchDiagnostic>,
+    pub source_files_without_coverage: Vec<String>,
+}
+
+// Get the reason why a coverage entry does not match any source file
+fn get_unmatched_reason(entry: &str) -> String {
+    let path = Path::new(entry);
+    if !path.exists() {
+        "No file found at the resolved path".into()
+    } else if !path.is_file() {
+        "The resolved path is not a file".into()
+    } else if !path.extension().is_some_and(check_ext) {
+        "The file extension is not supported".into()
+    } else {
+        "The file is not inside the project folder".into()
+    }
+}
+
+/// This Function get the folder of the repo to analyzed and a list of coverage files, each one with its format
+/// For each entry of the coverage files it checks if it matches a source file of the project folder
+/// and, when it does not, the reason why it failed
+/// It also returns the source files that do not match any entry
+pub fn get_matching_diagnostics<A: AsRef<Path>>(
+    files_path: A,
+    inputs: &[CoverageInput],
+) -> Result<MatchDiagnostics> {
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let mut source_files = read_files(files_path.as_ref())?;
+    let mut matched_files = Vec::<String>::new();
+    let mut entries = Vec::<MatchDiagnostic>::new();
+    inputs.iter().try_for_each(|input| -> Result<()> {
+        let mut covs = read_coverage(input, prefix)?
+            .into_keys()
+            .collect::<Vec<String>>();
+        covs.sort();
+        covs.into_iter().for_each(|entry| {
+            let matched = source_files.contains(&entry);
+            let diagnostic = if matched {
+                matched_files.push(entry.clone());
+                MatchDiagnostic {
+                    coverage_file: input.path.display().to_string(),
+                    source_file: Some(entry.clone()),
+                    entry,
+                    matched,
+                    reason: None,
+                }
+            } else {
+                MatchDiagnostic {
+                    coverage_file: input.path.display().to_string(),
+                    reason: Some(get_unmatched_reason(&entry)),
+                    entry,
+                    matched,
+                    source_file: None,
+                }
+            };
+            entries.push(diagnostic);
+        });
+        Ok(())
+    })?;
+    source_files.retain(|file| !matched_files.contains(file));
+    source_files.sort();
+    Ok(MatchDiagnostics {
+        project_folder: files_path.as_ref().display().to_string(),
+        number_of_entries: entries.len(),
+        number_of_entries_not_matched: entries.iter().filter(|e| !e.matched).count(),
+        entries,
+        source_files_without_coverage: source_files,
+    })
+}
+
+/// Coverage of the lines added or modified in a diff
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DiffCoverage {
+    pub file: String,
+    pub covered_lines: usize,
+    pub total_lines: usize,
+    pub coverage: f64,
+}
+
+impl DiffCoverage {
+    pub fn new(file: String, covered_lines: usize, total_lines: usize) -> Self {
+        // Without changed lines that can be covered there is nothing left to cover
+        let coverage = if total_lines == 0 {
+            100.0
+        } else {
+            f64::round(covered_lines as f64 / total_lines as f64 * 10000.0) / 100.0
+        };
+        Self {
+            file,
+            covered_lines,
+            total_lines,
+            coverage,
+        }
+    }
+}
+
+// Get the lines added or modified by each file from the hunks of a unified diff with no context
+// The paths are the ones of the new files, lines are 1-based
+pub(crate) fn get_changed_lines_from_diff(diff: &str) -> HashMap<String, Vec<usize>> {
+    let mut changed = HashMap::<String, Vec<usize>>::new();
+    let mut current: Option<String> = None;
+    diff.lines().for_each(|line| {
+        if let Some(name) = line.strip_prefix("+++ ") {
+            current = name
+                .strip_prefix("b/")
+                .or_else(|| (name != "/dev/null").then_some(name))
+                .map(|name| name.to_string());
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            // The hunk header has the form @@ -start,count +start,count @@
+            let new_range = hunk
+                .split(' ')
+                .find_map(|range| range.strip_prefix('+'))
+                .unwrap_or_default();
+            let mut range = new_range.split(',');
+            let start = range.next().and_then(|s| s.parse::<usize>().ok());
+            let count = range.next().map_or(Some(1), |c| c.parse::<usize>().ok());
+            if let (Some(file), Some(start), Some(count)) = (&current, start, count) {
+                changed
+                    .entry(file.clone())
+                    .or_default()
+                    .extend(start..start + count);
+            }
+        }
+    });
+    changed
+}
+
+// Run git diff against the given reference in the project folder
+// The paths of the diff are relative to the project folder
+pub(crate) fn get_changed_lines<A: AsRef<Path>>(
+    files_path: A,
+    git_ref: &str,
+) -> Result<HashMap<String, Vec<usize>>> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(files_path.as_ref())
+        .args(["diff", "--relative", "--no-color", "--no-ext-diff", "-U0"])
+        .arg(git_ref)
+        .arg("--")
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::GitError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(get_changed_lines_from_diff(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+// Compute the coverage of the changed lines of each file
+// Only the changed lines that can be covered are counted, the changed source files
+// without a coverage entry are returned apart since none of their lines can be counted
+pub(crate) fn get_diff_coverage_from_covs(
+    files_path: &Path,
+    covs: &CoverageData,
+    changed: &HashMap<String, Vec<usize>>,
+) -> (Vec<DiffCoverage>, DiffCoverage, Vec<String>) {
+    let key = |file: &str| {
+        files_path
+            .join(file)
+            .display()
+            .to_string()
+            .replace('\\', "/")
+    };
+    let mut files_without_coverage = changed
+        .keys()
+        .filter(|file| Path::new(file).extension().is_some_and(check_ext))
+        .filter(|file| !covs.contains_key(&key(file)))
+        .cloned()
+        .collect::<Vec<String>>();
+    files_without_coverage.sort();
+    let mut files = changed
+        .iter()
+        .filter_map(|(file, lines)| {
+            let arr = covs.get(&key(file))?;
+            let (covered, total) = lines
+                .iter()
+                .filter_map(|line| arr.get(line.checked_sub(1)?))
+                .filter_map(|hits| hits.as_i64().filter(|hits| *hits >= 0))
+                .fold((0, 0), |(covered, total), hits| {
+                    (covered + usize::from(hits > 0), total + 1)
+                });
+            Some(DiffCoverage::new(file.clone(), covered, total))
+        })
+        .filter(|diff| diff.total_lines > 0)
+        .collect::<Vec<DiffCoverage>>();
+    files.sort_by(|a, b| a.file.cmp(&b.file));
+    let (covered, total) = files.iter().fold((0, 0), |(covered, total), diff| {
+        (covered + diff.covered_lines, total + diff.total_lines)
+    });
+    (
+        files,
+        DiffCoverage::new("TOTAL".into(), covered, total),
+        files_without_coverage,
+    )
+}
+
+/// This Function get the folder of the repo to analyze, the coverage files and a git reference
+/// It computes the coverage of the lines added or modified since the git reference,
+/// only the changed lines that can be covered are counted
+/// It returns the tuple (files, total, files_without_coverage, files_skipped) with the coverage
+/// of each changed file, the overall one, the changed source files missing from the coverage files
+/// and the number of changed files skipped because the max runtime has been reached
+pub fn get_diff_coverage<A: AsRef<Path>>(
+    files_path: A,
+    inputs: &[CoverageInput],
+    git_ref: &str,
+    options: &AnalysisOptions,
+) -> Result<(Vec<DiffCoverage>, DiffCoverage, Vec<String>, usize)> {
+    let covs = get_coverage_data(inputs, &get_files_prefix(files_path.as_ref()), options)?;
+    // The files reached after the deadline are skipped and counted
+    let mut files_skipped = 0;
+    let changed = get_changed_lines(files_path.as_ref(), git_ref)?
+        .into_iter()
+        .filter(|_| {
+            let skipped = is_past_deadline(options);
+            files_skipped += usize::from(skipped);
+            !skipped
+        })
+        .collect::<HashMap<String, Vec<usize>>>();
+    let (files, total, files_without_coverage) =
+        get_diff_coverage_from_covs(files_path.as_ref(), &covs, &changed);
+    Ok((files, total, files_without_coverage, files_skipped))
+}
+
+/// Coverage of a file read from the covdir file and from the coveralls file of the same run,
+/// None when the file is missing from one of them
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CrossCheckFile {
+    pub file: String,
+    pub covdir_coverage: Option<f64>,
+    // Coverage percentage written in the covdir file
+    pub covdir_reported_coverage: Option<f64>,
+    pub coveralls_coverage: Option<f64>,
+}
+
+/// Result of the cross-check between the covdir file and the coveralls file of the same run
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CrossCheck {
+    pub covdir_coverage: f64,
+    // coveragePercent of the root of the covdir file
+    pub covdir_reported_coverage: f64,
+    pub coveralls_coverage: f64,
+    pub tolerance: f64,
+    // Files whose coverage differs by more than the tolerance, sorted by path
+    pub files: Vec<CrossCheckFile>,
+}
+
+impl CrossCheck {
+    /// Check if the two coverage files agree within the tolerance, both per file and for the project,
+    /// and if the coverage percentages of the covdir file agree with its lines
+    pub fn is_consistent(&self) -> bool {
+        self.files.is_empty()
+            && (self.covdir_coverage - self.coveralls_coverage).abs() <= self.tolerance
+            && (self.covdir_coverage - self.covdir_reported_coverage).abs() <= self.tolerance
+    }
+}
+
+// Get the covered and the coverable lines of a coverage array in the coveralls layout
+fn count_covered_lines(arr: &[Value]) -> (usize, usize) {
+    arr.iter()
+        .filter_map(|hits| hits.as_i64().filter(|hits| *hits >= 0))
+        .fold((0, 0), |(covered, coverable), hits| {
+            (covered + usize::from(hits > 0), coverable + 1)
+        })
+}
+
+// Get the percentage of the covered lines, 0 without coverable lines
+fn get_lines_coverage((covered, coverable): (usize, usize)) -> f64 {
+    if coverable == 0 {
+        0.
+    } else {
+        covered as f64 / coverable as f64 * 100.
+    }
+}
+
+/// This Function get the folder of the repo to analyze, the covdir and the coveralls files
+/// of the same run and a tolerance in percentage points
+/// It reads the two files with their own parsers and computes the coverage of each file
+/// and of the project from each one, to check the parsers and the pipeline against each other
+/// The coverage percentages written in the covdir file are checked against its lines too
+/// It returns the project coverage from each file and the files whose coverage differs
+/// by more than the tolerance, or that are missing from one of the two files
+pub fn get_cross_check<A: AsRef<Path>>(
+    files_path: A,
+    covdir: &Path,
+    coveralls: &Path,
+    tolerance: f64,
+    options: &AnalysisOptions,
+) -> Result<CrossCheck> {
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let covdir = read_json_covdir(fs::read_to_string(covdir)?, prefix)?;
+    let covdir_reported_coverage = covdir
+        .get("PROJECT_ROOT")
+        .ok_or(Error::HashMapError())?
+        .coverage;
+    let reported = apply_path_map(
+        covdir
+            .iter()
+            .filter(|(name, _)| *name != "PROJECT_ROOT")
+            .map(|(name, covdir)| (name.clone(), covdir.coverage))
+            .collect::<HashMap<String, f64>>(),
+        prefix,
+        options,
+    )?;
+    let covdir = apply_path_map(covdir_to_coverage_data(covdir), prefix, options)?;
+    let coveralls = get_coverage_data(
+        &[CoverageInput::new(
+            JsonFormat::Coveralls,
+            coveralls.to_path_buf(),
+        )],
+        prefix,
+        options,
+    )?;
+    let project_coverage = |covs: &CoverageData| {
+        get_lines_coverage(
+            covs.values()
+                .map(|arr| count_covered_lines(arr))
+                .fold((0, 0), |(covered, coverable), (c, t)| {
+                    (covered + c, coverable + t)
+                }),
+        )
+    };
+    let mut names = covdir
+        .keys()
+        .chain(coveralls.keys())
+        .collect::<Vec<&String>>();
+    names.sort();
+    names.dedup();
+    let files = names
+        .into_iter()
+        .filter_map(|name| {
+            let covdir_coverage = covdir
+                .get(name)
+                .map(|arr| get_lines_coverage(count_covered_lines(arr)));
+            let covdir_reported_coverage = reported.get(name).copied();
+            let coveralls_coverage = coveralls
+                .get(name)
+                .map(|arr| get_lines_coverage(count_covered_lines(arr)));
+            let consistent = match (
+                covdir_coverage,
+                covdir_reported_coverage,
+                coveralls_coverage,
+            ) {
+                (Some(a), Some(r), Some(b)) => {
+                    (a - b).abs() <= tolerance && (a - r).abs() <= tolerance
+                }
+                _ => false,
+            };
+            (!consistent).then(|| CrossCheckFile {
+                file: name.strip_prefix(prefix).unwrap_or(name).to_string(),
+                covdir_coverage,
+                covdir_reported_coverage,
+                coveralls_coverage,
+            })
+        })
+        .collect::<Vec<CrossCheckFile>>();
+    debug!("Files with a different coverage: {}", files.len());
+    Ok(CrossCheck {
+        covdir_coverage: project_coverage(&covdir),
+        covdir_reported_coverage,
+        coveralls_coverage: project_coverage(&coveralls),
+        tolerance,
+        files,
+    })
+}
+
+/// Line totals of a run, an entry of the track file to compare the next runs with
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TrackState {
+    // Time of the run, None in a delta and in the track files written by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub sloc: f64,
+    pub covered_lines: f64,
+    pub coverable_lines: f64,
+    pub coverage: f64,
+    // Coverage debt of the files mode, only when it is recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage_debt: Option<f64>,
+}
+
+impl TrackState {
+    pub fn new(sloc: f64, covered_lines: f64, coverable_lines: f64) -> Self {
+        let coverage = if coverable_lines == 0. {
+            0.
+        } else {
+            f64::round(covered_lines / coverable_lines * 10000.0) / 100.0
+        };
+        Self {
+            timestamp: None,
+            sloc,
+            covered_lines,
+            coverable_lines,
+            coverage,
+            coverage_debt: None,
+        }
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn coverage_debt(mut self, coverage_debt: Option<f64>) -> Self {
+        self.coverage_debt = coverage_debt;
+        self
+    }
+
+    /// Difference of the line totals, of the coverage and of the coverage debt from a previous run,
+    /// the delta has a coverage debt only if both runs recorded it
+    pub fn delta(&self, previous: &TrackState) -> TrackState {
+        TrackState {
+            timestamp: None,
+            sloc: self.sloc - previous.sloc,
+            covered_lines: self.covered_lines - previous.covered_lines,
+            coverable_lines: self.coverable_lines - previous.coverable_lines,
+            coverage: f64::round((self.coverage - previous.coverage) * 100.0) / 100.0,
+            coverage_debt: self
+                .coverage_debt
+                .zip(previous.coverage_debt)
+                .map(|(debt, previous)| debt - previous),
+        }
+    }
+}
+
+/// This Function get the metrics of the files of a run in files mode, computed with the line counts,
+/// and returns the total SLOC, covered and coverable lines of the files with coverage
+/// A file ignored or skipped after the deadline is not in the metrics, so it is not counted
+pub fn get_track_state(metrics: &[FileMetrics]) -> TrackState {
+    let (sloc, covered, coverable) = metrics.iter().filter(|m| m.file_path != "-").fold(
+        (0, 0, 0),
+        |(sloc, covered, coverable), m| {
+            (
+                sloc + m.sloc.unwrap_or_default(),
+                covered + m.covered_lines.unwrap_or_default(),
+                coverable + m.coverable_lines.unwrap_or_default(),
+            )
+        },
+    );
+    TrackState::new(sloc as f64, covered as f64, coverable as f64).timestamp(Utc::now())
+}
+
+/// Read the states of the previous runs from the track file, oldest first,
+/// empty if the file does not exist yet
+/// A track file with a single state, as written by older versions, is a history of one run
+pub fn read_track_history<A: AsRef<Path>>(path: A) -> Result<Vec<TrackState>> {
+    if !path.as_ref().exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read_to_string(path.as_ref())?;
+    let error = |e: serde_json::Error| {
+        Error::TrackStateError(path.as_ref().display().to_string(), e.to_string())
+    };
+    match serde_json::from_str::<Value>(&data).map_err(error)? {
+        Value::Array(_) => serde_json::from_str::<Vec<TrackState>>(&data).map_err(error),
+        _ => Ok(vec![
+            serde_json::from_str::<TrackState>(&data).map_err(error)?
+        ]),
+    }
+}
+
+/// Write the history of the states of the track file, oldest entry first
+pub fn write_track_history<A: AsRef<Path>>(path: A, history: &[TrackState]) -> Result<()> {
+    let data = serde_json::to_string_pretty(history)?;
+    fs::write(path, data)?;
+    Ok(())
+}
+
+/// This Function get the metrics of the files of a run in files mode, computed with the line counts,
+/// and returns the coverage debt of the project, i.e. the complexity left uncovered:
+/// the sum over the files of complexity * (1 - coverage)
+pub fn get_coverage_debt(metrics: &[FileMetrics]) -> f64 {
+    metrics
+        .iter()
+        .filter(|m| m.file_path != "-")
+        .filter_map(|m| m.coverage_debt)
+        .sum()
+}
+
+/// Change of the metrics of a file between the base and the head coverage,
+/// a file present only on one side has no metrics on the other one
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FileDelta {
+    pub file_path: String,
+    pub base: Option<Metrics>,
+    pub head: Option<Metrics>,
+    pub coverage_delta: Option<f64>,
+    pub wcc_plain_delta: Option<f64>,
+    pub crap_delta: Option<f64>,
+    pub skunk_delta: Option<f64>,
+    // The coverage dropped or the file became complex
+    pub regression: bool,
+}
+
+impl FileDelta {
+    pub fn new(file_path: String, base: Option<Metrics>, head: Option<Metrics>) -> Self {
+        let delta = |value: fn(&Metrics) -> f64| -> Option<f64> {
+            let (base, head) = (base.as_ref()?, head.as_ref()?);
+            Some(f64::round((value(head) - value(base)) * 100.0) / 100.0)
+        };
+        let coverage_delta = delta(|m| m.coverage);
+        let regression = match (&base, &head) {
+            (Some(base), Some(head)) => {
+                coverage_delta.is_some_and(|d| d < 0.) || (head.is_complex && !base.is_complex)
+            }
+            (None, Some(head)) => head.is_complex,
+            _ => false,
+        };
+        Self {
+            file_path,
+            coverage_delta,
+            wcc_plain_delta: delta(|m| m.wcc_plain),
+            crap_delta: delta(|m| m.crap),
+            skunk_delta: delta(|m| m.skunk),
+            base,
+            head,
+            regression,
+        }
+    }
+
+    // The metrics of the file are the same in the base and in the head
+    fn is_unchanged(&self) -> bool {
+        self.base == self.head
+    }
+}
+
+/// Direction of a threshold crossing of a file from the base to the head
+#[derive(Copy, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossingKind {
+    /// The file was under the thresholds in the base and is over them in the head
+    Broken,
+    /// The file was over the thresholds in the base and is under them in the head
+    Fixed,
+}
+
+impl fmt::Display for CrossingKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            CrossingKind::Broken => "broken",
+            CrossingKind::Fixed => "fixed",
+        };
+        s.fmt(f)
+    }
+}
+
+/// Metric of a file that crossed its threshold, with its values in the base and in the head
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MetricCrossing {
+    pub metric: String,
+    pub threshold: f64,
+    pub base: f64,
+    pub head: f64,
+}
+
+/// File that went from under the thresholds to over them, or vice versa, from the base to the head
+/// Unlike a regression, a file only present on one side or whose status did not change is not a crossing
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Crossing {
+    pub file_path: String,
+    pub kind: CrossingKind,
+    // Metrics that crossed their thresholds in the same direction of the file
+    pub metrics: Vec<MetricCrossing>,
+    // What made the file cross when no metric crossed its threshold,
+    // uncovered_lines for an uncovered lines budget or priority for a tests per file map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// Get the files of the deltas whose status changed between the base and the head
+// The metrics are checked against the thresholds of each file in the head metrics,
+// the given ones when the file has none
+// A file can be complex without any metric over its threshold, so when no metric crossed
+// the reason is taken from the side where the file is complex
+pub(crate) fn get_crossings(
+    files: &[FileDelta],
+    base_metrics: &[FileMetrics],
+    head_metrics: &[FileMetrics],
+    thresholds: &[f64],
+) -> Vec<Crossing> {
+    let values = |m: &Metrics| [m.wcc_plain, m.wcc_quantized, m.crap, m.skunk];
+    let (base_files, head_files) = (files_by_path(base_metrics), files_by_path(head_metrics));
+    files
+        .iter()
+        .filter_map(|f| {
+            let (base, head) = (f.base.as_ref()?, f.head.as_ref()?);
+            let thresholds = head_files
+                .get(f.file_path.as_str())
+                .map_or(thresholds, |m| m.file_thresholds(thresholds));
+            let (kind, complex) = match (base.is_complex, head.is_complex) {
+                (false, true) => (CrossingKind::Broken, head_files.get(f.file_path.as_str())),
+                (true, false) => (CrossingKind::Fixed, base_files.get(f.file_path.as_str())),
+                _ => return None,
+            };
+            let metrics = THRESHOLD_METRICS
+                .iter()
+                .zip(thresholds)
+                .zip(values(base).into_iter().zip(values(head)))
+                .filter(|((_, t), (b, h))| match kind {
+                    CrossingKind::Broken => b <= t && h > t,
+                    CrossingKind::Fixed => b > t && h <= t,
+                })
+                .map(|((metric, threshold), (base, head))| MetricCrossing {
+                    metric: metric.to_string(),
+                    threshold: *threshold,
+                    base,
+                    head,
+                })
+                .collect::<Vec<MetricCrossing>>();
+            let reason = match complex {
+                Some(m) if metrics.is_empty() => get_crossing_reason(m, thresholds),
+                _ => None,
+            };
+            Some(Crossing {
+                file_path: f.file_path.clone(),
+                kind,
+                metrics,
+                reason,
+            })
+        })
+        .collect()
+}
+
+// Get the files of the metrics by their path, without the summary rows
+fn files_by_path(metrics: &[FileMetrics]) -> HashMap<&str, &FileMetrics> {
+    metrics
+        .iter()
+        .filter(|m| m.file_path != "-")
+        .map(|m| (m.file_path.as_str(), m))
+        .collect()
+}
+
+// Get what made a complex file over none of the metric thresholds complex,
+// its priority over the CRAP threshold or else its uncovered lines budget
+fn get_crossing_reason(m: &FileMetrics, thresholds: &[f64]) -> Option<String> {
+    match (m.priority, m.uncovered_lines) {
+        (Some(priority), _) if priority > thresholds[2] => Some("priority".to_string()),
+        (_, Some(_)) => Some("uncovered_lines".to_string()),
+        _ => None,
+    }
+}
+
+/// Comparison of the metrics computed with the base and the head coverage on the same source
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CompareReport {
+    pub files: Vec<FileDelta>,
+    pub base_coverage: f64,
+    pub head_coverage: f64,
+    pub coverage_delta: f64,
+    pub base_complex_files: usize,
+    pub head_complex_files: usize,
+    pub regressions: usize,
+    // Files whose status changed, newly over or under the thresholds
+    #[serde(default)]
+    pub crossings: Vec<Crossing>,
+}
+
+// Compare the metrics of the files, only the files whose metrics changed are kept,
+// sorted by path
+pub(crate) fn compare_file_metrics(base: &[FileMetrics], head: &[FileMetrics]) -> Vec<FileDelta> {
+    let by_path = |metrics: &[FileMetrics]| -> HashMap<String, Metrics> {
+        metrics
+            .iter()
+            .filter(|m| m.file_path != "-")
+            .map(|m| (m.file_path.clone(), m.metrics))
+            .collect()
+    };
+    let (base, head) = (by_path(base), by_path(head));
+    let mut paths = base.keys().chain(head.keys()).collect::<Vec<&String>>();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .map(|path| {
+            FileDelta::new(
+                path.clone(),
+                base.get(path).copied(),
+                head.get(path).copied(),
+            )
+        })
+        .filter(|delta| !delta.is_unchanged())
+        .collect()
+}
+
+/// This Function get the folder of the repo to analyze and two coverages of it, e.g. of the main
+/// branch and of a pull request, and computes the metrics of the files with both
+/// It returns the files whose metrics changed from the base to the head and the overall deltas
+pub fn get_compare_report<A: AsRef<Path>>(
+    files_path: A,
+    base: &[CoverageInput],
+    head: &[CoverageInput],
+    metric: Complexity,
+    n_threads: usize,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> Result<CompareReport> {
+    let get_metrics = |inputs: &[CoverageInput]| -> Result<Output> {
+        get_metrics_concurrent_merged(
+            files_path.as_ref(),
+            inputs,
+            metric,
+            n_threads,
+            thresholds,
+            Sort::Path,
+            options,
+        )
+    };
+    let (base_metrics, _, base_complex, base_coverage) = get_metrics(base)?;
+    let (head_metrics, _, head_complex, head_coverage) = get_metrics(head)?;
+    let files = compare_file_metrics(&base_metrics, &head_metrics);
+    Ok(CompareReport {
+        regressions: files.iter().filter(|f| f.regression).count(),
+        crossings: get_crossings(&files, &base_metrics, &head_metrics, thresholds),
+        files,
+        base_coverage,
+        head_coverage,
+        coverage_delta: f64::round((head_coverage - base_coverage) * 100.0) / 100.0,
+        base_complex_files: base_complex.len(),
+        head_complex_files: head_complex.len(),
+    })
+}
+
+/// Wall times in seconds of the runs of the analysis with a number of threads
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BenchResult {
+    pub n_threads: usize,
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+    // Mean time of the first number of threads divided by the mean time of this one
+    pub speedup: f64,
+}
+
+/// Wall times of the analysis of a project with different numbers of threads
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BenchReport {
+    pub runs: usize,
+    pub results: Vec<BenchResult>,
+}
+
+impl BenchReport {
+    /// Get the result with the lowest mean time
+    pub fn fastest(&self) -> Option<&BenchResult> {
+        self.results.iter().min_by(|a, b| a.mean.total_cmp(&b.mean))
+    }
+}
+
+/// Numbers of threads of a benchmark, in order, and number of timed runs with each one
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchPlan {
+    pub threads: Vec<usize>,
+    pub runs: usize,
+}
+
+/// This Function get the folder of the repo to analyze and its coverage and runs the analysis
+/// in the given mode with each number of threads of the plan
+/// A first run is not timed and warms up the caches
+/// It returns the min, mean and max wall time of each number of threads
+pub fn get_bench_report<A: AsRef<Path>>(
+    files_path: A,
+    inputs: &[CoverageInput],
+    mode: Mode,
+    metric: Complexity,
+    plan: &BenchPlan,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> Result<BenchReport> {
+    let BenchPlan { threads, runs } = plan;
+    let runs = *runs;
+    if runs == 0 || threads.is_empty() || threads.contains(&0) {
+        return Err(Error::BenchError());
+    }
+    let files_path = files_path.as_ref();
+    let run = |n_threads: usize| -> Result<f64> {
+        let start = Instant::now();
+        if mode != Mode::Functions {
+            get_metrics_concurrent_merged(
+                files_path,
+                inputs,
+                metric,
+                n_threads,
+                thresholds,
+                Sort::Path,
+                options,
+            )?;
+        }
+        if mode != Mode::Files {
+            crate::functions::get_functions_metrics_concurrent_merged(
+                files_path,
+                inputs,
+                metric,
+                n_threads,
+                thresholds,
+                Sort::Path,
+                options,
+            )?;
+        }
+        Ok(start.elapsed().as_secs_f64())
+    };
+    // A first run not timed warms up the caches and the file system, so that
+    // the first number of threads is not slower only because it comes first
+    run(threads[0])?;
+    let mut results = Vec::<BenchResult>::new();
+    for &n_threads in threads {
+        let times = (0..runs)
+            .map(|_| run(n_threads))
+            .collect::<Result<Vec<f64>>>()?;
+        debug!("Wall times with {} threads: {:?}", n_threads, times);
+        let mean = times.iter().sum::<f64>() / runs as f64;
+        let baseline = results.first().map_or(mean, |first| first.mean);
+        results.push(BenchResult {
+            n_threads,
+            min: times.iter().copied().fold(f64::INFINITY, f64::min),
+            mean,
+            max: times.iter().copied().fold(0., f64::max),
+            speedup: baseline / mean,
+        });
+    }
+    Ok(BenchReport { runs, results })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::config::{CoverageFloors, LineExclusions, UncoveredLinesBudgets};
+    use crate::expression::ThresholdExpressions;
+    use crate::metrics::provider::{ComplexityProvider, RustCodeAnalysis};
+    use crate::utility::compare_float;
+
+    const JSON: &str = "./data/seahorse/seahorse.json";
+    const COVDIR: &str = "./data/seahorse/covdir.json";
+    const LCOV: &str = "./data/seahorse/lcov.info";
+    const PROJECT: &str = "./data/seahorse/";
+    const IGNORED: &str = "./data/seahorse/src/action.rs";
+
+    #[test]
+    fn test_metrics_coveralls_cyclomatic() {
+        let json = Path::new(JSON);
+        let project = Path::new(PROJECT);
+        let ignored = Path::new(IGNORED);
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+            project,
+            json,
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
         )
         .unwrap();
         let error = &metrics[3].metrics;
@@ -780,11 +3458,14 @@ mod tests {
         let cont = &metrics[2].metrics;
 
         assert_eq!(files_ignored.len(), 1);
-        assert!(files_ignored[0] == ignored.as_os_str().to_str().unwrap());
+        assert_eq!(
+            files_ignored[0],
+            FileIgnored::new(ignored.to_str().unwrap(), IgnoreReason::NoCoverage)
+        );
         assert!(compare_float(error.wcc_plain, 0.53125));
         assert!(compare_float(error.wcc_quantized, 0.03125));
-        assert!(compare_float(error.crap, 257.95924751059204));
-        assert!(compare_float(error.skunk, 64.00160000000001));
+        assert!(compare_float(error.crap, 257.94117647058823));
+        assert!(compare_float(error.skunk, 64.00000000000001));
         assert!(compare_float(ma.wcc_plain, 0.));
         assert!(compare_float(ma.wcc_quantized, 0.));
         assert!(compare_float(ma.crap, 552.));
@@ -795,14 +3476,196 @@ mod tests {
         assert!(compare_float(h.skunk, 0.));
         assert!(compare_float(app.wcc_plain, 79.21478060046189));
         assert!(compare_float(app.wcc_quantized, 0.792147806004619));
-        assert!(compare_float(app.crap, 123.95346471999996));
-        assert!(compare_float(app.skunk, 53.51999999999998));
-        assert!(compare_float(cont.wcc_plain, 24.31578947368421));
+        assert!(compare_float(app.crap, 123.97408556537728));
+        assert!(compare_float(app.skunk, 53.53535353535352));
+        assert!(compare_float(cont.wcc_plain, 24.31578947368421));
+        assert!(compare_float(cont.wcc_quantized, 0.7368421052631579));
+        assert!(compare_float(cont.crap, 33.468144844401756));
+        assert!(compare_float(cont.skunk, 9.9622641509434));
+    }
+
+    #[test]
+    fn test_metrics_coveralls_cognitive() {
+        let json = Path::new(JSON);
+        let project = Path::new(PROJECT);
+        let ignored = Path::new(IGNORED);
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+            project,
+            json,
+            Complexity::Cognitive,
+            8,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let error = &metrics[3].metrics;
+        let ma = &metrics[7].metrics;
+        let h = &metrics[5].metrics;
+        let app = &metrics[0].metrics;
+        let cont = &metrics[2].metrics;
+
+        assert_eq!(files_ignored.len(), 1);
+        assert_eq!(
+            files_ignored[0],
+            FileIgnored::new(ignored.to_str().unwrap(), IgnoreReason::NoCoverage)
+        );
+        assert!(compare_float(error.wcc_plain, 0.0625));
+        assert!(compare_float(error.wcc_quantized, 0.03125));
+        assert!(compare_float(error.crap, 5.334825971911256));
+        assert!(compare_float(error.skunk, 7.529411764705883));
+        assert!(compare_float(ma.wcc_plain, 0.));
+        assert!(compare_float(ma.wcc_quantized, 0.));
+        assert!(compare_float(ma.crap, 72.));
+        assert!(compare_float(ma.skunk, 32.));
+        assert!(compare_float(h.wcc_plain, 0.));
+        assert!(compare_float(h.wcc_quantized, 0.5));
+        assert!(compare_float(h.crap, 0.));
+        assert!(compare_float(h.skunk, 0.));
+        assert!(compare_float(app.wcc_plain, 66.540415704388));
+        assert!(compare_float(app.wcc_quantized, 0.792147806004619));
+        assert!(compare_float(app.crap, 100.91611477493021));
+        assert!(compare_float(app.skunk, 44.969696969696955));
+        assert!(compare_float(cont.wcc_plain, 18.42105263157895));
+        assert!(compare_float(cont.wcc_quantized, 0.8872180451127819));
+        assert!(compare_float(cont.crap, 25.268678170570336));
+        assert!(compare_float(cont.skunk, 7.547169811320757));
+    }
+
+    #[test]
+    fn test_metrics_covdir_cyclomatic() {
+        let covdir = Path::new(COVDIR);
+        let project = Path::new(PROJECT);
+        let ignored = Path::new(IGNORED);
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent_covdir(
+            project,
+            covdir,
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let error = &metrics[3].metrics;
+        let ma = &metrics[7].metrics;
+        let h = &metrics[5].metrics;
+        let app = &metrics[0].metrics;
+        let cont = &metrics[2].metrics;
+
+        assert_eq!(files_ignored.len(), 1);
+        assert_eq!(
+            files_ignored[0],
+            FileIgnored::new(ignored.to_str().unwrap(), IgnoreReason::NoCoverage)
+        );
+        assert!(compare_float(error.wcc_plain, 0.53125));
+        assert!(compare_float(error.wcc_quantized, 0.03125));
+        assert!(compare_float(error.crap, 257.95924751059204));
+        assert!(compare_float(error.skunk, 64.00160000000001));
+        assert!(compare_float(ma.wcc_plain, 0.));
+        assert!(compare_float(ma.wcc_quantized, 0.));
+        assert!(compare_float(ma.crap, 552.));
+        assert!(compare_float(ma.skunk, 92.));
+        assert!(compare_float(h.wcc_plain, 1.5));
+        assert!(compare_float(h.wcc_quantized, 0.5));
+        assert!(compare_float(h.crap, 3.));
+        assert!(compare_float(h.skunk, 0.));
+        assert!(compare_float(app.wcc_plain, 79.21478060046189));
+        assert!(compare_float(app.wcc_quantized, 0.792147806004619));
+        assert!(compare_float(app.crap, 123.95346471999996));
+        assert!(compare_float(app.skunk, 53.51999999999998));
+        assert!(compare_float(cont.wcc_plain, 24.31578947368421));
         assert!(compare_float(cont.wcc_quantized, 0.7368421052631579));
         assert!(compare_float(cont.crap, 33.468671704875));
         assert!(compare_float(cont.skunk, 9.965999999999998));
     }
 
+    #[test]
+    fn test_metrics_covdir_branches() {
+        // Generated by running the seahorse tests with -Cinstrument-coverage and then
+        // grcov -t covdir --branch, grcov 0.10.7 writes no branch counts in covdir files
+        let fixture = "./data/seahorse/covdir_grcov.json";
+        let (metrics, _, _, project_coverage) = get_metrics_concurrent_covdir(
+            PROJECT,
+            fixture,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        // The project and file coverages are the ones of the grcov report
+        let covdir: Value = serde_json::from_str(&fs::read_to_string(fixture).unwrap()).unwrap();
+        assert_eq!(project_coverage, 75.98);
+        assert_eq!(project_coverage, covdir["coveragePercent"]);
+        let children = &covdir["children"];
+        [
+            ("src/app.rs", 85.98),
+            ("src/command.rs", 84.03),
+            ("src/context.rs", 91.58),
+            ("src/error.rs", 0.),
+            ("src/flag.rs", 89.52),
+            ("src/help.rs", 100.),
+            ("examples/single_app.rs", 0.),
+        ]
+        .iter()
+        .for_each(|(file_path, coverage)| {
+            let m = metrics.iter().find(|m| m.file_path == *file_path).unwrap();
+            let (dir, name) = file_path.split_once('/').unwrap();
+            let entry = &children[dir]["children"][name];
+            assert_eq!(m.metrics.coverage, *coverage);
+            assert_eq!(m.metrics.coverage, entry["coveragePercent"]);
+            // The lines of the report add up to its counts
+            let lines = entry["coverage"].as_array().unwrap();
+            let covered = lines.iter().filter(|l| l.as_i64() > Some(0)).count();
+            let coverable = lines.iter().filter(|l| l.as_i64() >= Some(0)).count();
+            assert_eq!(Some(covered as u64), entry["linesCovered"].as_u64());
+            assert_eq!(Some(coverable as u64), entry["linesTotal"].as_u64());
+            // Without branch counts there is no branch coverage
+            assert_eq!(m.branch_coverage, None);
+        });
+        let project = metrics.iter().find(|m| m.file == "PROJECT").unwrap();
+        assert_eq!(project.branch_coverage, None);
+        // Without the percentages they are computed from the counts
+        let covs = read_json_covdir(
+            json!({
+                "children": {
+                    "lib.rs": {
+                        "coverage": [1, 0, 1],
+                        "linesCovered": 2,
+                        "linesMissed": 1,
+                        "linesTotal": 3,
+                        "branchesCovered": 1,
+                        "branchesTotal": 3,
+                        "name": "lib.rs"
+                    },
+                    "main.rs": {
+                        "coverage": [1],
+                        "coveragePercent": 100.0,
+                        "branchesCovered": 1,
+                        "branchesMissed": 1,
+                        "branchesTotal": 2,
+                        "branchesPercent": 50.0,
+                        "name": "main.rs"
+                    }
+                },
+                "linesCovered": 2,
+                "linesTotal": 3,
+                "name": ""
+            })
+            .to_string(),
+            "",
+        )
+        .unwrap();
+        assert_eq!(covs["PROJECT_ROOT"].coverage, 66.67);
+        assert_eq!(covs["PROJECT_ROOT"].branch_coverage, None);
+        let lib = covs.values().find(|c| c.name == "lib.rs").unwrap();
+        assert_eq!(lib.branch_coverage, Some(33.33));
+        let main = covs.values().find(|c| c.name == "main.rs").unwrap();
+        assert_eq!(main.branch_coverage, Some(50.));
+    }
+
     #[test]
     fn test_metrics_covdir_cognitive() {
         let covdir = Path::new(COVDIR);
@@ -815,6 +3678,7 @@ mod tests {
             8,
             &[30., 1.5, 35., 30.],
             Sort::WccPlain,
+            &AnalysisOptions::default(),
         )
         .unwrap();
         let error = &metrics[3].metrics;
@@ -824,7 +3688,10 @@ mod tests {
         let cont = &metrics[2].metrics;
 
         assert_eq!(files_ignored.len(), 1);
-        assert!(files_ignored[0] == ignored.as_os_str().to_str().unwrap());
+        assert_eq!(
+            files_ignored[0],
+            FileIgnored::new(ignored.to_str().unwrap(), IgnoreReason::NoCoverage)
+        );
         assert!(compare_float(error.wcc_plain, 0.0625));
         assert!(compare_float(error.wcc_quantized, 0.03125));
         assert!(compare_float(error.crap, 5.3350760901120005));
@@ -846,4 +3713,1784 @@ mod tests {
         assert!(compare_float(cont.crap, 25.268980546875));
         assert!(compare_float(cont.skunk, 7.549999999999997));
     }
+
+    #[test]
+    fn test_metrics_merged() {
+        let project = Path::new(PROJECT);
+        let thresholds = [30., 1.5, 35., 30.];
+        let lcov = CoverageInput::new(JsonFormat::Lcov, LCOV.into());
+        let coveralls = CoverageInput::new(JsonFormat::Coveralls, JSON.into());
+        let expected = get_metrics_concurrent(
+            project,
+            JSON,
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let merged = get_metrics_concurrent_merged(
+            project,
+            std::slice::from_ref(&lcov),
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        assert!(merged == expected);
+        let conflict = get_metrics_concurrent_merged(
+            project,
+            &[lcov.clone(), coveralls.clone()],
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        );
+        assert!(matches!(conflict, Err(Error::CoverageConflictError(_))));
+        // Summing the hits does not change which lines are covered
+        let summed = get_metrics_concurrent_merged(
+            project,
+            &[lcov, coveralls],
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default().merge_conflict(MergeConflict::Sum),
+        )
+        .unwrap();
+        assert!(summed == expected);
+    }
+
+    #[test]
+    fn test_matching_diagnostics() {
+        let seahorse = CoverageInput::new(JsonFormat::Coveralls, JSON.into());
+        let diagnostics = get_matching_diagnostics(PROJECT, &[seahorse]).unwrap();
+        assert_eq!(diagnostics.number_of_entries, 9);
+        assert_eq!(diagnostics.number_of_entries_not_matched, 0);
+        assert_eq!(diagnostics.source_files_without_coverage, vec![IGNORED]);
+        let data = CoverageInput::new(JsonFormat::Coveralls, "./data/data.json".into());
+        let diagnostics = get_matching_diagnostics("./data/", &[data]).unwrap();
+        assert_eq!(diagnostics.number_of_entries_not_matched, 1);
+        let main = &diagnostics.entries[0];
+        assert_eq!(main.entry, "./data/main.rs");
+        assert_eq!(
+            main.reason.as_deref(),
+            Some("No file found at the resolved path")
+        );
+        let simple = &diagnostics.entries[1];
+        assert!(simple.matched);
+        assert_eq!(simple.source_file.as_deref(), Some("./data/simple_main.rs"));
+    }
+
+    #[test]
+    fn test_metrics_empty_coverage() {
+        let project = "./data/empty_coverage/";
+        let json = "./data/empty_coverage/coveralls.json";
+        let thresholds = [30., 1.5, 35., 30.];
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+            project,
+            json,
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        assert!(files_ignored.is_empty());
+        let consts = metrics.iter().find(|m| m.file == "consts.rs").unwrap();
+        assert!(consts.no_coverable_lines);
+        assert!(compare_float(consts.metrics.coverage, 100.));
+        assert!(compare_float(consts.metrics.wcc_plain, 0.));
+        let sum = metrics.iter().find(|m| m.file == "sum.rs").unwrap();
+        assert!(!sum.no_coverable_lines);
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+            project,
+            json,
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default().empty_coverage(EmptyCoverage::Ignore),
+        )
+        .unwrap();
+        assert_eq!(
+            files_ignored,
+            vec![FileIgnored::new(
+                "./data/empty_coverage/src/consts.rs",
+                IgnoreReason::NoCoverableLines
+            )]
+        );
+        assert!(metrics.iter().all(|m| m.file != "consts.rs"));
+    }
+
+    #[test]
+    fn test_diff_coverage() {
+        let diff = "diff --git a/src/app.rs b/src/app.rs\n\
+                    --- a/src/app.rs\n\
+                    +++ b/src/app.rs\n\
+                    @@ -10,0 +11,3 @@ impl App {\n\
+                    @@ -40 +43 @@ fn new()\n\
+                    @@ -50,2 +53,0 @@\n\
+                    diff --git a/src/removed.rs b/src/removed.rs\n\
+                    --- a/src/removed.rs\n\
+                    +++ /dev/null\n\
+                    @@ -1,3 +0,0 @@\n";
+        let changed = get_changed_lines_from_diff(diff);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed["src/app.rs"], vec![11, 12, 13, 43]);
+        let covs = CoverageData::from([(
+            "./data/seahorse/src/app.rs".to_string(),
+            vec![Value::Null, Value::from(1), Value::from(0), Value::from(3)],
+        )]);
+        let changed = HashMap::from([
+            ("src/app.rs".to_string(), vec![1, 2, 3, 4, 5]),
+            ("src/flag.rs".to_string(), vec![1]),
+            ("README.md".to_string(), vec![1]),
+        ]);
+        let (files, total, files_without_coverage) =
+            get_diff_coverage_from_covs(Path::new(PROJECT), &covs, &changed);
+        assert_eq!(files_without_coverage, vec!["src/flag.rs"]);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].covered_lines, 2);
+        assert_eq!(files[0].total_lines, 3);
+        assert!(compare_float(total.coverage, 66.67));
+    }
+
+    #[test]
+    fn test_cross_check() {
+        let options = AnalysisOptions::default();
+        let check =
+            get_cross_check(PROJECT, Path::new(COVDIR), Path::new(JSON), 0.01, &options).unwrap();
+        assert!(check.is_consistent());
+        assert!(compare_float(
+            check.covdir_coverage,
+            check.coveralls_coverage
+        ));
+        // Uncover app.rs and drop context.rs from the coveralls file
+        let mut coveralls: Value =
+            serde_json::from_str(&fs::read_to_string(JSON).unwrap()).unwrap();
+        let files = coveralls["source_files"].as_array_mut().unwrap();
+        files.retain(|f| f["name"] != "src/context.rs");
+        files
+            .iter_mut()
+            .filter(|f| f["name"] == "src/app.rs")
+            .flat_map(|f| f["coverage"].as_array_mut().unwrap())
+            .filter(|line| line.is_u64())
+            .for_each(|line| *line = Value::from(0));
+        let path = "./data/seahorse_cross_check.json";
+        fs::write(path, coveralls.to_string()).unwrap();
+        let check = get_cross_check(PROJECT, Path::new(COVDIR), Path::new(path), 0.01, &options);
+        fs::remove_file(path).unwrap();
+        let check = check.unwrap();
+        assert!(!check.is_consistent());
+        assert!(check.coveralls_coverage < check.covdir_coverage);
+        let files = check
+            .files
+            .iter()
+            .map(|f| (f.file.as_str(), f.coveralls_coverage))
+            .collect::<Vec<(&str, Option<f64>)>>();
+        assert_eq!(files, [("src/app.rs", Some(0.)), ("src/context.rs", None)]);
+        assert!(check.files.iter().all(|f| f.covdir_coverage.is_some()));
+        // Change the percentages written in the covdir file, but not its lines
+        let mut covdir: Value = serde_json::from_str(&fs::read_to_string(COVDIR).unwrap()).unwrap();
+        covdir["coveragePercent"] = Value::from(50.);
+        covdir["children"]["src"]["children"]["flag.rs"]["coveragePercent"] = Value::from(10.);
+        let path = "./data/seahorse_cross_check_covdir.json";
+        fs::write(path, covdir.to_string()).unwrap();
+        let check = get_cross_check(PROJECT, Path::new(path), Path::new(JSON), 0.01, &options);
+        fs::remove_file(path).unwrap();
+        let check = check.unwrap();
+        assert!(!check.is_consistent());
+        assert_eq!(check.covdir_reported_coverage, 50.);
+        assert!(compare_float(
+            check.covdir_coverage,
+            check.coveralls_coverage
+        ));
+        let files = check
+            .files
+            .iter()
+            .map(|f| (f.file.as_str(), f.covdir_reported_coverage))
+            .collect::<Vec<(&str, Option<f64>)>>();
+        assert_eq!(files, [("src/flag.rs", Some(10.))]);
+    }
+
+    #[test]
+    fn test_track_state() {
+        let (metrics, _, _, project_coverage) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().line_counts(true),
+        )
+        .unwrap();
+        let state = get_track_state(&metrics);
+        assert_eq!(state.sloc, 1520.);
+        assert_eq!(state.covered_lines, 691.);
+        assert_eq!(state.coverable_lines, 895.);
+        // The line totals give the same coverage of the whole project
+        assert!(compare_float(state.coverage, project_coverage));
+        let previous = TrackState::new(320., 391., 495.);
+        let delta = state.delta(&previous);
+        assert_eq!(delta.sloc, 1200.);
+        assert_eq!(delta.covered_lines, 300.);
+        assert!(compare_float(delta.coverage, -1.78));
+        assert!(read_track_history("./data/seahorse/missing-track.json")
+            .unwrap()
+            .is_empty());
+        // Each run is appended to the history, a single state is a history of one run
+        let track = "./data/seahorse/track.json";
+        fs::write(track, serde_json::to_string(&previous).unwrap()).unwrap();
+        let mut history = read_track_history(track).unwrap();
+        assert_eq!(history, [previous]);
+        history.push(state.clone());
+        write_track_history(track, &history).unwrap();
+        let history = read_track_history(track).unwrap();
+        fs::remove_file(track).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1], state);
+        assert!(history[1].timestamp.is_some());
+    }
+
+    #[test]
+    fn test_coverage_debt() {
+        let (metrics, _, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().line_counts(true),
+        )
+        .unwrap();
+        let debt = get_coverage_debt(&metrics);
+        assert!((debt - 68.403).abs() < 0.001);
+        // Without the line counts the files have no debt and it is not serialized
+        let (no_counts, _, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(get_coverage_debt(&no_counts), 0.);
+        no_counts.iter().for_each(|m| {
+            let value = serde_json::to_value(m).unwrap();
+            ["sloc", "covered_lines", "coverage_debt"]
+                .iter()
+                .for_each(|field| assert!(value.get(field).is_none()));
+            // Only the PROJECT row keeps its coverable lines, for the aggregate reports
+            assert_eq!(value.get("coverable_lines").is_some(), m.file == "PROJECT");
+        });
+        // The summary rows have no debt and a fully covered file has none either
+        assert!(metrics
+            .iter()
+            .all(|m| (m.file_path == "-") == m.coverage_debt.is_none()));
+        assert!(metrics
+            .iter()
+            .filter(|m| m.file_path != "-" && m.metrics.coverage == 100.)
+            .all(|m| m.coverage_debt == Some(0.)));
+        // The debt is recorded in the track state with its delta from the previous run
+        let state = TrackState::new(1520., 691., 895.).coverage_debt(Some(debt));
+        let previous = TrackState::new(320., 391., 495.).coverage_debt(Some(80.));
+        assert_eq!(state.delta(&previous).coverage_debt, Some(debt - 80.));
+        let previous = TrackState::new(320., 391., 495.);
+        assert_eq!(state.delta(&previous).coverage_debt, None);
+    }
+
+    #[test]
+    fn test_bench_report() {
+        let inputs = [CoverageInput::new(JsonFormat::Coveralls, JSON.into())];
+        let bench = |threads: Vec<usize>, runs: usize| {
+            get_bench_report(
+                PROJECT,
+                &inputs,
+                Mode::Files,
+                Complexity::Cyclomatic,
+                &BenchPlan { threads, runs },
+                &[30., 1.5, 35., 30.],
+                &AnalysisOptions::default(),
+            )
+        };
+        let report = bench(vec![1, 2], 2).unwrap();
+        assert_eq!(report.runs, 2);
+        let threads = report
+            .results
+            .iter()
+            .map(|r| r.n_threads)
+            .collect::<Vec<usize>>();
+        assert_eq!(threads, [1, 2]);
+        report.results.iter().for_each(|r| {
+            assert!(0. < r.min && r.min <= r.mean && r.mean <= r.max);
+        });
+        // The speedup is relative to the first number of threads
+        assert!(compare_float(report.results[0].speedup, 1.));
+        assert!(report.fastest().is_some());
+        assert!(matches!(bench(vec![1], 0), Err(Error::BenchError())));
+        assert!(matches!(bench(vec![], 1), Err(Error::BenchError())));
+        assert!(matches!(bench(vec![0, 2], 1), Err(Error::BenchError())));
+    }
+
+    #[test]
+    fn test_compare_report() {
+        let file = |path: &str, coverage: f64, is_complex: bool| {
+            FileMetrics::new(
+                Metrics::default()
+                    .coverage(coverage)
+                    .crap(10.)
+                    .is_complex(is_complex),
+                path.into(),
+                path.into(),
+            )
+        };
+        let base = [
+            file("src/app.rs", 80., false),
+            file("src/flag.rs", 50., false),
+            file("src/help.rs", 30., false),
+            file("src/removed.rs", 10., true),
+        ];
+        let head = [
+            file("src/app.rs", 75.5, false),
+            file("src/flag.rs", 60., true),
+            file("src/help.rs", 30., false),
+            file("src/new.rs", 90., false),
+            FileMetrics::avg(Metrics::default()),
+        ];
+        let files = compare_file_metrics(&base, &head);
+        let paths = files
+            .iter()
+            .map(|f| f.file_path.as_str())
+            .collect::<Vec<&str>>();
+        // The unchanged files and the AVG, MIN and MAX rows are not compared
+        assert_eq!(
+            paths,
+            ["src/app.rs", "src/flag.rs", "src/new.rs", "src/removed.rs"]
+        );
+        assert_eq!(files[0].coverage_delta, Some(-4.5));
+        assert!(files[0].regression);
+        // A file that became complex is a regression even if its coverage grew
+        assert_eq!(files[1].coverage_delta, Some(10.));
+        assert!(files[1].regression);
+        assert_eq!(files[2].base, None);
+        assert_eq!(files[2].coverage_delta, None);
+        assert!(!files[2].regression);
+        assert_eq!(files[3].head, None);
+        assert!(!files[3].regression);
+        // Only the files on both sides whose status changed are crossings
+        let thresholds = [30., 1.5, 35., 30.];
+        let crossings = get_crossings(&files, &base, &head, &thresholds);
+        assert_eq!(crossings.len(), 1);
+        assert_eq!(crossings[0].file_path, "src/flag.rs");
+        assert_eq!(crossings[0].kind, CrossingKind::Broken);
+        // No metric crossed its threshold and without a budget or a priority there is no reason
+        assert!(crossings[0].metrics.is_empty());
+        assert_eq!(crossings[0].reason, None);
+        // A file over its uncovered lines budget crossed with no metric over its threshold
+        let mut budget_head = head.clone();
+        budget_head[1] = budget_head[1].clone().uncovered_lines(Some(12));
+        let crossings = get_crossings(&files, &base, &budget_head, &thresholds);
+        assert_eq!(crossings[0].reason.as_deref(), Some("uncovered_lines"));
+        // A priority over the CRAP threshold is the reason even with a budget
+        budget_head[1] = budget_head[1].clone().priority(Some(40.));
+        let crossings = get_crossings(&files, &base, &budget_head, &thresholds);
+        assert_eq!(crossings[0].reason.as_deref(), Some("priority"));
+        // A crossing has the metrics that crossed their thresholds with both values
+        let fixed = FileDelta::new(
+            "src/help.rs".into(),
+            Some(Metrics::default().crap(30.).skunk(40.).is_complex(true)),
+            Some(Metrics::default().crap(20.).skunk(25.).is_complex(false)),
+        );
+        let crossings = get_crossings(std::slice::from_ref(&fixed), &[], &[], &thresholds);
+        assert_eq!(crossings[0].kind, CrossingKind::Fixed);
+        assert_eq!(
+            crossings[0].metrics,
+            [MetricCrossing {
+                metric: "skunk".into(),
+                threshold: 30.,
+                base: 40.,
+                head: 25.,
+            }]
+        );
+        // The thresholds changed for a file, e.g. by its category, are the ones checked
+        let help = file("src/help.rs", 30., false).thresholds(Some(vec![30., 1.5, 25., 20.]));
+        let crossings = get_crossings(&[fixed], &[], &[help], &thresholds);
+        assert_eq!(crossings[0].reason, None);
+        assert_eq!(
+            crossings[0].metrics,
+            [MetricCrossing {
+                metric: "crap".into(),
+                threshold: 25.,
+                base: 30.,
+                head: 20.,
+            }]
+        );
+        // The same coverage on both sides has no deltas
+        let inputs = [CoverageInput::new(JsonFormat::Coveralls, JSON.into())];
+        let options = AnalysisOptions::default();
+        let report = get_compare_report(
+            PROJECT,
+            &inputs,
+            &inputs,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+        assert!(report.files.is_empty());
+        assert_eq!(report.coverage_delta, 0.);
+        assert_eq!(report.base_complex_files, report.head_complex_files);
+        assert_eq!(report.regressions, 0);
+        assert!(report.crossings.is_empty());
+    }
+
+    // Provider with the same lines of rust-code-analysis but without any complexity
+    #[derive(Debug)]
+    struct NoComplexity;
+
+    impl ComplexityProvider for NoComplexity {
+        fn complexity(&self, path: &Path, source: &[u8]) -> Result<ComplexityMetrics> {
+            let root = RustCodeAnalysis.complexity(path, source)?;
+            Ok(ComplexityMetrics {
+                cyclomatic: 0.,
+                cyclomatic_sum: 0.,
+                spaces: Vec::new(),
+                ..root
+            })
+        }
+    }
+
+    // Provider of a generated file with a complexity so high that CRAP overflows
+    #[derive(Debug)]
+    struct HugeComplexity;
+
+    impl ComplexityProvider for HugeComplexity {
+        fn complexity(&self, path: &Path, source: &[u8]) -> Result<ComplexityMetrics> {
+            let root = RustCodeAnalysis.complexity(path, source)?;
+            Ok(ComplexityMetrics {
+                cyclomatic: 1e200,
+                cyclomatic_sum: 1e200,
+                spaces: Vec::new(),
+                ..root
+            })
+        }
+    }
+
+    #[test]
+    fn test_max_uncovered_lines() {
+        let budgets = UncoveredLinesBudgets::new(
+            "[uncovered_lines]\n\"examples/**\" = 100\n\"examples/single_app.rs\" = 10\n",
+        )
+        .unwrap();
+        assert_eq!(budgets.budget("/examples/multiple_app.rs"), Some(100));
+        // The last matching pattern wins
+        assert_eq!(budgets.budget("examples/single_app.rs"), Some(10));
+        assert_eq!(budgets.budget("src/app.rs"), None);
+        let get_complex_files = |options: &AnalysisOptions| {
+            let (metrics, _, complex_files, _) = get_metrics_concurrent(
+                PROJECT,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                &[1000., 1000., 1000., 1000.],
+                Sort::WccPlain,
+                options,
+            )
+            .unwrap();
+            (metrics, complex_files)
+        };
+        // With thresholds that can not be reached only the files over their budget are complex
+        let (metrics, complex_files) =
+            get_complex_files(&AnalysisOptions::default().max_uncovered_lines(15));
+        let uncovered = |file_path: &str| {
+            metrics
+                .iter()
+                .find(|m| m.file_path == file_path)
+                .and_then(|m| m.uncovered_lines)
+        };
+        assert_eq!(uncovered("src/app.rs"), Some(53));
+        assert_eq!(uncovered("src/lib.rs"), Some(0));
+        assert!(complex_files
+            .iter()
+            .all(|m| m.uncovered_lines.unwrap() > 15));
+        assert_eq!(complex_files.len(), 5);
+        let (_, complex_files) = get_complex_files(
+            &AnalysisOptions::default()
+                .max_uncovered_lines(15)
+                .uncovered_lines_budgets(budgets),
+        );
+        let complex_files = complex_files
+            .iter()
+            .map(|m| m.file_path.as_str())
+            .collect::<Vec<&str>>();
+        assert!(complex_files.contains(&"examples/single_app.rs"));
+        assert!(!complex_files.contains(&"examples/multiple_app.rs"));
+        assert!(get_complex_files(&AnalysisOptions::default()).1.is_empty());
+        assert!(matches!(
+            UncoveredLinesBudgets::new("[uncovered_lines]\n\"**\" = 1.5\n"),
+            Err(Error::UncoveredLinesError(_))
+        ));
+    }
+
+    #[test]
+    fn test_tiers() {
+        let tiers = "3, 2,1".parse::<Tiers>().unwrap();
+        assert_eq!(tiers.tier(3.1), Tier::Critical);
+        assert_eq!(tiers.tier(3.), Tier::High);
+        assert_eq!(tiers.tier(1.5), Tier::Medium);
+        // A file at the threshold is not over it, so it is not complex and it is low
+        assert_eq!(tiers.tier(1.), Tier::Low);
+        assert!(!check_complexity(30., 0., 0., 0., &[30., 1.5, 35., 30.]));
+        assert!("1,2,3".parse::<Tiers>().is_err());
+        assert!("3,2".parse::<Tiers>().is_err());
+        assert!("3,2,x".parse::<Tiers>().is_err());
+        let (metrics, _, complex_files, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::Crap,
+            &AnalysisOptions::default().tiers("2,1.5,1".parse::<Tiers>().unwrap()),
+        )
+        .unwrap();
+        // A file is over the thresholds if and only if it is not low
+        metrics
+            .iter()
+            .filter(|m| m.file_path != "-")
+            .for_each(|m| assert_eq!(m.metrics.is_complex, m.tier != Some(Tier::Low)));
+        // The complex files are grouped by tier and sorted by CRAP inside each one
+        let tiers = complex_files
+            .iter()
+            .map(|m| (m.file_path.as_str(), m.tier.unwrap()))
+            .collect::<Vec<(&str, Tier)>>();
+        assert_eq!(
+            tiers,
+            [
+                ("examples/multiple_app.rs", Tier::Critical),
+                ("src/error.rs", Tier::Critical),
+                ("src/app.rs", Tier::Critical),
+                ("src/flag.rs", Tier::Medium),
+                ("src/command.rs", Tier::Medium)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_metrics_no_coverage_weight() {
+        let get_metrics = |options: &AnalysisOptions| {
+            get_metrics_concurrent(
+                PROJECT,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                Sort::Path,
+                options,
+            )
+            .unwrap()
+        };
+        let globs = get_exclude_globs(&["examples/**".to_string()]).unwrap();
+        let (expected, _, _, coverage) = get_metrics(&AnalysisOptions::default());
+        let (metrics, _, _, exempt_coverage) =
+            get_metrics(&AnalysisOptions::default().no_coverage_weight(globs.clone()));
+        let (_, _, _, excluded_coverage) = get_metrics(&AnalysisOptions::default().exclude(globs));
+        // The exempt files keep their metrics and are marked, but their lines are not in the project coverage
+        metrics
+            .iter()
+            .zip(&expected)
+            .filter(|(m, _)| m.file_path != "-")
+            .for_each(|(m, e)| {
+                assert_eq!(m.metrics, e.metrics);
+                assert_eq!(
+                    m.coverage_exempt,
+                    Some(m.file_path.starts_with("examples/"))
+                );
+            });
+        assert!(metrics.iter().any(|m| m.coverage_exempt == Some(true)));
+        assert!(!compare_float(exempt_coverage, coverage));
+        assert!(compare_float(exempt_coverage, excluded_coverage));
+        // The project coverage of a covdir file already counts all the files
+        let globs = get_exclude_globs(&["examples/**".to_string()]).unwrap();
+        assert!(matches!(
+            get_metrics_concurrent_covdir(
+                PROJECT,
+                COVDIR,
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                Sort::Path,
+                &AnalysisOptions::default().no_coverage_weight(globs),
+            ),
+            Err(Error::ModeError("--no-coverage-weight"))
+        ));
+    }
+
+    #[test]
+    fn test_threshold_expressions() {
+        let get_metrics = |thresholds: &[f64], expressions: &[&str]| {
+            let expressions = expressions.iter().fold(
+                ThresholdExpressions::default(),
+                |expressions, expression| expressions.expression(expression.parse().unwrap()),
+            );
+            get_metrics_concurrent(
+                PROJECT,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                thresholds,
+                Sort::Crap,
+                &AnalysisOptions::default().threshold_expressions(expressions),
+            )
+            .unwrap()
+        };
+        // A constant expression is the same as the constant threshold
+        let (_, _, expected, _) = get_metrics(&[30., 1.5, 35., 30.], &[]);
+        let (_, _, complex_files, _) = get_metrics(&[30., 1.5, 0., 30.], &["crap = 35"]);
+        // The thresholds changed by the expressions are stored with the metrics of the file
+        assert!(complex_files
+            .iter()
+            .all(|m| m.thresholds == Some(vec![30., 1.5, 35., 30.])));
+        let complex_files = complex_files
+            .into_iter()
+            .map(|m| m.thresholds(None))
+            .collect::<Vec<FileMetrics>>();
+        assert_eq!(complex_files, expected);
+        // The threshold of CRAP grows with the SLOC of each file
+        let (metrics, _, complex_files, _) =
+            get_metrics(&[1000., 1000., 0., 1000.], &["crap=2*sqrt(sloc)"]);
+        assert!(!complex_files.is_empty());
+        metrics.iter().filter(|m| m.file_path != "-").for_each(|m| {
+            let path = format!("{PROJECT}{}", m.file_path);
+            let root = get_complexity(Path::new(&path), &AnalysisOptions::default()).unwrap();
+            assert_eq!(m.metrics.is_complex, m.metrics.crap > 2. * root.sloc.sqrt());
+            assert_eq!(m.file_thresholds(&[])[2], 2. * root.sloc.sqrt());
+        });
+    }
+
+    #[test]
+    fn test_tests_per_file() {
+        let test_counts = TestCounts::new(
+            "# tests from the test metadata
+            src/app.rs 20
+            ./src/context.rs 1
+
+            src/flag.rs 20
+            src/help.rs 0",
+        )
+        .unwrap();
+        assert_eq!(test_counts.tests("/src/context.rs"), Some(1));
+        assert_eq!(test_counts.tests("examples/single_app.rs"), None);
+        assert!(compare_float(test_counts.weight(None), 1.));
+        assert!(compare_float(test_counts.weight(Some(20)), 1.));
+        assert!((test_counts.weight(Some(1)) - 4.392317423).abs() < 1e-9);
+        assert!((test_counts.weight(Some(100)) - 0.659684276).abs() < 1e-9);
+        assert_eq!(test_counts.weight(Some(0)), f64::INFINITY);
+        let (metrics, _, complex_files, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().tests_per_file(test_counts.clone()),
+        )
+        .unwrap();
+        let file = |file_path: &str| metrics.iter().find(|m| m.file_path == file_path).unwrap();
+        // The CRAP of context.rs is below the threshold, with a single test it is complex anyway
+        let context = file("src/context.rs");
+        assert!(context.metrics.crap < 35.);
+        assert!(context.metrics.is_complex);
+        assert_eq!(context.tests, Some(1));
+        assert!(compare_float(
+            context.priority.unwrap(),
+            context.metrics.crap * test_counts.weight(Some(1))
+        ));
+        // Without tests the weight is infinite and the priority is saturated
+        let help = file("src/help.rs");
+        assert!(help.metrics.crap > 0.);
+        assert_eq!(help.priority, Some(MAX_METRIC_VALUE));
+        assert!(help.metrics.is_complex);
+        // The files absent from the map have a neutral weight
+        let single_app = file("examples/single_app.rs");
+        assert_eq!(single_app.tests, None);
+        assert!(compare_float(
+            single_app.priority.unwrap(),
+            single_app.metrics.crap
+        ));
+        assert!(!single_app.metrics.is_complex);
+        assert_eq!(complex_files.len(), 7);
+        assert!(complex_files
+            .windows(2)
+            .all(|w| w[0].priority >= w[1].priority));
+        ["src/app.rs", "src/app.rs many", "src/app.rs -1"]
+            .iter()
+            .for_each(|map| {
+                assert!(matches!(
+                    TestCounts::new(map),
+                    Err(Error::TestCountsError(_))
+                ));
+            });
+    }
+
+    #[test]
+    fn test_order_by_fanin() {
+        let mut imports = Vec::new();
+        expand_use_tree(
+            &[],
+            "crate::{error::*, files::{self, Metrics as M}}",
+            &mut imports,
+        );
+        assert_eq!(
+            imports,
+            [
+                vec!["crate", "error"],
+                vec!["crate", "files"],
+                vec!["crate", "files", "Metrics"]
+            ]
+        );
+        let module = ["metrics".to_string(), "provider".to_string()];
+        assert_eq!(resolve_import(&module, &imports[0]), ["error"]);
+        assert_eq!(
+            resolve_import(&module, &["super".into(), "Tree".into()]),
+            ["metrics", "Tree"]
+        );
+        assert_eq!(
+            get_import_statements(
+                "pub(crate) use crate::{\n    a,\n    b, // b\n};\nmod c;\nmod d {}"
+            ),
+            ["use crate::{ a, b, }", "mod c"]
+        );
+        let (metrics, _, complex_files, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().order_by_fanin(true),
+        )
+        .unwrap();
+        let fan_in = |file_path: &str| {
+            metrics
+                .iter()
+                .find(|m| m.file_path == file_path)
+                .and_then(|m| m.fan_in)
+        };
+        // The error module is declared by lib.rs and used by context.rs and flag.rs
+        assert_eq!(fan_in("src/error.rs"), Some(3));
+        // The items re-exported by lib.rs are imported from the crate root
+        assert_eq!(fan_in("src/lib.rs"), Some(4));
+        assert_eq!(fan_in("examples/single_app.rs"), Some(0));
+        assert_eq!(complex_files[0].file_path, "src/error.rs");
+        assert!(complex_files.windows(2).all(|w| w[0].fan_in >= w[1].fan_in));
+    }
+
+    #[test]
+    fn test_strict_analysis() {
+        let project = "./data/syntax_errors/";
+        let broken = "./data/syntax_errors/src/broken.rs";
+        let errors = get_syntax_errors(broken, None).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0], (10, "missing identifier".into()));
+        assert!(get_syntax_errors("./data/syntax_errors/src/valid.rs", None)
+            .unwrap()
+            .is_empty());
+        // The errors are detected on the tree parsed for the metrics
+        let complexity = |path| get_complexity(Path::new(path), &AnalysisOptions::default());
+        assert!(complexity(broken).unwrap().has_syntax_errors);
+        assert!(
+            !complexity("./data/syntax_errors/src/valid.rs")
+                .unwrap()
+                .has_syntax_errors
+        );
+        let inputs = [CoverageInput::new(
+            JsonFormat::Lcov,
+            PathBuf::from("./data/syntax_errors/lcov.info"),
+        )];
+        let get_files_ignored = |strict_analysis: bool| {
+            let options = AnalysisOptions::default().strict_analysis(strict_analysis);
+            let (_, files_ignored, _, _) = get_metrics_concurrent_merged(
+                project,
+                &inputs,
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                Sort::WccPlain,
+                &options,
+            )
+            .unwrap();
+            let (_, functions_ignored, _, _) =
+                crate::functions::get_functions_metrics_concurrent_merged(
+                    project,
+                    &inputs,
+                    Complexity::Cyclomatic,
+                    2,
+                    &[30., 1.5, 35., 30.],
+                    Sort::WccPlain,
+                    &options,
+                )
+                .unwrap();
+            assert_eq!(files_ignored, functions_ignored);
+            files_ignored
+        };
+        // The partial metrics of the file are trusted by default
+        assert!(get_files_ignored(false).is_empty());
+        assert_eq!(
+            get_files_ignored(true),
+            vec![FileIgnored::new(broken, IgnoreReason::SyntaxErrors)]
+        );
+    }
+
+    #[test]
+    fn test_metrics_complexity_too_high() {
+        let (metrics, _, complex_files, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::Crap,
+            &AnalysisOptions::default().complexity_provider(Arc::new(HugeComplexity)),
+        )
+        .unwrap();
+        metrics.iter().filter(|m| m.file_path != "-").for_each(|m| {
+            assert_eq!(m.metrics.crap, MAX_METRIC_VALUE);
+            assert!(m.metrics.complexity_too_high);
+            assert!(m.metrics.is_complex);
+        });
+        assert!(complex_files.iter().all(|m| m.metrics.crap.is_finite()));
+        // The saturated values are exported as numbers instead of null
+        assert!(!serde_json::to_string(&metrics).unwrap().contains("null"));
+        // The flag is exported only when it is set
+        let metrics = Metrics::default();
+        assert!(serde_json::to_value(metrics)
+            .unwrap()
+            .get("complexity_too_high")
+            .is_none());
+        let metrics = Metrics::default().complexity_too_high(true);
+        assert_eq!(
+            serde_json::to_value(metrics).unwrap()["complexity_too_high"],
+            true
+        );
+    }
+
+    #[test]
+    fn test_metrics_complexity_provider() {
+        let (metrics, files_ignored, complex_files, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().complexity_provider(Arc::new(NoComplexity)),
+        )
+        .unwrap();
+        assert_eq!(
+            files_ignored,
+            vec![FileIgnored::new(IGNORED, IgnoreReason::NoCoverage)]
+        );
+        assert!(complex_files.is_empty());
+        metrics.iter().for_each(|m| {
+            assert!(compare_float(m.metrics.wcc_plain, 0.));
+            assert!(compare_float(m.metrics.crap, 0.));
+            assert!(compare_float(m.metrics.skunk, 0.));
+        });
+        // The functions are read from rust-code-analysis, so functions mode rejects the provider
+        assert!(matches!(
+            crate::functions::get_functions_metrics_concurrent(
+                PROJECT,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                Sort::WccPlain,
+                &AnalysisOptions::default().complexity_provider(Arc::new(NoComplexity)),
+            ),
+            Err(Error::ModeError("A custom complexity provider"))
+        ));
+    }
+
+    // Provider that hangs on src/app.rs
+    #[derive(Debug)]
+    struct SlowComplexity;
+
+    impl ComplexityProvider for SlowComplexity {
+        fn complexity(&self, path: &Path, source: &[u8]) -> Result<ComplexityMetrics> {
+            if path.ends_with("src/app.rs") {
+                thread::sleep(std::time::Duration::from_secs(2));
+            }
+            RustCodeAnalysis.complexity(path, source)
+        }
+    }
+
+    #[test]
+    fn test_metrics_file_timeout() {
+        let options = AnalysisOptions::default()
+            .complexity_provider(Arc::new(SlowComplexity))
+            .file_timeout(std::time::Duration::from_millis(500));
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        // The file that times out is ignored and the others are still analyzed
+        assert_eq!(files_ignored.len(), 2);
+        assert!(files_ignored
+            .iter()
+            .any(|f| f.file.ends_with("src/app.rs") && f.reason == IgnoreReason::Timeout));
+        assert!(metrics.iter().all(|m| m.file != "app.rs"));
+        assert!(metrics.iter().any(|m| m.file == "flag.rs"));
+    }
+
+    #[test]
+    fn test_metrics_deadline() {
+        let thresholds = [30., 1.5, 35., 30.];
+        let (expected, expected_ignored, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        // Nothing is skipped before the deadline
+        let deadline = Deadline::new(Instant::now() + std::time::Duration::from_secs(3600));
+        let options = AnalysisOptions::default().deadline(deadline.clone());
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &thresholds,
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(deadline.skipped(), 0);
+        assert_eq!(metrics.len(), expected.len());
+        assert_eq!(files_ignored, expected_ignored);
+        assert!(metrics.iter().all(|m| m.files_skipped.is_none()));
+        // The files reached after the deadline are skipped and counted on the PROJECT row,
+        // they are not added to the files ignored
+        let deadline = Deadline::new(Instant::now());
+        let options = AnalysisOptions::default().deadline(deadline.clone());
+        let (metrics, files_ignored, complex_files, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &thresholds,
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        assert!(deadline.is_expired());
+        assert!(files_ignored.is_empty());
+        let expected_files = expected.iter().filter(|m| m.file_path != "-").count();
+        assert_eq!(deadline.skipped(), expected_files + expected_ignored.len());
+        assert!(metrics.iter().all(|m| m.file_path == "-"));
+        let project = metrics.iter().find(|m| m.file == "PROJECT").unwrap();
+        assert_eq!(project.files_skipped, Some(deadline.skipped()));
+        assert!(complex_files.is_empty());
+        // The max runtime counts from the start of the run, not from the creation of the options
+        let start = Instant::now() - std::time::Duration::from_secs(10);
+        let options = AnalysisOptions::default()
+            .start(start)
+            .max_runtime(std::time::Duration::from_secs(5));
+        assert!(options.deadline.unwrap().is_expired());
+    }
+
+    #[test]
+    fn test_source_files_cache() {
+        let project = Path::new("./data/test_project/");
+        let entries = ["./data/test_project/src/flag.rs".to_string()];
+        let options = AnalysisOptions::default();
+        let files = get_source_files(project, entries.iter(), &options).unwrap();
+        assert_eq!(files, vec!["./data/test_project/src/flag.rs"]);
+        assert!(options.source_files_cache.is_none());
+        let options = options.cache_paths(true);
+        let files = get_source_files(project, entries.iter(), &options).unwrap();
+        let SourceFilesCache(cache) = options.source_files_cache.clone().unwrap();
+        assert_eq!(
+            cache.lock().unwrap()[&get_source_files_key(project, entries.iter())].matched,
+            files
+        );
+        let cached = get_source_files(project, entries.iter(), &options).unwrap();
+        assert_eq!(cached, files);
+        let uncached =
+            get_source_files(project, entries.iter(), &options.cache_paths(false)).unwrap();
+        assert_eq!(uncached, files);
+    }
+
+    #[test]
+    fn test_metrics_sort_path() {
+        let (metrics, _, complex_files, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::Path,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        // The last four entries are PROJECT, AVG, MAX and MIN
+        let files = &metrics[..metrics.len() - 4];
+        assert_eq!(files[0].file_path, "examples/multiple_app.rs");
+        assert!(files.windows(2).all(|w| w[0].file_path < w[1].file_path));
+        assert!(!complex_files.is_empty());
+        assert!(complex_files
+            .windows(2)
+            .all(|w| w[0].file_path < w[1].file_path));
+    }
+
+    #[test]
+    fn test_zero_coverage_files() {
+        let (metrics, _, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().line_counts(true),
+        )
+        .unwrap();
+        let files = get_zero_coverage_files(&metrics);
+        // Only the two examples are completely untested, the biggest one first
+        let paths = files
+            .iter()
+            .map(|f| f.file_path.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            paths,
+            ["examples/multiple_app.rs", "examples/single_app.rs"]
+        );
+        assert!(files[0].sloc > files[1].sloc);
+        assert!(files.iter().all(|f| f.complexity >= 1.0));
+    }
+
+    #[test]
+    fn test_metrics_line_exclusions() {
+        let get_metrics = |exclusions: &str| {
+            get_metrics_concurrent(
+                PROJECT,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                Sort::WccPlain,
+                &AnalysisOptions::default()
+                    .line_exclusions(LineExclusions::new(exclusions).unwrap()),
+            )
+        };
+        let (metrics, _, _, _) =
+            get_metrics("[exclusions]\n\"src/error.rs\" = [[14, 34]]\n").unwrap();
+        // Only the covered line 4 is left
+        let error = metrics.iter().find(|m| m.file == "error.rs").unwrap();
+        assert_eq!(error.excluded_lines, Some(16));
+        assert!(compare_float(error.metrics.coverage, 100.));
+        assert!(metrics
+            .iter()
+            .filter(|m| m.file != "error.rs")
+            .all(|m| m.excluded_lines.is_none()));
+        // The ranges must be inside the file
+        assert!(matches!(
+            get_metrics("[exclusions]\n\"src/error.rs\" = [[30, 40]]\n"),
+            Err(Error::LineExclusionsError(_))
+        ));
+    }
+
+    #[test]
+    fn test_metrics_comments() {
+        let options = AnalysisOptions::default().show_comment_ratio(true);
+        let (metrics, _, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        let context = metrics.iter().find(|m| m.file == "context.rs").unwrap();
+        assert!((context.comment_ratio.unwrap() - 35.09).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_metrics_active_features() {
+        let project = "./data/cfg_features/";
+        let json = "./data/cfg_features/coveralls.json";
+        let get_metrics = |options: &AnalysisOptions| {
+            get_metrics_concurrent(
+                project,
+                json,
+                Complexity::Cyclomatic,
+                1,
+                &[30., 1.5, 35., 30.],
+                Sort::WccPlain,
+                options,
+            )
+        };
+        let (metrics, _, _, coverage) = get_metrics(&AnalysisOptions::default()).unwrap();
+        assert!(metrics[0].cfg_excluded_lines.is_none());
+        assert!(coverage < 100.);
+        // The uncovered lines behind the inactive fast feature are excluded
+        let options = AnalysisOptions::default().active_features(HashSet::from(["std".into()]));
+        let (metrics, _, _, coverage) = get_metrics(&options).unwrap();
+        let lib = metrics.iter().find(|m| m.file == "lib.rs").unwrap();
+        assert_eq!(lib.cfg_excluded_lines, Some(3));
+        assert!(compare_float(lib.metrics.coverage, 100.));
+        assert!(compare_float(coverage, 100.));
+        // The covered lines behind an inactive feature are excluded too
+        let options = AnalysisOptions::default().active_features(HashSet::from(["fast".into()]));
+        let (metrics, _, _, _) = get_metrics(&options).unwrap();
+        let lib = metrics.iter().find(|m| m.file == "lib.rs").unwrap();
+        assert_eq!(lib.cfg_excluded_lines, Some(2));
+        assert!(matches!(
+            get_metrics_concurrent_covdir(
+                PROJECT,
+                COVDIR,
+                Complexity::Cyclomatic,
+                1,
+                &[30., 1.5, 35., 30.],
+                Sort::WccPlain,
+                &options,
+            ),
+            Err(Error::ModeError("--active-features"))
+        ));
+    }
+
+    #[test]
+    fn test_metrics_exclude() {
+        let options = AnalysisOptions::default()
+            .exclude(get_exclude_globs(&["examples/**".into(), "src/help.rs".into()]).unwrap());
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        assert!(metrics
+            .iter()
+            .all(|m| m.file_path != "examples/single_app.rs" && m.file_path != "src/help.rs"));
+        assert!(metrics.iter().any(|m| m.file_path == "src/app.rs"));
+        assert_eq!(
+            files_ignored,
+            vec![FileIgnored::new(IGNORED, IgnoreReason::NoCoverage)]
+        );
+    }
+
+    #[test]
+    fn test_metrics_files_from() {
+        let list = "./data/seahorse_files_from.txt";
+        fs::write(
+            list,
+            "# Shard 1\nsrc/app.rs\n./src/flag.rs\n\nsrc/action.rs\n",
+        )
+        .unwrap();
+        let options = AnalysisOptions::default().files_from(read_files_from(list).unwrap());
+        fs::remove_file(list).unwrap();
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        // The last four entries are PROJECT, AVG, MAX and MIN
+        let mut files = metrics[..metrics.len() - 4]
+            .iter()
+            .map(|m| m.file_path.as_str())
+            .collect::<Vec<&str>>();
+        files.sort();
+        assert_eq!(files, ["src/app.rs", "src/flag.rs"]);
+        // A listed file without coverage is still ignored
+        assert_eq!(
+            files_ignored,
+            vec![FileIgnored::new(IGNORED, IgnoreReason::NoCoverage)]
+        );
+    }
+
+    #[test]
+    fn test_metrics_categories() {
+        let categories = FileCategories::new("# Comment\nsrc/** core\nsrc/app.rs ui\n")
+            .unwrap()
+            .multiplier("ui", 100.);
+        let thresholds = [30., 1.5, 35., 30.];
+        let (metrics, _, complex_files, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default().categories(categories),
+        )
+        .unwrap();
+        let category = |file: &str| {
+            metrics
+                .iter()
+                .find(|m| m.file == file)
+                .and_then(|m| m.category.clone())
+        };
+        assert_eq!(category("app.rs").as_deref(), Some("ui"));
+        assert_eq!(category("flag.rs").as_deref(), Some("core"));
+        assert_eq!(category("single_app.rs").as_deref(), Some(DEFAULT_CATEGORY));
+        // The multiplier of ui raises the thresholds above the metrics of app.rs
+        let app = metrics.iter().find(|m| m.file == "app.rs").unwrap();
+        assert!(app.metrics.crap > thresholds[2]);
+        assert!(!app.metrics.is_complex);
+        assert!(complex_files.iter().all(|m| m.file != "app.rs"));
+    }
+
+    #[test]
+    fn test_metrics_sample() {
+        let thresholds = [30., 1.5, 35., 30.];
+        let sampled = |size: usize, seed: u64| {
+            let options = AnalysisOptions::default().sample(Sample::new(size, seed));
+            get_metrics_concurrent(
+                PROJECT,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                &thresholds,
+                Sort::WccPlain,
+                &options,
+            )
+            .unwrap()
+        };
+        // The hash of the sample is fixed, so a seed samples the same files with every build
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(
+            get_sample_rank(0, "src\\flag.rs"),
+            get_sample_rank(0, "src/flag.rs")
+        );
+        let (metrics, files_ignored, _, project_coverage) = sampled(3, 0);
+        let mut paths = metrics
+            .iter()
+            .filter(|m| m.file_path != "-")
+            .map(|m| m.file_path.as_str())
+            .collect::<Vec<&str>>();
+        paths.sort();
+        assert_eq!(paths, ["src/command.rs", "src/context.rs", "src/flag.rs"]);
+        // Only the files with coverage are sampled
+        assert!(files_ignored.is_empty());
+        let project = metrics.iter().find(|m| m.file == "PROJECT").unwrap();
+        assert_eq!(
+            project.estimate,
+            Some(Estimate {
+                sampled_files: 3,
+                total_files: 9,
+                seed: 0
+            })
+        );
+        assert!(metrics
+            .iter()
+            .filter(|m| m.file != "PROJECT")
+            .all(|m| m.estimate.is_none()));
+        // The same seed gives the same sample and covdir estimates the same coverage
+        assert_eq!(sampled(3, 0).0, metrics);
+        let (_, _, _, covdir_coverage) = get_metrics_concurrent_covdir(
+            PROJECT,
+            COVDIR,
+            Complexity::Cyclomatic,
+            2,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default().sample(Sample::new(3, 0)),
+        )
+        .unwrap();
+        assert!(compare_float(covdir_coverage, project_coverage));
+        // A sample with all the files is not an estimate
+        let expected = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let (all, _, _, all_coverage) = sampled(9, 0);
+        assert_eq!(all, expected.0);
+        assert!(compare_float(all_coverage, expected.3));
+    }
+
+    #[test]
+    fn test_coverage_dir_inputs() {
+        let project = Path::new(PROJECT);
+        let thresholds = [30., 1.5, 35., 30.];
+        let dir = Path::new("./data/coverage_dir/");
+        fs::create_dir_all(dir).unwrap();
+        // One coverage json for each half of the files, as if they were two crates
+        let mut coveralls: Value =
+            serde_json::from_str(&fs::read_to_string(JSON).unwrap()).unwrap();
+        let source_files = coveralls["source_files"].as_array().unwrap().clone();
+        let (first, second) = source_files.split_at(source_files.len() / 2);
+        coveralls["source_files"] = json!(first);
+        fs::write(dir.join("a.json"), coveralls.to_string()).unwrap();
+        coveralls["source_files"] = json!(second);
+        fs::write(dir.join("b.json"), coveralls.to_string()).unwrap();
+        fs::write(dir.join("notes.txt"), "not a coverage file").unwrap();
+        let inputs = get_coverage_dir_inputs(dir);
+        let merged = inputs.as_ref().ok().map(|inputs| {
+            get_metrics_concurrent_merged(
+                project,
+                inputs,
+                Complexity::Cyclomatic,
+                1,
+                &thresholds,
+                Sort::WccPlain,
+                &AnalysisOptions::default(),
+            )
+        });
+        fs::write(dir.join("c.json"), r#"{"files": []}"#).unwrap();
+        let unknown = get_coverage_dir_inputs(dir);
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(
+            inputs.unwrap(),
+            vec![
+                CoverageInput::new(JsonFormat::Coveralls, dir.join("a.json")),
+                CoverageInput::new(JsonFormat::Coveralls, dir.join("b.json")),
+            ]
+        );
+        let expected = get_metrics_concurrent(
+            project,
+            JSON,
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        assert!(merged.unwrap().unwrap() == expected);
+        assert!(matches!(unknown, Err(Error::CoverageFormatError(_))));
+        assert!(matches!(
+            get_coverage_dir_inputs("./data/seahorse/examples/"),
+            Err(Error::EmptyCoverageDirError(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_metrics_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let project = Path::new("./data/non_utf8/");
+        let src = project.join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::copy("./data/empty_coverage/src/sum.rs", src.join("sum.rs")).unwrap();
+        let non_utf8 = src.join(OsStr::from_bytes(b"inv\xffalid.rs"));
+        // Some filesystems only accept UTF-8 names
+        if fs::copy("./data/empty_coverage/src/sum.rs", &non_utf8).is_err() {
+            fs::remove_dir_all(project).unwrap();
+            return;
+        }
+        let json = project.join("coveralls.json");
+        fs::write(
+            &json,
+            r#"{"source_files": [{"name": "src/sum.rs", "coverage": [1, 1, 1, null, 0, null, null]}]}"#,
+        )
+        .unwrap();
+        let result = get_metrics_concurrent(
+            project,
+            &json,
+            Complexity::Cyclomatic,
+            1,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        );
+        fs::remove_dir_all(project).unwrap();
+        let (metrics, files_ignored, _, _) = result.unwrap();
+        assert!(metrics.iter().any(|m| m.file == "sum.rs"));
+        assert_eq!(
+            files_ignored,
+            vec![FileIgnored::new(
+                format!("./data/non_utf8/src/inv\u{FFFD}alid.rs{NON_UTF8_MARKER}"),
+                IgnoreReason::NoCoverage
+            )]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_metrics_non_utf8_project_folder() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let project = Path::new("./data/").join(OsStr::from_bytes(b"non_utf8_\xff/"));
+        let src = project.join("src");
+        // Some filesystems only accept UTF-8 names
+        if fs::create_dir_all(&src).is_err() {
+            return;
+        }
+        fs::copy("./data/empty_coverage/src/sum.rs", src.join("sum.rs")).unwrap();
+        let json = project.join("coveralls.json");
+        fs::write(
+            &json,
+            r#"{"source_files": [{"name": "src/sum.rs", "coverage": [1, 1, 1, null, 0, null, null]}]}"#,
+        )
+        .unwrap();
+        let result = get_metrics_concurrent(
+            &project,
+            &json,
+            Complexity::Cyclomatic,
+            1,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        );
+        fs::remove_dir_all(&project).unwrap();
+        // The files of the project folder are still matched and read from the real path
+        let (metrics, files_ignored, _, _) = result.unwrap();
+        assert!(files_ignored.is_empty());
+        let sum = metrics.iter().find(|m| m.file == "sum.rs").unwrap();
+        assert_eq!(sum.file_path, "src/sum.rs");
+        assert!(compare_float(sum.metrics.coverage, 75.));
+    }
+
+    #[test]
+    fn test_coveralls_payload() {
+        let original: Value = serde_json::from_str(&fs::read_to_string(JSON).unwrap()).unwrap();
+        let inputs = [CoverageInput::new(
+            JsonFormat::Coveralls,
+            PathBuf::from(JSON),
+        )];
+        let payload = get_coveralls_payload(PROJECT, &inputs, &AnalysisOptions::default()).unwrap();
+        // The metadata is emitted unchanged
+        let original = original.as_object().unwrap();
+        let payload = payload.as_object().unwrap();
+        assert_eq!(original.len(), payload.len());
+        original
+            .iter()
+            .filter(|(key, _)| *key != "source_files")
+            .for_each(|(key, value)| assert_eq!(payload.get(key), Some(value)));
+        assert_eq!(payload["git"]["branch"], "master");
+        // As well as the name and the coverage of each source file
+        let source_files = original["source_files"].as_array().unwrap();
+        assert_eq!(
+            source_files.len(),
+            payload["source_files"].as_array().unwrap().len()
+        );
+        source_files.iter().for_each(|file| {
+            let emitted = payload["source_files"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|f| f["name"] == file["name"])
+                .unwrap();
+            assert_eq!(emitted["coverage"], file["coverage"]);
+            assert_eq!(emitted["source_digest"], file["source_digest"]);
+            assert_eq!(emitted["branches"], file["branches"]);
+        });
+        // Without a coveralls file the digest is the SipHash of the source
+        let inputs = [CoverageInput::new(JsonFormat::Lcov, PathBuf::from(LCOV))];
+        let payload = get_coveralls_payload(PROJECT, &inputs, &AnalysisOptions::default()).unwrap();
+        let app = payload["source_files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|f| f["name"] == "src/app.rs")
+            .unwrap();
+        assert_eq!(app["source_digest"], "7c17e25956d61d28f4bbaaa5bfbbd002");
+        // The branches of the same file are merged, summing their hits
+        let branches = |hits: u64| {
+            json!({
+                "source_files": [{
+                    "name": "src/app.rs",
+                    "source_digest": "4829c8c7488ca36ddc47fb973eb1994a",
+                    "coverage": [1, null],
+                    "branches": [1, 0, 0, hits, 1, 0, 1, 0]
+                }]
+            })
+        };
+        let inputs = [1, 2].map(|hits| {
+            let path = PathBuf::from(format!("./data/seahorse/branches_{hits}.json"));
+            fs::write(&path, branches(hits).to_string()).unwrap();
+            CoverageInput::new(JsonFormat::Coveralls, path)
+        });
+        let payload = get_coveralls_payload(
+            PROJECT,
+            &inputs,
+            &AnalysisOptions::default().merge_conflict(MergeConflict::Sum),
+        );
+        inputs
+            .iter()
+            .for_each(|input| fs::remove_file(&input.path).unwrap());
+        let payload = payload.unwrap();
+        let app = &payload["source_files"][0];
+        assert_eq!(app["source_digest"], "4829c8c7488ca36ddc47fb973eb1994a");
+        assert_eq!(app["coverage"], json!([2, null]));
+        assert_eq!(app["branches"], json!([1, 0, 0, 3, 1, 0, 1, 0]));
+    }
+
+    #[test]
+    fn test_path_map() {
+        let path_map = PathMap::new(
+            "# Coverage collected on CI\n\n/ci/build/examples/=examples/\n/ci/build/=\n/ci/=src/\n",
+        )
+        .unwrap();
+        assert_eq!(path_map.rules().len(), 3);
+        // The first matching rule wins
+        assert_eq!(
+            path_map.rewrite("/ci/build/src/app.rs"),
+            Some((1, "src/app.rs".into()))
+        );
+        assert_eq!(path_map.rewrite("src/app.rs"), None);
+        let mut coveralls: Value =
+            serde_json::from_str(&fs::read_to_string(JSON).unwrap()).unwrap();
+        coveralls["source_files"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(|file| {
+                file["name"] = format!("/ci/build/{}", file["name"].as_str().unwrap()).into();
+            });
+        let covs = read_json(coveralls.to_string(), PROJECT).unwrap();
+        let (rewritten, counts) = rewrite_coverage_paths(covs.clone(), PROJECT, &path_map).unwrap();
+        let examples = rewritten
+            .keys()
+            .filter(|name| name.starts_with("./data/seahorse/examples/"))
+            .count();
+        assert!(examples > 0);
+        let covs_len = covs.len();
+        assert_eq!(counts, vec![examples, covs_len - examples, 0]);
+        // The rewritten paths match the files of the project folder
+        let thresholds = [30., 1.5, 35., 30.];
+        let options = AnalysisOptions::default().path_map(path_map);
+        let (metrics, files_ignored, _, _) = get_metrics_concurrent_from_covs(
+            Path::new(PROJECT),
+            apply_path_map(covs, PROJECT, &options).unwrap(),
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        let (expected, expected_ignored, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            1,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        // The PROJECT row has the paths rewritten by each rule
+        let mut metrics = metrics;
+        let project = metrics.iter_mut().find(|m| m.file == "PROJECT").unwrap();
+        let rules = project.path_map.take().unwrap();
+        assert_eq!(
+            rules
+                .iter()
+                .map(|rule| (rule.from.as_str(), rule.to.as_str(), rule.rewritten))
+                .collect::<Vec<(&str, &str, usize)>>(),
+            [
+                ("/ci/build/examples/", "examples/", examples),
+                ("/ci/build/", "", covs_len - examples),
+                ("/ci/", "src/", 0),
+            ]
+        );
+        assert!(metrics == expected);
+        assert_eq!(files_ignored, expected_ignored);
+        // Two paths rewritten to the same one are a conflict
+        let conflict = PathMap::new("/ci/build/src/context.rs=src/app.rs\n/ci/build/=\n").unwrap();
+        let covs = read_json(coveralls.to_string(), PROJECT).unwrap();
+        assert!(matches!(
+            rewrite_coverage_paths(covs, PROJECT, &conflict),
+            Err(Error::CoverageConflictError(_))
+        ));
+        ["no rule", "=src/"].iter().for_each(|map| {
+            assert!(matches!(PathMap::new(map), Err(Error::PathMapError(_))));
+        });
+    }
+
+    #[test]
+    fn test_owner_metrics() {
+        let codeowners = CodeOwners::new(
+            "# Default owners\n* @core\n/src/ @seahorse @core # inline comment\nerror.rs @errors\n/src/app.rs\n",
+        )
+        .unwrap();
+        assert_eq!(
+            codeowners.owners("src/context.rs", OwnersMatch::MostSpecific),
+            vec!["@core", "@seahorse"]
+        );
+        assert_eq!(
+            codeowners.owners("/src/error.rs", OwnersMatch::MostSpecific),
+            vec!["@errors"]
+        );
+        assert_eq!(
+            codeowners.owners("src/error.rs", OwnersMatch::Each),
+            vec!["@core", "@errors", "@seahorse"]
+        );
+        // The last matching rule has no owners
+        assert!(codeowners
+            .owners("src/app.rs", OwnersMatch::MostSpecific)
+            .is_empty());
+        assert_eq!(
+            codeowners.owners("examples/src/lib.rs", OwnersMatch::MostSpecific),
+            vec!["@core"]
+        );
+        let (metrics, _, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let files = metrics.iter().filter(|m| m.file_path != "-").count();
+        let owners = get_owner_metrics(&metrics, &codeowners, OwnersMatch::MostSpecific);
+        assert_eq!(
+            owners
+                .iter()
+                .map(|o| o.owner.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["(unowned)", "@core", "@errors", "@seahorse"]
+        );
+        // Each file counts once for every one of its owners
+        assert_eq!(owners[0].number_of_files, 1);
+        assert_eq!(owners[2].number_of_files, 1);
+        assert_eq!(
+            owners[0].number_of_files + owners[1].number_of_files + owners[2].number_of_files,
+            files
+        );
+        let app = metrics
+            .iter()
+            .find(|m| m.file_path.ends_with("src/app.rs"))
+            .unwrap();
+        assert_eq!(owners[0].crap_sum, app.metrics.crap);
+        assert_eq!(owners[0].avg.coverage, app.metrics.coverage);
+        assert_eq!(owners[0].max_crap, app.metrics.crap);
+        assert!(owners[3].avg.coverage <= 100.);
+        // Counting the owners of every rule no file is unowned
+        let each = get_owner_metrics(&metrics, &codeowners, OwnersMatch::Each);
+        assert_eq!(each[0].owner, "@core");
+        assert_eq!(each[0].number_of_files, files);
+    }
+
+    #[test]
+    fn test_directory_floors() {
+        let floors =
+            CoverageFloors::new("[coverage_floors]\n\"**\" = 50\n\"src\" = 90\n\"tests/**\" = 0\n")
+                .unwrap();
+        assert_eq!(floors.floor("src"), Some(90.));
+        assert_eq!(floors.floor("src/parser"), Some(50.));
+        assert!(matches!(
+            CoverageFloors::new("[coverage_floors]\n\"src\" = 120\n"),
+            Err(Error::CoverageFloorsError(_))
+        ));
+        let (mut metrics, _, _, _) = get_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            1,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().line_counts(true),
+        )
+        .unwrap();
+        // A file of the project folder itself is in the "." directory
+        let root = metrics
+            .iter_mut()
+            .find(|m| m.file_path.starts_with("src/"))
+            .unwrap();
+        root.file_path = "main.rs".into();
+        // A file of a subdirectory counts also for its parent directories
+        let nested = metrics
+            .iter_mut()
+            .find(|m| m.file_path.starts_with("src/"))
+            .unwrap();
+        nested.file_path = "src/parser/lexer.rs".into();
+        let lines = |files: &[&FileMetrics]| {
+            files.iter().fold((0, 0), |(covered, coverable), m| {
+                (
+                    covered + m.covered_lines.unwrap(),
+                    coverable + m.coverable_lines.unwrap(),
+                )
+            })
+        };
+        let files = metrics
+            .iter()
+            .filter(|m| m.file_path != "-")
+            .collect::<Vec<&FileMetrics>>();
+        let src = files
+            .iter()
+            .filter(|m| m.file_path.starts_with("src/"))
+            .copied()
+            .collect::<Vec<&FileMetrics>>();
+        let directories = get_directory_floors(&metrics, &floors);
+        assert_eq!(
+            directories
+                .iter()
+                .map(|d| (d.directory.as_str(), d.floor))
+                .collect::<Vec<(&str, f64)>>(),
+            vec![
+                (".", 50.),
+                ("examples", 50.),
+                ("src", 90.),
+                ("src/parser", 50.)
+            ]
+        );
+        // The project folder has all the files, with the same coverage of the PROJECT row
+        assert_eq!(directories[0].number_of_files, files.len());
+        assert_eq!(
+            (directories[0].covered_lines, directories[0].coverable_lines),
+            lines(&files)
+        );
+        let project = metrics.iter().find(|m| m.file == "PROJECT").unwrap();
+        assert_eq!(
+            directories[0].coverable_lines,
+            project.coverable_lines.unwrap()
+        );
+        assert_eq!(directories[2].number_of_files, src.len());
+        assert_eq!(
+            (directories[2].covered_lines, directories[2].coverable_lines),
+            lines(&src)
+        );
+        assert!(compare_float(
+            directories[2].coverage,
+            get_lines_coverage(lines(&src))
+        ));
+        assert_eq!(directories[3].number_of_files, 1);
+        assert_eq!(directories[2].is_below(), directories[2].coverage < 90.);
+    }
 }
@@ -1,17 +1,21 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::fs;
 use std::path::*;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 use crossbeam::channel::{unbounded, Receiver};
+use rust_code_analysis::FuncSpace;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::error::*;
 use crate::files::*;
+use crate::modes::*;
 use crate::utility::*;
 
 /// Struct with all the metrics computed for the root
@@ -24,6 +28,14 @@ pub struct RootMetrics {
     pub start_line: usize,
     pub end_line: usize,
     pub functions: Vec<FunctionMetrics>,
+    // Files skipped because the max runtime has been reached, only in the PROJECT row of an incomplete run
+    // and in its sidecar files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_skipped: Option<usize>,
+    // Rules of the path map with the number of paths each one has rewritten, only in the PROJECT row
+    // of a run with a path map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_map: Option<Vec<PathMapRule>>,
 }
 impl RootMetrics {
     pub fn new(
@@ -41,17 +53,26 @@ impl RootMetrics {
             start_line,
             end_line,
             functions,
+            ..Self::default()
         }
     }
 
+    pub fn files_skipped(mut self, files_skipped: Option<usize>) -> Self {
+        self.files_skipped = files_skipped;
+        self
+    }
+
+    pub fn path_map(mut self, path_map: Option<Vec<PathMapRule>>) -> Self {
+        self.path_map = path_map;
+        self
+    }
+
     pub fn avg(m: Metrics) -> Self {
         Self {
             metrics: m,
             file_name: "AVG".into(),
             file_path: "-".into(),
-            start_line: 0,
-            end_line: 0,
-            functions: Vec::<FunctionMetrics>::new(),
+            ..Self::default()
         }
     }
 
@@ -60,9 +81,7 @@ impl RootMetrics {
             metrics: m,
             file_name: "MIN".into(),
             file_path: "-".into(),
-            start_line: 0,
-            end_line: 0,
-            functions: Vec::<FunctionMetrics>::new(),
+            ..Self::default()
         }
     }
 
@@ -71,9 +90,7 @@ impl RootMetrics {
             metrics: m,
             file_name: "MAX".into(),
             file_path: "-".into(),
-            start_line: 0,
-            end_line: 0,
-            functions: Vec::<FunctionMetrics>::new(),
+            ..Self::default()
         }
     }
 }
@@ -87,6 +104,24 @@ pub struct FunctionMetrics {
     pub function_path: String,
     pub start_line: usize,
     pub end_line: usize,
+    // Coverage needed to pass the CRAP threshold, only for the functions above it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_coverage: Option<TargetCoverage>,
+    // Whether the coverage of the function is exact or estimated, only with covdir files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage_source: Option<CoverageSource>,
+    // The function is marked with a #[deprecated] attribute
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+    // Call count of the function in the hotness map, only with a hotness map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calls: Option<f64>,
+    // CRAP weighted by the hotness of the function, only with a hotness map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+    // Coverage required by the length of the function, only with a coverage by length
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_coverage: Option<f64>,
 }
 impl FunctionMetrics {
     pub fn new(
@@ -102,35 +137,318 @@ impl FunctionMetrics {
             function_path,
             start_line,
             end_line,
+            ..Self::default()
+        }
+    }
+
+    pub fn target_coverage(mut self, target_coverage: Option<TargetCoverage>) -> Self {
+        self.target_coverage = target_coverage;
+        self
+    }
+
+    pub fn coverage_source(mut self, coverage_source: CoverageSource) -> Self {
+        self.coverage_source = Some(coverage_source);
+        self
+    }
+
+    pub fn deprecated(mut self, deprecated: bool) -> Self {
+        self.deprecated = deprecated;
+        self
+    }
+
+    pub fn calls(mut self, calls: Option<f64>) -> Self {
+        self.calls = calls;
+        self
+    }
+
+    pub fn priority(mut self, priority: Option<f64>) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn required_coverage(mut self, required_coverage: Option<f64>) -> Self {
+        self.required_coverage = required_coverage;
+        self
+    }
+}
+
+/// Call counts of the functions of a project, e.g. obtained with a profiler,
+/// used to rank the hot functions higher
+#[derive(Clone, Debug, Default)]
+pub struct Hotness {
+    calls: Vec<(PathBuf, String, f64)>,
+    median: f64,
+}
+
+impl Hotness {
+    /// Read the hotness map, each line has a function given as path.rs::function_name,
+    /// with the path relative to the project folder, and its call count separated by whitespaces
+    /// Empty lines and lines starting with # are skipped
+    pub fn new(map: &str) -> Result<Self> {
+        let mut calls = map
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| -> Result<(PathBuf, String, f64)> {
+                let (selector, count) = line
+                    .rsplit_once(char::is_whitespace)
+                    .ok_or(Error::HotnessError(line.into()))?;
+                let count = count
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|count| *count >= 0.)
+                    .ok_or(Error::HotnessError(line.into()))?;
+                let (file, function) = parse_function_selector(selector.trim())?;
+                Ok((file.to_path_buf(), function.into(), count))
+            })
+            .collect::<Result<Vec<(PathBuf, String, f64)>>>()?;
+        calls.sort_by(|a, b| a.2.total_cmp(&b.2));
+        let median = calls.get(calls.len() / 2).map_or(0., |c| c.2);
+        Ok(Self { calls, median })
+    }
+
+    pub fn from_file<A: AsRef<Path>>(path: A) -> Result<Self> {
+        Self::new(&fs::read_to_string(path)?)
+    }
+
+    /// Get the call count of a function given the path of its file relative to the project folder
+    pub fn calls(&self, file_path: &str, function: &str) -> Option<f64> {
+        let file_path = Path::new(file_path.trim_start_matches('/'));
+        self.calls
+            .iter()
+            .find(|(file, name, _)| name == function && file_path.ends_with(file))
+            .map(|(_, _, calls)| *calls)
+    }
+
+    /// Get the weight of a call count, ln(1 + calls) / ln(1 + median) with the median of the map:
+    /// 1 for the median and for the functions absent from the map, more for the hotter ones
+    /// and 0 for the functions never called
+    pub fn weight(&self, calls: Option<f64>) -> f64 {
+        calls.map_or(1., |calls| get_log_ratio(calls, self.median))
+    }
+}
+
+/// Minimum coverage of a function that scales with its length, given as SLOC:COVERAGE
+/// breakpoints separated by commas, e.g. 50:60,200:80,1000:90
+/// The coverage is interpolated between the breakpoints and is the one of the first
+/// or of the last breakpoint for the functions shorter or longer than them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageByLength {
+    breakpoints: Vec<(f64, f64)>,
+}
+
+impl FromStr for CoverageByLength {
+    type Err = String;
+
+    fn from_str(coverage_by_length: &str) -> std::result::Result<Self, Self::Err> {
+        let mut breakpoints = coverage_by_length
+            .split(',')
+            .map(|breakpoint| {
+                let (sloc, coverage) = breakpoint
+                    .split_once(':')
+                    .ok_or(format!("{breakpoint:?} is not in the SLOC:COVERAGE form."))?;
+                let sloc = sloc
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|sloc| *sloc >= 0.)
+                    .ok_or(format!("{sloc:?} is not a valid number of lines."))?;
+                let coverage = coverage
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|coverage| (0. ..=100.).contains(coverage))
+                    .ok_or(format!("{coverage:?} is not a valid coverage."))?;
+                Ok((sloc, coverage))
+            })
+            .collect::<std::result::Result<Vec<(f64, f64)>, Self::Err>>()?;
+        breakpoints.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self { breakpoints })
+    }
+}
+
+impl CoverageByLength {
+    /// Get the coverage required to a function with the given number of source lines
+    pub fn required_coverage(&self, sloc: f64) -> f64 {
+        let next = self.breakpoints.iter().position(|(s, _)| *s >= sloc);
+        match next {
+            Some(0) => self.breakpoints[0].1,
+            Some(i) => {
+                let (s0, c0) = self.breakpoints[i - 1];
+                let (s1, c1) = self.breakpoints[i];
+                c0 + (c1 - c0) * (sloc - s0) / (s1 - s0)
+            }
+            None => self.breakpoints.last().map_or(0., |(_, c)| *c),
+        }
+    }
+}
+
+// Get the coverage required by the length of a function and mark the function as complex
+// when its coverage is lower, the required coverage is None without a coverage by length
+fn apply_coverage_by_length(
+    space: &FuncSpace,
+    m: Metrics,
+    options: &AnalysisOptions,
+) -> (Metrics, Option<f64>) {
+    match &options.coverage_by_length {
+        Some(coverage_by_length) => {
+            let required = coverage_by_length.required_coverage(space.metrics.loc.sloc());
+            let is_complex = m.is_complex || m.coverage < required;
+            (m.is_complex(is_complex), Some(required))
+        }
+        None => (m, None),
+    }
+}
+
+// Get the call count and the priority, the CRAP weighted by the hotness, of a function
+// Both are None without a hotness map
+fn get_function_priority(
+    space: &FuncSpace,
+    m: &Metrics,
+    file_path: &str,
+    options: &AnalysisOptions,
+) -> (Option<f64>, Option<f64>) {
+    match &options.hotness {
+        Some(hotness) => {
+            let calls = space
+                .name
+                .as_deref()
+                .and_then(|name| hotness.calls(file_path, name));
+            let weight = hotness.weight(calls);
+            (
+                calls,
+                Some(get_priority(m.crap, weight, options.max_metric_value)),
+            )
+        }
+        None => (None, None),
+    }
+}
+
+// Sort the complex functions by the chosen metric,
+// by the priority if there is a hotness map, unless they are sorted by path
+fn sort_complex_functions(functions: &mut [FunctionMetrics], sort_by: Sort) {
+    functions.sort_by(|a, b| match (sort_by, a.priority, b.priority) {
+        // The functions keep the order of their files, already sorted by path
+        (Sort::Path, _, _) => Ordering::Equal,
+        (_, Some(x), Some(y)) => y.total_cmp(&x),
+        _ => a.metrics.compare(&b.metrics, sort_by),
+    });
+}
+
+/// Source of the coverage used for the metrics of a function
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverageSource {
+    /// Coverage of the function given in the coverage file
+    Exact,
+    /// Coverage of the whole file, used when the coverage of the function is missing
+    Estimated,
+}
+
+impl fmt::Display for CoverageSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CoverageSource::Exact => write!(f, "exact"),
+            CoverageSource::Estimated => write!(f, "estimated"),
         }
     }
 }
 
-type Output = (Vec<RootMetrics>, Vec<String>, Vec<FunctionMetrics>, f64);
+/// Coverage that a function must reach to pass the CRAP threshold
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum TargetCoverage {
+    /// Minimum coverage percentage
+    Coverage(f64),
+    /// The complexity is too high to pass the threshold even with full coverage
+    Unreachable,
+}
+
+impl fmt::Display for TargetCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TargetCoverage::Coverage(coverage) => write!(f, "{coverage:.2}%"),
+            TargetCoverage::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
+
+/// Solve the CRAP formula COMP^2 * (1 - COV)^3 + COMP for the coverage,
+/// keeping the complexity fixed, to get the coverage needed to not exceed the CRAP threshold
+/// With full coverage CRAP is equal to COMP, so a COMP above the threshold is unreachable
+pub fn get_target_coverage(comp: f64, crap_threshold: f64) -> TargetCoverage {
+    if comp > crap_threshold {
+        return TargetCoverage::Unreachable;
+    }
+    if comp <= 0. {
+        return TargetCoverage::Coverage(0.);
+    }
+    let uncovered = ((crap_threshold - comp) / comp.powf(2.)).cbrt().min(1.);
+    TargetCoverage::Coverage(f64::round((1. - uncovered) * 10000.) / 100.)
+}
+
+// Get the target coverage of a function if it is requested and the function exceeds the CRAP threshold
+fn get_function_target_coverage(
+    space: &FuncSpace,
+    m: &Metrics,
+    metric: Complexity,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> Option<TargetCoverage> {
+    if !options.show_target_coverage || m.crap <= thresholds[2] {
+        return None;
+    }
+    let comp = match metric {
+        Complexity::Cyclomatic => space.metrics.cyclomatic.cyclomatic_sum(),
+        Complexity::Cognitive => space.metrics.cognitive.cognitive_sum(),
+    };
+    Some(get_target_coverage(comp, thresholds[2]))
+}
+
+type Output = (
+    Vec<RootMetrics>,
+    Vec<FileIgnored>,
+    Vec<FunctionMetrics>,
+    f64,
+);
+
+// The functions are read from the spaces of rust-code-analysis,
+// so a custom complexity provider cannot be used in functions mode
+fn check_complexity_provider(options: &AnalysisOptions, analysis: Analysis) -> Result<()> {
+    check_modes(
+        &[(
+            "A custom complexity provider",
+            options.complexity_provider.is_some(),
+        )],
+        analysis,
+    )
+}
 
 // job received by the consumer threads
 #[derive(Clone)]
 struct JobItem {
     chunk: Vec<String>,
-    covs: HashMap<String, Vec<Value>>,
+    covs: Arc<CoverageData>,
     metric: Complexity,
-    prefix: usize,
+    files_path: PathBuf,
     thresholds: Vec<f64>,
+    options: AnalysisOptions,
 }
 impl JobItem {
     fn new(
         chunk: Vec<String>,
-        covs: HashMap<String, Vec<Value>>,
+        covs: Arc<CoverageData>,
         metric: Complexity,
-        prefix: usize,
+        files_path: PathBuf,
         thresholds: Vec<f64>,
+        options: AnalysisOptions,
     ) -> Self {
         Self {
             chunk,
             covs,
             metric,
-            prefix,
+            files_path,
             thresholds,
+            options,
         }
     }
 }
@@ -139,8 +457,8 @@ impl fmt::Debug for JobItem {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Job: chunks:{:?}, metric:{}, prefix:{:?}, thresholds: {:?}",
-            self.chunk, self.metric, self.prefix, self.thresholds
+            "Job: chunks:{:?}, metric:{}, files_path:{:?}, thresholds: {:?}",
+            self.chunk, self.metric, self.files_path, self.thresholds
         )
     }
 }
@@ -149,14 +467,14 @@ impl fmt::Debug for JobItem {
 #[derive(Clone, Default, Debug)]
 pub struct FunctionConfig {
     pub(crate) res: Arc<Mutex<Vec<RootMetrics>>>,
-    pub(crate) files_ignored: Arc<Mutex<Vec<String>>>,
+    pub(crate) files_ignored: Arc<Mutex<Vec<FileIgnored>>>,
 }
 
 impl FunctionConfig {
     fn new() -> Self {
         Self {
             res: Arc::new(Mutex::new(Vec::<RootMetrics>::new())),
-            files_ignored: Arc::new(Mutex::new(Vec::<String>::new())),
+            files_ignored: Arc::new(Mutex::new(Vec::<FileIgnored>::new())),
         }
     }
 
@@ -170,6 +488,180 @@ impl FunctionConfig {
 
 type JobReceiver = Receiver<Option<JobItem>>;
 
+// Apply the deprecated policy of the options to a function inside the deprecated ranges
+// The function is always reported and marked as deprecated, only with the gate policy
+// it is counted as complex
+fn apply_deprecated_policy(
+    function: FunctionMetrics,
+    deprecated: &[(usize, usize)],
+    options: &AnalysisOptions,
+) -> FunctionMetrics {
+    let is_deprecated = deprecated
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&function.start_line));
+    if !is_deprecated {
+        return function;
+    }
+    match options.deprecated_policy {
+        DeprecatedPolicy::Gate => return function.deprecated(true),
+        DeprecatedPolicy::Exclude => debug!(
+            "Deprecated function {:?} excluded from the thresholds",
+            function.function_path
+        ),
+        DeprecatedPolicy::Warn if function.metrics.is_complex => warn!(
+            "Deprecated function {} is over the thresholds, not counted as complex",
+            function.function_path
+        ),
+        DeprecatedPolicy::Warn => {}
+    }
+    let metrics = function.metrics.is_complex(false);
+    FunctionMetrics {
+        metrics,
+        ..function
+    }
+    .deprecated(true)
+}
+
+// Get the root space of a file and if its syntax tree has errors within the file timeout of the options
+fn get_root_with_timeout(path: &Path, options: &AnalysisOptions) -> Result<(FuncSpace, bool)> {
+    let file = path.to_path_buf();
+    let command = options.compile_command(path).cloned();
+    analyze_with_timeout(path, options, move || {
+        get_root_with_errors(&file, command.as_ref())
+    })
+}
+
+// Compute the metrics of a single file and of its functions given its coverage, shared by the consumers
+// of every format, the covdir files also give the coverage of the file and of some of its functions
+// It returns the metrics of the file and its contribution to the project metrics,
+// or the reason why the file must be added to the files ignored
+fn get_root_metrics(
+    file: &str,
+    arr: &[Value],
+    covdir: Option<&Covdir>,
+    metric: Complexity,
+    files_path: &Path,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> Result<std::result::Result<(RootMetrics, JobComposer), IgnoreReason>> {
+    let path = &*get_source_path(files_path, file);
+    let prefix = get_files_prefix(files_path).len();
+    let file_name = path
+        .file_name()
+        .ok_or(Error::PathConversionError())?
+        .to_string_lossy()
+        .into();
+    let coverage = covdir.map(|covdir| covdir.coverage);
+    // A file whose analysis times out is added to the files ignored
+    let Some((root, has_errors)) = skip_on_timeout(get_root_with_timeout(path, options))? else {
+        return Ok(Err(IgnoreReason::Timeout));
+    };
+    // With the strict analysis a file parsed only partially is added to the files ignored
+    if has_syntax_errors(path, has_errors, options)? {
+        return Ok(Err(IgnoreReason::SyntaxErrors));
+    }
+    // A file without coverable lines is either ignored or counted as fully covered
+    let no_coverable_lines = !has_coverable_lines(arr, root.start_line, root.end_line);
+    if no_coverable_lines && options.empty_coverage == EmptyCoverage::Ignore {
+        return Ok(Err(IgnoreReason::NoCoverableLines));
+    }
+    let (covered_lines, tot_lines) = get_covered_lines(arr, root.start_line, root.end_line)?;
+    debug!(
+        "File: {:?} covered lines: {}  total lines: {}",
+        file, covered_lines, tot_lines
+    );
+    let spaces = get_spaces(&root)?;
+    let ploc = root.metrics.loc.ploc();
+    let comp = match metric {
+        Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
+        Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
+    };
+    let deprecated = get_deprecated_ranges(path)?;
+    let file_path = file.to_string().split_off(prefix);
+    let mut functions = Vec::<FunctionMetrics>::new();
+    spaces.iter().try_for_each(|el| -> Result<()> {
+        let space = el.0;
+        let function_path = el.1.to_string();
+        let function_name = format!(
+            "{} ({}, {})",
+            space.name.as_ref().ok_or(Error::PathConversionError())?,
+            space.start_line,
+            space.end_line
+        );
+        // With a covdir file the coverage of the function is used if present in it,
+        // otherwise the one of the whole file
+        let (function_coverage, coverage_source) = match covdir.map(|covdir| {
+            covdir
+                .functions
+                .iter()
+                .find(|f| f.start_line == space.start_line)
+        }) {
+            Some(Some(f)) => (Some(f.coverage), Some(CoverageSource::Exact)),
+            Some(None) => (coverage, Some(CoverageSource::Estimated)),
+            None => (None, None),
+        };
+        let (m, _): (Metrics, (f64, f64)) = Tree::get_metrics_from_space(
+            space,
+            arr,
+            metric,
+            function_coverage,
+            thresholds,
+            options.max_metric_value,
+        )?;
+        let (m, required_coverage) = apply_coverage_by_length(space, m, options);
+        let target_coverage = get_function_target_coverage(space, &m, metric, thresholds, options);
+        let (calls, priority) = get_function_priority(space, &m, &file_path, options);
+        let function = FunctionMetrics::new(
+            m,
+            function_name,
+            function_path,
+            space.start_line,
+            space.end_line,
+        )
+        .target_coverage(target_coverage)
+        .calls(calls)
+        .priority(priority)
+        .required_coverage(required_coverage);
+        let function = match coverage_source {
+            Some(coverage_source) => function.coverage_source(coverage_source),
+            None => function,
+        };
+        functions.push(apply_deprecated_policy(function, &deprecated, options));
+        Ok(())
+    })?;
+    record_function_sources(path, &file_path, arr, &functions, options)?;
+    let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) = if no_coverable_lines {
+        get_metrics_without_coverable_lines(&root, metric, thresholds, options.max_metric_value)?
+    } else {
+        Tree::get_metrics_from_space(
+            &root,
+            arr,
+            metric,
+            coverage,
+            thresholds,
+            options.max_metric_value,
+        )?
+    };
+    let composer_output = JobComposer {
+        covered_lines,
+        total_lines: tot_lines,
+        wcc_plain_sum: sp_sum,
+        wcc_quantized_sum: sq_sum,
+        ploc_sum: ploc,
+        comp_sum: comp,
+        ..JobComposer::default()
+    };
+    let root_metrics = RootMetrics::new(
+        m,
+        file_name,
+        file_path,
+        root.start_line,
+        root.end_line,
+        functions,
+    );
+    Ok(Ok((root_metrics, composer_output)))
+}
+
 // Consumer function run by ead independent thread
 fn consumer(
     receiver: JobReceiver,
@@ -189,80 +681,34 @@ fn consumer(
         let chunk = job.chunk;
         let covs = job.covs;
         let metric = job.metric;
-        let prefix = job.prefix;
+        let files_path = job.files_path;
         let thresholds = job.thresholds;
+        let options = job.options;
         // For each file in the chunk received
         for file in chunk {
-            let path = Path::new(&file);
-            let file_name = path
-                .file_name()
-                .ok_or(Error::PathConversionError())?
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .into();
+            // The files reached after the deadline are skipped
+            if is_past_deadline(&options) {
+                composer_output.files_skipped += 1;
+                continue;
+            }
             // Get the coverage vector from the coveralls file
             // if not present the file will be added to the files ignored
             let arr = match covs.get(&file) {
-                Some(arr) => arr.to_vec(),
+                Some(arr) => arr,
                 None => {
                     let mut f = files_ignored.lock()?;
-                    f.push(file);
+                    f.push(FileIgnored::new(file, IgnoreReason::NoCoverage));
                     continue;
                 }
             };
-            let root = get_root(path)?;
-            let (covered_lines, tot_lines) =
-                get_covered_lines(&arr, root.start_line, root.end_line)?;
-            debug!(
-                "File: {:?} covered lines: {}  total lines: {}",
-                file, covered_lines, tot_lines
-            );
-            let spaces = get_spaces(&root)?;
-            let ploc = root.metrics.loc.ploc();
-            let comp = match metric {
-                Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-                Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-            };
-            let mut functions = Vec::<FunctionMetrics>::new();
-            spaces.iter().try_for_each(|el| -> Result<()> {
-                let space = el.0;
-                let function_path = el.1.to_string();
-                let (m, _): (Metrics, (f64, f64)) =
-                    Tree::get_metrics_from_space(space, &arr, metric, None, &thresholds)?;
-                let function_name = format!(
-                    "{} ({}, {})",
-                    space.name.as_ref().ok_or(Error::PathConversionError())?,
-                    space.start_line,
-                    space.end_line
-                );
-                functions.push(FunctionMetrics::new(
-                    m,
-                    function_name,
-                    function_path,
-                    space.start_line,
-                    space.end_line,
-                ));
-                Ok(())
-            })?;
-            let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
-                Tree::get_metrics_from_space(&root, &arr, metric, None, &thresholds)?;
-            let file_path = file.clone().split_off(prefix);
             // Upgrade all the global variables and add metrics to the result and complex_files
-            let mut res = res.lock()?;
-            composer_output.covered_lines += covered_lines;
-            composer_output.total_lines += tot_lines;
-            composer_output.ploc_sum += ploc;
-            composer_output.wcc_plain_sum += sp_sum;
-            composer_output.wcc_quantized_sum += sq_sum;
-            composer_output.comp_sum += comp;
-            res.push(RootMetrics::new(
-                m,
-                file_name,
-                file_path,
-                root.start_line,
-                root.end_line,
-                functions,
-            ));
+            match get_root_metrics(&file, arr, None, metric, &files_path, &thresholds, &options)? {
+                Ok((root_metrics, file_composer)) => {
+                    composer_output.add(&file_composer);
+                    res.lock()?.push(root_metrics);
+                }
+                Err(reason) => files_ignored.lock()?.push(FileIgnored::new(file, reason)),
+            }
         }
     }
     if let Err(_e) = sender_composer.send(Some(composer_output)) {
@@ -272,16 +718,6 @@ fn consumer(
     Ok(())
 }
 
-// Chunks the vector of files in multiple chunk to be used by threads
-// It will return number of chunk with the same number of elements usually equal
-// Or very close to n_threads
-fn chunk_vector(vec: Vec<String>, n_threads: usize) -> Vec<Vec<String>> {
-    let chunks = vec.chunks((vec.len() / n_threads).max(1));
-    chunks
-        .map(|chunk| chunk.iter().map(|c| c.into()).collect::<Vec<String>>())
-        .collect::<Vec<Vec<String>>>()
-}
-
 /// This Function get the folder of the repo to analyzed and the path to the coveralls file obtained using grcov
 /// It also takes as arguments the complexity metrics that must be used between cognitive or cyclomatic
 /// If the a file is not found in the json that files will be skipped
@@ -293,21 +729,74 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
     n_threads: usize,
     thresholds: &[f64],
     sort_by: Sort,
+    options: &AnalysisOptions,
 ) -> Result<Output> {
     if thresholds.len() != 4 {
         return Err(Error::ThresholdsError());
     }
-    // Take all the files starting from the given project folder
-    let vec = read_files(files_path.as_ref())?;
+    check_complexity_provider(options, Analysis::Functions)?;
     // Read coveralls file to string and then get all the coverage vectors
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
-    )?;
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let covs = options.record_stage("reading coverage", || {
+        let file = fs::read_to_string(json_path)?;
+        apply_path_map(read_json(file, prefix)?, prefix, options)
+    })?;
+    get_functions_metrics_concurrent_from_covs(
+        files_path.as_ref(),
+        covs,
+        metric,
+        n_threads,
+        thresholds,
+        sort_by,
+        options,
+    )
+}
+
+/// This Function get the folder of the repo to analyzed and a list of coverage files, each one with its format
+/// All the coverage files are merged in a single coverage before computing the metrics,
+/// the files present in more than one coverage file are handled following the merge conflict of the options
+/// It also takes as arguments the complexity metrics that must be used between cognitive or cyclomatic
+/// If the a file is not found in the coverage that files will be skipped
+/// It returns the  tuple (res, files_ignored, complex_files, project_coverage)
+pub fn get_functions_metrics_concurrent_merged<A: AsRef<Path>>(
+    files_path: A,
+    inputs: &[CoverageInput],
+    metric: Complexity,
+    n_threads: usize,
+    thresholds: &[f64],
+    sort_by: Sort,
+    options: &AnalysisOptions,
+) -> Result<Output> {
+    if thresholds.len() != 4 {
+        return Err(Error::ThresholdsError());
+    }
+    check_complexity_provider(options, Analysis::Functions)?;
+    let covs = options.record_stage("reading coverage", || {
+        get_coverage_data(inputs, &get_files_prefix(files_path.as_ref()), options)
+    })?;
+    get_functions_metrics_concurrent_from_covs(
+        files_path.as_ref(),
+        covs,
+        metric,
+        n_threads,
+        thresholds,
+        sort_by,
+        options,
+    )
+}
+
+// Compute the metrics of all the functions in the project folder using the given coverage
+fn get_functions_metrics_concurrent_from_covs(
+    files_path: &Path,
+    covs: CoverageData,
+    metric: Complexity,
+    n_threads: usize,
+    thresholds: &[f64],
+    sort_by: Sort,
+    options: &AnalysisOptions,
+) -> Result<Output> {
+    // Take all the files starting from the given project folder
+    let vec = get_source_files(files_path, covs.keys(), options)?;
     let mut handlers = vec![];
     // Create a new vonfig with  all needed mutexes
     let cfg = FunctionConfig::new();
@@ -327,22 +816,19 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
         let h = thread::spawn(move || -> Result<()> { consumer(r, s, &config) });
         handlers.push(h);
     }
-    let prefix = files_path
-        .as_ref()
-        .to_str()
-        .ok_or(Error::PathConversionError())?
-        .to_string()
-        .len();
+    // The coverage is shared by all the jobs without copying it
+    let covs = Arc::new(covs);
     // Send all chunks to the consumers
     chunks
         .iter()
         .try_for_each(|chunk: &Vec<String>| -> Result<()> {
             let job = JobItem::new(
                 chunk.to_vec(),
-                covs.clone(),
+                Arc::clone(&covs),
                 metric,
-                prefix,
+                files_path.to_path_buf(),
                 thresholds.to_vec(),
+                options.clone(),
             );
             debug!("Sending job: {:?}", job);
             if let Err(_e) = sender.send(Some(job)) {
@@ -369,29 +855,35 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
     let mut files_ignored = cfg.files_ignored.lock()?;
     let mut res = cfg.res.lock()?;
     let composer_output = composer.join()??;
+    let files_skipped = get_files_skipped(&composer_output);
     let project_metric = RootMetrics::new(
-        get_project_metrics(composer_output, None)?,
+        get_project_metrics(composer_output, None, options.max_metric_value)?,
         "PROJECT".into(),
         "-".into(),
         0,
         0,
         Vec::<FunctionMetrics>::new(),
-    );
+    )
+    .files_skipped(files_skipped)
+    .path_map(options.path_map.as_ref().map(PathMap::get_rules_rewritten));
     let project_coverage = project_metric.metrics.coverage;
     files_ignored.sort();
-    res.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    if sort_by == Sort::Path {
+        res.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        res.iter_mut().for_each(|m| {
+            m.functions
+                .sort_by(|a, b| a.function_path.cmp(&b.function_path))
+        });
+    } else {
+        res.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    }
     // Get AVG MIN MAX and complex files
     let mut complex_functions = res
         .iter()
         .flat_map(|m| m.functions.clone())
         .filter(|m| m.metrics.is_complex)
         .collect::<Vec<FunctionMetrics>>();
-    complex_functions.sort_by(|a, b| match sort_by {
-        Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-        Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-        Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-        Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-    });
+    sort_complex_functions(&mut complex_functions, sort_by);
     let m = res
         .iter()
         .map(|metric| metric.metrics)
@@ -412,26 +904,29 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
 // Job received by the consumer threads for the covdir version
 struct JobItemCovDir {
     chunk: Vec<String>,
-    covs: HashMap<String, Covdir>,
+    covs: Arc<HashMap<String, Covdir>>,
     metric: Complexity,
-    prefix: usize,
+    files_path: PathBuf,
     thresholds: Vec<f64>,
+    options: AnalysisOptions,
 }
 
 impl JobItemCovDir {
     fn new(
         chunk: Vec<String>,
-        covs: HashMap<String, Covdir>,
+        covs: Arc<HashMap<String, Covdir>>,
         metric: Complexity,
-        prefix: usize,
+        files_path: PathBuf,
         thresholds: Vec<f64>,
+        options: AnalysisOptions,
     ) -> Self {
         Self {
             chunk,
             covs,
             metric,
-            prefix,
+            files_path,
             thresholds,
+            options,
         }
     }
 }
@@ -439,8 +934,8 @@ impl fmt::Debug for JobItemCovDir {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Job: chunks:{:?}, metric:{}, prefix:{:?}, thresholds: {:?}",
-            self.chunk, self.metric, self.prefix, self.thresholds
+            "Job: chunks:{:?}, metric:{}, files_path:{:?}, thresholds: {:?}",
+            self.chunk, self.metric, self.files_path, self.thresholds
         )
     }
 }
@@ -466,74 +961,42 @@ fn consumer_covdir(
         let chunk = job.chunk;
         let covs = job.covs;
         let metric = job.metric;
-        let prefix = job.prefix;
+        let files_path = job.files_path;
         let thresholds = job.thresholds;
+        let options = job.options;
         // For each file in the chunk received
         for file in chunk {
-            let path = Path::new(&file);
-            let file_name = path
-                .file_name()
-                .ok_or(Error::PathConversionError())?
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .into();
+            // The files reached after the deadline are skipped
+            if is_past_deadline(&options) {
+                composer_output.files_skipped += 1;
+                continue;
+            }
             // Get the coverage vector from the covdir file
             // If not present the file will be added to the files ignored
             let covdir = match covs.get(&file) {
                 Some(covdir) => covdir,
                 None => {
                     let mut f = files_ignored.lock()?;
-                    f.push(file);
+                    f.push(FileIgnored::new(file, IgnoreReason::NoCoverage));
                     continue;
                 }
             };
-            let arr = &covdir.arr;
-            let coverage = Some(covdir.coverage);
-            let root = get_root(path)?;
-            let spaces = get_spaces(&root)?;
-            let ploc = root.metrics.loc.ploc();
-            let comp = match metric {
-                Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-                Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-            };
-            let mut functions = Vec::<FunctionMetrics>::new();
-            spaces.iter().try_for_each(|el| -> Result<()> {
-                let space = el.0;
-                let function_path = el.1.to_string();
-                let function_name = format!(
-                    "{} ({}, {})",
-                    space.name.as_ref().ok_or(Error::ConversionError())?,
-                    space.start_line,
-                    space.end_line
-                );
-                let (m, _): (Metrics, (f64, f64)) =
-                    Tree::get_metrics_from_space(space, arr, metric, coverage, &thresholds)?;
-                functions.push(FunctionMetrics::new(
-                    m,
-                    function_name,
-                    function_path,
-                    space.start_line,
-                    space.end_line,
-                ));
-                Ok(())
-            })?;
-            let file_path = file.clone().split_off(prefix);
-            let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
-                Tree::get_metrics_from_space(&root, arr, metric, coverage, &thresholds)?;
             // Upgrade all the global variables and add metrics to the result and complex_files
-            let mut res = res.lock()?;
-            composer_output.ploc_sum += ploc;
-            composer_output.wcc_plain_sum += sp_sum;
-            composer_output.wcc_quantized_sum += sq_sum;
-            composer_output.comp_sum += comp;
-            res.push(RootMetrics::new(
-                m,
-                file_name,
-                file_path,
-                root.start_line,
-                root.end_line,
-                functions,
-            ));
+            match get_root_metrics(
+                &file,
+                &covdir.arr,
+                Some(covdir),
+                metric,
+                &files_path,
+                &thresholds,
+                &options,
+            )? {
+                Ok((root_metrics, file_composer)) => {
+                    composer_output.add(&file_composer);
+                    res.lock()?.push(root_metrics);
+                }
+                Err(reason) => files_ignored.lock()?.push(FileIgnored::new(file, reason)),
+            }
         }
     }
     if let Err(_e) = sender_composer.send(Some(composer_output)) {
@@ -550,21 +1013,20 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     n_threads: usize,
     thresholds: &[f64],
     sort_by: Sort,
+    options: &AnalysisOptions,
 ) -> Result<Output> {
     if thresholds.len() != 4 {
         return Err(Error::ThresholdsError());
     }
-    // Take all the files starting from the given project folder
-    let vec = read_files(files_path.as_ref())?;
+    check_complexity_provider(options, Analysis::FunctionsCovdir)?;
     // Read coveralls file to string and then get all the coverage vectors
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json_covdir(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
-    )?;
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let covs = options.record_stage("reading coverage", || {
+        let file = fs::read_to_string(json_path)?;
+        apply_path_map(read_json_covdir(file, prefix)?, prefix, options)
+    })?;
+    // Take all the files starting from the given project folder
+    let vec = get_source_files(files_path.as_ref(), covs.keys(), options)?;
     let mut handlers = vec![];
     // Create a new config with  all needed mutexes
     let cfg = FunctionConfig::new();
@@ -584,22 +1046,19 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
         let h = thread::spawn(move || -> Result<()> { consumer_covdir(r, s, &config) });
         handlers.push(h);
     }
-    let prefix = files_path
-        .as_ref()
-        .to_str()
-        .ok_or(Error::PathConversionError())?
-        .to_string()
-        .len();
+    // The coverage is shared by all the jobs without copying it
+    let covs = Arc::new(covs);
     // Send all chunks to the consumers
     chunks
         .iter()
         .try_for_each(|chunk: &Vec<String>| -> Result<()> {
             let job = JobItemCovDir::new(
                 chunk.to_vec(),
-                covs.clone(),
+                Arc::clone(&covs),
                 metric,
-                prefix,
+                files_path.as_ref().to_path_buf(),
                 thresholds.to_vec(),
+                options.clone(),
             );
             debug!("Sending job: {:?}", job);
             if let Err(_e) = sender.send(Some(job)) {
@@ -626,32 +1085,42 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     let mut files_ignored = cfg.files_ignored.lock()?;
     let mut res = cfg.res.lock()?;
     let project_coverage = covs
-        .get(&("PROJECT_ROOT".to_string()))
+        .get("PROJECT_ROOT")
         .ok_or(Error::HashMapError())?
         .coverage;
     let composer_output = composer.join()??;
+    let files_skipped = get_files_skipped(&composer_output);
     let project_metric = RootMetrics::new(
-        get_project_metrics(composer_output, Some(project_coverage))?,
+        get_project_metrics(
+            composer_output,
+            Some(project_coverage),
+            options.max_metric_value,
+        )?,
         "PROJECT".into(),
         "-".into(),
         0,
         0,
         Vec::<FunctionMetrics>::new(),
-    );
+    )
+    .files_skipped(files_skipped)
+    .path_map(options.path_map.as_ref().map(PathMap::get_rules_rewritten));
     files_ignored.sort();
-    res.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    if sort_by == Sort::Path {
+        res.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        res.iter_mut().for_each(|m| {
+            m.functions
+                .sort_by(|a, b| a.function_path.cmp(&b.function_path))
+        });
+    } else {
+        res.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    }
     // Get AVG MIN MAX and complex files
     let mut complex_functions = res
         .iter()
         .flat_map(|m| m.functions.clone())
         .filter(|m| m.metrics.is_complex)
         .collect::<Vec<FunctionMetrics>>();
-    complex_functions.sort_by(|a, b| match sort_by {
-        Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-        Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-        Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-        Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-    });
+    sort_complex_functions(&mut complex_functions, sort_by);
     let m = res
         .iter()
         .map(|metric| metric.metrics)
@@ -669,11 +1138,495 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     ))
 }
 
+/// Metrics of a module, i.e. a folder of the project, aggregated over its functions
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ModuleMetrics {
+    pub module: String,
+    // Average metrics of the functions of the module
+    pub metrics: Metrics,
+    pub functions: usize,
+    pub functions_over_threshold: usize,
+}
+
+// Compare two modules by the metric defining the worst one, the greater the worse
+// Path is not a metric, the modules are compared by path and the first one is the worst
+fn compare_modules(a: &ModuleMetrics, b: &ModuleMetrics, worst_by: Sort) -> Ordering {
+    match worst_by {
+        Sort::Path => b.module.cmp(&a.module),
+        _ => b.metrics.compare(&a.metrics, worst_by),
+    }
+}
+
+/// Get the module whose functions have the highest average of the given metric
+/// The modules are the folders containing the files, relative to the project folder
+/// It returns None if there are no functions
+pub fn get_worst_module(metrics: &[RootMetrics], worst_by: Sort) -> Option<ModuleMetrics> {
+    let mut modules = BTreeMap::<String, Vec<Metrics>>::new();
+    metrics
+        .iter()
+        .filter(|m| !m.functions.is_empty())
+        .for_each(|m| {
+            let module = match Path::new(&m.file_path).parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.display().to_string(),
+                _ => ".".into(),
+            };
+            modules
+                .entry(module)
+                .or_default()
+                .extend(m.functions.iter().map(|f| f.metrics));
+        });
+    modules
+        .into_iter()
+        .map(|(module, functions)| {
+            let (avg, _, _) = get_cumulative_values(&functions);
+            ModuleMetrics {
+                module,
+                metrics: avg,
+                functions: functions.len(),
+                functions_over_threshold: functions.iter().filter(|m| m.is_complex).count(),
+            }
+        })
+        .fold(None, |worst: Option<ModuleMetrics>, m| match worst {
+            Some(w) if compare_modules(&w, &m, worst_by) != Ordering::Less => Some(w),
+            _ => Some(m),
+        })
+}
+
+/// Full breakdown of the metrics of a single function, to check its values by hand
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FunctionBreakdown {
+    pub function: FunctionMetrics,
+    pub file_path: String,
+    pub complexity: f64,
+    pub sloc: f64,
+    pub ploc: f64,
+    pub covered_lines: f64,
+    pub total_lines: f64,
+    // Term COMP^2 * (1 - COV)^3 of CRAP, the other term is the complexity itself
+    pub crap_uncovered_term: f64,
+}
+
+// Get the term of CRAP depending on the coverage, computed as in the CRAP metric
+fn get_crap_uncovered_term(complexity: f64, covered_lines: f64, total_lines: f64) -> f64 {
+    let cov = if total_lines != 0. {
+        covered_lines / total_lines
+    } else {
+        0.0
+    };
+    complexity.powf(2.) * (1.0 - cov).powf(3.)
+}
+
+// Split a selector given as path.rs::function in the file path and the function name
+fn parse_function_selector(selector: &str) -> Result<(&Path, &str)> {
+    match selector.rsplit_once("::") {
+        Some((file, function)) if !file.is_empty() && !function.is_empty() => {
+            Ok((Path::new(file), function))
+        }
+        _ => Err(Error::FunctionSelectorError(selector.into())),
+    }
+}
+
+/// This Function get the folder of the repo to analyzed, a list of coverage files
+/// and a function given as path.rs::function_name, where the path is relative to the project folder
+/// It computes the metrics of the functions with that name in the files ending with that path,
+/// together with the values needed to compute them
+/// It returns an error if no function matches the selector
+pub fn get_function_breakdown<A: AsRef<Path>>(
+    files_path: A,
+    inputs: &[CoverageInput],
+    selector: &str,
+    metric: Complexity,
+    thresholds: &[f64],
+    options: &AnalysisOptions,
+) -> Result<Vec<FunctionBreakdown>> {
+    if thresholds.len() != 4 {
+        return Err(Error::ThresholdsError());
+    }
+    check_complexity_provider(options, Analysis::Functions)?;
+    let (file_selector, function_selector) = parse_function_selector(selector)?;
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let covs = get_coverage_data(inputs, prefix, options)?;
+    let files = get_source_files(files_path.as_ref(), covs.keys(), options)?;
+    let mut res = Vec::<FunctionBreakdown>::new();
+    for file in files.iter() {
+        let file_path = file.clone().split_off(prefix.len());
+        if !Path::new(&file_path).ends_with(file_selector) {
+            continue;
+        }
+        let arr = match covs.get(file) {
+            Some(arr) => arr,
+            None => continue,
+        };
+        let path = get_source_path(files_path.as_ref(), file);
+        let root = get_root(&path, options.compile_command(&path))?;
+        for (space, function_path) in get_spaces(&root)? {
+            if space.name.as_deref() != Some(function_selector) {
+                continue;
+            }
+            let (m, _): (Metrics, (f64, f64)) = Tree::get_metrics_from_space(
+                space,
+                arr,
+                metric,
+                None,
+                thresholds,
+                options.max_metric_value,
+            )?;
+            let (covered_lines, total_lines) =
+                get_covered_lines(arr, space.start_line, space.end_line)?;
+            let complexity = match metric {
+                Complexity::Cyclomatic => space.metrics.cyclomatic.cyclomatic_sum(),
+                Complexity::Cognitive => space.metrics.cognitive.cognitive_sum(),
+            };
+            let function_name = format!(
+                "{} ({}, {})",
+                function_selector, space.start_line, space.end_line
+            );
+            let target_coverage =
+                get_function_target_coverage(space, &m, metric, thresholds, options);
+            res.push(FunctionBreakdown {
+                function: FunctionMetrics::new(
+                    m,
+                    function_name,
+                    function_path,
+                    space.start_line,
+                    space.end_line,
+                )
+                .target_coverage(target_coverage),
+                file_path: file_path.clone(),
+                complexity,
+                sloc: space.metrics.loc.sloc(),
+                ploc: space.metrics.loc.ploc(),
+                covered_lines,
+                total_lines,
+                crap_uncovered_term: get_crap_uncovered_term(
+                    complexity,
+                    covered_lines,
+                    total_lines,
+                ),
+            });
+        }
+    }
+    if res.is_empty() {
+        return Err(Error::FunctionNotFoundError(selector.into()));
+    }
+    res.sort_by(|a, b| {
+        a.file_path
+            .cmp(&b.file_path)
+            .then(a.function.start_line.cmp(&b.function.start_line))
+    });
+    Ok(res)
+}
+
+/// Raw metrics of a function computed by rust-code-analysis joined with its coverage,
+/// before any weighting, the fields are the columns of the raw csv in this order
+/// The lines of code and the covered and coverable lines are numbers of lines,
+/// halstead_time is in seconds and the maintainability indexes are scores without unit
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct RawFunction {
+    pub file_path: String,
+    pub function_name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub cyclomatic: f64,
+    pub cyclomatic_sum: f64,
+    pub cognitive: f64,
+    pub cognitive_sum: f64,
+    pub sloc: f64,
+    pub ploc: f64,
+    pub lloc: f64,
+    pub cloc: f64,
+    pub blank: f64,
+    pub halstead_unique_operators: f64,
+    pub halstead_operators: f64,
+    pub halstead_unique_operands: f64,
+    pub halstead_operands: f64,
+    pub halstead_length: f64,
+    pub halstead_estimated_program_length: f64,
+    pub halstead_purity_ratio: f64,
+    pub halstead_vocabulary: f64,
+    pub halstead_volume: f64,
+    pub halstead_difficulty: f64,
+    pub halstead_level: f64,
+    pub halstead_effort: f64,
+    pub halstead_time: f64,
+    pub halstead_bugs: f64,
+    pub nargs: f64,
+    pub nexits: f64,
+    pub abc_assignments: f64,
+    pub abc_branches: f64,
+    pub abc_conditions: f64,
+    pub abc_magnitude: f64,
+    pub mi_original: f64,
+    pub mi_sei: f64,
+    pub mi_visual_studio: f64,
+    pub covered_lines: f64,
+    pub coverable_lines: f64,
+}
+
+impl RawFunction {
+    fn new(space: &FuncSpace, file_path: &str, covered_lines: f64, coverable_lines: f64) -> Self {
+        let m = &space.metrics;
+        Self {
+            file_path: file_path.into(),
+            function_name: space.name.clone().unwrap_or_default(),
+            start_line: space.start_line,
+            end_line: space.end_line,
+            cyclomatic: m.cyclomatic.cyclomatic(),
+            cyclomatic_sum: m.cyclomatic.cyclomatic_sum(),
+            cognitive: m.cognitive.cognitive(),
+            cognitive_sum: m.cognitive.cognitive_sum(),
+            sloc: m.loc.sloc(),
+            ploc: m.loc.ploc(),
+            lloc: m.loc.lloc(),
+            cloc: m.loc.cloc(),
+            blank: m.loc.blank(),
+            halstead_unique_operators: m.halstead.u_operators(),
+            halstead_operators: m.halstead.operators(),
+            halstead_unique_operands: m.halstead.u_operands(),
+            halstead_operands: m.halstead.operands(),
+            halstead_length: m.halstead.length(),
+            halstead_estimated_program_length: m.halstead.estimated_program_length(),
+            halstead_purity_ratio: m.halstead.purity_ratio(),
+            halstead_vocabulary: m.halstead.vocabulary(),
+            halstead_volume: m.halstead.volume(),
+            halstead_difficulty: m.halstead.difficulty(),
+            halstead_level: m.halstead.level(),
+            halstead_effort: m.halstead.effort(),
+            halstead_time: m.halstead.time(),
+            halstead_bugs: m.halstead.bugs(),
+            nargs: m.nargs.fn_args(),
+            nexits: m.nexits.exit(),
+            abc_assignments: m.abc.assignments(),
+            abc_branches: m.abc.branches(),
+            abc_conditions: m.abc.conditions(),
+            abc_magnitude: m.abc.magnitude(),
+            mi_original: m.mi.mi_original(),
+            mi_sei: m.mi.mi_sei(),
+            mi_visual_studio: m.mi.mi_visual_studio(),
+            covered_lines,
+            coverable_lines,
+        }
+    }
+}
+
+/// This Function get the folder of the repo to analyzed and a list of coverage files
+/// and returns the raw metrics of every function of the files with coverage joined with its coverage
+/// The functions are in the order of their files and of their lines,
+/// neither the thresholds nor the sort are applied
+pub fn get_raw_functions<A: AsRef<Path>>(
+    files_path: A,
+    inputs: &[CoverageInput],
+    options: &AnalysisOptions,
+) -> Result<Vec<RawFunction>> {
+    check_complexity_provider(options, Analysis::Functions)?;
+    let prefix = &get_files_prefix(files_path.as_ref());
+    let covs = get_coverage_data(inputs, prefix, options)?;
+    let mut files = get_source_files(files_path.as_ref(), covs.keys(), options)?;
+    files.sort();
+    let mut res = Vec::<RawFunction>::new();
+    for file in files.iter() {
+        let arr = match covs.get(file) {
+            Some(arr) => arr,
+            None => continue,
+        };
+        // The files reached after the deadline are skipped
+        if is_past_deadline(options) {
+            continue;
+        }
+        let file_path = file.clone().split_off(prefix.len());
+        let path = get_source_path(files_path.as_ref(), file);
+        let root = get_root(&path, options.compile_command(&path))?;
+        let mut functions = get_spaces(&root)?
+            .into_iter()
+            .map(|(space, _)| -> Result<RawFunction> {
+                let (covered_lines, coverable_lines) =
+                    get_covered_lines(arr, space.start_line, space.end_line)?;
+                Ok(RawFunction::new(
+                    space,
+                    &file_path,
+                    covered_lines,
+                    coverable_lines,
+                ))
+            })
+            .collect::<Result<Vec<RawFunction>>>()?;
+        functions.sort_by(|a, b| {
+            a.start_line
+                .cmp(&b.start_line)
+                .then(a.end_line.cmp(&b.end_line))
+        });
+        res.extend(functions);
+    }
+    Ok(res)
+}
+
+/// Function over the thresholds that was added or modified in a diff
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NewBreach {
+    pub function: FunctionMetrics,
+    pub file_path: String,
+    pub changed_lines: usize,
+}
+
+// Get the complex functions whose line range overlaps the changed lines of their file
+pub(crate) fn get_new_breaches_from_changes(
+    metrics: &[RootMetrics],
+    changed: &HashMap<String, Vec<usize>>,
+) -> Vec<NewBreach> {
+    metrics
+        .iter()
+        .filter_map(|root| Some((root, changed.get(root.file_path.trim_start_matches('/'))?)))
+        .flat_map(|(root, lines)| {
+            root.functions
+                .iter()
+                .filter(|f| f.metrics.is_complex)
+                .filter_map(move |f| {
+                    let changed_lines = lines
+                        .iter()
+                        .filter(|line| (f.start_line..=f.end_line).contains(line))
+                        .count();
+                    (changed_lines > 0).then(|| NewBreach {
+                        function: f.clone(),
+                        file_path: root.file_path.trim_start_matches('/').to_string(),
+                        changed_lines,
+                    })
+                })
+        })
+        .collect()
+}
+
+/// This Function get the folder of the repo, the metrics of its functions and a git reference
+/// It returns the functions over the thresholds that were added or modified since the git reference,
+/// the ones the author of the changes is responsible for, in the order of the metrics
+pub fn get_new_breaches<A: AsRef<Path>>(
+    files_path: A,
+    metrics: &[RootMetrics],
+    git_ref: &str,
+) -> Result<Vec<NewBreach>> {
+    let changed = get_changed_lines(files_path, git_ref)?;
+    Ok(get_new_breaches_from_changes(metrics, &changed))
+}
+
+/// Line of the source of a function with its coverage, None when the line is not coverable
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SourceLine {
+    pub number: usize,
+    pub hits: Option<u64>,
+}
+
+/// Source of a function over the thresholds annotated with the coverage of its lines,
+/// the language is the extension of its file
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FunctionSource {
+    pub function: FunctionMetrics,
+    pub file_path: String,
+    pub language: String,
+    // Body of the function, highlighted as a whole
+    pub code: String,
+    pub lines: Vec<SourceLine>,
+}
+
+// Source and line coverage of an analyzed file
+#[derive(Clone, Debug)]
+struct AnnotatedFile {
+    source: Vec<String>,
+    hits: Vec<Option<u64>>,
+}
+
+/// Sources and line coverage of the files with a function over the thresholds, keyed by their path
+/// relative to the project folder, recorded by the analysis with the coverage and the source path
+/// it already matched and shared by the clones of the options
+#[derive(Clone, Debug, Default)]
+pub struct FunctionSources(Arc<Mutex<HashMap<String, AnnotatedFile>>>);
+
+impl FunctionSources {
+    // Record the source and line coverage of a file if one of its functions is over the thresholds
+    fn record(
+        &self,
+        path: &Path,
+        file_path: &str,
+        arr: &[Value],
+        functions: &[FunctionMetrics],
+    ) -> Result<()> {
+        if !functions.iter().any(|f| f.metrics.is_complex) {
+            return Ok(());
+        }
+        let file = AnnotatedFile {
+            source: fs::read_to_string(path)?
+                .lines()
+                .map(String::from)
+                .collect(),
+            hits: arr.iter().map(|line| line.as_u64()).collect(),
+        };
+        self.0.lock()?.insert(file_path.to_string(), file);
+        Ok(())
+    }
+}
+
+// Record the source and line coverage of a file if the function sources are given in the options
+fn record_function_sources(
+    path: &Path,
+    file_path: &str,
+    arr: &[Value],
+    functions: &[FunctionMetrics],
+    options: &AnalysisOptions,
+) -> Result<()> {
+    match &options.function_sources {
+        Some(sources) => sources.record(path, file_path, arr, functions),
+        None => Ok(()),
+    }
+}
+
+/// This Function get the metrics of the functions and the sources recorded by their analysis
+/// It returns the source of each function over the thresholds with the coverage of each line,
+/// in the order of the metrics
+pub fn get_function_sources(
+    metrics: &[RootMetrics],
+    sources: &FunctionSources,
+) -> Result<Vec<FunctionSource>> {
+    let files = sources.0.lock()?;
+    let mut res = Vec::<FunctionSource>::new();
+    for root in metrics {
+        let file = match files.get(&root.file_path) {
+            Some(file) => file,
+            None => continue,
+        };
+        let language = Path::new(&root.file_path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_string();
+        res.extend(
+            root.functions
+                .iter()
+                .filter(|f| f.metrics.is_complex)
+                .map(|f| FunctionSource {
+                    function: f.clone(),
+                    file_path: root.file_path.trim_start_matches('/').to_string(),
+                    language: language.clone(),
+                    code: file
+                        .source
+                        .get(f.start_line.saturating_sub(1)..f.end_line.min(file.source.len()))
+                        .unwrap_or_default()
+                        .join("\n"),
+                    lines: (f.start_line..=f.end_line)
+                        .map(|number| SourceLine {
+                            number,
+                            hits: file.hits.get(number - 1).copied().flatten(),
+                        })
+                        .collect(),
+                }),
+        );
+    }
+    Ok(res)
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::metrics::provider::ComplexityMetrics;
     use crate::utility::compare_float;
+    use serde_json::Value;
 
     const JSON: &str = "./data/seahorse/seahorse.json";
     const COVDIR: &str = "./data/seahorse/covdir.json";
@@ -692,6 +1645,7 @@ mod tests {
             8,
             &[30., 1.5, 35., 30.],
             Sort::WccPlain,
+            &AnalysisOptions::default(),
         )
         .unwrap();
         let ma = &metrics[7].metrics;
@@ -702,7 +1656,10 @@ mod tests {
         let cont_bool_flag = &metrics[2].functions[3].metrics;
 
         assert_eq!(files_ignored.len(), 1);
-        assert!(files_ignored[0] == ignored.as_os_str().to_str().unwrap());
+        assert_eq!(
+            files_ignored[0],
+            FileIgnored::new(ignored.to_str().unwrap(), IgnoreReason::NoCoverage)
+        );
         assert!(compare_float(ma.wcc_plain, 0.));
         assert!(compare_float(ma.wcc_quantized, 0.));
         assert!(compare_float(ma.crap, 552.));
@@ -750,6 +1707,7 @@ mod tests {
             8,
             &[30., 1.5, 35., 30.],
             Sort::WccPlain,
+            &AnalysisOptions::default(),
         )
         .unwrap();
         let ma = &metrics[7].metrics;
@@ -760,7 +1718,10 @@ mod tests {
         let cont_bool_flag = &metrics[2].functions[3].metrics;
 
         assert_eq!(files_ignored.len(), 1);
-        assert!(files_ignored[0] == ignored.as_os_str().to_str().unwrap());
+        assert_eq!(
+            files_ignored[0],
+            FileIgnored::new(ignored.to_str().unwrap(), IgnoreReason::NoCoverage)
+        );
         assert!(compare_float(ma.wcc_plain, 0.));
         assert!(compare_float(ma.wcc_quantized, 0.));
         assert!(compare_float(ma.crap, 72.));
@@ -805,6 +1766,7 @@ mod tests {
             8,
             &[30., 1.5, 35., 30.],
             Sort::WccPlain,
+            &AnalysisOptions::default(),
         )
         .unwrap();
         let ma = &metrics[7].metrics;
@@ -815,7 +1777,10 @@ mod tests {
         let cont_bool_flag = &metrics[2].functions[3].metrics;
 
         assert_eq!(files_ignored.len(), 1);
-        assert!(files_ignored[0] == ignored.as_os_str().to_str().unwrap());
+        assert_eq!(
+            files_ignored[0],
+            FileIgnored::new(ignored.to_str().unwrap(), IgnoreReason::NoCoverage)
+        );
         assert!(compare_float(ma.wcc_plain, 0.));
         assert!(compare_float(ma.wcc_quantized, 0.));
         assert!(compare_float(ma.crap, 552.));
@@ -866,6 +1831,7 @@ mod tests {
             8,
             &[30., 1.5, 35., 30.],
             Sort::WccPlain,
+            &AnalysisOptions::default(),
         )
         .unwrap();
         let ma = &metrics[7].metrics;
@@ -876,7 +1842,10 @@ mod tests {
         let cont_bool_flag = &metrics[2].functions[3].metrics;
 
         assert_eq!(files_ignored.len(), 1);
-        assert!(files_ignored[0] == ignored.as_os_str().to_str().unwrap());
+        assert_eq!(
+            files_ignored[0],
+            FileIgnored::new(ignored.to_str().unwrap(), IgnoreReason::NoCoverage)
+        );
         assert!(compare_float(ma.wcc_plain, 0.));
         assert!(compare_float(ma.wcc_quantized, 0.));
         assert!(compare_float(ma.crap, 72.));
@@ -908,4 +1877,531 @@ mod tests {
         assert!(compare_float(cont_bool_flag.crap, 1.000430368875));
         assert!(compare_float(cont_bool_flag.skunk, 0.3019999999999999));
     }
+
+    #[test]
+    fn test_metrics_covdir_function_coverage() {
+        // Add the function coverage of bool_flag to the covdir fixture
+        let covdir_functions = "./data/seahorse/covdir_functions.json";
+        let mut covdir: Value = serde_json::from_str(&fs::read_to_string(COVDIR).unwrap()).unwrap();
+        covdir["children"]["src"]["children"]["context.rs"]["functions"] = serde_json::json!([
+            {"name": "bool_flag", "start": 84, "coveragePercent": 100.0}
+        ]);
+        fs::write(covdir_functions, covdir.to_string()).unwrap();
+        let (metrics, _, _, _) = get_functions_metrics_concurrent_covdir(
+            PROJECT,
+            covdir_functions,
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        fs::remove_file(covdir_functions).unwrap();
+        let functions = metrics.iter().flat_map(|m| m.functions.iter());
+        let bool_flag = functions
+            .clone()
+            .find(|f| f.function_name == "bool_flag (84, 90)")
+            .unwrap();
+        // With full coverage CRAP is equal to the complexity
+        assert_eq!(bool_flag.coverage_source, Some(CoverageSource::Exact));
+        assert!(compare_float(bool_flag.metrics.crap, 3.));
+        assert!(functions
+            .filter(|f| f.function_name != "bool_flag (84, 90)")
+            .all(|f| f.coverage_source == Some(CoverageSource::Estimated)));
+    }
+
+    #[test]
+    fn test_target_coverage() {
+        assert_eq!(get_target_coverage(36., 35.), TargetCoverage::Unreachable);
+        assert_eq!(get_target_coverage(5., 35.), TargetCoverage::Coverage(0.));
+        assert_eq!(
+            get_target_coverage(35., 35.),
+            TargetCoverage::Coverage(100.)
+        );
+        // CRAP at the target coverage is equal to the threshold
+        let TargetCoverage::Coverage(target) = get_target_coverage(10., 35.) else {
+            panic!("target coverage must be reachable");
+        };
+        let crap = 10_f64.powf(2.) * (1. - target / 100.).powf(3.) + 10.;
+        assert!((crap - 35.).abs() < 0.1);
+        let thresholds = [30., 1.5, 1.5, 30.];
+        let (metrics, _, _, _) = get_functions_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            8,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default().show_target_coverage(true),
+        )
+        .unwrap();
+        let functions = metrics.iter().flat_map(|m| m.functions.iter());
+        functions
+            .clone()
+            .for_each(|f| assert_eq!(f.target_coverage.is_some(), f.metrics.crap > thresholds[2]));
+        assert!(functions.clone().any(|f| f.target_coverage.is_some()));
+    }
+
+    #[test]
+    fn test_hotness() {
+        let hotness = Hotness::new(
+            "# calls from the profiler\n\
+             src/app.rs::run 1000\n\
+             \n\
+             app.rs::command_help_text 0\n\
+             src/flag.rs::value 10\n",
+        )
+        .unwrap();
+        assert_eq!(hotness.calls("/src/app.rs", "run"), Some(1000.));
+        assert_eq!(hotness.calls("src/app.rs", "command_help_text"), Some(0.));
+        assert_eq!(hotness.calls("src/command.rs", "run"), None);
+        // The median of the map and the functions absent from it have a neutral weight
+        assert!(compare_float(hotness.weight(None), 1.));
+        assert!(compare_float(hotness.weight(Some(10.)), 1.));
+        assert!((hotness.weight(Some(1000.)) - 2.881174527).abs() < 1e-9);
+        assert!(compare_float(hotness.weight(Some(0.)), 0.));
+        // A median of 0 weights infinitely the functions called at least once
+        let cold = Hotness::new("src/app.rs::run 0").unwrap();
+        assert!(compare_float(cold.weight(Some(0.)), 1.));
+        assert_eq!(cold.weight(Some(1.)), f64::INFINITY);
+        let (metrics, _, complex_functions, _) = get_functions_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[1., 0.5, 5., 5.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().hotness(hotness.clone()),
+        )
+        .unwrap();
+        let functions = metrics
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .collect::<Vec<&FunctionMetrics>>();
+        let run = functions
+            .iter()
+            .find(|f| f.function_path == "/App (24,370)/run (198,230)")
+            .unwrap();
+        assert_eq!(run.calls, Some(1000.));
+        assert!(compare_float(
+            run.priority.unwrap(),
+            run.metrics.crap * hotness.weight(Some(1000.))
+        ));
+        functions
+            .iter()
+            .filter(|f| f.calls.is_none())
+            .for_each(|f| assert!(compare_float(f.priority.unwrap(), f.metrics.crap)));
+        // The complex functions are ranked by priority
+        assert!(complex_functions
+            .windows(2)
+            .all(|w| w[0].priority >= w[1].priority));
+        [
+            "src/app.rs::run",
+            "src/app.rs::run many",
+            "src/app.rs::run -1",
+        ]
+        .iter()
+        .for_each(|map| {
+            assert!(matches!(Hotness::new(map), Err(Error::HotnessError(_))));
+        });
+        assert!(matches!(
+            Hotness::new("run 10"),
+            Err(Error::FunctionSelectorError(_))
+        ));
+    }
+
+    #[test]
+    fn test_coverage_by_length() {
+        let coverage_by_length = "200:80, 50:60,1000:90".parse::<CoverageByLength>().unwrap();
+        // The coverage is clamped outside the breakpoints and interpolated between them
+        assert!(compare_float(coverage_by_length.required_coverage(5.), 60.));
+        assert!(compare_float(
+            coverage_by_length.required_coverage(125.),
+            70.
+        ));
+        assert!(compare_float(
+            coverage_by_length.required_coverage(200.),
+            80.
+        ));
+        assert!(compare_float(
+            coverage_by_length.required_coverage(600.),
+            85.
+        ));
+        assert!(compare_float(
+            coverage_by_length.required_coverage(5000.),
+            90.
+        ));
+        let (metrics, _, complex_functions, _) = get_functions_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[1000., 1000., 1000., 1000.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().coverage_by_length(coverage_by_length),
+        )
+        .unwrap();
+        // With thresholds that can not be reached only the poorly covered functions are complex
+        let functions = metrics
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .collect::<Vec<&FunctionMetrics>>();
+        assert!(functions
+            .iter()
+            .all(|f| f.metrics.is_complex == (f.metrics.coverage < f.required_coverage.unwrap())));
+        assert!(!complex_functions.is_empty());
+        assert!(complex_functions.len() < functions.len());
+        ["", "50", "50:60,x:80", "50:120"].iter().for_each(|c| {
+            assert!(c.parse::<CoverageByLength>().is_err());
+        });
+    }
+
+    #[test]
+    fn test_function_complexity_too_high() {
+        // Synthetic function of a generated file without any covered line
+        let space = ComplexityMetrics {
+            start_line: 1,
+            end_line: 2,
+            ploc: 2.,
+            cyclomatic: 1e200,
+            cyclomatic_sum: 1e200,
+            ..Default::default()
+        };
+        let covs = [Value::from(0), Value::from(0)];
+        let thresholds = [30., 1.5, 35., 30.];
+        let (m, _) = Tree::get_metrics_from_space(
+            &space,
+            &covs,
+            Complexity::Cyclomatic,
+            None,
+            &thresholds,
+            MAX_METRIC_VALUE,
+        )
+        .unwrap();
+        assert_eq!(m.crap, MAX_METRIC_VALUE);
+        assert_eq!(m.skunk, MAX_METRIC_VALUE);
+        assert!(m.complexity_too_high);
+        assert!(m.is_complex);
+        // A lower max value saturates a finite value too
+        let space = ComplexityMetrics {
+            cyclomatic: 1e4,
+            cyclomatic_sum: 1e4,
+            ..space
+        };
+        let (m, _) = Tree::get_metrics_from_space(
+            &space,
+            &covs,
+            Complexity::Cyclomatic,
+            None,
+            &thresholds,
+            1e6,
+        )
+        .unwrap();
+        assert_eq!(m.crap, 1e6);
+        assert!(m.complexity_too_high);
+        assert!(compare_float(m.skunk, 40000.));
+        assert!(saturate_metric(f64::NAN, 1e6).1);
+        assert_eq!(saturate_metric(f64::INFINITY, 1e6), (1e6, true));
+        assert_eq!(saturate_metric(5., 1e6), (5., false));
+    }
+
+    // Get the functions of the deprecated data with a deprecated policy and the number of complex ones
+    fn get_deprecated_functions(
+        deprecated_policy: DeprecatedPolicy,
+    ) -> (Vec<FunctionMetrics>, usize) {
+        let inputs = [CoverageInput::new(
+            JsonFormat::Lcov,
+            PathBuf::from("./data/deprecated/lcov.info"),
+        )];
+        let (metrics, _, complex_functions, _) = get_functions_metrics_concurrent_merged(
+            "./data/deprecated/",
+            &inputs,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 5., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().deprecated_policy(deprecated_policy),
+        )
+        .unwrap();
+        (metrics[0].functions.clone(), complex_functions.len())
+    }
+
+    #[test]
+    fn test_deprecated_policy_gate() {
+        // The deprecated functions are marked but counted as any other one
+        let (functions, complex_functions) = get_deprecated_functions(DeprecatedPolicy::Gate);
+        assert_eq!(functions.len(), 3);
+        assert_eq!(complex_functions, 3);
+        functions.iter().for_each(|f| {
+            let deprecated = !f.function_name.starts_with("run ");
+            assert_eq!(f.deprecated, deprecated);
+            assert!(f.metrics.is_complex);
+            let value = serde_json::to_value(f).unwrap();
+            assert_eq!(value.get("deprecated").is_some(), deprecated);
+        });
+    }
+
+    #[test]
+    fn test_deprecated_policy_warn() {
+        // The function and the module marked as deprecated are reported but not complex
+        let (functions, complex_functions) = get_deprecated_functions(DeprecatedPolicy::Warn);
+        assert_eq!(functions.len(), 3);
+        assert_eq!(complex_functions, 1);
+        functions.iter().for_each(|f| {
+            let deprecated = !f.function_name.starts_with("run ");
+            assert_eq!(f.deprecated, deprecated);
+            assert_eq!(f.metrics.is_complex, !deprecated);
+        });
+    }
+
+    #[test]
+    fn test_deprecated_policy_exclude() {
+        // The excluded functions are only left out of the thresholds, not of the report
+        let (functions, complex_functions) = get_deprecated_functions(DeprecatedPolicy::Exclude);
+        assert_eq!(functions.len(), 3);
+        assert_eq!(complex_functions, 1);
+        functions.iter().for_each(|f| {
+            let deprecated = !f.function_name.starts_with("run ");
+            assert_eq!(f.deprecated, deprecated);
+            assert_eq!(f.metrics.is_complex, !deprecated);
+        });
+    }
+
+    #[test]
+    fn test_function_sources() {
+        let function_sources = FunctionSources::default();
+        let options = AnalysisOptions::default().function_sources(function_sources.clone());
+        let (metrics, _, _, _) = get_functions_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        let sources = get_function_sources(&metrics, &function_sources).unwrap();
+        // Only the complex functions get their source
+        assert_eq!(sources.len(), 4);
+        assert!(sources.iter().all(|s| s.function.metrics.is_complex));
+        let help = sources
+            .iter()
+            .find(|s| s.function.function_name == "command_help_text (329, 369)")
+            .unwrap();
+        assert_eq!(help.file_path, "src/app.rs");
+        assert_eq!(help.language, "rs");
+        assert_eq!(help.lines.len(), 41);
+        assert_eq!(help.lines[0].number, 329);
+        // The body is kept whole to be highlighted once
+        assert_eq!(help.code.lines().count(), 41);
+        assert!(help
+            .code
+            .starts_with("    fn command_help_text(&self) -> String {\n"));
+        assert_eq!(help.lines[0].hits, Some(4));
+        // An empty line is not coverable
+        assert_eq!(help.lines[2].hits, None);
+        assert!(help.lines.iter().any(|l| l.hits == Some(0)));
+    }
+
+    #[test]
+    fn test_function_breakdown() {
+        let inputs = [CoverageInput::new(
+            JsonFormat::Coveralls,
+            PathBuf::from(JSON),
+        )];
+        let thresholds = [30., 1.5, 35., 30.];
+        let options = AnalysisOptions::default();
+        let breakdown = get_function_breakdown(
+            PROJECT,
+            &inputs,
+            "src/context.rs::bool_flag",
+            Complexity::Cyclomatic,
+            &thresholds,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(breakdown.len(), 1);
+        let bool_flag = &breakdown[0];
+        assert_eq!(bool_flag.file_path, "src/context.rs");
+        assert!(compare_float(
+            bool_flag.function.metrics.wcc_plain,
+            2.142857142857143
+        ));
+        assert!(compare_float(
+            bool_flag.function.metrics.crap,
+            3.0416666666666665
+        ));
+        assert!((bool_flag.crap_uncovered_term - 9. / 216.).abs() < 1e-9);
+        // CRAP is the sum of its two terms
+        assert!(
+            (bool_flag.complexity + bool_flag.crap_uncovered_term
+                - bool_flag.function.metrics.crap)
+                .abs()
+                < 1e-9
+        );
+        assert!(matches!(
+            get_function_breakdown(
+                PROJECT,
+                &inputs,
+                "src/context.rs::missing",
+                Complexity::Cyclomatic,
+                &thresholds,
+                &options,
+            ),
+            Err(Error::FunctionNotFoundError(_))
+        ));
+        assert!(matches!(
+            get_function_breakdown(
+                PROJECT,
+                &inputs,
+                "bool_flag",
+                Complexity::Cyclomatic,
+                &thresholds,
+                &options,
+            ),
+            Err(Error::FunctionSelectorError(_))
+        ));
+    }
+
+    #[test]
+    fn test_worst_module() {
+        let function = |crap: f64, is_complex: bool| {
+            FunctionMetrics::new(
+                Metrics::default().crap(crap).is_complex(is_complex),
+                "f (1, 2)".into(),
+                "/f (1,2)".into(),
+                1,
+                2,
+            )
+        };
+        let root = |file_path: &str, functions: Vec<FunctionMetrics>| {
+            RootMetrics::new(
+                Metrics::default(),
+                "file.rs".into(),
+                file_path.into(),
+                1,
+                10,
+                functions,
+            )
+        };
+        let metrics = [
+            root("main.rs", vec![function(10., false)]),
+            root("src/parser/lexer.rs", vec![function(60., true)]),
+            root(
+                "src/parser/ast.rs",
+                vec![function(40., true), function(20., false)],
+            ),
+            root("src/utils.rs", vec![function(30., true)]),
+            RootMetrics::avg(Metrics::default()),
+        ];
+        let worst = get_worst_module(&metrics, Sort::Crap).unwrap();
+        assert_eq!(worst.module, "src/parser");
+        assert_eq!(worst.functions, 3);
+        assert_eq!(worst.functions_over_threshold, 2);
+        assert!(compare_float(worst.metrics.crap, 40.));
+        let worst = get_worst_module(&metrics[..1], Sort::Crap).unwrap();
+        assert_eq!(worst.module, ".");
+        assert_eq!(get_worst_module(&metrics[4..], Sort::Crap), None);
+        // By path the modules are not compared by a metric, the first one is reported
+        let worst = get_worst_module(&metrics, Sort::Path).unwrap();
+        assert_eq!(worst.module, ".");
+        let worst = get_worst_module(&metrics[1..], Sort::Path).unwrap();
+        assert_eq!(worst.module, "src");
+    }
+
+    #[test]
+    fn test_new_breaches() {
+        let function = |name: &str, start_line: usize, end_line: usize, is_complex: bool| {
+            FunctionMetrics::new(
+                Metrics::default().is_complex(is_complex),
+                name.into(),
+                format!("/{name}"),
+                start_line,
+                end_line,
+            )
+        };
+        let metrics = [
+            RootMetrics::new(
+                Metrics::default(),
+                "app.rs".into(),
+                "/src/app.rs".into(),
+                1,
+                100,
+                vec![
+                    function("run", 1, 20, true),
+                    function("parse", 21, 40, false),
+                    function("help", 41, 60, true),
+                ],
+            ),
+            RootMetrics::new(
+                Metrics::default(),
+                "flag.rs".into(),
+                "src/flag.rs".into(),
+                1,
+                30,
+                vec![function("value", 1, 30, true)],
+            ),
+            RootMetrics::avg(Metrics::default()),
+        ];
+        let changed = HashMap::from([
+            ("src/app.rs".to_string(), vec![18, 19, 20, 21, 30]),
+            ("src/other.rs".to_string(), vec![1]),
+        ]);
+        let breaches = get_new_breaches_from_changes(&metrics, &changed);
+        // Only the complex function touched by the diff is a new breach
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].function.function_name, "run");
+        assert_eq!(breaches[0].file_path, "src/app.rs");
+        assert_eq!(breaches[0].changed_lines, 3);
+        assert!(get_new_breaches_from_changes(&metrics, &HashMap::new()).is_empty());
+    }
+    #[test]
+    fn test_raw_functions() {
+        let inputs = [CoverageInput::new(
+            JsonFormat::Coveralls,
+            PathBuf::from(JSON),
+        )];
+        let options = AnalysisOptions::default();
+        let raw = get_raw_functions(PROJECT, &inputs, &options).unwrap();
+        let (metrics, _, _, _) = get_functions_metrics_concurrent(
+            PROJECT,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &options,
+        )
+        .unwrap();
+        // Every function analyzed has a row, whatever its metrics
+        let functions = metrics
+            .iter()
+            .filter(|m| m.file_path != "-")
+            .map(|m| m.functions.len())
+            .sum::<usize>();
+        assert_eq!(raw.len(), functions);
+        assert!(raw
+            .windows(2)
+            .all(|w| (&w[0].file_path, w[0].start_line) <= (&w[1].file_path, w[1].start_line)));
+        let breakdown = get_function_breakdown(
+            PROJECT,
+            &inputs,
+            "src/context.rs::bool_flag",
+            Complexity::Cyclomatic,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+        let bool_flag = raw
+            .iter()
+            .find(|r| r.file_path == "src/context.rs" && r.function_name == "bool_flag")
+            .unwrap();
+        assert_eq!(bool_flag.cyclomatic_sum, breakdown[0].complexity);
+        assert_eq!(bool_flag.sloc, breakdown[0].sloc);
+        assert_eq!(bool_flag.covered_lines, breakdown[0].covered_lines);
+        assert_eq!(bool_flag.coverable_lines, breakdown[0].total_lines);
+        assert!(bool_flag.halstead_volume > 0.);
+    }
 }
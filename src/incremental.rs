@@ -0,0 +1,203 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::error::*;
+use crate::files::{
+    apply_path_map, get_file_metrics, get_files_output, get_source_files, FileIgnored, FileMetrics,
+    IgnoreReason, JobComposer, Output,
+};
+use crate::utility::{get_files_prefix, AnalysisOptions, Complexity, CoverageData, Sort};
+
+/// Metrics of the files of a project kept up to date while its coverage is produced,
+/// e.g. by a watcher or an editor plugin, in files mode
+/// Only the files whose coverage changed are analyzed again, the project metrics
+/// are composed again from the contribution of each file
+#[derive(Clone, Debug)]
+pub struct IncrementalAnalyzer {
+    files_path: PathBuf,
+    sources: Vec<String>,
+    covs: CoverageData,
+    metric: Complexity,
+    thresholds: Vec<f64>,
+    sort_by: Sort,
+    options: AnalysisOptions,
+    // Metrics of each analyzed file and its contribution to the project metrics,
+    // or the reason why the file is ignored
+    files: HashMap<String, std::result::Result<(FileMetrics, JobComposer), IgnoreReason>>,
+}
+
+impl IncrementalAnalyzer {
+    /// Analyze all the files of the project folder with the initial coverage,
+    /// the keys of the coverage are the paths of the files as read from the coverage files
+    pub fn new<A: AsRef<Path>>(
+        files_path: A,
+        covs: CoverageData,
+        metric: Complexity,
+        thresholds: &[f64],
+        sort_by: Sort,
+        options: &AnalysisOptions,
+    ) -> Result<Self> {
+        if thresholds.len() != 4 {
+            return Err(Error::ThresholdsError());
+        }
+        let files_path = files_path.as_ref();
+        let prefix = &get_files_prefix(files_path);
+        let covs = apply_path_map(covs, prefix, options)?;
+        let sources = get_source_files(files_path, covs.keys(), options)?;
+        let mut analyzer = Self {
+            files_path: files_path.to_path_buf(),
+            sources,
+            covs: CoverageData::new(),
+            metric,
+            thresholds: thresholds.to_vec(),
+            sort_by,
+            options: options.clone(),
+            files: HashMap::new(),
+        };
+        analyzer.analyze(covs)?;
+        Ok(analyzer)
+    }
+
+    /// Update the coverage of some files and analyze them again,
+    /// the coverage of each file of the delta replaces the previous one
+    /// It returns the paths, relative to the project folder, of the files analyzed again
+    pub fn update(&mut self, delta: CoverageData) -> Result<Vec<String>> {
+        let prefix = &get_files_prefix(&self.files_path);
+        let delta = apply_path_map(delta, prefix, &self.options)?;
+        self.analyze(delta)
+    }
+
+    // Store the coverage and compute the metrics of the source files present in it
+    fn analyze(&mut self, covs: CoverageData) -> Result<Vec<String>> {
+        let prefix = get_files_prefix(&self.files_path).len();
+        let sources = self.sources.iter().collect::<HashSet<&String>>();
+        let mut analyzed = Vec::<String>::new();
+        for (file, arr) in covs {
+            if sources.contains(&file) {
+                let file_metrics = get_file_metrics(
+                    &file,
+                    arr.clone().into(),
+                    self.metric,
+                    &self.files_path,
+                    &self.thresholds,
+                    &self.options,
+                )?;
+                self.files.insert(file.clone(), file_metrics);
+                analyzed.push(file[prefix..].to_string());
+            } else {
+                debug!("Coverage of {:?} has no source file", file);
+            }
+            self.covs.insert(file, arr);
+        }
+        analyzed.sort();
+        debug!("Files analyzed again: {:?}", analyzed);
+        Ok(analyzed)
+    }
+
+    /// Get the current metrics of the project, as computed by a full run from the same coverage
+    /// It returns the tuple (res, files_ignored, complex_files, project_coverage)
+    pub fn report(&self) -> Result<Output> {
+        let mut res = Vec::<FileMetrics>::new();
+        let mut files_ignored = Vec::<FileIgnored>::new();
+        let mut composer_output = JobComposer::default();
+        self.sources
+            .iter()
+            .for_each(|file| match self.files.get(file) {
+                Some(Ok((file_metrics, file_composer))) => {
+                    composer_output.add(file_composer);
+                    res.push(file_metrics.clone());
+                }
+                Some(Err(reason)) => files_ignored.push(FileIgnored::new(file, *reason)),
+                None => files_ignored.push(FileIgnored::new(file, IgnoreReason::NoCoverage)),
+            });
+        get_files_output(
+            &self.files_path,
+            res,
+            files_ignored,
+            composer_output,
+            self.sort_by,
+            None,
+            &self.options,
+        )
+    }
+
+    /// Get the current coverage of the project
+    pub fn coverage(&self) -> &CoverageData {
+        &self.covs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use serde_json::Value;
+
+    use crate::files::get_metrics_concurrent;
+    use crate::utility::{compare_float, read_json};
+
+    const JSON: &str = "./data/seahorse/seahorse.json";
+    const PROJECT: &str = "./data/seahorse/";
+    const APP: &str = "./data/seahorse/src/app.rs";
+
+    #[test]
+    fn test_incremental_analyzer() {
+        let thresholds = [30., 1.5, 35., 30.];
+        let covs = read_json(fs::read_to_string(JSON).unwrap(), PROJECT).unwrap();
+        // Start without any covered line in app.rs
+        let mut initial = covs.clone();
+        initial.get_mut(APP).unwrap().iter_mut().for_each(|line| {
+            if line.is_u64() {
+                *line = Value::from(0);
+            }
+        });
+        let mut analyzer = IncrementalAnalyzer::new(
+            PROJECT,
+            initial,
+            Complexity::Cyclomatic,
+            &thresholds,
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let (metrics, _, _, coverage) = analyzer.report().unwrap();
+        let app = metrics
+            .iter()
+            .find(|m| m.file_path == "src/app.rs")
+            .unwrap();
+        assert_eq!(app.metrics.coverage, 0.);
+        // Only the files of the delta are analyzed again
+        let delta = CoverageData::from([(APP.to_string(), covs[APP].clone())]);
+        assert_eq!(analyzer.update(delta).unwrap(), vec!["src/app.rs"]);
+        let (metrics, files_ignored, complex_files, updated) = analyzer.report().unwrap();
+        assert!(updated > coverage);
+        let (expected, expected_ignored, expected_complex, expected_coverage) =
+            get_metrics_concurrent(
+                PROJECT,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                &thresholds,
+                Sort::WccPlain,
+                &AnalysisOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(metrics.len(), expected.len());
+        metrics
+            .iter()
+            .zip(&expected)
+            .filter(|(m, _)| m.file_path != "-")
+            .for_each(|(m, e)| assert!(m == e));
+        assert_eq!(files_ignored, expected_ignored);
+        assert_eq!(complex_files.len(), expected_complex.len());
+        assert!(compare_float(updated, expected_coverage));
+        assert_eq!(analyzer.coverage().len(), covs.len());
+        // A coverage without source file is stored without analyzing anything
+        let delta = CoverageData::from([("./data/seahorse/missing.rs".to_string(), Vec::new())]);
+        assert!(analyzer.update(delta).unwrap().is_empty());
+        assert_eq!(analyzer.coverage().len(), covs.len() + 1);
+    }
+}
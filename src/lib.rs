@@ -1,6 +1,16 @@
+pub mod compile_commands;
+pub mod config;
+pub mod contract;
 pub mod error;
+pub mod expression;
 pub mod files;
 pub mod functions;
+pub mod incremental;
 pub mod metrics;
+pub mod modes;
+#[cfg(feature = "otlp")]
+pub mod otlp;
 pub mod output;
+#[cfg(feature = "statsd")]
+pub mod statsd;
 pub mod utility;
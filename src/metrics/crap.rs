@@ -1,27 +1,28 @@
-use rust_code_analysis::FuncSpace;
 use serde_json::Value;
 
 use crate::error::*;
+use crate::metrics::provider::SpaceMetrics;
 use crate::utility::{get_covered_lines, Complexity};
 
+/// Formula of the CRAP metric, with COVERAGE as the covered fraction of the lines
+pub const CRAP_FORMULA: &str = "COMP^2*(1-COVERAGE)^3+COMP";
+
 // Calculate the CRAP value for a given space
 // (https://testing.googleblog.com/2011/02/this-code-is-crap.html#:~:text=CRAP%20is%20short%20for%20Change,partner%20in%20crime%20Bob%20Evans.)
 // Return the value in case of success and an specif error in case of fails
-pub(crate) fn crap_function(
-    space: &FuncSpace,
+pub(crate) fn crap_function<S: SpaceMetrics>(
+    space: &S,
     covs: &[Value],
     metric: Complexity,
     coverage: Option<f64>,
 ) -> Result<f64> {
-    let comp = match metric {
-        Complexity::Cyclomatic => space.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => space.metrics.cognitive.cognitive_sum(),
-    };
+    let comp = space.complexity_sum(metric);
 
     let cov = if let Some(coverage) = coverage {
         coverage / 100.0
     } else {
-        let (covered_lines, tot_lines) = get_covered_lines(covs, space.start_line, space.end_line)?;
+        let (covered_lines, tot_lines) =
+            get_covered_lines(covs, space.start_line(), space.end_line())?;
         if tot_lines != 0. {
             covered_lines / tot_lines
         } else {
@@ -34,7 +35,8 @@ pub(crate) fn crap_function(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utility::{get_root, read_json};
+    use crate::expression::Expression;
+    use crate::utility::{compare_float, get_root, read_json};
     use std::fs;
 
     const JSON: &str = "./data/data.json";
@@ -48,7 +50,7 @@ mod tests {
     fn test_crap_cyclomatic() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let crap_cy = crap_function(&root, &vec, COMP, None).unwrap();
         assert_eq!(crap_cy, 5.024);
@@ -58,7 +60,7 @@ mod tests {
     fn test_crap_cognitive() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let crap_cogn = crap_function(&root, &vec, COGN, None).unwrap();
         assert_eq!(crap_cogn, 3.576);
@@ -67,7 +69,7 @@ mod tests {
     fn test_crap_cyclomatic_function() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let crap_cy = crap_function(&root, &vec, COMP, None).unwrap();
         assert_eq!(crap_cy, 5.024);
@@ -77,9 +79,24 @@ mod tests {
     fn test_crap_cognitive_function() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let crap_cogn = crap_function(&root, &vec, COGN, None).unwrap();
         assert_eq!(crap_cogn, 3.576);
     }
+
+    #[test]
+    fn test_crap_formula() {
+        let root = get_root(FILE, None).unwrap();
+        let comp = root.complexity_sum(COMP);
+        [0., 25., 60., 100.].into_iter().for_each(|coverage| {
+            let formula = CRAP_FORMULA
+                .replace("COVERAGE", &(coverage / 100.).to_string())
+                .to_lowercase()
+                .parse::<Expression>()
+                .unwrap();
+            let crap = crap_function(&root, &[], COMP, Some(coverage)).unwrap();
+            assert!(compare_float(formula.eval(0., 0., comp), crap));
+        });
+    }
 }
@@ -1,3 +1,4 @@
 pub mod crap;
+pub mod provider;
 pub mod skunk;
 pub mod wcc;
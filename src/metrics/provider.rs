@@ -0,0 +1,157 @@
+use std::fmt;
+use std::path::Path;
+
+use rust_code_analysis::FuncSpace;
+
+use crate::error::*;
+use crate::utility::{get_checked_root, Complexity};
+
+/// Complexity values of a space of a source file, together with its nested spaces
+/// The values without the sum suffix refer only to the space itself,
+/// while the sums also include all the nested spaces
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ComplexityMetrics {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub sloc: f64,
+    pub ploc: f64,
+    pub cloc: f64,
+    pub cyclomatic: f64,
+    pub cyclomatic_sum: f64,
+    pub cognitive: f64,
+    pub cognitive_sum: f64,
+    pub spaces: Vec<ComplexityMetrics>,
+    /// The syntax tree of the file has errors, so the values could come from a partial parse
+    /// Only the root space reports it, a custom provider can leave it false
+    pub has_syntax_errors: bool,
+}
+
+impl From<&FuncSpace> for ComplexityMetrics {
+    fn from(space: &FuncSpace) -> Self {
+        Self {
+            start_line: space.start_line,
+            end_line: space.end_line,
+            sloc: space.metrics.loc.sloc(),
+            ploc: space.metrics.loc.ploc(),
+            cloc: space.metrics.loc.cloc(),
+            cyclomatic: space.metrics.cyclomatic.cyclomatic(),
+            cyclomatic_sum: space.metrics.cyclomatic.cyclomatic_sum(),
+            cognitive: space.metrics.cognitive.cognitive(),
+            cognitive_sum: space.metrics.cognitive.cognitive_sum(),
+            spaces: space.spaces.iter().map(ComplexityMetrics::from).collect(),
+            has_syntax_errors: false,
+        }
+    }
+}
+
+/// Source of the complexity values used to weight the coverage
+/// Implement it to compute the metrics with a different complexity analyzer
+pub trait ComplexityProvider: fmt::Debug + Send + Sync {
+    /// Get the complexity values of the root space of a source file
+    fn complexity(&self, path: &Path, source: &[u8]) -> Result<ComplexityMetrics>;
+}
+
+/// Default complexity provider based on rust-code-analysis
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RustCodeAnalysis;
+
+impl ComplexityProvider for RustCodeAnalysis {
+    fn complexity(&self, path: &Path, source: &[u8]) -> Result<ComplexityMetrics> {
+        get_checked_root(path, source.to_vec(), None).map(from_checked_root)
+    }
+}
+
+// Get the complexity values of a root space and if the syntax tree of its file has errors
+pub(crate) fn from_checked_root((root, has_syntax_errors): (FuncSpace, bool)) -> ComplexityMetrics {
+    ComplexityMetrics {
+        has_syntax_errors,
+        ..ComplexityMetrics::from(&root)
+    }
+}
+
+// Values of a space needed to compute the metrics
+pub(crate) trait SpaceMetrics {
+    fn start_line(&self) -> usize;
+    fn end_line(&self) -> usize;
+    fn ploc(&self) -> f64;
+    // Complexity of the space including all its nested spaces
+    fn complexity_sum(&self, metric: Complexity) -> f64;
+    // Complexity of the smallest space containing the line i, 0-based
+    fn min_space_complexity(&self, i: usize, metric: Complexity) -> f64;
+}
+
+impl SpaceMetrics for FuncSpace {
+    fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    fn end_line(&self) -> usize {
+        self.end_line
+    }
+
+    fn ploc(&self) -> f64 {
+        self.metrics.loc.ploc()
+    }
+
+    fn complexity_sum(&self, metric: Complexity) -> f64 {
+        match metric {
+            Complexity::Cyclomatic => self.metrics.cyclomatic.cyclomatic_sum(),
+            Complexity::Cognitive => self.metrics.cognitive.cognitive_sum(),
+        }
+    }
+
+    fn min_space_complexity(&self, i: usize, metric: Complexity) -> f64 {
+        let mut min_space = self;
+        let mut stack = vec![self];
+        while let Some(space) = stack.pop() {
+            for s in &space.spaces {
+                if i >= s.start_line && i <= s.end_line {
+                    min_space = s;
+                    stack.push(s);
+                }
+            }
+        }
+        match metric {
+            Complexity::Cyclomatic => min_space.metrics.cyclomatic.cyclomatic(),
+            Complexity::Cognitive => min_space.metrics.cognitive.cognitive(),
+        }
+    }
+}
+
+impl SpaceMetrics for ComplexityMetrics {
+    fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    fn end_line(&self) -> usize {
+        self.end_line
+    }
+
+    fn ploc(&self) -> f64 {
+        self.ploc
+    }
+
+    fn complexity_sum(&self, metric: Complexity) -> f64 {
+        match metric {
+            Complexity::Cyclomatic => self.cyclomatic_sum,
+            Complexity::Cognitive => self.cognitive_sum,
+        }
+    }
+
+    fn min_space_complexity(&self, i: usize, metric: Complexity) -> f64 {
+        let mut min_space = self;
+        let mut stack = vec![self];
+        while let Some(space) = stack.pop() {
+            for s in &space.spaces {
+                if i >= s.start_line && i <= s.end_line {
+                    min_space = s;
+                    stack.push(s);
+                }
+            }
+        }
+        match metric {
+            Complexity::Cyclomatic => min_space.cyclomatic,
+            Complexity::Cognitive => min_space.cognitive,
+        }
+    }
+}
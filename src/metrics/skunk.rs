@@ -1,7 +1,7 @@
-use rust_code_analysis::FuncSpace;
 use serde_json::Value;
 
 use crate::error::*;
+use crate::metrics::provider::SpaceMetrics;
 use crate::utility::{get_covered_lines, Complexity};
 
 const COMPLEXITY_FACTOR: f64 = 25.0;
@@ -10,20 +10,18 @@ const COMPLEXITY_FACTOR: f64 = 25.0;
 // https://www.fastruby.io/blog/code-quality/intruducing-skunk-stink-score-calculator.html
 // In this implementation the code smells are ignored.
 // Return the value in case of success and an specif error in case of fails
-pub(crate) fn skunk_nosmells_function(
-    space: &FuncSpace,
+pub(crate) fn skunk_nosmells_function<S: SpaceMetrics>(
+    space: &S,
     covs: &[Value],
     metric: Complexity,
     coverage: Option<f64>,
 ) -> Result<f64> {
-    let comp = match metric {
-        Complexity::Cyclomatic => space.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => space.metrics.cognitive.cognitive_sum(),
-    };
+    let comp = space.complexity_sum(metric);
     let cov = if let Some(coverage) = coverage {
         coverage / 100.0
     } else {
-        let (covered_lines, tot_lines) = get_covered_lines(covs, space.start_line, space.end_line)?;
+        let (covered_lines, tot_lines) =
+            get_covered_lines(covs, space.start_line(), space.end_line())?;
         if tot_lines != 0. {
             covered_lines / tot_lines
         } else {
@@ -54,7 +52,7 @@ mod tests {
     fn test_skunk_cyclomatic() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let skunk = skunk_nosmells_function(&root, &vec, COMP, None).unwrap();
         assert_eq!(skunk, 6.4);
@@ -64,7 +62,7 @@ mod tests {
     fn test_skunk_cognitive() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let skunk_cogn = skunk_nosmells_function(&root, &vec, COGN, None).unwrap();
         assert_eq!(skunk_cogn, 4.8);
@@ -74,7 +72,7 @@ mod tests {
     fn test_skunk_cyclomatic_function() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let skunk = skunk_nosmells_function(&root, &vec, COMP, None).unwrap();
         assert_eq!(skunk, 6.4);
@@ -84,7 +82,7 @@ mod tests {
     fn test_skunk_cognitive_function() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let skunk_cogn = skunk_nosmells_function(&root, &vec, COGN, None).unwrap();
         assert_eq!(skunk_cogn, 4.8);
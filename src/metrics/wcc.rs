@@ -1,39 +1,20 @@
-use rust_code_analysis::FuncSpace;
 use serde_json::Value;
 
 use crate::error::*;
+use crate::metrics::provider::SpaceMetrics;
 use crate::utility::Complexity;
 
 const THRESHOLD: f64 = 15.;
-// This function find the minimum space for a line i in the file
-// It returns the space
-fn get_min_space(root: &FuncSpace, i: usize) -> FuncSpace {
-    let mut min_space: FuncSpace = root.clone();
-    let mut stack: Vec<FuncSpace> = vec![root.clone()];
-    while let Some(space) = stack.pop() {
-        for s in space.spaces.into_iter() {
-            if i >= s.start_line && i <= s.end_line {
-                min_space = s.clone();
-                stack.push(s);
-            }
-        }
-    }
-    min_space
-}
-
 // Calculate the WCC plain value for a space
 // Return the value in case of success and an specif error in case of fails
-pub(crate) fn wcc_plain_function(
-    space: &FuncSpace,
+pub(crate) fn wcc_plain_function<S: SpaceMetrics>(
+    space: &S,
     covs: &[Value],
     metric: Complexity,
     is_covdir: bool,
 ) -> Result<(f64, f64)> {
-    let ploc = space.metrics.loc.ploc();
-    let comp = match metric {
-        Complexity::Cyclomatic => space.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => space.metrics.cognitive.cognitive_sum(),
-    };
+    let ploc = space.ploc();
+    let comp = space.complexity_sum(metric);
     let sum = covs
         .iter()
         .enumerate()
@@ -45,8 +26,8 @@ pub(crate) fn wcc_plain_function(
                 line.is_null()
             };
             let sum;
-            let start = space.start_line - 1;
-            let end = space.end_line;
+            let start = space.start_line() - 1;
+            let end = space.end_line();
             if !is_null && (start..end).contains(&i) {
                 // If the line is not null and is covered (cov>0) the add the complexity  to the sum
                 let cov = line.as_u64().ok_or(Error::ConversionError())?;
@@ -66,13 +47,13 @@ pub(crate) fn wcc_plain_function(
 // Calculate the WCC quantized value for a space
 // Return the value in case of success and an specif error in case of fails
 // If the complexity of the block/file is 0 the value if wcc quantized is the coverage of the file
-pub(crate) fn wcc_quantized_function(
-    space: &FuncSpace,
+pub(crate) fn wcc_quantized_function<S: SpaceMetrics>(
+    space: &S,
     covs: &[Value],
     metric: Complexity,
     is_covdir: bool,
 ) -> Result<(f64, f64)> {
-    let ploc = space.metrics.loc.ploc();
+    let ploc = space.ploc();
     let sum =
     //For each line find the minimum space and get complexity value then sum 1 if comp>threshold  else sum 1
         covs.iter()
@@ -85,18 +66,14 @@ pub(crate) fn wcc_quantized_function(
                     line.is_null()
                 };
                 let sum;
-                let start = space.start_line-1;
-                let end =space.end_line;
+                let start = space.start_line()-1;
+                let end =space.end_line();
                 if !is_null && (start..end).contains(&i) {
                     // Get line
                     let cov = line.as_u64().ok_or(Error::ConversionError())?;
                     if cov > 0 {
                         // If the line is covered get the space of the line and then check if the complexity is below the threshold
-                        let min_space: FuncSpace = get_min_space(space, i);
-                        let comp = match metric {
-                            Complexity::Cyclomatic => min_space.metrics.cyclomatic.cyclomatic(),
-                            Complexity::Cognitive => min_space.metrics.cognitive.cognitive(),
-                        };
+                        let comp = space.min_space_complexity(i, metric);
                         if comp > THRESHOLD {
                             sum = acc + 2.;
                         } else {
@@ -131,7 +108,7 @@ mod tests {
     fn test_wcc_plain_cyclomatic() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let (wcc, _) = wcc_plain_function(&root, &vec, COMP, false).unwrap();
         assert_eq!(wcc, 24. / 10.);
@@ -141,7 +118,7 @@ mod tests {
     fn test_wcc_plain_cognitive() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let (wcc_cogn, _) = wcc_plain_function(&root, &vec, COGN, false).unwrap();
         assert_eq!(wcc_cogn, 18. / 10.);
@@ -151,7 +128,7 @@ mod tests {
     fn test_wcc_quantized_cyclomatic() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let (wcc, _) = wcc_quantized_function(&root, &vec, COMP, false).unwrap();
         assert_eq!(wcc, 6. / 10.);
@@ -161,7 +138,7 @@ mod tests {
     fn test_wcc_quantized_cognitive() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let (wcc_cogn, _) = wcc_quantized_function(&root, &vec, COGN, false).unwrap();
         assert_eq!(wcc_cogn, 6. / 10.);
@@ -171,7 +148,7 @@ mod tests {
     fn test_wcc_plain_cyclomatic_function() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let (wcc, _) = wcc_plain_function(&root, &vec, COMP, false).unwrap();
         assert_eq!(wcc, 24. / 10.);
@@ -181,7 +158,7 @@ mod tests {
     fn test_wcc_plain_cognitive_function() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let (wcc_cogn, _) = wcc_plain_function(&root, &vec, COGN, false).unwrap();
         assert_eq!(wcc_cogn, 18. / 10.);
@@ -191,7 +168,7 @@ mod tests {
     fn test_wcc_quantized_cyclomatic_function() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let (wcc, _) = wcc_quantized_function(&root, &vec, COMP, false).unwrap();
         assert_eq!(wcc, 6. / 10.);
@@ -201,7 +178,7 @@ mod tests {
     fn test_wcc_quantized_cognitive_function() {
         let file = fs::read_to_string(JSON).unwrap();
         let covs = read_json(file, PREFIX).unwrap();
-        let root = get_root(FILE).unwrap();
+        let root = get_root(FILE, None).unwrap();
         let vec = covs.get(SIMPLE).unwrap().to_vec();
         let (wcc_cogn, _) = wcc_quantized_function(&root, &vec, COGN, false).unwrap();
         assert_eq!(wcc_cogn, 6. / 10.);
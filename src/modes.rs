@@ -0,0 +1,95 @@
+use crate::error::*;
+
+/// Kind of analysis an option can be used in
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+pub enum Analysis {
+    /// Files mode with a coveralls, lcov or codecov file
+    Files,
+    /// Files mode with a covdir file
+    FilesCovdir,
+    /// Functions mode with a coveralls, lcov or codecov file
+    Functions,
+    /// Functions mode with a covdir file
+    FunctionsCovdir,
+}
+
+const FILES: &[Analysis] = &[Analysis::Files, Analysis::FilesCovdir];
+const FILES_NOT_COVDIR: &[Analysis] = &[Analysis::Files];
+const FUNCTIONS: &[Analysis] = &[Analysis::Functions, Analysis::FunctionsCovdir];
+
+/// Options that can not be used in every analysis, with the analyses they can be used in
+pub const SUPPORTED_MODES: &[(&str, &[Analysis])] = &[
+    ("--gutter", FUNCTIONS),
+    ("--zero-coverage", FILES),
+    ("--coverage-debt", FILES),
+    ("--codeowners", FILES),
+    ("--sample", FILES),
+    ("--tiers", FILES),
+    ("--threshold-expression", FILES),
+    ("The coverage_floors table of the config file", FILES),
+    ("A custom complexity provider", FILES),
+    ("--active-features", FILES_NOT_COVDIR),
+    ("--no-coverage-weight", FILES_NOT_COVDIR),
+    ("The exclusions table of the config file", FILES_NOT_COVDIR),
+];
+
+fn get_analyses(option: &str) -> &'static [Analysis] {
+    SUPPORTED_MODES
+        .iter()
+        .find(|(name, _)| *name == option)
+        .map_or(&[], |(_, analyses)| *analyses)
+}
+
+/// Get a description of the analyses an option can be used in
+pub fn get_supported_modes(option: &str) -> String {
+    let analyses = get_analyses(option);
+    let mode = if analyses.contains(&Analysis::Functions) {
+        "functions mode"
+    } else {
+        "files mode"
+    };
+    if analyses.contains(&Analysis::FilesCovdir) || analyses.contains(&Analysis::FunctionsCovdir) {
+        mode.into()
+    } else {
+        format!("{mode} and not with covdir files")
+    }
+}
+
+/// Check that the options used in an analysis are supported by it.
+///
+/// The options are given as pairs of their name in [`SUPPORTED_MODES`]
+/// and whether they are used.
+pub fn check_modes(options: &[(&'static str, bool)], analysis: Analysis) -> Result<()> {
+    match options
+        .iter()
+        .find(|(option, used)| *used && !get_analyses(option).contains(&analysis))
+    {
+        Some((option, _)) => Err(Error::ModeError(option)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_modes() {
+        assert!(check_modes(&[("--gutter", true)], Analysis::FunctionsCovdir).is_ok());
+        assert!(check_modes(&[("--gutter", false)], Analysis::Files).is_ok());
+        assert!(matches!(
+            check_modes(&[("--tiers", true), ("--gutter", true)], Analysis::Files),
+            Err(Error::ModeError("--gutter"))
+        ));
+        assert_eq!(
+            Error::ModeError("--gutter").to_string(),
+            "--gutter can be used only in functions mode"
+        );
+        assert_eq!(
+            Error::ModeError("--active-features").to_string(),
+            "--active-features can be used only in files mode and not with covdir files"
+        );
+        // Every option of the table is supported somewhere
+        assert!(SUPPORTED_MODES.iter().all(|(_, a)| !a.is_empty()));
+    }
+}
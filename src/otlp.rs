@@ -0,0 +1,385 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::error::*;
+use crate::files::StageTimes;
+
+/// Name of the service and of the instrumentation scope of the exported data
+pub const OTLP_SERVICE_NAME: &str = "weighted-code-coverage";
+
+// Timeout of the connection to the collector and of its response
+const OTLP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Phase of a run exported as a span, e.g. the analysis or the writing of the outputs
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhaseSpan {
+    pub name: String,
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
+impl PhaseSpan {
+    pub fn new(name: &str, start: SystemTime, end: SystemTime) -> Self {
+        Self {
+            name: name.into(),
+            start,
+            end,
+        }
+    }
+}
+
+/// Headline metrics and phases of the run of a mode, all the modes of a run are exported together
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModeRun {
+    pub mode: String,
+    pub project_coverage: f64,
+    pub over_threshold: usize,
+    pub phases: Vec<PhaseSpan>,
+}
+
+impl ModeRun {
+    /// Create the run of a mode whose analysis went from start to analyzed and whose outputs
+    /// were written from analyzed to now
+    /// The stages recorded during the analysis, e.g. the reading of the coverage,
+    /// are split from the analysis phase
+    pub fn new(
+        mode: &str,
+        project_coverage: f64,
+        over_threshold: usize,
+        stage_times: Option<&StageTimes>,
+        start: SystemTime,
+        analyzed: SystemTime,
+    ) -> Self {
+        let mut phases = stage_times
+            .map_or_else(Vec::new, StageTimes::get)
+            .into_iter()
+            .map(|(name, start, end)| PhaseSpan::new(name, start, end))
+            .collect::<Vec<PhaseSpan>>();
+        let analysis_start = phases.iter().map(|p| p.end).max().unwrap_or(start);
+        phases.push(PhaseSpan::new("analysis", analysis_start, analyzed));
+        phases.push(PhaseSpan::new("output", analyzed, SystemTime::now()));
+        Self {
+            mode: mode.into(),
+            project_coverage,
+            over_threshold,
+            phases,
+        }
+    }
+}
+
+// Nanoseconds since the epoch, encoded as a string as required by OTLP/JSON for 64 bit integers
+fn unix_nano(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos())
+        .to_string()
+}
+
+// Get a pseudo-random id in hex made of n 64 bit words
+fn get_id(seed: &str, words: u64) -> String {
+    (0..words)
+        .map(|word| {
+            let mut hasher = DefaultHasher::new();
+            (seed, word, std::process::id(), unix_nano(SystemTime::now())).hash(&mut hasher);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+fn get_resource() -> Value {
+    json!({
+        "attributes": [
+            {"key": "service.name", "value": {"stringValue": OTLP_SERVICE_NAME}}
+        ]
+    })
+}
+
+fn get_scope() -> Value {
+    json!({"name": OTLP_SERVICE_NAME, "version": env!("CARGO_PKG_VERSION")})
+}
+
+// Build the OTLP/JSON payload with the headline metrics as gauges, with a data point for each mode
+fn get_metrics_payload(runs: &[ModeRun], time: SystemTime) -> Value {
+    let gauges = [
+        (
+            "project_coverage",
+            "%",
+            runs.iter()
+                .map(|run| run.project_coverage)
+                .collect::<Vec<f64>>(),
+        ),
+        (
+            "over_threshold",
+            "1",
+            runs.iter()
+                .map(|run| run.over_threshold as f64)
+                .collect::<Vec<f64>>(),
+        ),
+    ];
+    let metrics = gauges
+        .iter()
+        .map(|(name, unit, values)| {
+            let data_points = runs
+                .iter()
+                .zip(values)
+                .map(|(run, value)| {
+                    json!({
+                        "asDouble": value,
+                        "timeUnixNano": unix_nano(time),
+                        "attributes": [{"key": "wcc.mode", "value": {"stringValue": run.mode}}]
+                    })
+                })
+                .collect::<Vec<Value>>();
+            json!({
+                "name": format!("wcc.{name}"),
+                "unit": unit,
+                "gauge": {"dataPoints": data_points}
+            })
+        })
+        .collect::<Vec<Value>>();
+    json!({
+        "resourceMetrics": [{
+            "resource": get_resource(),
+            "scopeMetrics": [{"scope": get_scope(), "metrics": metrics}]
+        }]
+    })
+}
+
+// Get the span of a phase or of a group of phases, with its parent if it is not the root
+fn get_span(
+    trace_id: &str,
+    span_id: &str,
+    parent_id: Option<&str>,
+    name: &str,
+    phases: &[PhaseSpan],
+) -> Value {
+    let start = phases.iter().map(|p| p.start).min().unwrap_or(UNIX_EPOCH);
+    let end = phases.iter().map(|p| p.end).max().unwrap_or(UNIX_EPOCH);
+    let mut span = json!({
+        "traceId": trace_id,
+        "spanId": span_id,
+        "name": name,
+        "kind": 1,
+        "startTimeUnixNano": unix_nano(start),
+        "endTimeUnixNano": unix_nano(end)
+    });
+    if let Some(parent_id) = parent_id {
+        span["parentSpanId"] = parent_id.into();
+    }
+    span
+}
+
+// Build the OTLP/JSON payload with a root span for the whole run, a child span for each mode
+// and under it a span for each phase of the mode
+fn get_traces_payload(runs: &[ModeRun]) -> Value {
+    let trace_id = get_id("trace", 2);
+    let root_id = get_id("run", 1);
+    let all_phases = runs
+        .iter()
+        .flat_map(|run| run.phases.iter().cloned())
+        .collect::<Vec<PhaseSpan>>();
+    let mut spans = vec![get_span(&trace_id, &root_id, None, "wcc", &all_phases)];
+    for run in runs {
+        let mode_id = get_id(&run.mode, 1);
+        let mut mode_span = get_span(
+            &trace_id,
+            &mode_id,
+            Some(&root_id),
+            &format!("wcc {}", run.mode),
+            &run.phases,
+        );
+        mode_span["attributes"] = json!([{"key": "wcc.mode", "value": {"stringValue": run.mode}}]);
+        spans.push(mode_span);
+        spans.extend(run.phases.iter().map(|phase| {
+            get_span(
+                &trace_id,
+                &get_id(&format!("{} {}", run.mode, phase.name), 1),
+                Some(&mode_id),
+                &phase.name,
+                std::slice::from_ref(phase),
+            )
+        }));
+    }
+    json!({
+        "resourceSpans": [{
+            "resource": get_resource(),
+            "scopeSpans": [{"scope": get_scope(), "spans": spans}]
+        }]
+    })
+}
+
+// Send the payload with an HTTP POST to the path of the collector, only plain http is supported
+fn post_json(endpoint: &str, path: &str, payload: &Value) -> Result<()> {
+    let address = endpoint
+        .strip_prefix("http://")
+        .ok_or_else(|| Error::OtlpError(format!("{endpoint} is not an http:// endpoint")))?
+        .trim_end_matches('/');
+    let (host, base) = match address.split_once('/') {
+        Some((host, base)) => (host, format!("/{base}")),
+        None => (address, String::new()),
+    };
+    let socket = host
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::OtlpError(format!("{host} can not be resolved")))?;
+    let body = payload.to_string();
+    let mut stream = TcpStream::connect_timeout(&socket, OTLP_TIMEOUT)?;
+    stream.set_read_timeout(Some(OTLP_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {base}{path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(Error::OtlpError(format!("{path} answered {status:?}"))),
+    }
+}
+
+/// Export the headline metrics of the modes of a run as OTLP gauges and their phases as spans
+/// to the collector at endpoint, given as http://host:port, over OTLP/HTTP with JSON encoding
+/// The gauges are wcc.project_coverage and wcc.over_threshold, the number of files or functions
+/// over the thresholds, with a data point for each mode with the mode as attribute
+/// Nothing is exported without runs, a failure while exporting only prints a warning
+pub fn emit_otlp_metrics(endpoint: &str, runs: &[ModeRun]) {
+    if runs.is_empty() {
+        return;
+    }
+    let metrics = get_metrics_payload(runs, SystemTime::now());
+    let traces = get_traces_payload(runs);
+    debug!(
+        "Exporting metrics and spans to OTLP collector at {}",
+        endpoint
+    );
+    [("/v1/metrics", metrics), ("/v1/traces", traces)]
+        .iter()
+        .for_each(|(path, payload)| {
+            if let Err(e) = post_json(endpoint, path, payload) {
+                warn!(
+                    "Failed to export {} to OTLP collector at {}: {}",
+                    path, endpoint, e
+                );
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Read a request and answer 200, returning the request line and the body
+    fn accept_request(listener: &TcpListener) -> (String, Value) {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let request = line.trim_end().to_string();
+        let mut length = 0;
+        loop {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        (request, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn test_emit_otlp_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let collector =
+            thread::spawn(move || (accept_request(&listener), accept_request(&listener)));
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let run = |mode: &str, project_coverage, over_threshold, start| ModeRun {
+            mode: mode.into(),
+            project_coverage,
+            over_threshold,
+            phases: vec![
+                PhaseSpan::new("reading coverage", at(start), at(start + 1)),
+                PhaseSpan::new("analysis", at(start + 1), at(start + 2)),
+                PhaseSpan::new("output", at(start + 2), at(start + 3)),
+            ],
+        };
+        let runs = [run("functions", 75.5, 3, 10), run("files", 80., 1, 13)];
+        emit_otlp_metrics(&endpoint, &runs);
+        let ((metrics_request, metrics), (traces_request, traces)) = collector.join().unwrap();
+        assert_eq!(metrics_request, "POST /v1/metrics HTTP/1.1");
+        let gauges = &metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        assert_eq!(gauges[0]["name"], "wcc.project_coverage");
+        let points = &gauges[0]["gauge"]["dataPoints"];
+        assert_eq!(points[0]["asDouble"], 75.5);
+        assert_eq!(
+            points[0]["attributes"][0]["value"]["stringValue"],
+            "functions"
+        );
+        assert_eq!(points[1]["asDouble"], 80.0);
+        assert_eq!(points[1]["attributes"][0]["value"]["stringValue"], "files");
+        assert_eq!(gauges[1]["gauge"]["dataPoints"][0]["asDouble"], 3.0);
+        assert_eq!(traces_request, "POST /v1/traces HTTP/1.1");
+        let spans = traces["resourceSpans"][0]["scopeSpans"][0]["spans"]
+            .as_array()
+            .unwrap();
+        // The root span covers both modes, each mode covers its phases
+        assert_eq!(spans.len(), 9);
+        assert_eq!(spans[0]["name"], "wcc");
+        assert!(spans[0].get("parentSpanId").is_none());
+        assert_eq!(spans[0]["startTimeUnixNano"], "10000000000");
+        assert_eq!(spans[0]["endTimeUnixNano"], "16000000000");
+        assert_eq!(spans[1]["name"], "wcc functions");
+        assert_eq!(spans[1]["parentSpanId"], spans[0]["spanId"]);
+        assert_eq!(spans[1]["endTimeUnixNano"], "13000000000");
+        let names = spans[2..5].iter().map(|s| &s["name"]).collect::<Vec<_>>();
+        assert_eq!(names, ["reading coverage", "analysis", "output"]);
+        assert!(spans[2..5]
+            .iter()
+            .all(|s| s["parentSpanId"] == spans[1]["spanId"]));
+        assert_eq!(spans[5]["name"], "wcc files");
+        assert_eq!(spans[5]["startTimeUnixNano"], "13000000000");
+        assert_eq!(spans[6]["parentSpanId"], spans[5]["spanId"]);
+        // An unreachable collector does not fail the run
+        emit_otlp_metrics("not an endpoint", &runs);
+    }
+
+    #[test]
+    fn test_mode_run_phases() {
+        let stage_times = StageTimes::default();
+        let start = SystemTime::now();
+        stage_times.record("reading coverage", || ());
+        let analyzed = SystemTime::now();
+        let run = ModeRun::new("files", 50., 0, Some(&stage_times), start, analyzed);
+        let names = run
+            .phases
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["reading coverage", "analysis", "output"]);
+        // The analysis starts when the coverage is read
+        assert_eq!(run.phases[1].start, run.phases[0].end);
+        assert_eq!(run.phases[1].end, analyzed);
+        let run = ModeRun::new("files", 50., 0, None, start, analyzed);
+        assert_eq!(run.phases[0], PhaseSpan::new("analysis", start, analyzed));
+    }
+}
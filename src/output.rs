@@ -1,6 +1,9 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fs;
 use std::fs::File;
 use std::path::*;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use chrono::{DateTime, Utc};
 use csv;
@@ -9,10 +12,18 @@ use tera::Context;
 use tera::Tera;
 use tracing::debug;
 
+use crate::contract::ContractResult;
 use crate::error::*;
-use crate::files::FileMetrics;
-use crate::functions::{FunctionMetrics, RootMetrics};
-use crate::utility::Sort;
+use crate::files::{
+    sort_complex_files, BenchReport, CompareReport, CrossCheck, DiffCoverage, DirectoryFloor,
+    Estimate, FileIgnored, FileMetrics, MatchDiagnostics, Metrics, OwnerMetrics, PathMapRule, Tier,
+    TrackState, ZeroCoverageFile,
+};
+use crate::functions::{
+    get_worst_module, FunctionBreakdown, FunctionMetrics, FunctionSource, NewBreach, RawFunction,
+    RootMetrics,
+};
+use crate::utility::{get_cumulative_values, Complexity, Sort};
 
 // Struct for JSON for files
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -22,8 +33,20 @@ pub struct JSONOutput {
     number_of_complex_files: usize,
     metrics: Vec<FileMetrics>,
     files_ignored: Vec<String>,
+    // Reason why each file is ignored, the files ignored are kept as paths for the older readers
+    #[serde(default)]
+    files_ignored_reasons: Vec<FileIgnored>,
     complex_files: Vec<FileMetrics>,
     project_coverage: f64,
+    // Sample the project coverage has been estimated from, only when the files are sampled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimate: Option<Estimate>,
+    // Files skipped because the max runtime has been reached, only when the results are incomplete
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    files_skipped: Option<usize>,
+    // Paths rewritten by each rule of the path map, only when a path map is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path_map: Option<Vec<PathMapRule>>,
 }
 
 // Struct for JSON for functions
@@ -34,8 +57,41 @@ pub struct JSONOutputFunc {
     number_of_complex_functions: usize,
     files: Vec<RootMetrics>,
     files_ignored: Vec<String>,
+    // Reason why each file is ignored, the files ignored are kept as paths for the older readers
+    #[serde(default)]
+    files_ignored_reasons: Vec<FileIgnored>,
     complex_functions: Vec<FunctionMetrics>,
     project_coverage: f64,
+    // Files skipped because the max runtime has been reached, only when the results are incomplete
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    files_skipped: Option<usize>,
+    // Paths rewritten by each rule of the path map, only when a path map is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path_map: Option<Vec<PathMapRule>>,
+}
+
+/// Maximum number of rows in a page of the HTML report, larger reports are split in more pages
+pub const HTML_PAGE_SIZE: usize = 1000;
+
+// Links between the pages of the HTML report
+#[derive(Serialize, Debug, PartialEq)]
+struct HTMLPagination {
+    // Number of the page, starting from 1
+    page: usize,
+    pages: Vec<String>,
+    previous_page: Option<String>,
+    next_page: Option<String>,
+}
+
+impl HTMLPagination {
+    fn new(index: usize, pages: &[String]) -> Self {
+        Self {
+            page: index + 1,
+            pages: pages.to_vec(),
+            previous_page: index.checked_sub(1).map(|i| pages[i].clone()),
+            next_page: pages.get(index + 1).cloned(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -44,11 +100,37 @@ struct HTMLTemplateFile {
     number_of_files_ignored: usize,
     number_of_complex_files: usize,
     metrics: Vec<FileMetrics>,
-    files_ignored: Vec<String>,
-    complex_files: Vec<FileMetrics>,
+    files_ignored: Vec<FileIgnored>,
+    // Complex files in a section for each tier, a single section without the tiers
+    complex_tiers: Vec<HTMLTier>,
     project_coverage: f64,
+    files_skipped: Option<usize>,
+    estimate: Option<Estimate>,
     bulma_version: String,
     date: DateTime<Utc>,
+    pagination: HTMLPagination,
+}
+
+// Complex files of a tier in the HTML report, the tier is None without the tiers
+#[derive(Serialize, Debug, PartialEq)]
+struct HTMLTier {
+    tier: Option<Tier>,
+    files: Vec<FileMetrics>,
+}
+
+// Split the complex files, sorted by tier, in a section for each tier
+fn get_html_tiers(complex_files: &[FileMetrics]) -> Vec<HTMLTier> {
+    let mut sections = Vec::<HTMLTier>::new();
+    complex_files
+        .iter()
+        .for_each(|m| match sections.last_mut() {
+            Some(section) if section.tier == m.tier => section.files.push(m.clone()),
+            _ => sections.push(HTMLTier {
+                tier: m.tier,
+                files: vec![m.clone()],
+            }),
+        });
+    sections
 }
 
 #[derive(Serialize)]
@@ -57,18 +139,23 @@ struct HTMLTemplateFunction {
     number_of_files_ignored: usize,
     number_of_complex_files: usize,
     metrics: Vec<RootMetrics>,
-    files_ignored: Vec<String>,
+    files_ignored: Vec<FileIgnored>,
     complex_functions: Vec<FunctionMetrics>,
+    // Annotated sources of the complex functions, only in the first page
+    function_sources: Vec<FunctionSource>,
     project_coverage: f64,
+    files_skipped: Option<usize>,
     bulma_version: String,
+    highlightjs_version: String,
     date: DateTime<Utc>,
+    pagination: HTMLPagination,
 }
 
 trait PrintResult<T> {
     fn print_result(result: &T, files_ignored: usize, complex_files: usize);
     fn print_json_to_file(
         result: &T,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         project_coverage: f64,
         json_path: &Path,
         project_folder: &Path,
@@ -76,14 +163,14 @@ trait PrintResult<T> {
     ) -> Result<()>;
     fn print_csv_to_file(
         result: &T,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         project_coverage: f64,
         csv_path: &Path,
         sort_by: Sort,
     ) -> Result<()>;
     fn print_html_to_file(
         result: &T,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         html: &Path,
         project_folder: &Path,
         project_coverage: f64,
@@ -92,30 +179,195 @@ trait PrintResult<T> {
 }
 struct Text;
 
+// Get the json report in files mode, with the complex files sorted by the chosen metric
+fn get_json_output(
+    result: &[FileMetrics],
+    files_ignored: &[FileIgnored],
+    project_coverage: f64,
+    project_folder: &Path,
+    sort_by: Sort,
+) -> JSONOutput {
+    let mut complex_files = result
+        .iter()
+        .filter(|m| m.metrics.is_complex)
+        .cloned()
+        .collect::<Vec<FileMetrics>>();
+    sort_complex_files(&mut complex_files, sort_by);
+    export_to_json(
+        project_folder,
+        result,
+        files_ignored,
+        &complex_files,
+        project_coverage,
+    )
+}
+
+// Get the json report in functions mode, with the complex functions sorted by the chosen metric
+fn get_json_output_function(
+    result: &[RootMetrics],
+    files_ignored: &[FileIgnored],
+    project_coverage: f64,
+    project_folder: &Path,
+    sort_by: Sort,
+) -> JSONOutputFunc {
+    let mut complex_functions: Vec<FunctionMetrics> = result
+        .iter()
+        .flat_map(|m| m.functions.clone())
+        .filter(|m| m.metrics.is_complex)
+        .collect::<Vec<FunctionMetrics>>();
+    complex_functions.sort_by(|a, b| compare_functions(a, b, sort_by));
+    export_to_json_function(
+        project_folder,
+        result,
+        files_ignored,
+        &complex_functions,
+        project_coverage,
+    )
+}
+
 impl PrintResult<Vec<FileMetrics>> for Text {
     fn print_result(result: &Vec<FileMetrics>, files_ignored: usize, complex_files: usize) {
+        // The category column is shown only when the categories are given
+        let show_category = result.iter().any(|m| m.category.is_some());
+        let mut category_header = if show_category {
+            format!(" | {: <20}", "CATEGORY")
+        } else {
+            String::new()
+        };
+        // The comment ratio column is shown only when it is requested
+        let show_comment_ratio = result.iter().any(|m| m.comment_ratio.is_some());
+        if show_comment_ratio {
+            category_header.push_str(&format!(" | {: <20}", "COMMENT RATIO"));
+        }
+        // The complexity too high column is shown only when some file has saturated metrics
+        let show_too_high = result.iter().any(|m| m.metrics.complexity_too_high);
+        if show_too_high {
+            category_header.push_str(&format!(" | {: <20}", "COMPLEXITY TOO HIGH"));
+        }
+        // The uncovered lines column is shown only when an uncovered lines budget is given
+        let show_uncovered_lines = result.iter().any(|m| m.uncovered_lines.is_some());
+        if show_uncovered_lines {
+            category_header.push_str(&format!(" | {: <20}", "UNCOVERED LINES"));
+        }
+        // The branch coverage column is shown only with covdir files with branch coverage
+        let show_branch_coverage = result.iter().any(|m| m.branch_coverage.is_some());
+        if show_branch_coverage {
+            category_header.push_str(&format!(" | {: <20}", "BRANCH COVERAGE"));
+        }
+        // The fan-in column is shown only when the complex files are ordered by it
+        let show_fan_in = result.iter().any(|m| m.fan_in.is_some());
+        if show_fan_in {
+            category_header.push_str(&format!(" | {: <20}", "FAN-IN"));
+        }
+        // The tests and priority columns are shown only with a tests per file map
+        let show_priority = result.iter().any(|m| m.priority.is_some());
+        if show_priority {
+            category_header.push_str(&format!(" | {: <20} | {: <20}", "TESTS", "PRIORITY"));
+        }
+        // The tier column is shown only when the tiers are given
+        let show_tier = result.iter().any(|m| m.tier.is_some());
+        if show_tier {
+            category_header.push_str(&format!(" | {: <20}", "TIER"));
+        }
+        // The coverage exempt column is shown only when the no coverage weight globs are given
+        let show_coverage_exempt = result.iter().any(|m| m.coverage_exempt.is_some());
+        if show_coverage_exempt {
+            category_header.push_str(&format!(" | {: <20}", "COVERAGE EXEMPT"));
+        }
         println!(
-            "{0: <20} | {1: <20} | {2: <20} | {3: <20} | {4: <20} | {5: <20} | {6: <30}",
-            "FILE", "WCC PLAIN", "WCC QUANTIZED", "CRAP", "SKUNKSCORE", "IS_COMPLEX", "PATH"
+            "{0: <20} | {1: <20} | {2: <20} | {3: <20} | {4: <20} | {5: <20} | {6: <30}{7}",
+            "FILE",
+            "WCC PLAIN",
+            "WCC QUANTIZED",
+            "CRAP",
+            "SKUNKSCORE",
+            "IS_COMPLEX",
+            "PATH",
+            category_header
         );
         result.iter().for_each(|m| {
+            let mut category = if show_category {
+                format!(" | {: <20}", m.category.as_deref().unwrap_or("-"))
+            } else {
+                String::new()
+            };
+            if show_comment_ratio {
+                let comment_ratio = m
+                    .comment_ratio
+                    .map_or("-".into(), |r| format!("{r:.2}%"));
+                category.push_str(&format!(" | {comment_ratio: <20}"));
+            }
+            if show_too_high {
+                category.push_str(&format!(" | {: <20}", m.metrics.complexity_too_high));
+            }
+            if show_uncovered_lines {
+                let uncovered_lines = m.uncovered_lines.map_or("-".into(), |u| u.to_string());
+                category.push_str(&format!(" | {uncovered_lines: <20}"));
+            }
+            if show_branch_coverage {
+                let branch_coverage = m
+                    .branch_coverage
+                    .map_or("-".into(), |b| format!("{b:.2}%"));
+                category.push_str(&format!(" | {branch_coverage: <20}"));
+            }
+            if show_fan_in {
+                let fan_in = m.fan_in.map_or("-".into(), |f| f.to_string());
+                category.push_str(&format!(" | {fan_in: <20}"));
+            }
+            if show_priority {
+                let tests = m.tests.map_or("-".into(), |t| t.to_string());
+                let priority = m.priority.map_or("-".into(), |p| format!("{p:.3}"));
+                category.push_str(&format!(" | {tests: <20} | {priority: <20}"));
+            }
+            if show_tier {
+                let tier = m.tier.map_or("-".into(), |t| t.to_string());
+                category.push_str(&format!(" | {tier: <20}"));
+            }
+            if show_coverage_exempt {
+                let exempt = m.coverage_exempt.map_or("-".into(), |e| e.to_string());
+                category.push_str(&format!(" | {exempt: <20}"));
+            }
             println!(
-                "{0: <20} | {1: <20.3} | {2: <20.3} | {3: <20.3} | {4: <20.3} | {5: <20} | {6: <30}",
+                "{0: <20} | {1: <20.3} | {2: <20.3} | {3: <20.3} | {4: <20.3} | {5: <20} | {6: <30}{7}",
                 m.file,
                 m.metrics.wcc_plain,
                 m.metrics.wcc_quantized,
                 m.metrics.crap,
                 m.metrics.skunk,
                 m.metrics.is_complex,
-                m.file_path
+                m.file_path,
+                category
             );
         });
         println!("FILES IGNORED: {files_ignored}");
         println!("COMPLEX FILES: {complex_files}");
+        // The excluded lines are shown only when the line exclusions are given
+        if result.iter().any(|m| m.excluded_lines.is_some()) {
+            let excluded_lines: usize = result.iter().filter_map(|m| m.excluded_lines).sum();
+            println!("EXCLUDED LINES: {excluded_lines}");
+        }
+        if result.iter().any(|m| m.cfg_excluded_lines.is_some()) {
+            let cfg_excluded_lines: usize =
+                result.iter().filter_map(|m| m.cfg_excluded_lines).sum();
+            println!("LINES EXCLUDED BY CFG: {cfg_excluded_lines}");
+        }
+        if show_coverage_exempt {
+            let exempt = result
+                .iter()
+                .filter(|m| m.coverage_exempt == Some(true))
+                .count();
+            println!("COVERAGE EXEMPT FILES: {exempt}");
+        }
+        if let Some(e) = get_estimate(result) {
+            println!(
+                "ESTIMATE: THE PROJECT METRICS ARE FROM A SAMPLE OF {} FILES OF {}, SEED {}",
+                e.sampled_files, e.total_files, e.seed
+            );
+        }
     }
     fn print_csv_to_file(
         result: &Vec<FileMetrics>,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         project_coverage: f64,
         csv_path: &Path,
         sort_by: Sort,
@@ -125,12 +377,7 @@ impl PrintResult<Vec<FileMetrics>> for Text {
             .filter(|m| m.metrics.is_complex)
             .cloned()
             .collect::<Vec<FileMetrics>>();
-        complex_files.sort_by(|a, b| match sort_by {
-            Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-            Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-            Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-            Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-        });
+        sort_complex_files(&mut complex_files, sort_by);
         let mut writer = csv::Writer::from_path(csv_path)?;
         writer.write_record([
             "FILE",
@@ -165,6 +412,31 @@ impl PrintResult<Vec<FileMetrics>> for Text {
             "-",
             "-",
         ])?;
+        if let Some(files_skipped) = get_files_skipped(result) {
+            writer.write_record([
+                "FILES_SKIPPED",
+                files_skipped.to_string().as_str(),
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+            ])?;
+        }
+        // The sampled files, the files with coverage and the seed of an estimate
+        if let Some(e) = get_estimate(result) {
+            writer.write_record([
+                "ESTIMATE",
+                e.sampled_files.to_string().as_str(),
+                e.total_files.to_string().as_str(),
+                e.seed.to_string().as_str(),
+                "-",
+                "-",
+                "-",
+                "-",
+            ])?;
+        }
         writer.write_record([
             "LIST OF COMPLEX FILES",
             "----------",
@@ -175,7 +447,22 @@ impl PrintResult<Vec<FileMetrics>> for Text {
             "----------",
             "----------",
         ])?;
+        let mut tier = None;
         complex_files.iter().try_for_each(|m| -> Result<()> {
+            // With the tiers the complex files are listed in a section for each tier
+            if let Some(section) = m.tier.filter(|t| tier != Some(*t)) {
+                tier = Some(section);
+                writer.write_record([
+                    format!("{} FILES", section.to_string().to_uppercase()).as_str(),
+                    "----------",
+                    "----------",
+                    "----------",
+                    "----------",
+                    "----------",
+                    "----------",
+                    "----------",
+                ])?;
+            }
             writer.write_record([
                 &m.file,
                 &format!("{:.3}", m.metrics.wcc_plain),
@@ -208,16 +495,17 @@ impl PrintResult<Vec<FileMetrics>> for Text {
             "----------",
             "----------",
         ])?;
+        // The reason why a file is ignored is written in the last column
         files_ignored.iter().try_for_each(|file| -> Result<()> {
             writer.write_record([
-                file.as_str(),
+                file.file.as_str(),
                 format!("{:.3}", 0.).as_str(),
                 format!("{:.3}", 0.).as_str(),
                 format!("{:.3}", 0.).as_str(),
                 format!("{:.3}", 0.).as_str(),
                 format!("{}", true).as_str(),
                 "-",
-                "-",
+                file.reason.to_string().as_str(),
             ])?;
             Ok(())
         })?;
@@ -236,36 +524,25 @@ impl PrintResult<Vec<FileMetrics>> for Text {
     }
     fn print_json_to_file(
         result: &Vec<FileMetrics>,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         project_coverage: f64,
         json_path: &Path,
         project_folder: &Path,
         sort_by: Sort,
     ) -> Result<()> {
-        let mut complex_files = result
-            .iter()
-            .filter(|m| m.metrics.is_complex)
-            .cloned()
-            .collect::<Vec<FileMetrics>>();
-        complex_files.sort_by(|a, b| match sort_by {
-            Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-            Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-            Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-            Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-        });
-        let json = export_to_json(
-            project_folder,
+        let json = get_json_output(
             result,
             files_ignored,
-            &complex_files,
             project_coverage,
+            project_folder,
+            sort_by,
         );
         serde_json::to_writer(&File::create(json_path)?, &json)?;
         Ok(())
     }
     fn print_html_to_file(
         result: &Vec<FileMetrics>,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         html: &Path,
         project_folder: &Path,
         project_coverage: f64,
@@ -283,34 +560,107 @@ impl PrintResult<Vec<FileMetrics>> for Text {
             .filter(|m| m.metrics.is_complex)
             .cloned()
             .collect::<Vec<FileMetrics>>();
-        complex_files.sort_by(|a, b| match sort_by {
-            Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-            Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-            Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-            Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-        });
-        let template = HTMLTemplateFile {
-            project_folder: project_folder.display().to_string(),
-            number_of_files_ignored: files_ignored.len(),
-            number_of_complex_files: complex_files.len(),
-            metrics: result.to_vec(),
-            files_ignored: files_ignored.to_vec(),
-            complex_files,
-            project_coverage,
-            bulma_version: "0.9.1".to_string(),
-            date: Utc::now(),
-        };
-        let output = tera.render("files.html", &Context::from_serialize(&template)?)?;
-        //let mut file = File::create("./output/index.html")?;
-        fs::write(html, output)?;
-        Ok(())
+        sort_complex_files(&mut complex_files, sort_by);
+        let pages = get_html_page_rows(result, HTML_PAGE_SIZE, |_| 1, |m| m.file_path == "-");
+        let paths = get_html_page_paths(html, pages.len());
+        let names = get_html_page_names(&paths)?;
+        let date = Utc::now();
+        // The complex and ignored files are shown only in the first page
+        pages
+            .iter()
+            .zip(paths.iter())
+            .enumerate()
+            .try_for_each(|(i, (page, path))| -> Result<()> {
+                let template = HTMLTemplateFile {
+                    project_folder: project_folder.display().to_string(),
+                    number_of_files_ignored: files_ignored.len(),
+                    number_of_complex_files: complex_files.len(),
+                    metrics: page.clone(),
+                    files_ignored: if i == 0 {
+                        files_ignored.to_vec()
+                    } else {
+                        Vec::new()
+                    },
+                    complex_tiers: if i == 0 {
+                        get_html_tiers(&complex_files)
+                    } else {
+                        Vec::new()
+                    },
+                    project_coverage,
+                    files_skipped: get_files_skipped(result),
+                    estimate: get_estimate(result),
+                    bulma_version: "0.9.1".to_string(),
+                    date,
+                    pagination: HTMLPagination::new(i, &names),
+                };
+                let output = tera.render("files.html", &Context::from_serialize(&template)?)?;
+                fs::write(path, output)?;
+                Ok(())
+            })
     }
 }
 impl PrintResult<Vec<RootMetrics>> for Text {
     fn print_result(result: &Vec<RootMetrics>, files_ignored: usize, complex_files: usize) {
+        // The target coverage column is shown only when it has been computed
+        let show_target_coverage = result
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .any(|f| f.target_coverage.is_some());
+        // The coverage source column is shown only with covdir files
+        let show_coverage_source = result
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .any(|f| f.coverage_source.is_some());
+        let mut target_header = if show_target_coverage {
+            format!(" | {: <20}", "TARGET COVERAGE")
+        } else {
+            String::new()
+        };
+        if show_coverage_source {
+            target_header += &format!(" | {: <20}", "COVERAGE SOURCE");
+        }
+        // The deprecated column is shown only when some function is marked as deprecated
+        let show_deprecated = result
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .any(|f| f.deprecated);
+        if show_deprecated {
+            target_header += &format!(" | {: <20}", "DEPRECATED");
+        }
+        // The complexity too high column is shown only when some function has saturated metrics
+        let show_too_high = result
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .any(|f| f.metrics.complexity_too_high);
+        if show_too_high {
+            target_header += &format!(" | {: <20}", "COMPLEXITY TOO HIGH");
+        }
+        // The calls and priority columns are shown only with a hotness map
+        let show_priority = result
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .any(|f| f.priority.is_some());
+        if show_priority {
+            target_header += &format!(" | {: <20} | {: <20}", "CALLS", "PRIORITY");
+        }
+        // The required coverage column is shown only with a coverage by length
+        let show_required_coverage = result
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .any(|f| f.required_coverage.is_some());
+        if show_required_coverage {
+            target_header += &format!(" | {: <20}", "REQUIRED COVERAGE");
+        }
         println!(
-            "{0: <20} | {1: <20} | {2: <20} | {3: <20} | {4: <20} | {5: <20} | {6: <30}",
-            "FUNCTION", "WCC PLAIN", "WCC QUANTIZED", "CRAP", "SKUNKSCORE", "IS_COMPLEX", "PATH"
+            "{0: <20} | {1: <20} | {2: <20} | {3: <20} | {4: <20} | {5: <20} | {6: <30}{7}",
+            "FUNCTION",
+            "WCC PLAIN",
+            "WCC QUANTIZED",
+            "CRAP",
+            "SKUNKSCORE",
+            "IS_COMPLEX",
+            "PATH",
+            target_header
         );
         result.iter().for_each(|m| {
             println!(
@@ -324,15 +674,41 @@ impl PrintResult<Vec<RootMetrics>> for Text {
                 m.file_path
             );
             m.functions.iter().for_each(|f|{
+                let mut target = match (show_target_coverage, &f.target_coverage) {
+                    (false, _) => String::new(),
+                    (true, Some(t)) => format!(" | {: <20}", t.to_string()),
+                    (true, None) => format!(" | {: <20}", "-"),
+                };
+                match (show_coverage_source, &f.coverage_source) {
+                    (false, _) => {}
+                    (true, Some(s)) => target += &format!(" | {: <20}", s.to_string()),
+                    (true, None) => target += &format!(" | {: <20}", "-"),
+                }
+                if show_deprecated {
+                    target += &format!(" | {: <20}", f.deprecated);
+                }
+                if show_too_high {
+                    target += &format!(" | {: <20}", f.metrics.complexity_too_high);
+                }
+                if show_priority {
+                    let calls = f.calls.map_or("-".to_string(), |c| c.to_string());
+                    let priority = f.priority.map_or("-".to_string(), |p| format!("{p:.3}"));
+                    target += &format!(" | {: <20} | {: <20}", calls, priority);
+                }
+                if show_required_coverage {
+                    let required = f.required_coverage.map_or("-".to_string(), |c| format!("{c:.3}"));
+                    target += &format!(" | {: <20}", required);
+                }
                 println!(
-                    "{0: <20} | {1: <20.3} | {2: <20.3} | {3: <20.3} | {4: <20.3} | {5: <20} | {6: <30}",
+                    "{0: <20} | {1: <20.3} | {2: <20.3} | {3: <20.3} | {4: <20.3} | {5: <20} | {6: <30}{7}",
                     f.function_name,
                     f.metrics.wcc_plain,
                     f.metrics.wcc_quantized,
                     f.metrics.crap,
                     f.metrics.skunk,
                     f.metrics.is_complex,
-                    f.function_path
+                    f.function_path,
+                    target
                 );
             });
         });
@@ -341,36 +717,25 @@ impl PrintResult<Vec<RootMetrics>> for Text {
     }
     fn print_json_to_file(
         result: &Vec<RootMetrics>,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         project_coverage: f64,
         json_path: &Path,
         project_folder: &Path,
         sort_by: Sort,
     ) -> Result<()> {
-        let mut complex_functions: Vec<FunctionMetrics> = result
-            .iter()
-            .flat_map(|m| m.functions.clone())
-            .filter(|m| m.metrics.is_complex)
-            .collect::<Vec<FunctionMetrics>>();
-        complex_functions.sort_by(|a, b| match sort_by {
-            Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-            Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-            Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-            Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-        });
-        let json = export_to_json_function(
-            project_folder,
+        let json = get_json_output_function(
             result,
             files_ignored,
-            &complex_functions,
             project_coverage,
+            project_folder,
+            sort_by,
         );
         serde_json::to_writer(&File::create(json_path)?, &json)?;
         Ok(())
     }
     fn print_csv_to_file(
         result: &Vec<RootMetrics>,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         project_coverage: f64,
         csv_path: &Path,
         sort_by: Sort,
@@ -380,12 +745,7 @@ impl PrintResult<Vec<RootMetrics>> for Text {
             .flat_map(|m| m.functions.clone())
             .filter(|m| m.metrics.is_complex)
             .collect::<Vec<FunctionMetrics>>();
-        complex_functions.sort_by(|a, b| match sort_by {
-            Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-            Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-            Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-            Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-        });
+        complex_functions.sort_by(|a, b| compare_functions(a, b, sort_by));
         let mut writer = csv::Writer::from_path(csv_path)?;
         writer.write_record([
             "FUNCTION",
@@ -433,6 +793,18 @@ impl PrintResult<Vec<RootMetrics>> for Text {
             "-",
             "-",
         ])?;
+        if let Some(files_skipped) = get_files_skipped_function(result) {
+            writer.write_record([
+                "FILES_SKIPPED",
+                files_skipped.to_string().as_str(),
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+            ])?;
+        }
         writer.write_record([
             "LIST OF COMPLEX FUNCTIONS",
             "----------",
@@ -476,16 +848,17 @@ impl PrintResult<Vec<RootMetrics>> for Text {
             "----------",
             "----------",
         ])?;
+        // The reason why a file is ignored is written in the last column
         files_ignored.iter().try_for_each(|file| -> Result<()> {
             writer.write_record([
-                file.as_str(),
+                file.file.as_str(),
                 format!("{:.3}", 0.).as_str(),
                 format!("{:.3}", 0.).as_str(),
                 format!("{:.3}", 0.).as_str(),
                 format!("{:.3}", 0.).as_str(),
                 format!("{}", true).as_str(),
                 "-",
-                "-",
+                file.reason.to_string().as_str(),
             ])?;
             Ok(())
         })?;
@@ -504,54 +877,239 @@ impl PrintResult<Vec<RootMetrics>> for Text {
     }
     fn print_html_to_file(
         result: &Vec<RootMetrics>,
-        files_ignored: &[String],
+        files_ignored: &[FileIgnored],
         html: &Path,
         project_folder: &Path,
         project_coverage: f64,
         sort_by: Sort,
     ) -> Result<()> {
-        let tera = match Tera::new("src/templates/*.html") {
-            Ok(t) => t,
-            Err(e) => {
-                println!("Parsing error(s): {e}");
-                ::std::process::exit(1);
-            }
-        };
-        let mut complex_functions: Vec<FunctionMetrics> = result
-            .iter()
-            .flat_map(|m| m.functions.clone())
-            .filter(|m| m.metrics.is_complex)
-            .collect::<Vec<FunctionMetrics>>();
-        complex_functions.sort_by(|a, b| match sort_by {
-            Sort::WccPlain => b.metrics.wcc_plain.total_cmp(&a.metrics.wcc_plain),
-            Sort::WccQuantized => b.metrics.wcc_quantized.total_cmp(&a.metrics.wcc_quantized),
-            Sort::Crap => b.metrics.crap.total_cmp(&a.metrics.crap),
-            Sort::Skunk => b.metrics.skunk.total_cmp(&a.metrics.skunk),
-        });
-        let template = HTMLTemplateFunction {
-            project_folder: project_folder.display().to_string(),
-            number_of_files_ignored: files_ignored.len(),
-            number_of_complex_files: complex_functions.len(),
-            metrics: result.to_vec(),
-            files_ignored: files_ignored.to_vec(),
-            complex_functions,
+        print_html_function(
+            result,
+            files_ignored,
+            &[],
+            html,
+            project_folder,
             project_coverage,
-            bulma_version: "0.9.1".to_string(),
-            date: Utc::now(),
-        };
-        let output = tera.render("functions.html", &Context::from_serialize(&template)?)?;
-        //let mut file = File::create("./output/index.html")?;
-        fs::write(html, output)?;
-        Ok(())
+            sort_by,
+        )
+    }
+}
+
+// Order of the complex functions in the reports
+// Sorted by path the functions keep the order of their files, already sorted by path
+fn compare_functions(a: &FunctionMetrics, b: &FunctionMetrics, sort_by: Sort) -> Ordering {
+    a.metrics.compare(&b.metrics, sort_by)
+}
+
+// Print the HTML report of the functions, the sources of the complex functions follow their table
+fn print_html_function(
+    result: &[RootMetrics],
+    files_ignored: &[FileIgnored],
+    function_sources: &[FunctionSource],
+    html: &Path,
+    project_folder: &Path,
+    project_coverage: f64,
+    sort_by: Sort,
+) -> Result<()> {
+    let tera = match Tera::new("src/templates/*.html") {
+        Ok(t) => t,
+        Err(e) => {
+            println!("Parsing error(s): {e}");
+            ::std::process::exit(1);
+        }
+    };
+    let mut complex_functions: Vec<FunctionMetrics> = result
+        .iter()
+        .flat_map(|m| m.functions.clone())
+        .filter(|m| m.metrics.is_complex)
+        .collect::<Vec<FunctionMetrics>>();
+    complex_functions.sort_by(|a, b| compare_functions(a, b, sort_by));
+    let mut function_sources = function_sources.to_vec();
+    function_sources.sort_by(|a, b| compare_functions(&a.function, &b.function, sort_by));
+    // Each file takes a row for itself and one for each of its functions
+    let pages = get_html_page_rows(
+        result,
+        HTML_PAGE_SIZE,
+        |m| m.functions.len() + 1,
+        |m| m.file_path == "-",
+    );
+    let paths = get_html_page_paths(html, pages.len());
+    let names = get_html_page_names(&paths)?;
+    let date = Utc::now();
+    // The complex functions, their sources and ignored files are shown only in the first page
+    pages
+        .iter()
+        .zip(paths.iter())
+        .enumerate()
+        .try_for_each(|(i, (page, path))| -> Result<()> {
+            let template = HTMLTemplateFunction {
+                project_folder: project_folder.display().to_string(),
+                number_of_files_ignored: files_ignored.len(),
+                number_of_complex_files: complex_functions.len(),
+                metrics: page.clone(),
+                files_ignored: if i == 0 {
+                    files_ignored.to_vec()
+                } else {
+                    Vec::new()
+                },
+                complex_functions: if i == 0 {
+                    complex_functions.clone()
+                } else {
+                    Vec::new()
+                },
+                function_sources: if i == 0 {
+                    function_sources.clone()
+                } else {
+                    Vec::new()
+                },
+                project_coverage,
+                files_skipped: get_files_skipped_function(result),
+                bulma_version: "0.9.1".to_string(),
+                highlightjs_version: "11.9.0".to_string(),
+                date,
+                pagination: HTMLPagination::new(i, &names),
+            };
+            let output = tera.render("functions.html", &Context::from_serialize(&template)?)?;
+            fs::write(path, output)?;
+            Ok(())
+        })
+}
+
+/// Prints the given coveralls payload in a json file, ready to be uploaded again
+pub fn print_coveralls_to_json<A: AsRef<Path>>(payload: &serde_json::Value, path: A) -> Result<()> {
+    debug!("Exporting coveralls payload...");
+    fs::write(path, serde_json::to_string(payload)?)?;
+    Ok(())
+}
+
+// Split the items of the HTML report in pages of at most page_size rows,
+// an item with more rows than page_size gets a page for itself
+// There is always at least one page, even without items
+fn get_html_pages<T, F: Fn(&T) -> usize>(items: &[T], page_size: usize, rows: F) -> Vec<&[T]> {
+    let mut pages = Vec::new();
+    let mut start = 0;
+    let mut page_rows = 0;
+    for (i, item) in items.iter().enumerate() {
+        let item_rows = rows(item);
+        if page_rows > 0 && page_rows + item_rows > page_size {
+            pages.push(&items[start..i]);
+            start = i;
+            page_rows = 0;
+        }
+        page_rows += item_rows;
     }
+    pages.push(&items[start..]);
+    pages
+}
+
+// Get the rows of each page of the HTML report
+// The summary rows, i.e. PROJECT, AVG, MAX and MIN, are left out of the pages
+// and added at the end of every page, so the totals are on the first page too
+fn get_html_page_rows<T: Clone, F: Fn(&T) -> usize, S: Fn(&T) -> bool>(
+    items: &[T],
+    page_size: usize,
+    rows: F,
+    is_summary: S,
+) -> Vec<Vec<T>> {
+    let (summary, items): (Vec<T>, Vec<T>) = items.iter().cloned().partition(|m| is_summary(m));
+    get_html_pages(&items, page_size, rows)
+        .into_iter()
+        .map(|page| page.iter().chain(summary.iter()).cloned().collect())
+        .collect()
+}
+
+// Get the paths of the pages of the HTML report
+// The first page is written at the given path, the others next to it with the page number appended
+fn get_html_page_paths(html: &Path, pages: usize) -> Vec<PathBuf> {
+    let stem = html.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = html
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..=pages)
+        .map(|page| {
+            if page == 1 {
+                html.to_path_buf()
+            } else {
+                html.with_file_name(format!("{stem}-{page}{extension}"))
+            }
+        })
+        .collect()
+}
+
+// Get the file names of the pages, used as links since all the pages are in the same folder
+fn get_html_page_names(paths: &[PathBuf]) -> Result<Vec<String>> {
+    paths
+        .iter()
+        .map(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .map(String::from)
+                .ok_or(Error::PathConversionError())
+        })
+        .collect()
+}
+
+// Get the sample the PROJECT row has been estimated from, None if the files have not been sampled
+fn get_estimate(metrics: &[FileMetrics]) -> Option<Estimate> {
+    metrics
+        .iter()
+        .find(|m| m.file == "PROJECT" && m.file_path == "-")
+        .and_then(|m| m.estimate)
+}
+
+// Get the files skipped because of the max runtime from the PROJECT row, None if the results are complete
+fn get_files_skipped(metrics: &[FileMetrics]) -> Option<usize> {
+    metrics
+        .iter()
+        .find(|m| m.file == "PROJECT" && m.file_path == "-")
+        .and_then(|m| m.files_skipped)
+}
+
+// Get the files skipped because of the max runtime from the PROJECT row in functions mode
+fn get_files_skipped_function(metrics: &[RootMetrics]) -> Option<usize> {
+    metrics
+        .iter()
+        .find(|m| m.file_name == "PROJECT" && m.file_path == "-")
+        .and_then(|m| m.files_skipped)
+}
+
+// Get the rules of the path map with the paths they have rewritten from the PROJECT row
+fn get_path_map(metrics: &[FileMetrics]) -> Option<Vec<PathMapRule>> {
+    metrics
+        .iter()
+        .find(|m| m.file == "PROJECT" && m.file_path == "-")
+        .and_then(|m| m.path_map.clone())
+}
+
+// Get the rules of the path map with the paths they have rewritten from the PROJECT row
+// in functions mode
+fn get_path_map_function(metrics: &[RootMetrics]) -> Option<Vec<PathMapRule>> {
+    metrics
+        .iter()
+        .find(|m| m.file_name == "PROJECT" && m.file_path == "-")
+        .and_then(|m| m.path_map.clone())
+}
+
+// Print the number of paths of the coverage rewritten by each rule of the path map
+fn print_path_map(rules: &[PathMapRule]) {
+    println!("PATH MAP:");
+    rules.iter().for_each(|rule| {
+        println!("{}={} rewrote {} paths", rule.from, rule.to, rule.rewritten);
+    });
+}
+
+// Get the paths of the files ignored
+fn get_files_ignored_paths(files_ignored: &[FileIgnored]) -> Vec<String> {
+    files_ignored.iter().map(|f| f.file.clone()).collect()
 }
 
 // Export all metrics to a json file
 pub fn export_to_json(
     project_folder: &Path,
     metrics: &[FileMetrics],
-    files_ignored: &[String],
-    complex_files: &Vec<FileMetrics>,
+    files_ignored: &[FileIgnored],
+    complex_files: &[FileMetrics],
     project_coverage: f64,
 ) -> JSONOutput {
     let number_of_files_ignored = files_ignored.len();
@@ -562,9 +1120,13 @@ pub fn export_to_json(
         number_of_files_ignored,
         number_of_complex_files,
         metrics: metrics.to_vec(),
-        files_ignored: files_ignored.to_vec(),
+        files_ignored: get_files_ignored_paths(files_ignored),
+        files_ignored_reasons: files_ignored.to_vec(),
         complex_files: complex_files.to_vec(),
         project_coverage,
+        estimate: get_estimate(metrics),
+        files_skipped: get_files_skipped(metrics),
+        path_map: get_path_map(metrics),
     }
 }
 
@@ -572,8 +1134,8 @@ pub fn export_to_json(
 pub fn export_to_json_function(
     project_folder: &Path,
     metrics: &[RootMetrics],
-    files_ignored: &[String],
-    complex_functions: &Vec<FunctionMetrics>,
+    files_ignored: &[FileIgnored],
+    complex_functions: &[FunctionMetrics],
     project_coverage: f64,
 ) -> JSONOutputFunc {
     let number_of_files_ignored = files_ignored.len();
@@ -583,24 +1145,188 @@ pub fn export_to_json_function(
         number_of_files_ignored,
         number_of_complex_functions,
         files: metrics.to_vec(),
-        files_ignored: files_ignored.to_vec(),
+        files_ignored: get_files_ignored_paths(files_ignored),
+        files_ignored_reasons: files_ignored.to_vec(),
         complex_functions: complex_functions.to_vec(),
         project_coverage,
+        files_skipped: get_files_skipped_function(metrics),
+        path_map: get_path_map_function(metrics),
+    }
+}
+
+/// Aggregate of a run printed as json by the summary json option, without the metrics
+/// of each file or function: the metrics of the PROJECT, AVG, MAX and MIN rows and the counts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JSONSummary {
+    mode: String,
+    project_folder: String,
+    project_coverage: f64,
+    number_of_files: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    number_of_functions: Option<usize>,
+    number_of_files_ignored: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    number_of_complex_files: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    number_of_complex_functions: Option<usize>,
+    project: Metrics,
+    avg: Metrics,
+    max: Metrics,
+    min: Metrics,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimate: Option<Estimate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    files_skipped: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path_map: Option<Vec<PathMapRule>>,
+}
+
+// Get the metrics of the summary rows, PROJECT, AVG, MAX and MIN, given with their names
+fn get_summary_rows<'a, I: Iterator<Item = (&'a str, &'a Metrics)>>(rows: I) -> [Metrics; 4] {
+    let mut summary = [Metrics::default(); 4];
+    rows.for_each(|(name, m)| {
+        if let Some(i) = ["PROJECT", "AVG", "MAX", "MIN"]
+            .iter()
+            .position(|n| *n == name)
+        {
+            summary[i] = *m;
+        }
+    });
+    summary
+}
+
+// Get the summary of a run in files mode
+fn get_summary_json(
+    metrics: &[FileMetrics],
+    files_ignored: &[FileIgnored],
+    complex_files: &[FileMetrics],
+    project_folder: &Path,
+    project_coverage: f64,
+) -> JSONSummary {
+    let files = metrics.iter().filter(|m| m.file_path != "-");
+    let [project, avg, max, min] = get_summary_rows(
+        metrics
+            .iter()
+            .filter(|m| m.file_path == "-")
+            .map(|m| (m.file.as_str(), &m.metrics)),
+    );
+    JSONSummary {
+        mode: "files".into(),
+        project_folder: project_folder.display().to_string(),
+        project_coverage,
+        number_of_files: files.count(),
+        number_of_functions: None,
+        number_of_files_ignored: files_ignored.len(),
+        number_of_complex_files: Some(complex_files.len()),
+        number_of_complex_functions: None,
+        project,
+        avg,
+        max,
+        min,
+        estimate: get_estimate(metrics),
+        files_skipped: get_files_skipped(metrics),
+        path_map: get_path_map(metrics),
     }
 }
 
+// Get the summary of a run in functions mode
+fn get_summary_json_function(
+    metrics: &[RootMetrics],
+    files_ignored: &[FileIgnored],
+    complex_functions: &[FunctionMetrics],
+    project_folder: &Path,
+    project_coverage: f64,
+) -> JSONSummary {
+    let files = metrics.iter().filter(|m| m.file_path != "-");
+    let [project, avg, max, min] = get_summary_rows(
+        metrics
+            .iter()
+            .filter(|m| m.file_path == "-")
+            .map(|m| (m.file_name.as_str(), &m.metrics)),
+    );
+    JSONSummary {
+        mode: "functions".into(),
+        project_folder: project_folder.display().to_string(),
+        project_coverage,
+        number_of_files: files.clone().count(),
+        number_of_functions: Some(files.map(|m| m.functions.len()).sum()),
+        number_of_files_ignored: files_ignored.len(),
+        number_of_complex_files: None,
+        number_of_complex_functions: Some(complex_functions.len()),
+        project,
+        avg,
+        max,
+        min,
+        estimate: None,
+        files_skipped: get_files_skipped_function(metrics),
+        path_map: get_path_map_function(metrics),
+    }
+}
+
+/// Prints only the summary of a run in files mode as json to stdout, on a single line
+pub fn print_summary_json(
+    metrics: &[FileMetrics],
+    files_ignored: &[FileIgnored],
+    complex_files: &[FileMetrics],
+    project_folder: &Path,
+    project_coverage: f64,
+) -> Result<()> {
+    let summary = get_summary_json(
+        metrics,
+        files_ignored,
+        complex_files,
+        project_folder,
+        project_coverage,
+    );
+    println!("{}", serde_json::to_string(&summary)?);
+    Ok(())
+}
+
+/// Prints only the summary of a run in functions mode as json to stdout, on a single line
+pub fn print_summary_json_function(
+    metrics: &[RootMetrics],
+    files_ignored: &[FileIgnored],
+    complex_functions: &[FunctionMetrics],
+    project_folder: &Path,
+    project_coverage: f64,
+) -> Result<()> {
+    let summary = get_summary_json_function(
+        metrics,
+        files_ignored,
+        complex_functions,
+        project_folder,
+        project_coverage,
+    );
+    println!("{}", serde_json::to_string(&summary)?);
+    Ok(())
+}
+
 /// This Function get the folder of the repo to analyzed and the path to the json obtained using grcov
 /// It prints all the WCC, CRAP and SkunkScore values for all the files in the folders
 /// the output will be print as follows:
 /// FILE       | WCC PLAIN | WCC QUANTIZED | CRAP       | SKUNKSCORE | "IS_COMPLEX" | "PATH"
 /// if the a file is not found in the json that files will be skipped
-
 pub fn get_metrics_output(
     metrics: &Vec<FileMetrics>,
-    files_ignored: &Vec<String>,
-    complex_files: &Vec<FileMetrics>,
+    files_ignored: &[FileIgnored],
+    complex_files: &[FileMetrics],
 ) {
     Text::print_result(metrics, files_ignored.len(), complex_files.len());
+    // The complex files are listed from the one with more dependants when ordered by fan-in
+    if complex_files.iter().any(|m| m.fan_in.is_some()) {
+        println!("COMPLEX FILES BY FAN-IN:");
+        complex_files.iter().for_each(|m| {
+            println!(
+                "{} (fan-in {}, CRAP {:.3})",
+                m.file_path,
+                m.fan_in.unwrap_or_default(),
+                m.metrics.crap
+            );
+        });
+    }
+    if let Some(rules) = get_path_map(metrics) {
+        print_path_map(&rules);
+    }
 }
 
 /// Prints the the given  metrics ,files ignored and complex files  in a csv format
@@ -608,7 +1334,7 @@ pub fn get_metrics_output(
 /// "FILE","WCC PLAIN","WCC QUANTIZED","CRAP","SKUNK","IGNORED","IS COMPLEX","FILE PATH",
 pub fn print_metrics_to_csv<A: AsRef<Path> + Copy>(
     metrics: &Vec<FileMetrics>,
-    files_ignored: &[String],
+    files_ignored: &[FileIgnored],
     csv_path: A,
     project_coverage: f64,
     sort_by: Sort,
@@ -626,7 +1352,7 @@ pub fn print_metrics_to_csv<A: AsRef<Path> + Copy>(
 /// Prints the the given  metrics ,files ignored and complex files  in a json format
 pub fn print_metrics_to_json<A: AsRef<Path> + Copy>(
     metrics: &Vec<FileMetrics>,
-    files_ignored: &[String],
+    files_ignored: &[FileIgnored],
     json_output: A,
     project_folder: A,
     project_coverage: f64,
@@ -646,7 +1372,7 @@ pub fn print_metrics_to_json<A: AsRef<Path> + Copy>(
 /// Prints the the given  metrics ,files ignored and complex files  in a json format
 pub fn print_metrics_to_html<A: AsRef<Path> + Copy>(
     metrics: &Vec<FileMetrics>,
-    files_ignored: &[String],
+    files_ignored: &[FileIgnored],
     html: A,
     project_folder: A,
     project_coverage: f64,
@@ -663,12 +1389,30 @@ pub fn print_metrics_to_html<A: AsRef<Path> + Copy>(
     )
 }
 
+/// Prints the given metrics per function, the number of files ignored and complex functions
+/// followed by the module with the highest average of the worst_by metric
 pub fn get_metrics_output_function(
     metrics: &Vec<RootMetrics>,
-    files_ignored: &[String],
-    complex_files: &Vec<FunctionMetrics>,
+    files_ignored: &[FileIgnored],
+    complex_files: &[FunctionMetrics],
+    worst_by: Sort,
 ) {
     Text::print_result(metrics, files_ignored.len(), complex_files.len());
+    if let Some(worst) = get_worst_module(metrics, worst_by) {
+        let (name, value) = match worst_by {
+            Sort::WccPlain => ("WCC PLAIN", worst.metrics.wcc_plain),
+            Sort::WccQuantized => ("WCC QUANTIZED", worst.metrics.wcc_quantized),
+            Sort::Crap | Sort::Path => ("CRAP", worst.metrics.crap),
+            Sort::Skunk => ("SKUNK", worst.metrics.skunk),
+        };
+        println!(
+            "Worst module: {} (avg {} {:.2}, {} functions over threshold)",
+            worst.module, name, value, worst.functions_over_threshold
+        );
+    }
+    if let Some(rules) = get_path_map_function(metrics) {
+        print_path_map(&rules);
+    }
 }
 
 /// Prints the the given  metrics per function ,files ignored and complex function  in a csv format
@@ -676,7 +1420,7 @@ pub fn get_metrics_output_function(
 /// "FUNCTION","WCC PLAIN","WCC QUANTIZED","CRAP","SKUNK","IGNORED","IS COMPLEX","FILE PATH",
 pub fn print_metrics_to_csv_function<A: AsRef<Path> + Copy>(
     metrics: &Vec<RootMetrics>,
-    files_ignored: &[String],
+    files_ignored: &[FileIgnored],
     csv_path: A,
     project_coverage: f64,
     sort_by: Sort,
@@ -691,10 +1435,23 @@ pub fn print_metrics_to_csv_function<A: AsRef<Path> + Copy>(
     )
 }
 
+/// Prints the raw metrics of every function joined with its coverage in a csv format,
+/// one row per function with a header of the names of the fields
+pub fn print_raw_functions_to_csv<A: AsRef<Path>>(
+    functions: &[RawFunction],
+    csv_path: A,
+) -> Result<()> {
+    debug!("Exporting raw functions to csv...");
+    let mut writer = csv::Writer::from_path(csv_path.as_ref())?;
+    functions.iter().try_for_each(|f| writer.serialize(f))?;
+    writer.flush()?;
+    Ok(())
+}
+
 /// Prints the the given  metrics per function,files ignored and complex functions  in a json format
 pub fn print_metrics_to_json_function<A: AsRef<Path> + Copy>(
     metrics: &Vec<RootMetrics>,
-    files_ignored: &[String],
+    files_ignored: &[FileIgnored],
     json_output: A,
     project_folder: A,
     project_coverage: f64,
@@ -711,30 +1468,1280 @@ pub fn print_metrics_to_json_function<A: AsRef<Path> + Copy>(
     )
 }
 
-/// Prints the the given  metrics per function, files ignored and complex functions  in a json format
-pub fn print_metrics_to_html_function<A: AsRef<Path> + Copy>(
-    metrics: &Vec<RootMetrics>,
-    files_ignored: &[String],
-    html: A,
-    project_folder: A,
+// Flat record of a line of the NDJSON output, with the metadata of the run on each line
+// The function columns are null in files mode
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct NDJSONRecord {
+    run_date: DateTime<Utc>,
+    mode: String,
+    project_folder: String,
     project_coverage: f64,
-    sort_by: Sort,
-) -> Result<()> {
-    debug!("Exporting to HTML...");
-    Text::print_html_to_file(
-        metrics,
-        files_ignored,
-        html.as_ref(),
-        project_folder.as_ref(),
-        project_coverage,
-        sort_by,
-    )
+    file: String,
+    file_path: String,
+    function_name: Option<String>,
+    function_path: Option<String>,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    wcc_plain: f64,
+    wcc_quantized: f64,
+    crap: f64,
+    skunk: f64,
+    coverage: f64,
+    is_complex: bool,
+    complexity_too_high: bool,
+    // Sample of the run, only when the files are sampled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimate: Option<Estimate>,
+    // Files skipped because the max runtime has been reached, only in an incomplete run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    files_skipped: Option<usize>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::files::*;
+impl NDJSONRecord {
+    // Record with only the metadata of the run, dated now
+    fn new(mode: &str, project_folder: &Path, project_coverage: f64) -> Self {
+        Self {
+            run_date: Utc::now(),
+            mode: mode.into(),
+            project_folder: project_folder.display().to_string(),
+            project_coverage,
+            file: String::new(),
+            file_path: String::new(),
+            function_name: None,
+            function_path: None,
+            start_line: None,
+            end_line: None,
+            wcc_plain: 0.,
+            wcc_quantized: 0.,
+            crap: 0.,
+            skunk: 0.,
+            coverage: 0.,
+            is_complex: false,
+            complexity_too_high: false,
+            estimate: None,
+            files_skipped: None,
+        }
+    }
+
+    fn estimate(mut self, estimate: Option<Estimate>) -> Self {
+        self.estimate = estimate;
+        self
+    }
+
+    fn files_skipped(mut self, files_skipped: Option<usize>) -> Self {
+        self.files_skipped = files_skipped;
+        self
+    }
+
+    fn file(mut self, file: &str, file_path: &str) -> Self {
+        self.file = file.into();
+        self.file_path = file_path.into();
+        self
+    }
+
+    fn function(mut self, function: &FunctionMetrics) -> Self {
+        self.function_name = Some(function.function_name.clone());
+        self.function_path = Some(function.function_path.clone());
+        self.start_line = Some(function.start_line);
+        self.end_line = Some(function.end_line);
+        self
+    }
+
+    fn metrics(mut self, m: &Metrics) -> Self {
+        self.wcc_plain = m.wcc_plain;
+        self.wcc_quantized = m.wcc_quantized;
+        self.crap = m.crap;
+        self.skunk = m.skunk;
+        self.coverage = m.coverage;
+        self.is_complex = m.is_complex;
+        self.complexity_too_high = m.complexity_too_high;
+        self
+    }
+}
+
+// Write the records one per line
+fn write_ndjson<A: AsRef<Path>>(records: &[NDJSONRecord], path: A) -> Result<()> {
+    let mut content = String::new();
+    records.iter().try_for_each(|record| -> Result<()> {
+        content.push_str(&serde_json::to_string(record)?);
+        content.push('\n');
+        Ok(())
+    })?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Prints the given metrics in NDJSON, one flat object per file in the order of the metrics,
+/// each one with the date, the mode, the project folder and the project coverage of the run
+/// The PROJECT, AVG, MAX and MIN rows are left out, the project coverage is on each line
+/// and so are the sample of an estimate and the files skipped of an incomplete run
+pub fn print_metrics_to_ndjson<A: AsRef<Path>>(
+    metrics: &[FileMetrics],
+    ndjson: A,
+    project_folder: &Path,
+    project_coverage: f64,
+) -> Result<()> {
+    debug!("Exporting to ndjson...");
+    let run = NDJSONRecord::new("files", project_folder, project_coverage)
+        .estimate(get_estimate(metrics))
+        .files_skipped(get_files_skipped(metrics));
+    let records = metrics
+        .iter()
+        .filter(|m| m.file_path != "-")
+        .map(|m| run.clone().file(&m.file, &m.file_path).metrics(&m.metrics))
+        .collect::<Vec<NDJSONRecord>>();
+    write_ndjson(&records, ndjson)
+}
+
+/// Prints the given metrics per function in NDJSON, one flat object per function
+/// in the order of the metrics, with the location of the function and of its file as columns
+/// and the date, the mode, the project folder, the project coverage and the files skipped of the run
+pub fn print_metrics_to_ndjson_function<A: AsRef<Path>>(
+    metrics: &[RootMetrics],
+    ndjson: A,
+    project_folder: &Path,
+    project_coverage: f64,
+) -> Result<()> {
+    debug!("Exporting to ndjson...");
+    let run = NDJSONRecord::new("functions", project_folder, project_coverage)
+        .files_skipped(get_files_skipped_function(metrics));
+    let records = metrics
+        .iter()
+        .filter(|m| m.file_path != "-")
+        .flat_map(|root| {
+            let file = run.clone().file(&root.file_name, &root.file_path);
+            root.functions
+                .iter()
+                .map(move |f| file.clone().function(f).metrics(&f.metrics))
+        })
+        .collect::<Vec<NDJSONRecord>>();
+    write_ndjson(&records, ndjson)
+}
+
+/// Name of the measurement of the headline metrics in the InfluxDB line protocol,
+/// the points of the files and of the functions have a _file and a _function suffix
+pub const INFLUX_MEASUREMENT: &str = "wcc";
+
+// Escape the commas, the equal signs and the spaces of a tag value of the line protocol
+fn escape_influx_tag(value: &str) -> String {
+    value
+        .chars()
+        .fold(String::with_capacity(value.len()), |mut escaped, c| {
+            if matches!(c, ',' | '=' | ' ') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        })
+}
+
+// Format a point of the line protocol with the metrics as float fields and is_complex as boolean
+fn get_influx_point(
+    measurement: &str,
+    tags: &[(&str, &str)],
+    m: &Metrics,
+    fields: &[(&str, String)],
+    timestamp: u128,
+) -> String {
+    let tags = tags
+        .iter()
+        .map(|(key, value)| format!(",{key}={}", escape_influx_tag(value)))
+        .collect::<String>();
+    let mut values = vec![
+        format!("wcc_plain={}", m.wcc_plain),
+        format!("wcc_quantized={}", m.wcc_quantized),
+        format!("crap={}", m.crap),
+        format!("skunk={}", m.skunk),
+        format!("coverage={}", m.coverage),
+    ];
+    values.extend(fields.iter().map(|(key, value)| format!("{key}={value}")));
+    format!("{measurement}{tags} {} {timestamp}", values.join(","))
+}
+
+// Get the fields marking the headline point of a partial run, the sampled files, the files with
+// coverage and the seed of an estimate and the files skipped of an incomplete run
+fn get_influx_partial_fields(
+    estimate: Option<Estimate>,
+    files_skipped: Option<usize>,
+) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+    if let Some(e) = estimate {
+        fields.extend([
+            ("sampled_files", format!("{}i", e.sampled_files)),
+            ("total_files", format!("{}i", e.total_files)),
+            ("seed", format!("{}u", e.seed)),
+        ]);
+    }
+    if let Some(files_skipped) = files_skipped {
+        fields.push(("files_skipped", format!("{files_skipped}i")));
+    }
+    fields
+}
+
+// Get the headline point of a run, with the metrics of the PROJECT row, the project coverage,
+// the number of files or functions over the thresholds and the fields of a partial run
+fn get_influx_headline(
+    mode: &str,
+    complexity: Complexity,
+    project: Option<&Metrics>,
+    project_coverage: f64,
+    over_threshold: usize,
+    partial_fields: Vec<(&'static str, String)>,
+    timestamp: u128,
+) -> String {
+    let complexity = complexity.to_string();
+    let m = Metrics {
+        coverage: project_coverage,
+        ..project.copied().unwrap_or_default()
+    };
+    let mut fields = vec![("over_threshold", format!("{over_threshold}i"))];
+    fields.extend(partial_fields);
+    get_influx_point(
+        INFLUX_MEASUREMENT,
+        &[("mode", mode), ("complexity", &complexity)],
+        &m,
+        &fields,
+        timestamp,
+    )
+}
+
+// Nanoseconds since the epoch, the default precision of the line protocol
+fn get_influx_timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos())
+}
+
+/// Prints the headline metrics of a run in the InfluxDB line protocol, tagged by mode and complexity
+/// With per_file a point for each file is added, tagged by its path too
+/// All the points have the same timestamp, the time of the run in nanoseconds
+pub fn print_metrics_to_influx<A: AsRef<Path>>(
+    metrics: &[FileMetrics],
+    influx: A,
+    complexity: Complexity,
+    project_coverage: f64,
+    complex_files: usize,
+    per_file: bool,
+) -> Result<()> {
+    debug!("Exporting to influx...");
+    let timestamp = get_influx_timestamp();
+    let project = metrics.iter().find(|m| m.file == "PROJECT");
+    let mut lines = vec![get_influx_headline(
+        "files",
+        complexity,
+        project.map(|m| &m.metrics),
+        project_coverage,
+        complex_files,
+        get_influx_partial_fields(get_estimate(metrics), get_files_skipped(metrics)),
+        timestamp,
+    )];
+    if per_file {
+        let complexity = complexity.to_string();
+        let measurement = format!("{INFLUX_MEASUREMENT}_file");
+        lines.extend(metrics.iter().filter(|m| m.file_path != "-").map(|m| {
+            get_influx_point(
+                &measurement,
+                &[
+                    ("mode", "files"),
+                    ("complexity", &complexity),
+                    ("file_path", &m.file_path),
+                ],
+                &m.metrics,
+                &[("is_complex", m.metrics.is_complex.to_string())],
+                timestamp,
+            )
+        }));
+    }
+    fs::write(influx, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Prints the headline metrics of a run per function in the InfluxDB line protocol,
+/// tagged by mode and complexity
+/// With per_function a point for each function is added, tagged by its file and function paths too
+/// All the points have the same timestamp, the time of the run in nanoseconds
+pub fn print_metrics_to_influx_function<A: AsRef<Path>>(
+    metrics: &[RootMetrics],
+    influx: A,
+    complexity: Complexity,
+    project_coverage: f64,
+    complex_functions: usize,
+    per_function: bool,
+) -> Result<()> {
+    debug!("Exporting to influx...");
+    let timestamp = get_influx_timestamp();
+    let project = metrics.iter().find(|m| m.file_name == "PROJECT");
+    let mut lines = vec![get_influx_headline(
+        "functions",
+        complexity,
+        project.map(|m| &m.metrics),
+        project_coverage,
+        complex_functions,
+        get_influx_partial_fields(None, get_files_skipped_function(metrics)),
+        timestamp,
+    )];
+    if per_function {
+        let complexity = complexity.to_string();
+        let measurement = format!("{INFLUX_MEASUREMENT}_function");
+        lines.extend(
+            metrics
+                .iter()
+                .filter(|m| m.file_path != "-")
+                .flat_map(|root| root.functions.iter().map(move |f| (root, f)))
+                .map(|(root, f)| {
+                    get_influx_point(
+                        &measurement,
+                        &[
+                            ("mode", "functions"),
+                            ("complexity", &complexity),
+                            ("file_path", &root.file_path),
+                            ("function_path", &f.function_path),
+                        ],
+                        &f.metrics,
+                        &[("is_complex", f.metrics.is_complex.to_string())],
+                        timestamp,
+                    )
+                }),
+        );
+    }
+    fs::write(influx, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Kind of a difference between two json reports
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DifferenceKind {
+    /// The value differs in the two reports
+    Changed,
+    /// The file is only in the fresh report
+    Added,
+    /// The file is only in the expected report
+    Removed,
+}
+
+/// Value of a json report that differs from the one of a fresh run, with its path in the report
+/// The rows of a file are identified by its path, e.g. metrics[src/app.rs].metrics.crap
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ReportDifference {
+    pub path: String,
+    pub kind: DifferenceKind,
+    pub expected: serde_json::Value,
+    pub actual: serde_json::Value,
+}
+
+/// Maximum number of differences printed when a json report is out of date
+pub const MAX_REPORT_DIFFERENCES: usize = 10;
+
+// Keys of the json report that do not depend on the sources
+const REPORT_IGNORED_KEYS: [&str; 1] = ["project_folder"];
+
+// Get the key of a row of a json report, the path of its file or the name of a summary row
+fn get_report_row_key(row: &serde_json::Value) -> Option<&str> {
+    match row.get("file_path")?.as_str()? {
+        "-" => row.get("file").or(row.get("file_name"))?.as_str(),
+        file_path => Some(file_path),
+    }
+}
+
+// Get the rows of an array of a json report by their keys, in their order,
+// None if any row has no key or the same key of another one
+fn get_report_rows(rows: &[serde_json::Value]) -> Option<Vec<(&str, &serde_json::Value)>> {
+    let rows = rows
+        .iter()
+        .map(|row| Some((get_report_row_key(row)?, row)))
+        .collect::<Option<Vec<(&str, &serde_json::Value)>>>()?;
+    let keys = rows.iter().map(|(key, _)| *key).collect::<HashSet<&str>>();
+    (keys.len() == rows.len()).then_some(rows)
+}
+
+// Find the row of a json report with the given key
+fn find_report_row<'a>(
+    rows: &[(&str, &'a serde_json::Value)],
+    key: &str,
+) -> Option<&'a serde_json::Value> {
+    rows.iter().find(|(k, _)| *k == key).map(|(_, row)| *row)
+}
+
+// Compare two values of the json reports, adding the differences found to the vector
+// The arrays of rows of files are compared by the path of the files, the other ones by index
+fn compare_report_values(
+    path: &str,
+    expected: &serde_json::Value,
+    actual: &serde_json::Value,
+    tolerance: f64,
+    differences: &mut Vec<ReportDifference>,
+) {
+    use serde_json::Value;
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => e
+            .keys()
+            .chain(a.keys().filter(|key| !e.contains_key(*key)))
+            .filter(|key| !REPORT_IGNORED_KEYS.contains(&key.as_str()))
+            .for_each(|key| {
+                compare_report_values(
+                    &child(key),
+                    e.get(key).unwrap_or(&Value::Null),
+                    a.get(key).unwrap_or(&Value::Null),
+                    tolerance,
+                    differences,
+                )
+            }),
+        (Value::Array(e), Value::Array(a)) => match get_report_rows(e).zip(get_report_rows(a)) {
+            Some((e, a)) => e
+                .iter()
+                .map(|(key, _)| key)
+                .chain(
+                    a.iter()
+                        .map(|(key, _)| key)
+                        .filter(|key| find_report_row(&e, key).is_none()),
+                )
+                .for_each(|key| {
+                    let path = format!("{path}[{key}]");
+                    match (find_report_row(&e, key), find_report_row(&a, key)) {
+                        (Some(e), Some(a)) => {
+                            compare_report_values(&path, e, a, tolerance, differences)
+                        }
+                        (e, a) => differences.push(ReportDifference {
+                            path,
+                            kind: match e {
+                                Some(_) => DifferenceKind::Removed,
+                                None => DifferenceKind::Added,
+                            },
+                            expected: e.cloned().unwrap_or(Value::Null),
+                            actual: a.cloned().unwrap_or(Value::Null),
+                        }),
+                    }
+                }),
+            None => (0..e.len().max(a.len())).for_each(|i| {
+                compare_report_values(
+                    &format!("{path}[{i}]"),
+                    e.get(i).unwrap_or(&Value::Null),
+                    a.get(i).unwrap_or(&Value::Null),
+                    tolerance,
+                    differences,
+                )
+            }),
+        },
+        (Value::Number(e), Value::Number(a))
+            if e.as_f64()
+                .zip(a.as_f64())
+                .is_some_and(|(e, a)| (e - a).abs() <= tolerance) => {}
+        _ if expected == actual => {}
+        _ => differences.push(ReportDifference {
+            path: path.into(),
+            kind: DifferenceKind::Changed,
+            expected: expected.clone(),
+            actual: actual.clone(),
+        }),
+    }
+}
+
+/// Compare a json report, e.g. committed in the repository, with the one of a fresh run
+/// The numbers are equal when their difference is within the tolerance,
+/// a value missing from one of the reports is null and the project folder is not compared
+/// It returns the differing values in the order of the report
+pub fn compare_reports(
+    expected: &serde_json::Value,
+    actual: &serde_json::Value,
+    tolerance: f64,
+) -> Vec<ReportDifference> {
+    let mut differences = Vec::new();
+    compare_report_values("", expected, actual, tolerance, &mut differences);
+    differences
+}
+
+/// Compare the json report of a previous run in files mode with the one of the given metrics
+/// It returns the differing values, see compare_reports
+pub fn verify_json_report<A: AsRef<Path>>(
+    expected: A,
+    metrics: &[FileMetrics],
+    files_ignored: &[FileIgnored],
+    project_folder: &Path,
+    project_coverage: f64,
+    sort_by: Sort,
+    tolerance: f64,
+) -> Result<Vec<ReportDifference>> {
+    let expected = serde_json::from_str(&fs::read_to_string(expected)?)?;
+    let actual = serde_json::to_value(get_json_output(
+        metrics,
+        files_ignored,
+        project_coverage,
+        project_folder,
+        sort_by,
+    ))?;
+    Ok(compare_reports(&expected, &actual, tolerance))
+}
+
+/// Compare the json report of a previous run in functions mode with the one of the given metrics
+/// It returns the differing values, see compare_reports
+pub fn verify_json_report_function<A: AsRef<Path>>(
+    expected: A,
+    metrics: &[RootMetrics],
+    files_ignored: &[FileIgnored],
+    project_folder: &Path,
+    project_coverage: f64,
+    sort_by: Sort,
+    tolerance: f64,
+) -> Result<Vec<ReportDifference>> {
+    let expected = serde_json::from_str(&fs::read_to_string(expected)?)?;
+    let actual = serde_json::to_value(get_json_output_function(
+        metrics,
+        files_ignored,
+        project_coverage,
+        project_folder,
+        sort_by,
+    ))?;
+    Ok(compare_reports(&expected, &actual, tolerance))
+}
+
+/// Prints the first differences between the json reports, at most max of them,
+/// followed by the number of the other ones
+/// the output will be print as follows:
+/// PATH | EXPECTED | ACTUAL
+pub fn print_report_differences(differences: &[ReportDifference], max: usize) {
+    let changed = differences
+        .iter()
+        .filter(|d| d.kind == DifferenceKind::Changed)
+        .collect::<Vec<&ReportDifference>>();
+    if !changed.is_empty() {
+        println!(
+            "{0: <50} | {1: <25} | {2: <25}",
+            "PATH", "EXPECTED", "ACTUAL"
+        );
+        changed.iter().take(max).for_each(|d| {
+            println!(
+                "{0: <50} | {1: <25} | {2: <25}",
+                d.path,
+                d.expected.to_string(),
+                d.actual.to_string()
+            );
+        });
+        if changed.len() > max {
+            println!("... and {} more differences", changed.len() - max);
+        }
+    }
+    [
+        (DifferenceKind::Added, "Files added:"),
+        (DifferenceKind::Removed, "Files removed:"),
+    ]
+    .iter()
+    .for_each(|(kind, title)| {
+        let paths = differences
+            .iter()
+            .filter(|d| d.kind == *kind)
+            .map(|d| d.path.as_str())
+            .collect::<Vec<&str>>();
+        if !paths.is_empty() {
+            println!("{title}");
+            paths.iter().for_each(|path| println!("  {path}"));
+        }
+    });
+}
+
+/// Prints the the given  metrics per function, files ignored and complex functions  in a json format
+/// The complex functions are followed by their sources with the uncovered lines highlighted
+pub fn print_metrics_to_html_function<A: AsRef<Path> + Copy>(
+    metrics: &[RootMetrics],
+    files_ignored: &[FileIgnored],
+    function_sources: &[FunctionSource],
+    html: A,
+    project_folder: A,
+    project_coverage: f64,
+    sort_by: Sort,
+) -> Result<()> {
+    debug!("Exporting to HTML...");
+    print_html_function(
+        metrics,
+        files_ignored,
+        function_sources,
+        html.as_ref(),
+        project_folder.as_ref(),
+        project_coverage,
+        sort_by,
+    )
+}
+
+// Get the lcov record of a file where each line is covered only if the innermost
+// function containing it is not complex, the lines outside functions follow the file
+fn get_gutter_record(root: &RootMetrics) -> String {
+    let lines = (root.start_line..=root.end_line)
+        .map(|line| {
+            let is_complex = root
+                .functions
+                .iter()
+                .filter(|f| f.start_line <= line && line <= f.end_line)
+                .min_by_key(|f| f.end_line - f.start_line)
+                .map_or(root.metrics.is_complex, |f| f.metrics.is_complex);
+            (line, usize::from(!is_complex))
+        })
+        .collect::<Vec<(usize, usize)>>();
+    let mut record = format!("TN:\nSF:{}\n", root.file_path);
+    lines.iter().for_each(|(line, hits)| {
+        record.push_str(&format!("DA:{line},{hits}\n"));
+    });
+    let hit = lines.iter().filter(|(_, hits)| *hits > 0).count();
+    record.push_str(&format!("LF:{}\nLH:{hit}\nend_of_record\n", lines.len()));
+    record
+}
+
+/// Prints the given metrics per function as a lcov file for the editors, e.g. Coverage Gutters
+/// A line is covered when the innermost function containing it does not exceed the thresholds,
+/// and not covered when it does, so the editor highlights the complex functions
+/// The lines outside functions follow the thresholds of their file
+pub fn print_metrics_to_gutter<A: AsRef<Path>>(metrics: &[RootMetrics], gutter: A) -> Result<()> {
+    debug!("Exporting gutter file...");
+    let gutter_file = metrics
+        .iter()
+        .filter(|root| root.file_path != "-" && root.end_line > 0)
+        .map(get_gutter_record)
+        .collect::<String>();
+    fs::write(gutter.as_ref(), gutter_file)?;
+    Ok(())
+}
+
+/// Prints the matching between the coverage entries and the source files in a json format
+pub fn print_diagnostics_to_json<A: AsRef<Path>>(
+    diagnostics: &MatchDiagnostics,
+    json_output: A,
+) -> Result<()> {
+    debug!("Exporting diagnostics to json...");
+    serde_json::to_writer(&File::create(json_output.as_ref())?, diagnostics)?;
+    Ok(())
+}
+
+/// Prints the files with a coverage of 0% in a json format, with their complexity and SLOC
+pub fn print_zero_coverage_to_json<A: AsRef<Path>>(
+    files: &[ZeroCoverageFile],
+    json_output: A,
+) -> Result<()> {
+    debug!("Exporting zero coverage files to json...");
+    serde_json::to_writer(&File::create(json_output.as_ref())?, files)?;
+    Ok(())
+}
+
+// Get the path of the sidecar file of a source file under the sidecars directory,
+// mirroring its path relative to the project folder
+fn get_sidecar_path(sidecars: &Path, file_path: &str) -> PathBuf {
+    sidecars.join(format!("{}.wcc.json", file_path.trim_start_matches('/')))
+}
+
+// Write a sidecar file, creating the directories of its source tree
+fn write_sidecar<T: Serialize>(path: &Path, metrics: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    serde_json::to_writer(&File::create(path)?, metrics)?;
+    Ok(())
+}
+
+/// Prints the metrics of each file in a <file>.wcc.json sidecar file, e.g. to show them in the editors,
+/// the sidecar files are written under the sidecars directory mirroring the source tree of the project
+pub fn print_metrics_to_sidecars<A: AsRef<Path>>(
+    metrics: &[FileMetrics],
+    sidecars: A,
+) -> Result<()> {
+    debug!("Exporting to sidecar files...");
+    // The sidecar files of a sample have the estimate of the run,
+    // the ones of an incomplete run the files skipped
+    let estimate = get_estimate(metrics);
+    let files_skipped = get_files_skipped(metrics);
+    metrics
+        .iter()
+        .filter(|m| m.file_path != "-")
+        .try_for_each(|m| {
+            write_sidecar(
+                &get_sidecar_path(sidecars.as_ref(), &m.file_path),
+                &m.clone().estimate(estimate).files_skipped(files_skipped),
+            )
+        })
+}
+
+/// Prints the metrics of each file, with the ones of its functions, in a <file>.wcc.json sidecar file,
+/// the sidecar files are written under the sidecars directory mirroring the source tree of the project
+pub fn print_metrics_to_sidecars_function<A: AsRef<Path>>(
+    metrics: &[RootMetrics],
+    sidecars: A,
+) -> Result<()> {
+    debug!("Exporting to sidecar files...");
+    // The sidecar files of an incomplete run have the files skipped
+    let files_skipped = get_files_skipped_function(metrics);
+    metrics
+        .iter()
+        .filter(|m| m.file_path != "-")
+        .try_for_each(|m| {
+            write_sidecar(
+                &get_sidecar_path(sidecars.as_ref(), &m.file_path),
+                &m.clone().files_skipped(files_skipped),
+            )
+        })
+}
+
+/// Prints the metrics of the files of each owner
+/// the output will be print as follows:
+/// OWNER | FILES | COMPLEX FILES | AVG COVERAGE | AVG CRAP | MAX CRAP | CRAP SUM | SKUNK SUM
+pub fn print_owner_metrics(owners: &[OwnerMetrics]) {
+    println!(
+        "{0: <30} | {1: <10} | {2: <15} | {3: <15} | {4: <15} | {5: <15} | {6: <15} | {7: <15}",
+        "OWNER",
+        "FILES",
+        "COMPLEX FILES",
+        "AVG COVERAGE",
+        "AVG CRAP",
+        "MAX CRAP",
+        "CRAP SUM",
+        "SKUNK SUM"
+    );
+    owners.iter().for_each(|o| {
+        println!(
+            "{0: <30} | {1: <10} | {2: <15} | {3: <15.2} | {4: <15.3} | {5: <15.3} | {6: <15.3} | {7: <15.3}",
+            o.owner,
+            o.number_of_files,
+            o.number_of_complex_files,
+            o.avg.coverage,
+            o.avg.crap,
+            o.max_crap,
+            o.crap_sum,
+            o.skunk_sum
+        );
+    });
+}
+
+/// Prints the coverage of each directory with a floor, the required one and whether it is below it
+pub fn print_directory_floors(directories: &[DirectoryFloor]) {
+    println!(
+        "{0: <30} | {1: <10} | {2: <15} | {3: <15} | {4: <10}",
+        "DIRECTORY", "FILES", "COVERAGE", "FLOOR", "STATUS"
+    );
+    directories.iter().for_each(|d| {
+        println!(
+            "{0: <30} | {1: <10} | {2: <15.2} | {3: <15.2} | {4: <10}",
+            d.directory,
+            d.number_of_files,
+            d.coverage,
+            d.floor,
+            if d.is_below() { "BELOW" } else { "OK" }
+        );
+    });
+    println!(
+        "DIRECTORIES BELOW THEIR FLOOR: {}",
+        directories.iter().filter(|d| d.is_below()).count()
+    );
+}
+
+/// Prints the metrics of the files of each owner in a json format
+pub fn print_owner_metrics_to_json<A: AsRef<Path>>(
+    owners: &[OwnerMetrics],
+    json_output: A,
+) -> Result<()> {
+    debug!("Exporting owner metrics to json...");
+    serde_json::to_writer(&File::create(json_output.as_ref())?, owners)?;
+    Ok(())
+}
+
+/// Prints the coverage of the lines changed in a diff for each file and the overall one
+/// the output will be print as follows:
+/// FILE       | COVERED LINES | CHANGED LINES | COVERAGE
+/// The changed files without coverage are listed after the total, followed by the number
+/// of the changed files skipped when the max runtime has been reached
+pub fn print_diff_coverage(
+    files: &[DiffCoverage],
+    total: &DiffCoverage,
+    files_without_coverage: &[String],
+    files_skipped: usize,
+) {
+    println!(
+        "{0: <40} | {1: <20} | {2: <20} | {3: <20}",
+        "FILE", "COVERED LINES", "CHANGED LINES", "COVERAGE"
+    );
+    files.iter().chain(std::iter::once(total)).for_each(|d| {
+        println!(
+            "{0: <40} | {1: <20} | {2: <20} | {3: <20.2}",
+            d.file, d.covered_lines, d.total_lines, d.coverage
+        );
+    });
+    if !files_without_coverage.is_empty() {
+        println!("Changed files without coverage:");
+        files_without_coverage
+            .iter()
+            .for_each(|file| println!("{file}"));
+    }
+    if files_skipped > 0 {
+        print_incomplete(files_skipped);
+    }
+}
+
+/// Prints the files whose coverage differs between the covdir and the coveralls files
+/// and the project coverage from each one
+/// the output will be print as follows:
+/// FILE       | COVDIR COVERAGE | COVDIR REPORTED | COVERALLS COVERAGE | DIFFERENCE
+/// COVDIR REPORTED is the coverage percentage written in the covdir file
+/// A file missing from one of the two files is shown with - as its coverage
+pub fn print_cross_check(check: &CrossCheck) {
+    println!(
+        "{0: <40} | {1: <20} | {2: <20} | {3: <20} | {4: <20}",
+        "FILE", "COVDIR COVERAGE", "COVDIR REPORTED", "COVERALLS COVERAGE", "DIFFERENCE"
+    );
+    let format = |coverage: Option<f64>| coverage.map_or("-".into(), |c| format!("{c:.2}"));
+    let rows = check
+        .files
+        .iter()
+        .map(|f| {
+            (
+                f.file.as_str(),
+                f.covdir_coverage,
+                f.covdir_reported_coverage,
+                f.coveralls_coverage,
+            )
+        })
+        .chain(std::iter::once((
+            "PROJECT",
+            Some(check.covdir_coverage),
+            Some(check.covdir_reported_coverage),
+            Some(check.coveralls_coverage),
+        )));
+    rows.for_each(|(file, covdir, reported, coveralls)| {
+        let difference = match (covdir, coveralls) {
+            (Some(a), Some(b)) => Some((a - b).abs()),
+            _ => None,
+        };
+        println!(
+            "{0: <40} | {1: <20} | {2: <20} | {3: <20} | {4: <20}",
+            file,
+            format(covdir),
+            format(reported),
+            format(coveralls),
+            format(difference)
+        );
+    });
+    println!("TOLERANCE: {:.2}", check.tolerance);
+}
+
+/// Prints that the results are incomplete because the max runtime of the run has been reached
+pub fn print_incomplete(files_skipped: usize) {
+    println!("INCOMPLETE: MAX RUNTIME REACHED, FILES SKIPPED: {files_skipped}");
+}
+
+/// Prints the functions over the thresholds that were changed since the git reference
+/// the output will be print as follows:
+/// FUNCTION | FILE PATH | CHANGED LINES | WCC PLAIN | WCC QUANTIZED | CRAP | SKUNKSCORE
+pub fn print_new_breaches(breaches: &[NewBreach]) {
+    println!(
+        "{0: <30} | {1: <30} | {2: <15} | {3: <15} | {4: <15} | {5: <15} | {6: <15}",
+        "FUNCTION",
+        "FILE PATH",
+        "CHANGED LINES",
+        "WCC PLAIN",
+        "WCC QUANTIZED",
+        "CRAP",
+        "SKUNKSCORE"
+    );
+    breaches.iter().for_each(|b| {
+        let m = &b.function.metrics;
+        println!(
+            "{0: <30} | {1: <30} | {2: <15} | {3: <15.3} | {4: <15.3} | {5: <15.3} | {6: <15.3}",
+            b.function.function_name,
+            b.file_path,
+            b.changed_lines,
+            m.wcc_plain,
+            m.wcc_quantized,
+            m.crap,
+            m.skunk
+        );
+    });
+}
+
+/// Prints the files whose metrics changed between the base and the head coverage and the overall deltas
+/// the output will be print as follows:
+/// FILE PATH  | BASE COVERAGE | HEAD COVERAGE | COVERAGE DELTA | CRAP DELTA | REGRESSION
+/// A file present only on one side has a - on the other one
+pub fn print_compare_report(report: &CompareReport) {
+    let value = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{v:.2}"));
+    let delta = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{v:+.2}"));
+    println!(
+        "{0: <40} | {1: <15} | {2: <15} | {3: <15} | {4: <15} | {5: <10}",
+        "FILE PATH", "BASE COVERAGE", "HEAD COVERAGE", "COVERAGE DELTA", "CRAP DELTA", "REGRESSION"
+    );
+    report.files.iter().for_each(|f| {
+        println!(
+            "{0: <40} | {1: <15} | {2: <15} | {3: <15} | {4: <15} | {5: <10}",
+            f.file_path,
+            value(f.base.map(|m| m.coverage)),
+            value(f.head.map(|m| m.coverage)),
+            delta(f.coverage_delta),
+            delta(f.crap_delta),
+            f.regression
+        );
+    });
+    println!(
+        "PROJECT COVERAGE: {:.2}% -> {:.2}% ({:+.2}%)",
+        report.base_coverage, report.head_coverage, report.coverage_delta
+    );
+    println!(
+        "COMPLEX FILES: {} -> {}",
+        report.base_complex_files, report.head_complex_files
+    );
+    println!("REGRESSIONS: {}", report.regressions);
+    println!("CROSSINGS: {}", report.crossings.len());
+    report.crossings.iter().for_each(|c| {
+        let mut metrics = c
+            .metrics
+            .iter()
+            .map(|m| {
+                format!(
+                    "{} {:.2} -> {:.2} (threshold {:.2})",
+                    m.metric, m.base, m.head, m.threshold
+                )
+            })
+            .collect::<Vec<String>>();
+        metrics.extend(c.reason.clone());
+        println!(
+            "{}: {} {}",
+            c.kind.to_string().to_uppercase(),
+            c.file_path,
+            metrics.join(", ")
+        );
+    });
+}
+
+/// Prints the spaces of a file with the metrics of rust-code-analysis as pretty json to stdout
+pub fn print_ast_metrics(root: &serde_json::Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(root)?);
+    Ok(())
+}
+
+/// Prints the comparison between the base and the head coverage in a json file
+pub fn print_compare_report_to_json<A: AsRef<Path>>(
+    report: &CompareReport,
+    json_output: A,
+) -> Result<()> {
+    debug!("Exporting compare report to json...");
+    serde_json::to_writer(&File::create(json_output.as_ref())?, report)?;
+    Ok(())
+}
+
+/// Prints the wall times of the benchmark for each number of threads and the fastest one
+pub fn print_bench_report(report: &BenchReport) {
+    println!(
+        "{0: <10} | {1: <10} | {2: <10} | {3: <10} | {4: <10}",
+        "THREADS", "MIN (s)", "MEAN (s)", "MAX (s)", "SPEEDUP"
+    );
+    report.results.iter().for_each(|r| {
+        println!(
+            "{0: <10} | {1: <10.3} | {2: <10.3} | {3: <10.3} | {4: <10.2}",
+            r.n_threads, r.min, r.mean, r.max, r.speedup
+        );
+    });
+    println!("RUNS: {}", report.runs);
+    if let Some(fastest) = report.fastest() {
+        println!("FASTEST: {} THREADS", fastest.n_threads);
+    }
+}
+
+/// Prints the wall times of the benchmark in a json file
+pub fn print_bench_report_to_json<A: AsRef<Path>>(
+    report: &BenchReport,
+    json_output: A,
+) -> Result<()> {
+    debug!("Exporting bench report to json...");
+    serde_json::to_writer(&File::create(json_output.as_ref())?, report)?;
+    Ok(())
+}
+
+/// Summary of a project in an aggregation of json reports, with the metrics of its PROJECT row
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AggregateProject {
+    pub label: String,
+    pub report: String,
+    pub project_folder: String,
+    pub number_of_files: usize,
+    pub number_of_complex_files: usize,
+    pub number_of_files_ignored: usize,
+    pub project_coverage: f64,
+    // Coverable lines of the project, 0 when its report does not have them
+    pub coverable_lines: usize,
+    pub metrics: Metrics,
+}
+
+/// Roll-up of the json reports in files mode of more projects, e.g. the repositories of an organization
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AggregateReport {
+    pub number_of_projects: usize,
+    pub number_of_files: usize,
+    pub number_of_complex_files: usize,
+    pub number_of_files_ignored: usize,
+    // Coverage of the projects weighted by their coverable lines
+    pub coverage: f64,
+    pub avg: Metrics,
+    pub max: Metrics,
+    pub projects: Vec<AggregateProject>,
+    // Worst files of all the projects, their paths prefixed by the label of their project
+    pub worst_files: Vec<FileMetrics>,
+}
+
+// Get the label of a project, the name of its folder or of its report when the folder has none,
+// with a numeric suffix when another project already has it
+fn get_project_label(report: &Path, project_folder: &str, labels: &[String]) -> String {
+    let name = Path::new(project_folder)
+        .file_name()
+        .or_else(|| report.file_stem())
+        .map_or("project".into(), |name| name.to_string_lossy().to_string());
+    let mut label = name.clone();
+    let mut suffix = 2;
+    while labels.contains(&label) {
+        label = format!("{name}-{suffix}");
+        suffix += 1;
+    }
+    label
+}
+
+/// Aggregate the json reports of runs in files mode of more projects
+/// The files of all the projects are ranked by the chosen metric, the worst first,
+/// and only the first top ones are kept
+pub fn aggregate_json_reports<A: AsRef<Path>>(
+    reports: &[A],
+    sort_by: Sort,
+    top: usize,
+) -> Result<AggregateReport> {
+    let mut labels = Vec::<String>::new();
+    let mut projects = Vec::<AggregateProject>::new();
+    let mut files = Vec::<FileMetrics>::new();
+    for report in reports {
+        let report = report.as_ref();
+        let output: JSONOutput = serde_json::from_str(&fs::read_to_string(report)?)
+            .map_err(|_| Error::AggregateReportError(report.display().to_string()))?;
+        let label = get_project_label(report, &output.project_folder, &labels);
+        let project_files = output
+            .metrics
+            .iter()
+            .filter(|m| m.file_path != "-")
+            .map(|m| FileMetrics {
+                file_path: format!("{label}/{}", m.file_path.trim_start_matches('/')),
+                ..m.clone()
+            })
+            .collect::<Vec<FileMetrics>>();
+        let project = output
+            .metrics
+            .iter()
+            .find(|m| m.file == "PROJECT" && m.file_path == "-");
+        let metrics = project.map_or(Metrics::default(), |m| m.metrics);
+        let coverable_lines = project.and_then(|m| m.coverable_lines).unwrap_or_default();
+        debug!(
+            "Report {:?} of {} with {} files",
+            report,
+            label,
+            project_files.len()
+        );
+        projects.push(AggregateProject {
+            label: label.clone(),
+            report: report.display().to_string(),
+            project_folder: output.project_folder,
+            number_of_files: project_files.len(),
+            number_of_complex_files: output.number_of_complex_files,
+            number_of_files_ignored: output.number_of_files_ignored,
+            project_coverage: output.project_coverage,
+            coverable_lines,
+            metrics,
+        });
+        labels.push(label);
+        files.extend(project_files);
+    }
+    let number_of_files = files.len();
+    let coverable_lines = projects.iter().map(|p| p.coverable_lines).sum::<usize>();
+    let coverage = match coverable_lines {
+        0 => 0.,
+        n => {
+            projects
+                .iter()
+                .map(|p| p.project_coverage * p.coverable_lines as f64)
+                .sum::<f64>()
+                / n as f64
+        }
+    };
+    let (avg, max) = match number_of_files {
+        0 => (Metrics::default(), Metrics::default()),
+        n => {
+            let m = files.iter().map(|f| f.metrics).collect::<Vec<Metrics>>();
+            let (mut avg, max, _) = get_cumulative_values(&m);
+            avg.coverage /= n as f64;
+            (avg, max)
+        }
+    };
+    files.sort_by(|a, b| match sort_by {
+        Sort::Path => a.file_path.cmp(&b.file_path),
+        _ => a.metrics.compare(&b.metrics, sort_by),
+    });
+    files.truncate(top);
+    Ok(AggregateReport {
+        number_of_projects: projects.len(),
+        number_of_files,
+        number_of_complex_files: projects.iter().map(|p| p.number_of_complex_files).sum(),
+        number_of_files_ignored: projects.iter().map(|p| p.number_of_files_ignored).sum(),
+        coverage,
+        avg,
+        max,
+        projects,
+        worst_files: files,
+    })
+}
+
+/// Prints the summary of each project of an aggregation, the overall one and the worst files
+/// the output will be print as follows:
+/// PROJECT | FILES | COMPLEX FILES | FILES IGNORED | COVERAGE | CRAP | SKUNKSCORE
+/// FILE PATH | WCC PLAIN | WCC QUANTIZED | CRAP | SKUNKSCORE | IS_COMPLEX
+pub fn print_aggregate_report(report: &AggregateReport) {
+    println!(
+        "{0: <30} | {1: <10} | {2: <15} | {3: <15} | {4: <15} | {5: <15} | {6: <15}",
+        "PROJECT", "FILES", "COMPLEX FILES", "FILES IGNORED", "COVERAGE", "CRAP", "SKUNKSCORE"
+    );
+    report.projects.iter().for_each(|p| {
+        println!(
+            "{0: <30} | {1: <10} | {2: <15} | {3: <15} | {4: <15.2} | {5: <15.3} | {6: <15.3}",
+            p.label,
+            p.number_of_files,
+            p.number_of_complex_files,
+            p.number_of_files_ignored,
+            p.project_coverage,
+            p.metrics.crap,
+            p.metrics.skunk
+        );
+    });
+    println!("PROJECTS: {}", report.number_of_projects);
+    println!("FILES: {}", report.number_of_files);
+    println!("COMPLEX FILES: {}", report.number_of_complex_files);
+    println!("FILES IGNORED: {}", report.number_of_files_ignored);
+    println!("COVERAGE: {:.2}%", report.coverage);
+    println!(
+        "AVG CRAP: {:.3} MAX CRAP: {:.3}",
+        report.avg.crap, report.max.crap
+    );
+    println!();
+    println!(
+        "{0: <50} | {1: <15} | {2: <15} | {3: <15} | {4: <15} | {5: <10}",
+        "FILE PATH", "WCC PLAIN", "WCC QUANTIZED", "CRAP", "SKUNKSCORE", "IS_COMPLEX"
+    );
+    report.worst_files.iter().for_each(|f| {
+        println!(
+            "{0: <50} | {1: <15.3} | {2: <15.3} | {3: <15.3} | {4: <15.3} | {5: <10}",
+            f.file_path,
+            f.metrics.wcc_plain,
+            f.metrics.wcc_quantized,
+            f.metrics.crap,
+            f.metrics.skunk,
+            f.metrics.is_complex
+        );
+    });
+}
+
+/// Prints the aggregation of the json reports in a json file
+pub fn print_aggregate_report_to_json<A: AsRef<Path>>(
+    report: &AggregateReport,
+    json_output: A,
+) -> Result<()> {
+    debug!("Exporting aggregate report to json...");
+    serde_json::to_writer(&File::create(json_output.as_ref())?, report)?;
+    Ok(())
+}
+
+/// Prints the result of each contract and of each of its clauses
+/// the output will be print as follows:
+/// CONTRACT src: FAIL (7 files)
+///     min_coverage >= 80: FAIL (0.00) src/error.rs
+pub fn print_contract_results(results: &[ContractResult]) {
+    let status = |passed: bool| if passed { "PASS" } else { "FAIL" };
+    results.iter().for_each(|r| {
+        let dir = if r.dir.is_empty() { "." } else { &r.dir };
+        println!(
+            "CONTRACT {}: {} ({} files)",
+            dir,
+            status(r.passed),
+            r.number_of_files
+        );
+        r.clauses.iter().for_each(|c| {
+            let operator = if c.clause.starts_with("min_") {
+                ">="
+            } else {
+                "<="
+            };
+            let files = if c.files.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", c.files.join(", "))
+            };
+            println!(
+                "    {} {} {}: {} ({:.2}){}",
+                c.clause,
+                operator,
+                c.limit,
+                status(c.passed),
+                c.value,
+                files
+            );
+        });
+    });
+}
+
+/// Prints the line totals of the run and their delta from the previous run of the track file,
+/// e.g. SLOC +1200, covered +300, to tell whether a coverage drop comes from new untested code
+pub fn print_track_delta(state: &TrackState, delta: Option<&TrackState>) {
+    println!(
+        "SLOC: {}, COVERED LINES: {}, COVERABLE LINES: {}, COVERAGE: {:.2}%",
+        state.sloc, state.covered_lines, state.coverable_lines, state.coverage
+    );
+    match delta {
+        Some(d) => println!(
+            "DELTA: SLOC {:+}, covered {:+}, coverable {:+}, coverage {:+.2}%",
+            d.sloc, d.covered_lines, d.coverable_lines, d.coverage
+        ),
+        None => println!("DELTA: no previous run tracked"),
+    }
+    if let Some(coverage_debt) = state.coverage_debt {
+        match delta.and_then(|d| d.coverage_debt) {
+            Some(d) => println!("COVERAGE DEBT: {coverage_debt:.3}, DELTA: {d:+.3}"),
+            None => println!("COVERAGE DEBT: {coverage_debt:.3}"),
+        }
+    }
+}
+
+/// Prints the full breakdown of the metrics of the given functions, one value per line
+pub fn print_function_breakdown(breakdown: &[FunctionBreakdown]) {
+    breakdown.iter().for_each(|b| {
+        let m = &b.function.metrics;
+        println!("{0: <20} {1}", "FUNCTION", b.function.function_name);
+        println!("{0: <20} {1}", "FILE PATH", b.file_path);
+        println!("{0: <20} {1}", "FUNCTION PATH", b.function.function_path);
+        println!("{0: <20} {1}", "COMPLEXITY", b.complexity);
+        println!("{0: <20} {1}", "SLOC", b.sloc);
+        println!("{0: <20} {1}", "PLOC", b.ploc);
+        println!("{0: <20} {1}", "COVERED LINES", b.covered_lines);
+        println!("{0: <20} {1}", "COVERABLE LINES", b.total_lines);
+        println!("{0: <20} {1:.2}%", "COVERAGE", m.coverage);
+        println!("{0: <20} {1}", "WCC PLAIN", m.wcc_plain);
+        println!("{0: <20} {1}", "WCC QUANTIZED", m.wcc_quantized);
+        println!(
+            "{0: <20} {1} = COMP^2 * (1 - COV)^3 ({2}) + COMP ({3})",
+            "CRAP", m.crap, b.crap_uncovered_term, b.complexity
+        );
+        println!("{0: <20} {1}", "SKUNK", m.skunk);
+        println!("{0: <20} {1}", "IS COMPLEX", m.is_complex);
+        if let Some(target_coverage) = &b.function.target_coverage {
+            println!("{0: <20} {1}", "TARGET COVERAGE", target_coverage);
+        }
+        println!();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::*;
     use crate::functions::*;
     use crate::utility::*;
     use std::fs;
@@ -753,6 +2760,7 @@ mod tests {
             8,
             &[30., 1.5, 35., 30.],
             Sort::WccPlain,
+            &AnalysisOptions::default(),
         )
         .unwrap();
         Text::print_csv_to_file(
@@ -782,6 +2790,7 @@ mod tests {
             8,
             &[30., 1.5, 35., 30.],
             Sort::WccPlain,
+            &AnalysisOptions::default(),
         )
         .unwrap();
         let to_compare = export_to_json(
@@ -804,9 +2813,11 @@ mod tests {
                         skunk: 15.87012987012987,
                         is_complex: true,
                         coverage: 91.56,
+                        complexity_too_high: false,
                     },
                     file: "flag.rs".into(),
                     file_path: "src/flag.rs".into(),
+                    ..Default::default()
                 },
                 FileMetrics {
                     metrics: Metrics {
@@ -816,9 +2827,12 @@ mod tests {
                         skunk: 15.87012987012987,
                         is_complex: false,
                         coverage: 91.55844155844156,
+                        complexity_too_high: false,
                     },
                     file: "PROJECT".into(),
                     file_path: "-".into(),
+                    coverable_lines: Some(154),
+                    ..Default::default()
                 },
                 FileMetrics {
                     metrics: Metrics {
@@ -828,9 +2842,11 @@ mod tests {
                         skunk: 15.87012987012987,
                         is_complex: false,
                         coverage: 91.56,
+                        complexity_too_high: false,
                     },
                     file: "AVG".into(),
                     file_path: "-".into(),
+                    ..Default::default()
                 },
                 FileMetrics {
                     metrics: Metrics {
@@ -840,9 +2856,11 @@ mod tests {
                         skunk: 15.87012987012987,
                         is_complex: false,
                         coverage: 0.0,
+                        complexity_too_high: false,
                     },
                     file: "MAX".into(),
                     file_path: "-".into(),
+                    ..Default::default()
                 },
                 FileMetrics {
                     metrics: Metrics {
@@ -852,12 +2870,15 @@ mod tests {
                         skunk: 15.87012987012987,
                         is_complex: false,
                         coverage: 100.0,
+                        complexity_too_high: false,
                     },
                     file: "MIN".into(),
                     file_path: "-".into(),
+                    ..Default::default()
                 },
             ],
             files_ignored: Vec::<String>::new(),
+            files_ignored_reasons: Vec::<FileIgnored>::new(),
             complex_files: vec![FileMetrics {
                 metrics: Metrics {
                     wcc_plain: 34.696335078534034,
@@ -866,11 +2887,16 @@ mod tests {
                     skunk: 15.87012987012987,
                     is_complex: true,
                     coverage: 91.56,
+                    complexity_too_high: false,
                 },
                 file: "flag.rs".into(),
                 file_path: "src/flag.rs".into(),
+                ..Default::default()
             }],
             project_coverage: 91.56,
+            estimate: None,
+            files_skipped: None,
+            path_map: None,
         };
         assert!(to_compare == expected);
     }
@@ -886,6 +2912,7 @@ mod tests {
                 8,
                 &[30., 1.5, 35., 30.],
                 Sort::WccPlain,
+                &AnalysisOptions::default(),
             )
             .unwrap();
         Text::print_csv_to_file(
@@ -915,6 +2942,7 @@ mod tests {
                 8,
                 &[30., 1.5, 35., 30.],
                 Sort::WccPlain,
+                &AnalysisOptions::default(),
             )
             .unwrap();
         let path = Path::new(FOLDER);
@@ -937,12 +2965,15 @@ mod tests {
                             crap: 48.32881221072737,
                             skunk: 15.87012987012987,
                             is_complex: true,
-                            coverage: 91.56
+                            coverage: 91.56,
+                            complexity_too_high: false
                         },
                         file_name: "flag.rs".into(),
                         file_path: "src/flag.rs".into(),
                         start_line: 1,
                         end_line: 261,
+                        files_skipped: None,
+                        path_map: None,
                         functions: vec![
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -951,12 +2982,18 @@ mod tests {
                                     crap: 1.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "opiton_index (155, 175)".into(),
                                 function_path: "/opiton_index (155,175)".into(),
                                 start_line: 155,
-                                end_line: 175
+                                end_line: 175,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics{
@@ -965,12 +3002,18 @@ mod tests {
                                     crap: 1.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "construct_fail_1 (179, 181)".into(),
                                 function_path: "/construct_fail_1 (179,181)".into(),
                                 start_line: 179,
-                                end_line: 181
+                                end_line: 181,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics{
@@ -979,12 +3022,18 @@ mod tests {
                                     crap: 1.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "construct_fail_2 (185, 187)".into(),
                                 function_path: "/construct_fail_2 (185,187)".into(),
                                 start_line: 185,
-                                end_line: 187
+                                end_line: 187,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -993,12 +3042,18 @@ mod tests {
                                     crap: 1.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "construct_fail_3 (191, 193)".into(),
                                 function_path: "/construct_fail_3 (191,193)".into(),
                                 start_line: 191,
-                                end_line: 193
+                                end_line: 193,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1007,12 +3062,18 @@ mod tests {
                                     crap: 3.0040964952207556,
                                     skunk: 0.9230769230769231,
                                     is_complex: false,
-                                    coverage: 92.31
+                                    coverage: 92.31,
+                                    complexity_too_high: false
                                 },
                                 function_name: "bool_flag_test (196, 209)".into(),
                                 function_path: "/bool_flag_test (196,209)".into(),
                                 start_line: 196,
-                                end_line: 209
+                                end_line: 209,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics{
@@ -1021,12 +3082,18 @@ mod tests {
                                     crap: 3.003279883381924,
                                     skunk: 0.8571428571428567,
                                     is_complex: false,
-                                    coverage: 92.86
+                                    coverage: 92.86,
+                                    complexity_too_high: false
                                 },
                                 function_name: "string_flag_test (212, 226)".into(),
                                 function_path: "/string_flag_test (212,226)".into(),
                                 start_line: 212,
-                                end_line: 226
+                                end_line: 226,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1035,12 +3102,18 @@ mod tests {
                                     crap: 3.003279883381924,
                                     skunk: 0.8571428571428567,
                                     is_complex: false,
-                                    coverage: 92.86
+                                    coverage: 92.86,
+                                    complexity_too_high: false
                                 },
                                 function_name: "int_flag_test (229, 243)".into(),
                                 function_path: "/int_flag_test (229,243)".into(),
                                 start_line: 229,
-                                end_line: 243
+                                end_line: 243,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1049,12 +3122,18 @@ mod tests {
                                     crap: 3.003279883381924,
                                     skunk: 0.8571428571428567,
                                     is_complex: false,
-                                    coverage: 92.86
+                                    coverage: 92.86,
+                                    complexity_too_high: false
                                 },
                                 function_name: "float_flag_test (246, 260)".into(),
                                 function_path: "/float_flag_test (246,260)".into(),
                                 start_line: 246,
-                                end_line: 260
+                                end_line: 260,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1063,12 +3142,18 @@ mod tests {
                                     crap: 4.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "new (47, 74)".into(),
                                 function_path: "/Flag (36,148)/new (47,74)".into(),
                                 start_line: 47,
-                                end_line: 74
+                                end_line: 74,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1077,12 +3162,18 @@ mod tests {
                                     crap: 2.0,
                                     skunk: 4.0,
                                     is_complex: false,
-                                    coverage: 0.0
+                                    coverage: 0.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "description (86, 89)".into(),
                                 function_path: "/Flag (36,148)/description (86,89)".into(),
                                 start_line: 86,
-                                end_line: 89
+                                end_line: 89,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1091,12 +3182,18 @@ mod tests {
                                     crap: 2.011661807580175,
                                     skunk: 1.1428571428571435,
                                     is_complex: false,
-                                    coverage: 85.71
+                                    coverage: 85.71,
+                                    complexity_too_high: false
                                 },
                                 function_name: "alias (105, 112)".into(),
                                 function_path: "/Flag (36,148)/alias (105,112)".into(),
                                 start_line: 105,
-                                end_line: 112
+                                end_line: 112,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1105,12 +3202,18 @@ mod tests {
                                     crap: 7.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "option_index (115, 122)".into(),
                                 function_path: "/Flag (36,148)/option_index (115,122)".into(),
                                 start_line: 115,
-                                end_line: 122
+                                end_line: 122,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics{
@@ -1119,12 +3222,18 @@ mod tests {
                                     crap: 17.93099938937513,
                                     skunk: 14.11764705882353,
                                     is_complex: false,
-                                    coverage: 76.47
+                                    coverage: 76.47,
+                                    complexity_too_high: false
                                 },
                                 function_name: "value (125, 147)".into(),
                                 function_path: "/Flag (36,148)/value (125,147)".into(),
                                 start_line: 125,
-                                end_line: 147
+                                end_line: 147,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics{
@@ -1133,12 +3242,18 @@ mod tests {
                                     crap: 3.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "<anonymous> (117, 119)".into(),
                                 function_path: "/Flag (36,148)/option_index (115,122)/<anonymous> (117,119)".into(),
                                 start_line: 117,
-                                end_line: 119
+                                end_line: 119,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1147,12 +3262,18 @@ mod tests {
                                     crap: 1.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "<anonymous> (120, 120)".into(),
                                 function_path: "/Flag (36,148)/option_index (115,122)/<anonymous> (120,120)".into(),
                                 start_line: 120,
-                                end_line: 120
+                                end_line: 120,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             },
                             FunctionMetrics {
                                 metrics: Metrics {
@@ -1161,12 +3282,18 @@ mod tests {
                                     crap: 1.0,
                                     skunk: 0.0,
                                     is_complex: false,
-                                    coverage: 100.0
+                                    coverage: 100.0,
+                                    complexity_too_high: false
                                 },
                                 function_name: "<anonymous> (118, 118)".into(),
                                 function_path: "/Flag (36,148)/option_index (115,122)/<anonymous> (117,119)/<anonymous> (118,118)".into(),
                                 start_line: 118,
-                                end_line: 118
+                                end_line: 118,
+                                target_coverage: None,
+                                coverage_source: None,
+                                deprecated: false,
+                                calls: None,
+                                priority: None, required_coverage: None
                             }
                         ]
                     },
@@ -1177,12 +3304,15 @@ mod tests {
                             crap: 48.32881221072737,
                             skunk: 15.87012987012987,
                             is_complex: false,
-                            coverage: 91.55844155844156
+                            coverage: 91.55844155844156,
+                            complexity_too_high: false
                         },
                         file_name: "PROJECT".into(),
                         file_path: "-".into(),
                         start_line: 0,
                         end_line: 0,
+                        files_skipped: None,
+                        path_map: None,
                         functions: Vec::<FunctionMetrics>::new()
                     },
                     RootMetrics {
@@ -1192,12 +3322,15 @@ mod tests {
                             crap: 48.32881221072737,
                             skunk: 15.87012987012987,
                             is_complex: false,
-                            coverage: 91.56
+                            coverage: 91.56,
+                            complexity_too_high: false
                         },
                         file_name: "AVG".into(),
                         file_path: "-".into(),
                         start_line: 0,
                         end_line: 0,
+                        files_skipped: None,
+                        path_map: None,
                         functions: Vec::<FunctionMetrics>::new()
                     },
                     RootMetrics {
@@ -1207,12 +3340,15 @@ mod tests {
                             crap: 48.32881221072737,
                             skunk: 15.87012987012987,
                             is_complex: false,
-                            coverage: 0.0
+                            coverage: 0.0,
+                            complexity_too_high: false
                         },
                         file_name: "MAX".into(),
                         file_path: "-".into(),
                         start_line: 0,
                         end_line: 0,
+                        files_skipped: None,
+                        path_map: None,
                         functions: Vec::<FunctionMetrics>::new()
                     },
                     RootMetrics {
@@ -1222,19 +3358,693 @@ mod tests {
                             crap: 48.32881221072737,
                             skunk: 15.87012987012987,
                             is_complex: false,
-                            coverage: 100.0
+                            coverage: 100.0,
+                            complexity_too_high: false
                         },
                         file_name: "MIN".into(),
                         file_path: "-".into(),
                         start_line: 0,
                         end_line: 0,
+                        files_skipped: None,
+                        path_map: None,
                         functions: Vec::<FunctionMetrics>::new()
                     }
                 ],
                 files_ignored: Vec::<String>::new(),
+            files_ignored_reasons: Vec::<FileIgnored>::new(),
                 complex_functions: Vec::<FunctionMetrics>::new(),
-                project_coverage: 91.56
+                project_coverage: 91.56,
+                files_skipped: None,
+                path_map: None,
         };
         assert!(to_compare == expected);
     }
+
+    #[test]
+    fn test_gutter() {
+        let complex = Metrics::default().is_complex(true);
+        let simple = Metrics::default();
+        let root = RootMetrics::new(
+            simple,
+            "lib.rs".into(),
+            "src/lib.rs".into(),
+            1,
+            6,
+            vec![
+                FunctionMetrics::new(complex, "f (2, 5)".into(), "/f (2,5)".into(), 2, 5),
+                FunctionMetrics::new(simple, "g (3, 4)".into(), "/f (2,5)/g (3,4)".into(), 3, 4),
+            ],
+        );
+        let path = Path::new("./data/test_project/gutter.info");
+        print_metrics_to_gutter(&[root, RootMetrics::avg(simple)], path).unwrap();
+        let gutter = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(
+            gutter,
+            "TN:\nSF:src/lib.rs\nDA:1,1\nDA:2,0\nDA:3,1\nDA:4,1\nDA:5,0\nDA:6,1\nLF:6\nLH:4\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn test_html_pages() {
+        let rows = [3, 2, 1, 5, 1];
+        let pages = get_html_pages(&rows, 4, |r| *r);
+        assert_eq!(pages, vec![&[3][..], &[2, 1][..], &[5][..], &[1][..]]);
+        assert_eq!(get_html_pages::<usize, _>(&[], 4, |r| *r).len(), 1);
+        // The summary rows, here the zeros, are at the end of every page
+        let pages = get_html_page_rows(&[3, 0, 2, 1, 0], 4, |r| *r, |r| *r == 0);
+        assert_eq!(pages, vec![vec![3, 0, 0], vec![2, 1, 0, 0]]);
+        let paths = get_html_page_paths(Path::new("./data/report.html"), 3);
+        assert_eq!(
+            get_html_page_names(&paths).unwrap(),
+            vec!["report.html", "report-2.html", "report-3.html"]
+        );
+        let names = get_html_page_names(&paths).unwrap();
+        let first = HTMLPagination::new(0, &names);
+        assert_eq!(first.previous_page, None);
+        assert_eq!(first.next_page.as_deref(), Some("report-2.html"));
+        let last = HTMLPagination::new(2, &names);
+        assert_eq!(last.page, 3);
+        assert_eq!(last.previous_page.as_deref(), Some("report-2.html"));
+        assert_eq!(last.next_page, None);
+    }
+
+    #[test]
+    fn test_html_summary_rows() {
+        let mut metrics = (0..=HTML_PAGE_SIZE)
+            .map(|i| FileMetrics {
+                file: format!("file_{i}.rs"),
+                file_path: format!("src/file_{i}.rs"),
+                ..Default::default()
+            })
+            .collect::<Vec<FileMetrics>>();
+        metrics.extend(["PROJECT", "AVG", "MAX", "MIN"].map(|file| FileMetrics {
+            file: file.into(),
+            file_path: "-".into(),
+            ..Default::default()
+        }));
+        let html = Path::new("./data/test_project/summary_rows.html");
+        print_metrics_to_html(&metrics, &[], html, Path::new(FOLDER), 0., Sort::WccPlain).unwrap();
+        let first = fs::read_to_string(html).unwrap();
+        let second_path = Path::new("./data/test_project/summary_rows-2.html");
+        let second = fs::read_to_string(second_path).unwrap();
+        fs::remove_file(html).unwrap();
+        fs::remove_file(second_path).unwrap();
+        // The summary rows are on every page, after the files of the page
+        [&first, &second].iter().for_each(|page| {
+            let files = page.find("file_").unwrap();
+            ["PROJECT", "AVG", "MAX", "MIN"]
+                .iter()
+                .for_each(|row| assert!(page.find(&format!("> {row} <")).unwrap() > files));
+        });
+        assert!(
+            first.contains("> file_0.rs <")
+                && !first.contains(&format!("> file_{HTML_PAGE_SIZE}.rs <"))
+        );
+        assert!(second.contains(&format!("> file_{HTML_PAGE_SIZE}.rs <")));
+    }
+
+    #[test]
+    fn test_html_tiers() {
+        let complex = |file: &str, tier: Tier| FileMetrics {
+            metrics: Metrics {
+                is_complex: true,
+                ..Default::default()
+            },
+            file: file.into(),
+            file_path: format!("src/{file}"),
+            tier: Some(tier),
+            ..Default::default()
+        };
+        let metrics = vec![
+            complex("low.rs", Tier::Low),
+            complex("critical.rs", Tier::Critical),
+            complex("other_low.rs", Tier::Low),
+        ];
+        let html = Path::new("./data/test_project/tiers.html");
+        print_metrics_to_html(&metrics, &[], html, Path::new(FOLDER), 0., Sort::WccPlain).unwrap();
+        let page = fs::read_to_string(html).unwrap();
+        fs::remove_file(html).unwrap();
+        // The complex files are in a section for each tier, from the most severe
+        let complex_files = page.find("Complex Files").unwrap();
+        let critical = page.find("Critical Files : 1").unwrap();
+        let low = page.find("Low Files : 2").unwrap();
+        assert!(complex_files < critical && critical < low);
+        assert!(page[critical..low].contains("> critical.rs <"));
+        assert!(page[low..].contains("> low.rs <") && page[low..].contains("> other_low.rs <"));
+        // Without the tiers there is a single section
+        let sections = get_html_tiers(&[FileMetrics::default(), FileMetrics::default()]);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].tier, None);
+    }
+
+    #[test]
+    fn test_files_ignored_json() {
+        let files_ignored = [
+            FileIgnored::new("src/build.rs", IgnoreReason::NoCoverage),
+            FileIgnored::new("src/gen.rs", IgnoreReason::Timeout),
+        ];
+        let output = export_to_json(Path::new("."), &[], &files_ignored, &[], 0.);
+        let value = serde_json::to_value(&output).unwrap();
+        // The files ignored stay paths and their reasons are kept aside
+        assert_eq!(
+            value["files_ignored"],
+            serde_json::json!(["src/build.rs", "src/gen.rs"])
+        );
+        assert_eq!(
+            value["files_ignored_reasons"],
+            serde_json::json!([
+                { "file": "src/build.rs", "reason": "no_coverage" },
+                { "file": "src/gen.rs", "reason": "timeout" }
+            ])
+        );
+        // A report without the reasons is still read
+        let mut value = serde_json::to_value(export_to_json_function(
+            Path::new("."),
+            &[],
+            &files_ignored,
+            &[],
+            0.,
+        ))
+        .unwrap();
+        value
+            .as_object_mut()
+            .unwrap()
+            .remove("files_ignored_reasons");
+        let output: JSONOutputFunc = serde_json::from_value(value).unwrap();
+        assert_eq!(output.files_ignored, ["src/build.rs", "src/gen.rs"]);
+        assert!(output.files_ignored_reasons.is_empty());
+    }
+
+    #[test]
+    fn test_verify_json_report() {
+        let (metrics, files_ignored, _, project_coverage) = get_metrics_concurrent(
+            FOLDER,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let report = "./data/test_project/to_verify.json";
+        print_metrics_to_json(
+            &metrics,
+            &files_ignored,
+            Path::new(report),
+            Path::new(FOLDER),
+            project_coverage,
+            Sort::WccPlain,
+        )
+        .unwrap();
+        // The project folder is not compared
+        let verify = |metrics: &[FileMetrics], tolerance: f64| {
+            verify_json_report(
+                report,
+                metrics,
+                &files_ignored,
+                Path::new("./other/"),
+                project_coverage,
+                Sort::WccPlain,
+                tolerance,
+            )
+            .unwrap()
+        };
+        assert!(verify(&metrics, 0.).is_empty());
+        let mut stale = metrics.clone();
+        stale[0].metrics.crap += 0.5;
+        assert!(verify(&stale, 1.).is_empty());
+        let differences = verify(&stale, 0.01);
+        fs::remove_file(report).unwrap();
+        assert_eq!(differences.len(), 2);
+        let file_path = &stale[0].file_path;
+        assert_eq!(
+            differences[0].path,
+            format!("metrics[{file_path}].metrics.crap")
+        );
+        assert_eq!(
+            differences[1].path,
+            format!("complex_files[{file_path}].metrics.crap")
+        );
+        assert_eq!(differences[0].kind, DifferenceKind::Changed);
+        // The rows are compared by the path of their files, not by their order
+        let differences = compare_reports(
+            &serde_json::json!({"metrics": [
+                {"file_path": "src/a.rs", "crap": 1.},
+                {"file_path": "src/b.rs", "crap": 2.},
+                {"file_path": "-", "file": "PROJECT", "crap": 3.},
+            ]}),
+            &serde_json::json!({"metrics": [
+                {"file_path": "src/c.rs", "crap": 4.},
+                {"file_path": "-", "file": "PROJECT", "crap": 3.5},
+                {"file_path": "src/b.rs", "crap": 2.},
+            ]}),
+            0.,
+        );
+        let paths_kinds = differences
+            .iter()
+            .map(|d| (d.path.as_str(), d.kind))
+            .collect::<Vec<(&str, DifferenceKind)>>();
+        assert_eq!(
+            paths_kinds,
+            [
+                ("metrics[src/a.rs]", DifferenceKind::Removed),
+                ("metrics[PROJECT].crap", DifferenceKind::Changed),
+                ("metrics[src/c.rs]", DifferenceKind::Added),
+            ]
+        );
+        let differences = compare_reports(
+            &serde_json::json!({"files": [1, "a"]}),
+            &serde_json::json!({"files": [1], "new": true}),
+            0.,
+        );
+        assert_eq!(differences[0].path, "files[1]");
+        assert_eq!(differences[0].actual, serde_json::Value::Null);
+        assert_eq!(differences[1].path, "new");
+    }
+
+    #[test]
+    fn test_aggregate_json_reports() {
+        let write_report = |project: &str, report: &str| {
+            let (metrics, files_ignored, _, project_coverage) = get_metrics_concurrent(
+                project,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                Sort::WccPlain,
+                &AnalysisOptions::default(),
+            )
+            .unwrap();
+            print_metrics_to_json(
+                &metrics,
+                &files_ignored,
+                Path::new(report),
+                Path::new(project),
+                project_coverage,
+                Sort::WccPlain,
+            )
+            .unwrap();
+            let coverable_lines = metrics[metrics.len() - 4].coverable_lines.unwrap();
+            (metrics.len() - 4, project_coverage, coverable_lines as f64)
+        };
+        let reports = [
+            "./data/test_project/aggregate_seahorse.json",
+            "./data/test_project/aggregate_test_project.json",
+            "./data/test_project/aggregate_seahorse_copy.json",
+        ];
+        let seahorse = write_report("./data/seahorse/", reports[0]);
+        let test_project = write_report(FOLDER, reports[1]);
+        fs::copy(reports[0], reports[2]).unwrap();
+        let report = aggregate_json_reports(&reports, Sort::Crap, 3);
+        let functions = "./data/test_project/aggregate_functions.json";
+        fs::write(functions, r#"{"project_folder": ".", "files": []}"#).unwrap();
+        let wrong = aggregate_json_reports(&[functions], Sort::Crap, 3);
+        reports
+            .iter()
+            .chain([&functions])
+            .for_each(|report| fs::remove_file(report).unwrap());
+        let report = report.unwrap();
+        assert_eq!(report.number_of_projects, 3);
+        // The projects with the same folder get different labels
+        let labels = report
+            .projects
+            .iter()
+            .map(|p| p.label.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(labels, ["seahorse", "test_project", "seahorse-2"]);
+        assert_eq!(report.number_of_files, 2 * seahorse.0 + test_project.0);
+        // The coverage is weighted by the coverable lines of the projects, not by their files
+        assert!(compare_float(
+            report.coverage,
+            (seahorse.1 * seahorse.2 + test_project.1 * test_project.2 + seahorse.1 * seahorse.2)
+                / (2. * seahorse.2 + test_project.2)
+        ));
+        // The worst files of all the projects are ranked together
+        let worst = report
+            .worst_files
+            .iter()
+            .map(|f| f.file_path.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            worst,
+            [
+                "seahorse/examples/multiple_app.rs",
+                "seahorse-2/examples/multiple_app.rs",
+                "seahorse/src/error.rs"
+            ]
+        );
+        assert_eq!(report.max.crap, report.worst_files[0].metrics.crap);
+        assert!(matches!(wrong, Err(Error::AggregateReportError(_))));
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let (metrics, _, _, project_coverage) = get_metrics_concurrent(
+            FOLDER,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let ndjson = "./data/test_project/to_compare.ndjson";
+        print_metrics_to_ndjson(&metrics, ndjson, Path::new(FOLDER), project_coverage).unwrap();
+        let content = fs::read_to_string(ndjson).unwrap();
+        fs::remove_file(ndjson).unwrap();
+        let files = metrics.iter().filter(|m| m.file_path != "-").count();
+        assert_eq!(content.lines().count(), files);
+        let record: serde_json::Value =
+            serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(record["mode"], "files");
+        assert_eq!(record["file_path"], metrics[0].file_path);
+        assert_eq!(record["crap"], metrics[0].metrics.crap);
+        assert_eq!(record["project_coverage"], project_coverage);
+        assert!(record["function_name"].is_null());
+        let (metrics, _, _, project_coverage) = get_functions_metrics_concurrent(
+            FOLDER,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let ndjson = "./data/test_project/to_compare_functions.ndjson";
+        print_metrics_to_ndjson_function(&metrics, ndjson, Path::new(FOLDER), project_coverage)
+            .unwrap();
+        let content = fs::read_to_string(ndjson).unwrap();
+        fs::remove_file(ndjson).unwrap();
+        let functions = metrics.iter().flat_map(|m| m.functions.iter());
+        assert_eq!(content.lines().count(), functions.clone().count());
+        // Each line is a flat object with the same columns
+        content.lines().zip(functions).for_each(|(line, f)| {
+            let record: serde_json::Value = serde_json::from_str(line).unwrap();
+            let record = record.as_object().unwrap();
+            assert_eq!(record.len(), 17);
+            assert!(record.values().all(|v| !v.is_object() && !v.is_array()));
+            assert_eq!(record["function_name"], f.function_name);
+            assert_eq!(record["start_line"], f.start_line);
+        });
+    }
+
+    #[test]
+    fn test_sidecars() {
+        let (metrics, _, _, _) = get_metrics_concurrent(
+            FOLDER,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let sidecars = "./data/test_project/sidecars";
+        print_metrics_to_sidecars(&metrics, sidecars).unwrap();
+        let (functions, _, _, _) = get_functions_metrics_concurrent(
+            FOLDER,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let sidecars_functions = "./data/test_project/sidecars_functions";
+        print_metrics_to_sidecars_function(&functions, sidecars_functions).unwrap();
+        // The sidecar files mirror the source tree and skip the summary rows
+        let read = |sidecars: &str, file_path: &str| {
+            let path = get_sidecar_path(Path::new(sidecars), file_path);
+            serde_json::from_str::<serde_json::Value>(&fs::read_to_string(path).unwrap()).unwrap()
+        };
+        let file = metrics.iter().find(|m| m.file_path != "-").unwrap();
+        let sidecar = read(sidecars, &file.file_path);
+        let root = functions.iter().find(|m| m.file_path != "-").unwrap();
+        let sidecar_functions = read(sidecars_functions, &root.file_path);
+        let count = |dir: &str| {
+            let mut stack = vec![PathBuf::from(dir)];
+            let mut count = 0;
+            while let Some(dir) = stack.pop() {
+                for entry in fs::read_dir(dir).unwrap() {
+                    let path = entry.unwrap().path();
+                    if path.is_dir() {
+                        stack.push(path);
+                    } else {
+                        assert!(path.to_str().unwrap().ends_with(".wcc.json"));
+                        count += 1;
+                    }
+                }
+            }
+            count
+        };
+        let files = count(sidecars);
+        let roots = count(sidecars_functions);
+        fs::remove_dir_all(sidecars).unwrap();
+        fs::remove_dir_all(sidecars_functions).unwrap();
+        assert_eq!(
+            serde_json::from_value::<FileMetrics>(sidecar).unwrap(),
+            *file
+        );
+        assert_eq!(sidecar_functions["file_path"], root.file_path.as_str());
+        assert_eq!(
+            sidecar_functions["functions"].as_array().unwrap().len(),
+            root.functions.len()
+        );
+        assert_eq!(files, metrics.len() - 4);
+        assert_eq!(
+            roots,
+            functions.iter().filter(|m| m.file_path != "-").count()
+        );
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let (metrics, files_ignored, complex_files, project_coverage) = get_metrics_concurrent(
+            FOLDER,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let summary = get_summary_json(
+            &metrics,
+            &files_ignored,
+            &complex_files,
+            Path::new(FOLDER),
+            project_coverage,
+        );
+        let summary = serde_json::to_value(summary).unwrap();
+        assert_eq!(summary["mode"], "files");
+        assert_eq!(summary["number_of_files"], metrics.len() - 4);
+        assert_eq!(summary["number_of_complex_files"], complex_files.len());
+        assert!(summary.get("number_of_functions").is_none());
+        let project = &metrics
+            .iter()
+            .find(|m| m.file == "PROJECT")
+            .unwrap()
+            .metrics;
+        assert_eq!(summary["project"]["crap"], project.crap);
+        assert_eq!(
+            summary["min"],
+            serde_json::to_value(metrics[metrics.len() - 1].metrics).unwrap()
+        );
+        let (metrics, files_ignored, complex_functions, project_coverage) =
+            get_functions_metrics_concurrent(
+                FOLDER,
+                JSON,
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                Sort::WccPlain,
+                &AnalysisOptions::default(),
+            )
+            .unwrap();
+        let summary = get_summary_json_function(
+            &metrics,
+            &files_ignored,
+            &complex_functions,
+            Path::new(FOLDER),
+            project_coverage,
+        );
+        let summary = serde_json::to_value(summary).unwrap();
+        // The summary has no metrics of the files or of the functions
+        assert_eq!(summary.as_object().unwrap().len(), 11);
+        assert_eq!(
+            summary["number_of_functions"],
+            metrics.iter().map(|m| m.functions.len()).sum::<usize>()
+        );
+        assert_eq!(
+            summary["number_of_complex_functions"],
+            complex_functions.len()
+        );
+        assert_eq!(summary["project_coverage"], project_coverage);
+    }
+
+    #[test]
+    fn test_influx() {
+        let (metrics, _, complex_files, project_coverage) = get_metrics_concurrent(
+            FOLDER,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        let influx = "./data/test_project/to_compare.influx";
+        print_metrics_to_influx(
+            &metrics,
+            influx,
+            Complexity::Cyclomatic,
+            project_coverage,
+            complex_files.len(),
+            true,
+        )
+        .unwrap();
+        let content = fs::read_to_string(influx).unwrap();
+        fs::remove_file(influx).unwrap();
+        let lines = content.lines().collect::<Vec<&str>>();
+        let files = metrics.iter().filter(|m| m.file_path != "-").count();
+        assert_eq!(lines.len(), files + 1);
+        let project = &metrics
+            .iter()
+            .find(|m| m.file == "PROJECT")
+            .unwrap()
+            .metrics;
+        let (headline, timestamp) = lines[0].rsplit_once(' ').unwrap();
+        assert_eq!(
+            headline,
+            format!(
+                "wcc,mode=files,complexity=cyclomatic wcc_plain={},wcc_quantized={},crap={},skunk={},\
+                 coverage={project_coverage},over_threshold={}i",
+                project.wcc_plain,
+                project.wcc_quantized,
+                project.crap,
+                project.skunk,
+                complex_files.len()
+            )
+        );
+        // All the points of a run have the same timestamp
+        assert!(lines.iter().all(|line| line.ends_with(timestamp)));
+        assert!(lines[1].starts_with(&format!(
+            "wcc_file,mode=files,complexity=cyclomatic,file_path={} ",
+            metrics[0].file_path
+        )));
+        assert!(lines[1].contains(&format!("is_complex={}", metrics[0].metrics.is_complex)));
+        assert_eq!(escape_influx_tag("/App (24,370)"), "/App\\ (24\\,370)");
+    }
+
+    #[test]
+    fn test_estimate_outputs() {
+        let project = "./data/seahorse/";
+        let (metrics, files_ignored, complex_files, project_coverage) = get_metrics_concurrent(
+            project,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default().sample(Sample::new(3, 0)),
+        )
+        .unwrap();
+        let csv = "./data/seahorse/estimate.csv";
+        let ndjson = "./data/seahorse/estimate.ndjson";
+        let influx = "./data/seahorse/estimate.influx";
+        print_metrics_to_csv(
+            &metrics,
+            &files_ignored,
+            csv,
+            project_coverage,
+            Sort::WccPlain,
+        )
+        .unwrap();
+        print_metrics_to_ndjson(&metrics, ndjson, Path::new(project), project_coverage).unwrap();
+        print_metrics_to_influx(
+            &metrics,
+            influx,
+            Complexity::Cyclomatic,
+            project_coverage,
+            complex_files.len(),
+            false,
+        )
+        .unwrap();
+        let [csv, ndjson, influx] =
+            [csv, ndjson, influx].map(|path| fs::read_to_string(path).unwrap());
+        ["estimate.csv", "estimate.ndjson", "estimate.influx"]
+            .iter()
+            .for_each(|name| fs::remove_file(format!("{project}{name}")).unwrap());
+        // Each output has the sampled files, the files with coverage and the seed
+        assert!(csv.lines().any(|line| line == "ESTIMATE,3,9,0,-,-,-,-"));
+        assert!(ndjson.lines().all(|line| {
+            serde_json::from_str::<serde_json::Value>(line).unwrap()["estimate"]
+                == serde_json::json!({ "sampled_files": 3, "total_files": 9, "seed": 0 })
+        }));
+        assert!(influx.contains(",sampled_files=3i,total_files=9i,seed=0u "));
+    }
+
+    #[test]
+    fn test_incomplete_outputs() {
+        let project = "./data/seahorse/";
+        let (metrics, _, complex_files, project_coverage) = get_metrics_concurrent(
+            project,
+            JSON,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            Sort::WccPlain,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+        // The PROJECT row of a run over its max runtime has the files skipped
+        let metrics = metrics
+            .into_iter()
+            .map(|m| match m.file.as_str() {
+                "PROJECT" => m.files_skipped(Some(2)),
+                _ => m,
+            })
+            .collect::<Vec<FileMetrics>>();
+        let ndjson = "./data/seahorse/incomplete.ndjson";
+        let influx = "./data/seahorse/incomplete.influx";
+        let sidecars = "./data/seahorse/incomplete_sidecars/";
+        print_metrics_to_ndjson(&metrics, ndjson, Path::new(project), project_coverage).unwrap();
+        print_metrics_to_influx(
+            &metrics,
+            influx,
+            Complexity::Cyclomatic,
+            project_coverage,
+            complex_files.len(),
+            false,
+        )
+        .unwrap();
+        print_metrics_to_sidecars(&metrics, sidecars).unwrap();
+        let [ndjson, influx, sidecar] = [
+            ndjson,
+            influx,
+            "./data/seahorse/incomplete_sidecars/src/app.rs.wcc.json",
+        ]
+        .map(|path| fs::read_to_string(path).unwrap());
+        ["incomplete.ndjson", "incomplete.influx"]
+            .iter()
+            .for_each(|name| fs::remove_file(format!("{project}{name}")).unwrap());
+        fs::remove_dir_all(sidecars).unwrap();
+        // Each output has the files skipped, so it does not look complete
+        assert!(ndjson.lines().all(|line| {
+            serde_json::from_str::<serde_json::Value>(line).unwrap()["files_skipped"] == 2
+        }));
+        assert!(influx.contains(",files_skipped=2i "));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&sidecar).unwrap()["files_skipped"],
+            2
+        );
+    }
 }
@@ -0,0 +1,62 @@
+use std::net::UdpSocket;
+
+use tracing::{debug, warn};
+
+use crate::error::*;
+
+/// Prefix of all the gauges sent to StatsD
+pub const STATSD_PREFIX: &str = "wcc";
+
+// Format the gauges in the StatsD line protocol, one per line
+fn get_gauges(gauges: &[(&str, f64)]) -> String {
+    gauges
+        .iter()
+        .map(|(name, value)| format!("{STATSD_PREFIX}.{name}:{value}|g"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Send the gauges in a single UDP datagram to the StatsD agent
+fn send_gauges(endpoint: &str, gauges: &[(&str, f64)]) -> Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.send_to(get_gauges(gauges).as_bytes(), endpoint)?;
+    Ok(())
+}
+
+/// Emit the headline metrics of a run as StatsD gauges to the agent at endpoint, given as host:port
+/// The gauges are wcc.project_coverage and wcc.over_threshold, the number of files or functions
+/// over the thresholds
+/// A failure while sending only prints a warning
+pub fn emit_statsd_metrics(endpoint: &str, project_coverage: f64, over_threshold: usize) {
+    let gauges = [
+        ("project_coverage", project_coverage),
+        ("over_threshold", over_threshold as f64),
+    ];
+    debug!("Sending gauges to StatsD agent at {}", endpoint);
+    if let Err(e) = send_gauges(endpoint, &gauges) {
+        warn!(
+            "Failed to send metrics to StatsD agent at {}: {}",
+            endpoint, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit_statsd_metrics() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let endpoint = agent.local_addr().unwrap().to_string();
+        emit_statsd_metrics(&endpoint, 75.5, 3);
+        let mut buf = [0; 128];
+        let (len, _) = agent.recv_from(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "wcc.project_coverage:75.5|g\nwcc.over_threshold:3|g"
+        );
+        // An unreachable agent does not fail the run
+        emit_statsd_metrics("not an endpoint", 75.5, 3);
+    }
+}
@@ -1,24 +1,55 @@
 use core::cmp::Ordering;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fmt;
 use std::fs;
+use std::hash::Hasher;
 use std::path::*;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use rust_code_analysis::{get_function_spaces, guess_language, read_file, FuncSpace, SpaceKind};
-use serde_json::Map;
+use crossbeam::channel::{bounded, RecvTimeoutError};
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use rust_code_analysis::{
+    action, get_from_ext, guess_language, metrics, read_file, AstCallback, AstCfg, AstNode,
+    Callback, FuncSpace, ParserTrait, PreprocResults, SpaceKind, LANG,
+};
 use serde_json::Value;
-use tracing::debug;
+use siphasher::sip128::{Hasher128, SipHasher13};
+use tracing::{debug, warn};
 
+use crate::compile_commands::{CompileCommand, CompileCommands};
+use crate::config::{LineExclusions, UncoveredLinesBudgets};
 use crate::error::*;
+use crate::expression::ThresholdExpressions;
 use crate::files::*;
+use crate::functions::{CoverageByLength, FunctionSources, Hotness};
 use crate::metrics::crap::*;
+use crate::metrics::provider::{ComplexityProvider, SpaceMetrics};
 use crate::metrics::skunk::*;
 use crate::metrics::wcc::*;
 
 const COMPLEXITY_FACTOR: f64 = 25.0;
 
+/// Marker appended to the paths that are not valid UTF-8, which are shown lossily converted
+pub const NON_UTF8_MARKER: &str = " [non-UTF-8 path]";
+
+/// Default maximum value of CRAP and SKUNK, 2^53 is the largest integer a f64 can hold exactly
+/// A greater or infinite value, e.g. of a generated file, is saturated to the maximum
+/// and flagged as complexity too high
+pub const MAX_METRIC_VALUE: f64 = 9007199254740992.0;
+
+/// Coverage of each file, using the path to the file as key
+/// Each array contains the hits of every line, null for the lines that can not be covered
+pub type CoverageData = HashMap<String, Vec<Value>>;
+
+/// Top-level fields of a coveralls file other than source_files,
+/// e.g. service_name, service_job_id, parallel and git
+pub type CoverallsMetadata = serde_json::Map<String, Value>;
+
 /// Complexity Metrics
 #[derive(Copy, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Complexity {
@@ -68,6 +99,10 @@ pub enum JsonFormat {
     Covdir,
     /// Cognitive metric.
     Coveralls,
+    /// Lcov tracefile.
+    Lcov,
+    /// Codecov report json.
+    Codecov,
 }
 
 impl FromStr for JsonFormat {
@@ -77,6 +112,8 @@ impl FromStr for JsonFormat {
         match json_type {
             "covdir" => Ok(JsonFormat::Covdir),
             "coveralls" => Ok(JsonFormat::Coveralls),
+            "lcov" => Ok(JsonFormat::Lcov),
+            "codecov" => Ok(JsonFormat::Codecov),
             _ => Err(format!(
                 "{json_type:?} is not a supported code coverage output format."
             )),
@@ -87,7 +124,7 @@ impl FromStr for JsonFormat {
 impl JsonFormat {
     /// All output formats.
     pub const fn all() -> &'static [&'static str] {
-        &["covdir", "coveralls"]
+        &["covdir", "coveralls", "lcov", "codecov"]
     }
 
     /// Default output format.
@@ -96,6 +133,638 @@ impl JsonFormat {
     }
 }
 
+/// Coverage file with the format used to parse it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageInput {
+    pub format: JsonFormat,
+    pub path: PathBuf,
+}
+
+impl CoverageInput {
+    pub fn new(format: JsonFormat, path: PathBuf) -> Self {
+        Self { format, path }
+    }
+}
+
+impl FromStr for CoverageInput {
+    type Err = String;
+
+    // Parse a coverage file given as FORMAT:PATH
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let (format, path) = input
+            .split_once(':')
+            .ok_or(format!("{input:?} must be in the FORMAT:PATH form."))?;
+        Ok(CoverageInput::new(format.parse()?, PathBuf::from(path)))
+    }
+}
+
+/// Behaviour when the same file is present in more than one coverage file
+#[derive(Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MergeConflict {
+    /// Stop the analysis with an error.
+    Error,
+    /// Sum the hits of each line.
+    Sum,
+}
+
+impl FromStr for MergeConflict {
+    type Err = String;
+
+    fn from_str(merge_conflict: &str) -> std::result::Result<Self, Self::Err> {
+        match merge_conflict {
+            "error" => Ok(MergeConflict::Error),
+            "sum" => Ok(MergeConflict::Sum),
+            _ => Err(format!(
+                "{merge_conflict:?} is not a supported merge conflict behaviour."
+            )),
+        }
+    }
+}
+
+impl MergeConflict {
+    /// All merge conflict behaviours.
+    pub const fn all() -> &'static [&'static str] {
+        &["error", "sum"]
+    }
+
+    /// Default merge conflict behaviour.
+    pub const fn default() -> &'static str {
+        "error"
+    }
+}
+
+/// Owners a file counts for when it matches more than one rule of the CODEOWNERS file
+#[derive(Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OwnersMatch {
+    /// Count the file for the owners of every matching rule.
+    Each,
+    /// Count the file only for the owners of the last matching rule, as GitHub does.
+    MostSpecific,
+}
+
+impl FromStr for OwnersMatch {
+    type Err = String;
+
+    fn from_str(owners_match: &str) -> std::result::Result<Self, Self::Err> {
+        match owners_match {
+            "each" => Ok(OwnersMatch::Each),
+            "most-specific" => Ok(OwnersMatch::MostSpecific),
+            _ => Err(format!(
+                "{owners_match:?} is not a supported owners match behaviour."
+            )),
+        }
+    }
+}
+
+impl OwnersMatch {
+    /// All owners match behaviours.
+    pub const fn all() -> &'static [&'static str] {
+        &["each", "most-specific"]
+    }
+
+    /// Default owners match behaviour.
+    pub const fn default() -> &'static str {
+        "most-specific"
+    }
+}
+
+/// Behaviour for the files without lines that can be covered
+#[derive(Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EmptyCoverage {
+    /// Count the file as fully covered and flag it.
+    Include,
+    /// Add the file to the files ignored.
+    Ignore,
+}
+
+impl FromStr for EmptyCoverage {
+    type Err = String;
+
+    fn from_str(empty_coverage: &str) -> std::result::Result<Self, Self::Err> {
+        match empty_coverage {
+            "include" => Ok(EmptyCoverage::Include),
+            "ignore" => Ok(EmptyCoverage::Ignore),
+            _ => Err(format!(
+                "{empty_coverage:?} is not a supported empty coverage behaviour."
+            )),
+        }
+    }
+}
+
+impl EmptyCoverage {
+    /// All empty coverage behaviours.
+    pub const fn all() -> &'static [&'static str] {
+        &["include", "ignore"]
+    }
+
+    /// Default empty coverage behaviour.
+    pub const fn default() -> &'static str {
+        "include"
+    }
+}
+
+/// Behaviour for the functions marked with a #[deprecated] attribute
+#[derive(Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeprecatedPolicy {
+    /// Report the function marked as deprecated without counting it as complex.
+    Exclude,
+    /// Like exclude, with a warning for each function over the thresholds.
+    Warn,
+    /// Report the function marked as deprecated and count it as any other one.
+    Gate,
+}
+
+impl FromStr for DeprecatedPolicy {
+    type Err = String;
+
+    fn from_str(deprecated_policy: &str) -> std::result::Result<Self, Self::Err> {
+        match deprecated_policy {
+            "exclude" => Ok(DeprecatedPolicy::Exclude),
+            "warn" => Ok(DeprecatedPolicy::Warn),
+            "gate" => Ok(DeprecatedPolicy::Gate),
+            _ => Err(format!(
+                "{deprecated_policy:?} is not a supported deprecated policy."
+            )),
+        }
+    }
+}
+
+impl DeprecatedPolicy {
+    /// All deprecated policies.
+    pub const fn all() -> &'static [&'static str] {
+        &["exclude", "warn", "gate"]
+    }
+
+    /// Default deprecated policy.
+    pub const fn default() -> &'static str {
+        "gate"
+    }
+}
+
+/// Options used while analyzing the project
+#[derive(Clone, Debug)]
+pub struct AnalysisOptions {
+    pub merge_conflict: MergeConflict,
+    // Rules to rewrite the paths of the coverage files before they are matched
+    pub path_map: Option<PathMap>,
+    // Include directories and macros of the C/C++ files, used to preprocess them
+    pub compile_commands: Option<CompileCommands>,
+    pub empty_coverage: EmptyCoverage,
+    pub deprecated_policy: DeprecatedPolicy,
+    pub show_target_coverage: bool,
+    pub show_comment_ratio: bool,
+    // Custom source of the complexity values, rust-code-analysis when None, only in files mode
+    pub complexity_provider: Option<Arc<dyn ComplexityProvider>>,
+    // Cache of the source files matched with the coverage entries, shared by the clones of the options
+    pub source_files_cache: Option<SourceFilesCache>,
+    pub categories: Option<FileCategories>,
+    // Order the complex files by their fan-in in the import graph, only in files mode
+    pub order_by_fanin: bool,
+    pub line_exclusions: Option<LineExclusions>,
+    // Features enabled in the coverage run, the items behind the other cfg features are excluded,
+    // only in files mode
+    pub active_features: Option<HashSet<String>>,
+    // Maximum number of uncovered lines of every file, only in files mode
+    pub max_uncovered_lines: Option<usize>,
+    // Uncovered lines budgets of the files matching glob patterns, they take precedence over the global one
+    pub uncovered_lines_budgets: Option<UncoveredLinesBudgets>,
+    // Call counts of the functions, to weight their priority, only in functions mode
+    pub hotness: Option<Hotness>,
+    // Number of tests exercising each file, to weight their priority, only in files mode
+    pub tests_per_file: Option<TestCounts>,
+    // Thresholds computed for each file from its SLOC, PLOC and complexity, only in files mode
+    pub threshold_expressions: Option<ThresholdExpressions>,
+    // Multipliers of the thresholds bounding the severity tiers of the files, only in files mode
+    pub tiers: Option<Tiers>,
+    // Minimum coverage of the functions by their length, only in functions mode
+    pub coverage_by_length: Option<CoverageByLength>,
+    // Glob patterns of the files to exclude, relative to the project folder
+    pub exclude: Option<GlobSet>,
+    // Glob patterns of the files whose lines are left out of the project coverage, relative to the
+    // project folder, their metrics are still computed, only in files mode
+    pub no_coverage_weight: Option<GlobSet>,
+    // Files to analyze, relative to the project folder, all files if not given
+    pub files_from: Option<HashSet<String>>,
+    // Maximum time for the complexity analysis of a single file
+    pub file_timeout: Option<Duration>,
+    // Start of the run, the max runtime is counted from it
+    pub start: Instant,
+    // Deadline of the whole analysis, the files reached after it are skipped
+    pub deadline: Option<Deadline>,
+    // Sample of the files with coverage to analyze in files mode, all of them without it
+    pub sample: Option<Sample>,
+    // Maximum value of CRAP and SKUNK, a greater one is saturated
+    pub max_metric_value: f64,
+    // Add the files with syntax errors to the files ignored instead of trusting their partial metrics
+    pub strict_analysis: bool,
+    // Keep the SLOC, the complexity, the covered and coverable lines and the coverage debt of each file,
+    // needed by the track file, the coverage debt, the coverage floors and the zero coverage files,
+    // only in files mode
+    pub line_counts: bool,
+    // Start and end of the stages of the analysis, recorded only when given
+    pub stage_times: Option<StageTimes>,
+    // Sources and line coverage of the files with a function over the thresholds, recorded only when
+    // given, only in functions mode
+    pub function_sources: Option<FunctionSources>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            merge_conflict: MergeConflict::Error,
+            path_map: None,
+            compile_commands: None,
+            empty_coverage: EmptyCoverage::Include,
+            deprecated_policy: DeprecatedPolicy::Gate,
+            show_target_coverage: false,
+            show_comment_ratio: false,
+            complexity_provider: None,
+            source_files_cache: None,
+            categories: None,
+            order_by_fanin: false,
+            line_exclusions: None,
+            active_features: None,
+            max_uncovered_lines: None,
+            uncovered_lines_budgets: None,
+            hotness: None,
+            tests_per_file: None,
+            threshold_expressions: None,
+            tiers: None,
+            coverage_by_length: None,
+            exclude: None,
+            no_coverage_weight: None,
+            files_from: None,
+            file_timeout: None,
+            start: Instant::now(),
+            deadline: None,
+            sample: None,
+            max_metric_value: MAX_METRIC_VALUE,
+            strict_analysis: false,
+            line_counts: false,
+            stage_times: None,
+            function_sources: None,
+        }
+    }
+}
+
+impl AnalysisOptions {
+    pub fn merge_conflict(mut self, merge_conflict: MergeConflict) -> Self {
+        self.merge_conflict = merge_conflict;
+        self
+    }
+
+    pub fn path_map(mut self, path_map: PathMap) -> Self {
+        self.path_map = Some(path_map);
+        self
+    }
+
+    pub fn compile_commands(mut self, compile_commands: CompileCommands) -> Self {
+        self.compile_commands = Some(compile_commands);
+        self
+    }
+
+    pub fn empty_coverage(mut self, empty_coverage: EmptyCoverage) -> Self {
+        self.empty_coverage = empty_coverage;
+        self
+    }
+
+    pub fn deprecated_policy(mut self, deprecated_policy: DeprecatedPolicy) -> Self {
+        self.deprecated_policy = deprecated_policy;
+        self
+    }
+
+    pub fn show_target_coverage(mut self, show_target_coverage: bool) -> Self {
+        self.show_target_coverage = show_target_coverage;
+        self
+    }
+
+    pub fn show_comment_ratio(mut self, show_comment_ratio: bool) -> Self {
+        self.show_comment_ratio = show_comment_ratio;
+        self
+    }
+
+    pub fn complexity_provider(mut self, complexity_provider: Arc<dyn ComplexityProvider>) -> Self {
+        self.complexity_provider = Some(complexity_provider);
+        self
+    }
+
+    // A new cache is created only if the options do not have one yet
+    pub fn cache_paths(mut self, cache_paths: bool) -> Self {
+        self.source_files_cache = match cache_paths {
+            true => Some(self.source_files_cache.unwrap_or_default()),
+            false => None,
+        };
+        self
+    }
+
+    pub fn categories(mut self, categories: FileCategories) -> Self {
+        self.categories = Some(categories);
+        self
+    }
+
+    pub fn order_by_fanin(mut self, order_by_fanin: bool) -> Self {
+        self.order_by_fanin = order_by_fanin;
+        self
+    }
+
+    pub fn line_exclusions(mut self, line_exclusions: LineExclusions) -> Self {
+        self.line_exclusions = Some(line_exclusions);
+        self
+    }
+
+    pub fn active_features(mut self, active_features: HashSet<String>) -> Self {
+        self.active_features = Some(active_features);
+        self
+    }
+
+    pub fn max_uncovered_lines(mut self, max_uncovered_lines: usize) -> Self {
+        self.max_uncovered_lines = Some(max_uncovered_lines);
+        self
+    }
+
+    pub fn uncovered_lines_budgets(
+        mut self,
+        uncovered_lines_budgets: UncoveredLinesBudgets,
+    ) -> Self {
+        self.uncovered_lines_budgets = Some(uncovered_lines_budgets);
+        self
+    }
+
+    pub fn hotness(mut self, hotness: Hotness) -> Self {
+        self.hotness = Some(hotness);
+        self
+    }
+
+    pub fn tests_per_file(mut self, tests_per_file: TestCounts) -> Self {
+        self.tests_per_file = Some(tests_per_file);
+        self
+    }
+
+    pub fn threshold_expressions(mut self, threshold_expressions: ThresholdExpressions) -> Self {
+        self.threshold_expressions = Some(threshold_expressions);
+        self
+    }
+
+    pub fn tiers(mut self, tiers: Tiers) -> Self {
+        self.tiers = Some(tiers);
+        self
+    }
+
+    pub fn coverage_by_length(mut self, coverage_by_length: CoverageByLength) -> Self {
+        self.coverage_by_length = Some(coverage_by_length);
+        self
+    }
+
+    pub fn exclude(mut self, exclude: GlobSet) -> Self {
+        self.exclude = Some(exclude);
+        self
+    }
+
+    pub fn no_coverage_weight(mut self, no_coverage_weight: GlobSet) -> Self {
+        self.no_coverage_weight = Some(no_coverage_weight);
+        self
+    }
+
+    pub fn files_from(mut self, files_from: HashSet<String>) -> Self {
+        self.files_from = Some(files_from);
+        self
+    }
+
+    pub fn file_timeout(mut self, file_timeout: Duration) -> Self {
+        self.file_timeout = Some(file_timeout);
+        self
+    }
+
+    pub fn start(mut self, start: Instant) -> Self {
+        self.start = start;
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set the deadline of the analysis to the start of the run plus the max runtime
+    pub fn max_runtime(self, max_runtime: Duration) -> Self {
+        let deadline = Deadline::new(self.start + max_runtime);
+        self.deadline(deadline)
+    }
+
+    pub fn sample(mut self, sample: Sample) -> Self {
+        self.sample = Some(sample);
+        self
+    }
+
+    pub fn max_metric_value(mut self, max_metric_value: f64) -> Self {
+        self.max_metric_value = max_metric_value;
+        self
+    }
+
+    pub fn strict_analysis(mut self, strict_analysis: bool) -> Self {
+        self.strict_analysis = strict_analysis;
+        self
+    }
+
+    pub fn line_counts(mut self, line_counts: bool) -> Self {
+        self.line_counts = line_counts;
+        self
+    }
+
+    pub fn stage_times(mut self, stage_times: StageTimes) -> Self {
+        self.stage_times = Some(stage_times);
+        self
+    }
+
+    pub fn function_sources(mut self, function_sources: FunctionSources) -> Self {
+        self.function_sources = Some(function_sources);
+        self
+    }
+
+    // Run a stage of the analysis, recording its times if the stage times are given
+    pub(crate) fn record_stage<T>(&self, name: &'static str, stage: impl FnOnce() -> T) -> T {
+        match &self.stage_times {
+            Some(stage_times) => stage_times.record(name, stage),
+            None => stage(),
+        }
+    }
+
+    // Check if a file, given with its path relative to the project folder, is excluded
+    pub(crate) fn is_excluded(&self, file_path: &str) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(file_path.trim_start_matches('/')))
+    }
+
+    // Get the compile command of a C/C++ file, if it is given
+    pub(crate) fn compile_command(&self, path: &Path) -> Option<&CompileCommand> {
+        self.compile_commands.as_ref()?.command(path)
+    }
+
+    // Check if a file, given with its path relative to the project folder, is one of the files to analyze
+    pub(crate) fn is_listed(&self, file_path: &str) -> bool {
+        self.files_from
+            .as_ref()
+            .is_none_or(|files| files.contains(file_path.trim_start_matches('/')))
+    }
+}
+
+// Run the analysis of a file on a worker thread and wait for at most the file timeout of the options
+// Without a file timeout the analysis is run on the current thread
+// When the timeout expires the worker is left running and FileTimeoutError is returned
+pub(crate) fn analyze_with_timeout<T, F>(
+    path: &Path,
+    options: &AnalysisOptions,
+    analyze: F,
+) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let timeout = match options.file_timeout {
+        Some(timeout) => timeout,
+        None => return analyze(),
+    };
+    let (sender, receiver) = bounded(1);
+    thread::spawn(move || {
+        // The receiver is gone when the timeout already expired
+        let _ = sender.send(analyze());
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(res) => res,
+        Err(RecvTimeoutError::Timeout) => Err(Error::FileTimeoutError(
+            path.display().to_string(),
+            timeout.as_millis(),
+        )),
+        Err(RecvTimeoutError::Disconnected) => Err(Error::ConcurrentError()),
+    }
+}
+
+// Get the result of an analysis run with analyze_with_timeout, None when it timed out
+pub(crate) fn skip_on_timeout<T>(res: Result<T>) -> Result<Option<T>> {
+    match res {
+        Err(Error::FileTimeoutError(file_path, timeout)) => {
+            warn!(
+                "Timeout: the analysis of {} took more than {} ms, file skipped",
+                file_path, timeout
+            );
+            Ok(None)
+        }
+        res => res.map(Some),
+    }
+}
+
+/// Read the files to analyze from a list with one path per line, relative to the project folder
+/// Empty lines and lines starting with # are skipped
+pub fn read_files_from<A: AsRef<Path>>(path: A) -> Result<HashSet<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.replace('\\', "/")
+                .trim_start_matches("./")
+                .trim_start_matches('/')
+                .to_string()
+        })
+        .collect())
+}
+
+/// Build the set of glob patterns of the files to exclude from the analysis
+pub fn get_exclude_globs(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    patterns.iter().try_for_each(|p| -> Result<()> {
+        builder.add(Glob::new(p)?);
+        Ok(())
+    })?;
+    Ok(builder.build()?)
+}
+
+/// Category of the files not matched by any pattern
+pub const DEFAULT_CATEGORY: &str = "default";
+
+/// Multiplier of the thresholds of a category given as CATEGORY=MULTIPLIER
+#[derive(Clone, Debug, PartialEq)]
+pub struct CategoryThreshold {
+    pub category: String,
+    pub multiplier: f64,
+}
+
+impl FromStr for CategoryThreshold {
+    type Err = String;
+
+    fn from_str(category_threshold: &str) -> std::result::Result<Self, Self::Err> {
+        let (category, multiplier) = category_threshold.split_once('=').ok_or(format!(
+            "{category_threshold:?} is not in the CATEGORY=MULTIPLIER form."
+        ))?;
+        let multiplier = multiplier
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("{multiplier:?} is not a valid multiplier."))?;
+        Ok(Self {
+            category: category.trim().into(),
+            multiplier,
+        })
+    }
+}
+
+/// Categories of the files of a project, each one with its thresholds multiplier
+#[derive(Clone, Debug, Default)]
+pub struct FileCategories {
+    patterns: Vec<(GlobMatcher, String)>,
+    multipliers: HashMap<String, f64>,
+}
+
+impl FileCategories {
+    /// Read the mapping file, each line has a glob pattern, relative to the project folder,
+    /// and the name of the category separated by whitespaces
+    /// Empty lines and lines starting with # are skipped, the last matching pattern wins
+    pub fn new(mapping: &str) -> Result<Self> {
+        let patterns = mapping
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| -> Result<(GlobMatcher, String)> {
+                let (glob, category) = line
+                    .split_once(char::is_whitespace)
+                    .ok_or(Error::CategoriesError(line.into()))?;
+                Ok((
+                    Glob::new(glob)?.compile_matcher(),
+                    category.trim().to_string(),
+                ))
+            })
+            .collect::<Result<Vec<(GlobMatcher, String)>>>()?;
+        Ok(Self {
+            patterns,
+            multipliers: HashMap::new(),
+        })
+    }
+
+    pub fn from_file<A: AsRef<Path>>(path: A) -> Result<Self> {
+        Self::new(&fs::read_to_string(path)?)
+    }
+
+    pub fn multiplier(mut self, category: &str, multiplier: f64) -> Self {
+        self.multipliers.insert(category.into(), multiplier);
+        self
+    }
+
+    /// Get the category of a file given its path relative to the project folder
+    pub fn category(&self, file_path: &str) -> &str {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|(glob, _)| glob.is_match(file_path))
+            .map_or(DEFAULT_CATEGORY, |(_, category)| category)
+    }
+
+    /// Scale the thresholds with the multiplier of the category, 1 if not given
+    pub fn thresholds(&self, category: &str, thresholds: &[f64]) -> Vec<f64> {
+        let multiplier = self.multipliers.get(category).copied().unwrap_or(1.0);
+        thresholds.iter().map(|t| t * multiplier).collect()
+    }
+}
+
 /// Mode
 #[derive(Copy, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Mode {
@@ -140,6 +809,8 @@ pub enum Sort {
     Crap,
     /// Skunk
     Skunk,
+    /// File path, in lexicographic order
+    Path,
 }
 
 impl FromStr for Sort {
@@ -151,6 +822,7 @@ impl FromStr for Sort {
             "wcc_quantized" => Ok(Sort::WccQuantized),
             "crap" => Ok(Sort::Crap),
             "skunk" => Ok(Sort::Skunk),
+            "path" => Ok(Sort::Path),
             _ => Err(format!("{sort:?} is not a supported metric.")),
         }
     }
@@ -159,6 +831,11 @@ impl FromStr for Sort {
 impl Sort {
     /// All sorts.
     pub const fn all() -> &'static [&'static str] {
+        &["wcc_plain", "wcc_quantized", "crap", "skunk", "path"]
+    }
+
+    /// Sorts by the value of a metric.
+    pub const fn metrics() -> &'static [&'static str] {
         &["wcc_plain", "wcc_quantized", "crap", "skunk"]
     }
 
@@ -169,34 +846,40 @@ impl Sort {
 }
 
 pub(crate) trait Visit {
-    fn get_metrics_from_space(
-        space: &FuncSpace,
+    fn get_metrics_from_space<S: SpaceMetrics>(
+        space: &S,
         covs: &[Value],
         metric: Complexity,
         coverage: Option<f64>,
         thresholds: &[f64],
+        max_value: f64,
     ) -> Result<(Metrics, (f64, f64))>;
 }
 pub(crate) struct Tree;
 
 impl Visit for Tree {
-    fn get_metrics_from_space(
-        space: &FuncSpace,
+    fn get_metrics_from_space<S: SpaceMetrics>(
+        space: &S,
         covs: &[Value],
         metric: Complexity,
         coverage: Option<f64>,
         thresholds: &[f64],
+        max_value: f64,
     ) -> Result<(Metrics, (f64, f64))> {
         let covdir = coverage.is_some();
         let (wcc_plain, sp_sum) = wcc_plain_function(space, covs, metric, covdir)?;
         let (wcc_quantized, sq_sum) = wcc_quantized_function(space, covs, metric, covdir)?;
-        let crap = crap_function(space, covs, metric, coverage)?;
-        let skunk = skunk_nosmells_function(space, covs, metric, coverage)?;
+        let (crap, crap_saturated) =
+            saturate_metric(crap_function(space, covs, metric, coverage)?, max_value);
+        let (skunk, skunk_saturated) = saturate_metric(
+            skunk_nosmells_function(space, covs, metric, coverage)?,
+            max_value,
+        );
         let is_complex = check_complexity(wcc_plain, wcc_quantized, crap, skunk, thresholds);
         let coverage = if let Some(coverage) = coverage {
             coverage
         } else {
-            let (covl, tl) = get_covered_lines(covs, space.start_line, space.end_line)?;
+            let (covl, tl) = get_covered_lines(covs, space.start_line(), space.end_line())?;
             if tl == 0.0 {
                 0.0
             } else {
@@ -210,11 +893,71 @@ impl Visit for Tree {
             skunk,
             is_complex,
             f64::round(coverage * 100.0) / 100.0,
-        );
+        )
+        .complexity_too_high(crap_saturated || skunk_saturated);
         Ok((m, (sp_sum, sq_sum)))
     }
 }
 
+// Get the metrics of a space without lines that can be covered
+// The space is considered fully covered, so it has no covered line weighted by WCC
+// while CRAP and SKUNK only depend on its complexity
+pub(crate) fn get_metrics_without_coverable_lines<S: SpaceMetrics>(
+    space: &S,
+    metric: Complexity,
+    thresholds: &[f64],
+    max_value: f64,
+) -> Result<(Metrics, (f64, f64))> {
+    let (crap, crap_saturated) =
+        saturate_metric(crap_function(space, &[], metric, Some(100.0))?, max_value);
+    let (skunk, skunk_saturated) = saturate_metric(
+        skunk_nosmells_function(space, &[], metric, Some(100.0))?,
+        max_value,
+    );
+    let is_complex = check_complexity(0.0, 0.0, crap, skunk, thresholds);
+    let m = Metrics::new(0.0, 0.0, crap, skunk, is_complex, 100.0)
+        .complexity_too_high(crap_saturated || skunk_saturated);
+    Ok((m, (0.0, 0.0)))
+}
+
+// Saturate a value of CRAP or SKUNK to the max value, a NaN one too,
+// it returns the value and if it has been saturated
+pub(crate) fn saturate_metric(value: f64, max_value: f64) -> (f64, bool) {
+    if value.is_nan() || value > max_value {
+        (max_value, true)
+    } else {
+        (value, false)
+    }
+}
+
+// Get the ratio of the logarithms of two counts, used to weight the priority by a count
+// relative to the median of a map, a zero denominator gives an infinite ratio, or 1 if both are zero
+pub(crate) fn get_log_ratio(numerator: f64, denominator: f64) -> f64 {
+    match (numerator.ln_1p(), denominator.ln_1p()) {
+        (n, d) if d > 0. => n / d,
+        (n, _) if n > 0. => f64::INFINITY,
+        _ => 1.,
+    }
+}
+
+// Get the priority of a file or function, its CRAP multiplied by its weight and saturated
+// to the max value, without CRAP the priority is 0 whatever the weight
+pub(crate) fn get_priority(crap: f64, weight: f64, max_value: f64) -> f64 {
+    if crap > 0. {
+        saturate_metric(crap * weight, max_value).0
+    } else {
+        0.
+    }
+}
+
+// Check if at least one line between start and end can be covered
+// The lines that can not be covered are null in coveralls and -1 in covdir
+pub(crate) fn has_coverable_lines(covs: &[Value], start: usize, end: usize) -> bool {
+    covs.iter().enumerate().any(|(i, line)| {
+        (start - 1..end).contains(&i) && !line.is_null() && line.as_i64() != Some(-1)
+    })
+}
+
 #[inline(always)]
 #[allow(dead_code)]
 pub(crate) fn compare_float(a: f64, b: f64) -> bool {
@@ -223,7 +966,7 @@ pub(crate) fn compare_float(a: f64, b: f64) -> bool {
 
 // Check all possible valid extensions
 #[inline(always)]
-fn check_ext(ext: &OsStr) -> bool {
+pub(crate) fn check_ext(ext: &OsStr) -> bool {
     ext == "rs"
         || ext == "cpp"
         || ext == "c"
@@ -255,13 +998,71 @@ pub(crate) fn read_files(files_path: &Path) -> Result<Vec<String>> {
             let ext = path.extension();
 
             if ext.is_some() && check_ext(ext.ok_or(Error::PathConversionError())?) {
-                vec.push(path.display().to_string().replace('\\', "/"));
+                vec.push(get_source_key(files_path, &path));
             }
         }
     }
     Ok(vec)
 }
 
+// Number of chunks sent to each thread, more chunks than threads let an idle thread
+// take the next chunk while another one is still analyzing a big file
+const CHUNKS_PER_THREAD: usize = 4;
+
+// Chunks the vector of files in multiple chunks to be used by threads
+// The files are sorted by size, largest first, and dealt to the chunks in turn,
+// so the big files start early and each chunk gets a similar amount of bytes
+pub(crate) fn chunk_vector(vec: Vec<String>, n_threads: usize) -> Vec<Vec<String>> {
+    let n_chunks = (n_threads * CHUNKS_PER_THREAD).min(vec.len()).max(1);
+    let mut sizes = vec
+        .into_iter()
+        .map(|file| (fs::metadata(&file).map_or(0, |m| m.len()), file))
+        .collect::<Vec<(u64, String)>>();
+    sizes.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    let mut chunks = vec![vec![]; n_chunks];
+    sizes
+        .into_iter()
+        .enumerate()
+        .for_each(|(i, (_, file))| chunks[i % n_chunks].push(file));
+    chunks.retain(|chunk| !chunk.is_empty());
+    chunks
+}
+
+// Get the prefix joined to the entries of the coverage files, the path of the project folder
+// A project folder that is not valid UTF-8 is converted lossily, the same conversion of its files
+pub(crate) fn get_files_prefix(files_path: &Path) -> String {
+    files_path.to_string_lossy().into_owned()
+}
+
+// Get the key of a source file in the coverage data, its path with / as separator
+// The project folder is converted as the prefix of the coverage entries,
+// while a path inside it that is not valid UTF-8 is converted lossily and marked,
+// so it can not match any coverage entry and it is reported among the files ignored
+pub(crate) fn get_source_key(files_path: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(files_path).unwrap_or(path);
+    match path.to_str() {
+        Some(path) => path.replace('\\', "/"),
+        None if relative.to_str().is_some() => path.to_string_lossy().replace('\\', "/"),
+        None => format!(
+            "{}{NON_UTF8_MARKER}",
+            path.to_string_lossy().replace('\\', "/")
+        ),
+    }
+}
+
+// Get the path of a source file from its key in the coverage data
+// The key has a lossy conversion of a project folder that is not valid UTF-8,
+// so its relative path is joined to the project folder
+pub(crate) fn get_source_path<'a>(files_path: &Path, file: &'a str) -> Cow<'a, Path> {
+    if files_path.to_str().is_some() {
+        return Cow::Borrowed(Path::new(file));
+    }
+    let relative = file
+        .strip_prefix(get_files_prefix(files_path).as_str())
+        .unwrap_or(file);
+    Cow::Owned(files_path.join(relative.trim_start_matches('/')))
+}
+
 fn get_prefix<'a>(prefix: &'a str, to_check: &'a str) -> Result<String> {
     let mut s1 = prefix.split('/').collect::<Vec<&str>>();
     let mut s2 = to_check.split('/').collect::<Vec<&str>>();
@@ -280,20 +1081,150 @@ fn get_prefix<'a>(prefix: &'a str, to_check: &'a str) -> Result<String> {
 // Return a HashMap with all the files arrays of covered lines using the path to the file as key
 pub(crate) fn read_json(file: String, prefix: &str) -> Result<HashMap<String, Vec<Value>>> {
     debug!("Reading coveralls json...");
-    let val: Value = serde_json::from_str(file.as_str())?;
+    read_json_entries(&file, prefix)?
+        .into_iter()
+        .map(|(name, x)| -> Result<(String, Vec<Value>)> {
+            let value = x["coverage"]
+                .as_array()
+                .ok_or(Error::ConversionError())?
+                .to_vec();
+            Ok((name, value))
+        })
+        .collect()
+}
+
+// Read the source files of a coveralls json file, each one with all its fields,
+// using the path to the file as key
+pub(crate) fn read_json_entries(file: &str, prefix: &str) -> Result<HashMap<String, Value>> {
+    let mut val: Value = serde_json::from_str(file)?;
     let vec = val["source_files"]
-        .as_array()
+        .as_array_mut()
         .ok_or(Error::ReadingJSONError())?;
-    let mut covs = HashMap::<String, Vec<Value>>::new();
-    vec.iter().try_for_each(|x| -> Result<()> {
+    let mut entries = HashMap::<String, Value>::new();
+    vec.iter_mut().try_for_each(|x| -> Result<()> {
         let n = x["name"].as_str().ok_or(Error::PathConversionError())?;
         let prefix = get_prefix(prefix, n)?;
-        let name = Path::new(&prefix).join(x["name"].as_str().ok_or(Error::PathConversionError())?);
-        let value = x["coverage"]
+        let name = Path::new(&prefix).join(n);
+        entries.insert(name.display().to_string().replace('\\', "/"), x.take());
+        Ok(())
+    })?;
+    Ok(entries)
+}
+
+// Compute the digest of some bytes as a lowercase hexadecimal string of 128 bits,
+// the source digest of the files of a coveralls payload without one in the inputs
+// SipHash is stable across Rust versions, unlike the hasher of the standard library
+pub(crate) fn get_source_digest(data: &[u8]) -> String {
+    let mut hasher = SipHasher13::new();
+    hasher.write(data);
+    format!("{:032x}", hasher.finish128().as_u128())
+}
+
+// Read the top-level metadata of a coveralls json file, leaving it unchanged
+pub(crate) fn read_coveralls_metadata(file: &str) -> Result<CoverallsMetadata> {
+    let mut metadata = match serde_json::from_str(file)? {
+        Value::Object(metadata) => metadata,
+        _ => return Err(Error::ReadingJSONError()),
+    };
+    metadata.remove("source_files");
+    Ok(metadata)
+}
+
+// This function read the content of a lcov tracefile
+// Return a HashMap with all the files arrays of covered lines using the path to the file as key
+// The arrays have the same layout of the coveralls ones, null for the lines that can not be covered
+pub(crate) fn read_lcov(file: String, prefix: &str) -> Result<CoverageData> {
+    debug!("Reading lcov file...");
+    let mut covs = CoverageData::new();
+    let mut current: Option<(String, Vec<Value>)> = None;
+    file.lines().try_for_each(|line| -> Result<()> {
+        let line = line.trim();
+        if let Some(n) = line.strip_prefix("SF:") {
+            let prefix = get_prefix(prefix, n)?;
+            let name = Path::new(&prefix).join(n);
+            current = Some((name.display().to_string().replace('\\', "/"), Vec::new()));
+        } else if let Some(da) = line.strip_prefix("DA:") {
+            let (_, arr) = current.as_mut().ok_or(Error::ReadingLcovError())?;
+            let mut fields = da.split(',');
+            let line_number = fields
+                .next()
+                .and_then(|l| l.trim().parse::<usize>().ok())
+                .filter(|l| *l > 0)
+                .ok_or(Error::ReadingLcovError())?;
+            let hits = fields
+                .next()
+                .and_then(|h| h.trim().parse::<u64>().ok())
+                .ok_or(Error::ReadingLcovError())?;
+            if arr.len() < line_number {
+                arr.resize(line_number, Value::Null);
+            }
+            arr[line_number - 1] = Value::from(hits);
+        } else if line == "end_of_record" {
+            let (name, arr) = current.take().ok_or(Error::ReadingLcovError())?;
+            covs.insert(name, arr);
+        }
+        Ok(())
+    })?;
+    Ok(covs)
+}
+
+// Get the hits of a line from its Codecov coverage: the hits, true or false,
+// or the covered and total branches of a partial line, e.g. "1/2", which is hit when a branch is
+fn get_codecov_hits(coverage: &Value) -> Result<Value> {
+    match coverage {
+        Value::Null => Ok(Value::Null),
+        Value::Bool(hit) => Ok(Value::from(u64::from(*hit))),
+        Value::Number(hits) => hits
+            .as_u64()
+            .map(Value::from)
+            .ok_or(Error::ReadingCodecovError()),
+        Value::String(partial) => {
+            let (covered, total) = partial
+                .split_once('/')
+                .ok_or(Error::ReadingCodecovError())?;
+            covered
+                .parse::<u64>()
+                .ok()
+                .filter(|_| total.parse::<u64>().is_ok())
+                .map(|covered| Value::from(u64::from(covered > 0)))
+                .ok_or(Error::ReadingCodecovError())
+        }
+        _ => Err(Error::ReadingCodecovError()),
+    }
+}
+
+// This function read the content of a Codecov report json
+// The report.files object maps the path of each file to its line coverage, run-length encoded
+// as [coverage, count] pairs from the first line, e.g. [[null, 2], [1, 3]] for two lines that can not
+// be covered followed by three lines hit once
+// Return a HashMap with all the files arrays of covered lines using the path to the file as key
+// The arrays have the same layout of the coveralls ones, null for the lines that can not be covered
+pub(crate) fn read_codecov(file: String, prefix: &str) -> Result<CoverageData> {
+    debug!("Reading Codecov json...");
+    let val: Value = serde_json::from_str(file.as_str())?;
+    let files = val["report"]["files"]
+        .as_object()
+        .ok_or(Error::ReadingCodecovError())?;
+    let mut covs = CoverageData::new();
+    files.iter().try_for_each(|(n, file)| -> Result<()> {
+        let prefix = get_prefix(prefix, n)?;
+        let name = Path::new(&prefix).join(n);
+        let mut arr = Vec::<Value>::new();
+        file["line_coverage"]
             .as_array()
-            .ok_or(Error::ConversionError())?
-            .to_vec();
-        covs.insert(name.display().to_string().replace('\\', "/"), value);
+            .ok_or(Error::ReadingCodecovError())?
+            .iter()
+            .try_for_each(|run| -> Result<()> {
+                let (coverage, count) = match run.as_array().map(Vec::as_slice) {
+                    Some([coverage, count]) => (coverage, count),
+                    _ => return Err(Error::ReadingCodecovError()),
+                };
+                let count = count.as_u64().ok_or(Error::ReadingCodecovError())? as usize;
+                let hits = get_codecov_hits(coverage)?;
+                arr.resize(arr.len() + count, hits);
+                Ok(())
+            })?;
+        covs.insert(name.display().to_string().replace('\\', "/"), arr);
         Ok(())
     })?;
     Ok(covs)
@@ -306,13 +1237,74 @@ pub(crate) struct Covdir {
     pub(crate) name: String,
     pub(crate) arr: Vec<Value>,
     pub(crate) coverage: f64,
+    pub(crate) functions: Vec<CovdirFunction>,
+    // Percentage of the covered branches, only when grcov is run with the branch coverage
+    pub(crate) branch_coverage: Option<f64>,
+}
+
+// Coverage of a function given in a covdir file
+#[derive(Clone, Default, Debug, PartialEq)]
+pub(crate) struct CovdirFunction {
+    pub(crate) name: String,
+    pub(crate) start_line: usize,
+    pub(crate) coverage: f64,
+}
+
+// Get a percentage of a covdir file entry, the one given by grcov or the one computed from
+// the covered and the total counts, rounded to two decimals as grcov does
+// It is None when the entry has neither or has no total
+fn read_covdir_percent(value: &Value, percent: &str, covered: &str, total: &str) -> Option<f64> {
+    value[percent].as_f64().or_else(|| {
+        let covered = value[covered].as_f64()?;
+        let total = value[total].as_f64().filter(|total| *total > 0.)?;
+        Some(f64::round(covered / total * 10000.) / 100.)
+    })
+}
+
+// Read the line coverage of a covdir file entry, without coverable lines it is 0 as in grcov
+fn read_covdir_coverage(value: &Value) -> Result<f64> {
+    match read_covdir_percent(value, "coveragePercent", "linesCovered", "linesTotal") {
+        Some(coverage) => Ok(coverage),
+        None if value["linesTotal"].as_f64() == Some(0.) => Ok(0.),
+        None => Err(Error::ConversionError()),
+    }
+}
+
+// Read the optional branch coverage of a covdir file entry, None without branches
+fn read_covdir_branch_coverage(value: &Value) -> Option<f64> {
+    value["branchesTotal"]
+        .as_f64()
+        .is_none_or(|total| total > 0.)
+        .then(|| read_covdir_percent(value, "branchesPercent", "branchesCovered", "branchesTotal"))
+        .flatten()
+}
+
+// Read the optional coverage of the functions of a covdir file entry
+// Each function has its name, the line where it starts and its coverage percentage
+fn read_covdir_functions(value: &Value) -> Result<Vec<CovdirFunction>> {
+    let functions = match value["functions"].as_array() {
+        Some(functions) => functions,
+        None => return Ok(Vec::new()),
+    };
+    functions
+        .iter()
+        .map(|f| -> Result<CovdirFunction> {
+            Ok(CovdirFunction {
+                name: f["name"].as_str().ok_or(Error::ConversionError())?.into(),
+                start_line: f["start"].as_u64().ok_or(Error::ConversionError())? as usize,
+                coverage: f["coveragePercent"]
+                    .as_f64()
+                    .ok_or(Error::ConversionError())?,
+            })
+        })
+        .collect()
 }
 
 // This function read the content of the coveralls  json file obtain by using grcov
 // Return a HashMap with all the files arrays of covered lines using the path to the file as key
 pub(crate) fn read_json_covdir(file: String, map_prefix: &str) -> Result<HashMap<String, Covdir>> {
     debug!("Reading covdir json...");
-    let val: Map<String, Value> = serde_json::from_str(file.as_str())?;
+    let val: Value = serde_json::from_str(file.as_str())?;
     let mut res: HashMap<String, Covdir> = HashMap::<String, Covdir>::new();
     let mut stack = vec![(
         val["children"]
@@ -323,9 +1315,9 @@ pub(crate) fn read_json_covdir(file: String, map_prefix: &str) -> Result<HashMap
     let covdir = Covdir {
         name: val["name"].as_str().ok_or(Error::ConversionError())?.into(),
         arr: vec![],
-        coverage: val["coveragePercent"]
-            .as_f64()
-            .ok_or(Error::ConversionError())?,
+        coverage: read_covdir_coverage(&val)?,
+        functions: Vec::new(),
+        branch_coverage: read_covdir_branch_coverage(&val),
     };
     res.insert("PROJECT_ROOT".into(), covdir);
     while let Some((val, prefix)) = stack.pop() {
@@ -362,9 +1354,9 @@ pub(crate) fn read_json_covdir(file: String, map_prefix: &str) -> Result<HashMap
                         .as_array()
                         .ok_or(Error::ConversionError())?
                         .to_vec(),
-                    coverage: value["coveragePercent"]
-                        .as_f64()
-                        .ok_or(Error::ConversionError())?,
+                    coverage: read_covdir_coverage(value)?,
+                    functions: read_covdir_functions(value)?,
+                    branch_coverage: read_covdir_branch_coverage(value),
                 };
                 let name_path = format!("{prefix}/{key}");
                 let map_prefix = get_prefix(map_prefix, name_path.as_str())?;
@@ -376,14 +1368,63 @@ pub(crate) fn read_json_covdir(file: String, map_prefix: &str) -> Result<HashMap
     Ok(res)
 }
 
+// Convert the covdir coverage to the coveralls layout, replacing the -1 of
+// the lines that can not be covered with null
+pub(crate) fn covdir_to_coverage_data(covs: HashMap<String, Covdir>) -> CoverageData {
+    covs.into_iter()
+        .filter(|(name, _)| name != "PROJECT_ROOT")
+        .map(|(name, covdir)| {
+            let arr = covdir
+                .arr
+                .into_iter()
+                .map(|line| {
+                    if line.as_i64() == Some(-1) {
+                        Value::Null
+                    } else {
+                        line
+                    }
+                })
+                .collect::<Vec<Value>>();
+            (name, arr)
+        })
+        .collect()
+}
+
+// Read a coverage file with the parser of its format
+// Return a HashMap with all the files arrays of covered lines in the coveralls layout
+pub(crate) fn read_coverage(input: &CoverageInput, prefix: &str) -> Result<CoverageData> {
+    let file = fs::read_to_string(&input.path)?;
+    match input.format {
+        JsonFormat::Coveralls => read_json(file, prefix),
+        JsonFormat::Covdir => Ok(covdir_to_coverage_data(read_json_covdir(file, prefix)?)),
+        JsonFormat::Lcov => read_lcov(file, prefix),
+        JsonFormat::Codecov => read_codecov(file, prefix),
+    }
+}
+
+// Detect the format of a coverage json from its top-level keys, None if it is not a known one
+pub(crate) fn detect_json_format(file: &str) -> Option<JsonFormat> {
+    let val: Value = serde_json::from_str(file).ok()?;
+    if val["source_files"].is_array() {
+        Some(JsonFormat::Coveralls)
+    } else if val["children"].is_object() {
+        Some(JsonFormat::Covdir)
+    } else if val["report"]["files"].is_object() {
+        Some(JsonFormat::Codecov)
+    } else {
+        None
+    }
+}
+
 // Get the code coverage in percentage between start and end
 pub(crate) fn get_covered_lines(covs: &[Value], start: usize, end: usize) -> Result<(f64, f64)> {
-    // Count the number of covered lines
+    // Count the number of covered lines, the lines that can not be covered are null,
+    // or -1 in covdir
     let (tot_lines, covered_lines) =
         covs.iter()
             .enumerate()
             .try_fold((0., 0.), |acc, (i, line)| -> Result<(f64, f64)> {
-                let is_null = line.is_null();
+                let is_null = line.is_null() || line.as_i64() == Some(-1);
                 let sum;
                 if !is_null && (start - 1..end).contains(&i) {
                     let cov = line.as_u64().ok_or(Error::ConversionError())?;
@@ -400,15 +1441,367 @@ pub(crate) fn get_covered_lines(covs: &[Value], start: usize, end: usize) -> Res
     Ok((covered_lines, tot_lines))
 }
 
-// Get the root FuncSpace from a file
-pub(crate) fn get_root<A: AsRef<Path>>(path: A) -> Result<FuncSpace> {
+// Get the preprocessor data of a C/C++ file from its compile command, None without it
+pub(crate) fn get_preproc_results(
+    path: &Path,
+    command: Option<&CompileCommand>,
+) -> Option<Arc<PreprocResults>> {
+    command.map(|command| Arc::new(command.preproc_results(path)))
+}
+
+// Check if a file is written in C/C++, without a compile command it is parsed with a best effort
+pub(crate) fn is_c_cpp(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .and_then(get_from_ext)
+        == Some(LANG::Cpp)
+}
+
+// Callback of rust-code-analysis computing the spaces of a file and checking if its syntax tree has errors
+struct CheckedSpaces;
+
+impl Callback for CheckedSpaces {
+    type Res = Option<(FuncSpace, bool)>;
+    type Cfg = PathBuf;
+
+    fn call<T: ParserTrait>(path: Self::Cfg, parser: &T) -> Self::Res {
+        let root = metrics(parser, &path)?;
+        Some((root, parser.get_root().has_error()))
+    }
+}
+
+// Get the root FuncSpace of a source and if its syntax tree has errors, parsing it only once
+// The macros of the preprocessor data, if given, are known to the parser
+pub(crate) fn get_checked_root(
+    path: &Path,
+    data: Vec<u8>,
+    pr: Option<Arc<PreprocResults>>,
+) -> Result<(FuncSpace, bool)> {
+    let lang = guess_language(&data, path)
+        .0
+        .ok_or(Error::LanguageError())?;
+    debug!("{:?} is written in {:?}", path, lang);
+    action::<CheckedSpaces>(&lang, data, path, pr, path.to_path_buf()).ok_or(Error::MetricsError())
+}
+
+// Get the root FuncSpace from a file, preprocessed with its compile command if given,
+// and if its syntax tree has errors
+pub(crate) fn get_root_with_errors<A: AsRef<Path>>(
+    path: A,
+    command: Option<&CompileCommand>,
+) -> Result<(FuncSpace, bool)> {
+    let path = path.as_ref();
+    get_checked_root(path, read_file(path)?, get_preproc_results(path, command))
+}
+
+// Get the root FuncSpace from a file, preprocessed with its compile command if given
+pub(crate) fn get_root<A: AsRef<Path>>(
+    path: A,
+    command: Option<&CompileCommand>,
+) -> Result<FuncSpace> {
+    Ok(get_root_with_errors(path, command)?.0)
+}
+
+/// Get all the spaces of a file with the metrics computed by rust-code-analysis as json,
+/// without the coverage, e.g. to check if a wrong complexity comes from the analyzer
+pub fn get_ast_metrics<A: AsRef<Path>>(path: A, options: &AnalysisOptions) -> Result<Value> {
+    let path = path.as_ref();
+    let root = get_root(path, options.compile_command(path))?;
+    Ok(serde_json::to_value(&root)?)
+}
+
+// Check if an attribute item is #[deprecated] or #[deprecated(...)]
+fn is_deprecated_attribute(node: &AstNode) -> bool {
+    node.children
+        .iter()
+        .filter(|attribute| attribute.r#type == "attribute")
+        .any(|attribute| {
+            attribute
+                .children
+                .first()
+                .is_some_and(|name| name.r#type == "identifier" && name.value == "deprecated")
+        })
+}
+
+// Get the 1-based line ranges of the items marked with a #[deprecated] attribute in a Rust file
+// A deprecated module or impl block makes all its functions deprecated
+pub(crate) fn get_deprecated_ranges<A: AsRef<Path>>(path: A) -> Result<Vec<(usize, usize)>> {
+    let data = read_file(path.as_ref())?;
+    let lang = guess_language(&data, path.as_ref())
+        .0
+        .ok_or(Error::LanguageError())?;
+    if lang != LANG::Rust {
+        return Ok(Vec::new());
+    }
+    // The comments are kept in the tree and skipped when looking for the item of an attribute
+    let cfg = AstCfg {
+        id: String::new(),
+        comment: false,
+        span: true,
+    };
+    let root = match action::<AstCallback>(&lang, data, path.as_ref(), None, cfg).root {
+        Some(root) => root,
+        None => return Ok(Vec::new()),
+    };
+    // The attributes are the siblings preceding their item
+    let mut ranges = Vec::new();
+    let mut stack = vec![&root];
+    while let Some(node) = stack.pop() {
+        let mut deprecated = false;
+        for child in &node.children {
+            if child.r#type == "attribute_item" {
+                deprecated |= is_deprecated_attribute(child);
+                continue;
+            }
+            // The comments between an attribute and its item are skipped
+            if deprecated && matches!(child.r#type, "line_comment" | "block_comment") {
+                continue;
+            }
+            if deprecated {
+                if let Some((start, _, end, _)) = child.span {
+                    ranges.push((start, end));
+                }
+                deprecated = false;
+            }
+            stack.push(child);
+        }
+    }
+    Ok(ranges)
+}
+
+// Evaluate the predicates of the token tree of a cfg attribute with the active features,
+// each predicate is an identifier followed by its string value or by the token tree of its predicates
+// A predicate is None when it does not depend only on the features, e.g. unix or test
+fn eval_cfg_predicates(token_tree: &AstNode, features: &HashSet<String>) -> Vec<Option<bool>> {
+    let tokens = token_tree
+        .children
+        .iter()
+        .filter(|token| !matches!(token.r#type, "(" | ")" | ","))
+        .collect::<Vec<&AstNode>>();
+    let mut values = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let name = tokens[i];
+        let arg = tokens
+            .get(i + 1)
+            .filter(|arg| matches!(arg.r#type, "string_literal" | "token_tree"));
+        let value = match (name.value.as_str(), arg) {
+            ("feature", Some(arg)) if arg.r#type == "string_literal" => {
+                Some(features.contains(arg.value.trim_matches('"')))
+            }
+            ("all", Some(arg)) if arg.r#type == "token_tree" => {
+                let values = eval_cfg_predicates(arg, features);
+                match values.contains(&Some(false)) {
+                    true => Some(false),
+                    false => values.iter().all(|v| v.is_some()).then_some(true),
+                }
+            }
+            ("any", Some(arg)) if arg.r#type == "token_tree" => {
+                let values = eval_cfg_predicates(arg, features);
+                match values.contains(&Some(true)) {
+                    true => Some(true),
+                    false => values.iter().all(|v| v.is_some()).then_some(false),
+                }
+            }
+            ("not", Some(arg)) if arg.r#type == "token_tree" => {
+                match eval_cfg_predicates(arg, features).as_slice() {
+                    [value] => value.map(|v| !v),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        values.push(value);
+        i += 1 + arg.map_or(0, |_| 1);
+    }
+    values
+}
+
+// Check if an attribute item is a #[cfg(...)] whose predicate is false with the active features
+fn is_inactive_cfg_attribute(node: &AstNode, features: &HashSet<String>) -> bool {
+    node.children
+        .iter()
+        .filter(|attribute| attribute.r#type == "attribute")
+        .any(|attribute| match attribute.children.as_slice() {
+            [name, token_tree]
+                if name.r#type == "identifier"
+                    && name.value == "cfg"
+                    && token_tree.r#type == "token_tree" =>
+            {
+                eval_cfg_predicates(token_tree, features) == [Some(false)]
+            }
+            _ => false,
+        })
+}
+
+// Get the 1-based line ranges of the items behind a cfg attribute that is false
+// with the active features in a Rust file, e.g. #[cfg(feature = "x")] when x is not active
+// An inner attribute, e.g. #![cfg(feature = "x")], excludes the module containing it
+pub(crate) fn get_cfg_excluded_ranges<A: AsRef<Path>>(
+    path: A,
+    features: &HashSet<String>,
+) -> Result<Vec<(usize, usize)>> {
     let data = read_file(path.as_ref())?;
     let lang = guess_language(&data, path.as_ref())
         .0
         .ok_or(Error::LanguageError())?;
-    debug!("{:?} is written in {:?}", path.as_ref(), lang);
-    let root = get_function_spaces(&lang, data, path.as_ref(), None).ok_or(Error::MetricsError())?;
-    Ok(root)
+    if lang != LANG::Rust {
+        return Ok(Vec::new());
+    }
+    let cfg = AstCfg {
+        id: String::new(),
+        comment: true,
+        span: true,
+    };
+    let root = match action::<AstCallback>(&lang, data, path.as_ref(), None, cfg).root {
+        Some(root) => root,
+        None => return Ok(Vec::new()),
+    };
+    // The attributes are the siblings preceding their item, the comments between them are skipped
+    let mut ranges = Vec::new();
+    let mut stack = vec![&root];
+    while let Some(node) = stack.pop() {
+        let inner_inactive = node.children.iter().any(|child| {
+            child.r#type == "inner_attribute_item" && is_inactive_cfg_attribute(child, features)
+        });
+        if inner_inactive {
+            if let Some((start, _, end, _)) = node.span {
+                ranges.push((start, end));
+            }
+            continue;
+        }
+        let mut inactive = false;
+        for child in &node.children {
+            match child.r#type {
+                "attribute_item" => inactive |= is_inactive_cfg_attribute(child, features),
+                "line_comment" | "block_comment" => {}
+                _ if inactive => {
+                    if let Some((start, _, end, _)) = child.span {
+                        ranges.push((start, end));
+                    }
+                    inactive = false;
+                }
+                _ => stack.push(child),
+            }
+        }
+    }
+    ranges.sort_unstable();
+    Ok(ranges)
+}
+
+// Callback of rust-code-analysis checking if the syntax tree of a file has errors
+struct SyntaxErrorsChecker;
+
+impl Callback for SyntaxErrorsChecker {
+    type Res = bool;
+    type Cfg = ();
+
+    fn call<T: ParserTrait>(_cfg: Self::Cfg, parser: &T) -> Self::Res {
+        parser.get_root().has_error()
+    }
+}
+
+// Get the code of a node of the syntax tree, made of the code of its leaves
+fn get_node_code(node: &AstNode) -> String {
+    if node.children.is_empty() {
+        return node.value.clone();
+    }
+    node.children
+        .iter()
+        .map(get_node_code)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Get the syntax errors of a file, each one with its 1-based line and its description
+// A file with syntax errors is parsed only partially and its metrics could be wrong
+// An error is either some code that can not be parsed or a missing token, a leaf without code
+// A C/C++ file is preprocessed with its compile command if given
+pub(crate) fn get_syntax_errors<A: AsRef<Path>>(
+    path: A,
+    command: Option<&CompileCommand>,
+) -> Result<Vec<(usize, String)>> {
+    let path = path.as_ref();
+    let data = read_file(path)?;
+    let lang = guess_language(&data, path)
+        .0
+        .ok_or(Error::LanguageError())?;
+    let pr = get_preproc_results(path, command);
+    if !action::<SyntaxErrorsChecker>(&lang, data.clone(), path, pr.clone(), ()) {
+        return Ok(Vec::new());
+    }
+    let cfg = AstCfg {
+        id: String::new(),
+        comment: false,
+        span: true,
+    };
+    let root = action::<AstCallback>(&lang, data, path, pr, cfg).root;
+    let mut errors = Vec::new();
+    let mut stack = root.iter().collect::<Vec<&AstNode>>();
+    while let Some(node) = stack.pop() {
+        let line = node.span.map_or(0, |s| s.0);
+        let is_missing = node.children.is_empty()
+            && node.value.is_empty()
+            && node
+                .span
+                .is_some_and(|(sr, sc, er, ec)| (sr, sc) == (er, ec));
+        if node.r#type == "ERROR" {
+            errors.push((line, format!("unexpected {:?}", get_node_code(node))));
+        } else if is_missing {
+            errors.push((line, format!("missing {}", node.r#type)));
+        } else {
+            stack.extend(node.children.iter());
+        }
+    }
+    // The tree has errors even if none of its nodes can be pointed out
+    if errors.is_empty() {
+        errors.push((1, "syntax error".into()));
+    }
+    errors.sort();
+    Ok(errors)
+}
+
+// Check if a file must be added to the files ignored because it has syntax errors,
+// only with the strict analysis of the options
+// has_errors tells if the syntax tree already parsed for the metrics has errors,
+// the file is parsed again only to point them out
+// A C/C++ file without compile command that can not be fully parsed is reported anyway
+// The reason is printed as a warning and each error in verbose mode
+pub(crate) fn has_syntax_errors(
+    path: &Path,
+    has_errors: bool,
+    options: &AnalysisOptions,
+) -> Result<bool> {
+    let command = options.compile_command(path);
+    let best_effort = command.is_none() && is_c_cpp(path);
+    if !has_errors || (!options.strict_analysis && !best_effort) {
+        return Ok(false);
+    }
+    let errors = get_syntax_errors(path, command)?;
+    match errors.first() {
+        Some((line, _)) if options.strict_analysis => warn!(
+            "Strict analysis: {} has {} syntax errors, the first at line {}, file ignored",
+            path.display(),
+            errors.len(),
+            line
+        ),
+        Some((line, _)) => warn!(
+            "{} has {} syntax errors without a compile command, the first at line {}, its metrics could be wrong",
+            path.display(),
+            errors.len(),
+            line
+        ),
+        None => {}
+    }
+    errors.iter().for_each(|(line, error)| {
+        debug!(
+            "Syntax error in {} at line {}: {}",
+            path.display(),
+            line,
+            error
+        );
+    });
+    Ok(options.strict_analysis)
 }
 
 // Get all spaces stating from root.
@@ -450,8 +1843,39 @@ pub(crate) fn check_complexity(
         || skunk > thresholds[3]
 }
 
+// Get the highest values a space could reach for each metric, thus when it is fully covered
+// for WCC or not covered at all for CRAP and SKUNK.
+// The order is the same of the thresholds: WCC PLAIN, WCC QUANTIZED, CRAP, SKUNK
+pub(crate) fn get_max_reachable_values(comp: f64, sloc: f64, ploc: f64) -> [f64; 4] {
+    let (wcc_plain, wcc_quantized) = if ploc != 0.0 {
+        (comp * sloc / ploc, 2.0 * sloc / ploc)
+    } else {
+        (0.0, 0.0)
+    };
+    [
+        wcc_plain,
+        wcc_quantized,
+        comp.powf(2.) + comp,
+        (comp / COMPLEXITY_FACTOR) * 100.,
+    ]
+}
+
+// Get the thresholds that are greater or equal to the highest reachable value of their metric
+// Return a vector of (metric name, threshold, maximum reachable value)
+pub(crate) fn get_unreachable_thresholds(
+    max_reachable: &[f64; 4],
+    thresholds: &[f64],
+) -> Vec<(&'static str, f64, f64)> {
+    ["WCC PLAIN", "WCC QUANTIZED", "CRAP", "SKUNK"]
+        .into_iter()
+        .zip(thresholds.iter().zip(max_reachable))
+        .filter(|(_, (threshold, max))| **threshold > 0.0 && *threshold >= *max)
+        .map(|(metric, (threshold, max))| (metric, *threshold, *max))
+        .collect()
+}
+
 // GET average, maximum and minimum given all the metrics
-pub(crate) fn get_cumulative_values(metrics: &Vec<Metrics>) -> (Metrics, Metrics, Metrics) {
+pub(crate) fn get_cumulative_values(metrics: &[Metrics]) -> (Metrics, Metrics, Metrics) {
     let mut min = Metrics::min();
     let mut max = Metrics::default();
     let (wcc, wccq, crap, skunk, cov) = metrics.iter().fold((0.0, 0.0, 0.0, 0.0, 0.0), |acc, m| {
@@ -481,6 +1905,7 @@ pub(crate) fn get_cumulative_values(metrics: &Vec<Metrics>) -> (Metrics, Metrics
 pub(crate) fn get_project_metrics(
     values: JobComposer,
     project_coverage: Option<f64>,
+    max_value: f64,
 ) -> Result<Metrics> {
     let project_coverage = if let Some(cov) = project_coverage {
         cov
@@ -492,11 +1917,18 @@ pub(crate) fn get_project_metrics(
     let mut m = Metrics::default();
     m = m.wcc_plain(values.wcc_plain_sum / values.ploc_sum);
     m = m.wcc_quantized(values.wcc_quantized_sum / values.ploc_sum);
-    m = m.crap(
+    let (crap, crap_saturated) = saturate_metric(
         ((values.comp_sum.powf(2.)) * ((1.0 - project_coverage / 100.).powf(3.))) + values.comp_sum,
+        max_value,
+    );
+    let (skunk, skunk_saturated) = saturate_metric(
+        (values.comp_sum / COMPLEXITY_FACTOR) * (100. - (project_coverage)),
+        max_value,
     );
-    m = m.skunk((values.comp_sum / COMPLEXITY_FACTOR) * (100. - (project_coverage)));
+    m = m.crap(crap);
+    m = m.skunk(skunk);
     m = m.coverage(project_coverage);
+    m = m.complexity_too_high(crap_saturated || skunk_saturated);
     Ok(m)
 }
 
@@ -505,10 +1937,26 @@ mod tests {
 
     use super::*;
     const JSON: &str = "./data/data.json";
+    const LCOV: &str = "./data/lcov.info";
+    const CODECOV: &str = "./data/seahorse/codecov.json";
+    const COVDIR_GRCOV: &str = "./data/seahorse/covdir_grcov.json";
     const PREFIX: &str = "../rust-data-structures-main/";
     const SIMPLE: &str = "../rust-data-structures-main/data/simple_main.rs";
     const MAIN: &str = "../rust-data-structures-main/data/main.rs";
 
+    #[test]
+    fn test_source_digest() {
+        let digest = get_source_digest(b"fn main() {}\n");
+        assert_eq!(digest.len(), 32);
+        assert!(digest
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        // The same source always has the same digest and a change of the source changes it
+        assert_eq!(digest, get_source_digest(b"fn main() {}\n"));
+        assert_ne!(digest, get_source_digest(b"fn main() { }\n"));
+        assert_eq!(get_source_digest(b""), "b91c5ed031fb33032134935d61d9b40d");
+    }
+
     #[test]
     fn test_read_json() {
         let file = fs::read_to_string(JSON).unwrap();
@@ -524,4 +1972,131 @@ mod tests {
         let value_null = vec.get(1).unwrap();
         assert!(value_null.is_null());
     }
+
+    #[test]
+    fn test_read_lcov() {
+        let file = fs::read_to_string(LCOV).unwrap();
+        let covs = read_lcov(file, PREFIX).unwrap();
+        let json = read_json(fs::read_to_string(JSON).unwrap(), PREFIX).unwrap();
+        assert_eq!(covs, json);
+        let input = "lcov:./data/lcov.info".parse::<CoverageInput>().unwrap();
+        assert_eq!(
+            input,
+            CoverageInput::new(JsonFormat::Lcov, PathBuf::from(LCOV))
+        );
+        assert!("./data/lcov.info".parse::<CoverageInput>().is_err());
+    }
+
+    #[test]
+    fn test_read_codecov() {
+        // The report of the seahorse fixture has the lines of the grcov report of the same run
+        let file = fs::read_to_string(CODECOV).unwrap();
+        let covs = read_codecov(file, PREFIX).unwrap();
+        let covdir = covdir_to_coverage_data(
+            read_json_covdir(fs::read_to_string(COVDIR_GRCOV).unwrap(), PREFIX).unwrap(),
+        );
+        assert_eq!(covs, covdir);
+        // The partial lines are hit when a branch is, true and false are one and zero hits
+        let file = r#"{"report": {"files": {"data/main.rs": {"line_coverage": [
+            ["1/2", 1], ["0/2", 1], [true, 2], [false, 1], [null, 1], [3, 1]
+        ]}}}}"#;
+        let covs = read_codecov(file.into(), PREFIX).unwrap();
+        assert_eq!(
+            covs.into_values().next().unwrap(),
+            [1, 0, 1, 1, 0]
+                .into_iter()
+                .map(Value::from)
+                .chain([Value::Null, Value::from(3)])
+                .collect::<Vec<Value>>()
+        );
+        assert!(matches!(
+            read_codecov(
+                r#"{"report": {"files": {"a.rs": {"line_coverage": [[1]]}}}}"#.into(),
+                PREFIX
+            ),
+            Err(Error::ReadingCodecovError())
+        ));
+        assert!(matches!(
+            read_codecov(fs::read_to_string(JSON).unwrap(), PREFIX),
+            Err(Error::ReadingCodecovError())
+        ));
+    }
+
+    #[test]
+    fn test_ast_metrics() {
+        let root = get_ast_metrics("./data/simple_main.rs", &AnalysisOptions::default()).unwrap();
+        assert_eq!(root["kind"], "unit");
+        assert_eq!(root["metrics"]["cyclomatic"]["sum"], 4.);
+        let main = &root["spaces"][0];
+        assert_eq!(main["name"], "main");
+        assert_eq!(main["start_line"], 1);
+        assert_eq!(main["end_line"], 12);
+        assert_eq!(main["metrics"]["cyclomatic"]["sum"], 3.);
+        assert_eq!(main["metrics"]["loc"]["ploc"], 10.);
+        assert!(matches!(
+            get_ast_metrics("./data/missing.rs", &AnalysisOptions::default()),
+            Err(Error::WrongFile(_))
+        ));
+    }
+
+    #[test]
+    fn test_unreachable_thresholds() {
+        let max = get_max_reachable_values(10., 20., 10.);
+        assert_eq!(max, [20., 4., 110., 40.]);
+        let unreachable = get_unreachable_thresholds(&max, &[35., 1.5, 110., 0.]);
+        assert_eq!(
+            unreachable,
+            vec![("WCC PLAIN", 35., 20.), ("CRAP", 110., 110.)]
+        );
+        assert!(get_unreachable_thresholds(&max, &[19., 3.9, 109., 39.]).is_empty());
+    }
+
+    #[test]
+    fn test_deprecated_ranges() {
+        let ranges = get_deprecated_ranges("./data/deprecated/src/lib.rs").unwrap();
+        assert_eq!(ranges, [(29, 37), (7, 15)]);
+        let ranges = get_deprecated_ranges("./data/deprecated/src/comments.rs").unwrap();
+        assert_eq!(ranges, [(4, 6)]);
+        assert!(get_deprecated_ranges("./data/seahorse/src/app.rs")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_cfg_excluded_ranges() {
+        let path = "./data/cfg_features/src/lib.rs";
+        let get_ranges = |features: &[&str]| {
+            let features = features.iter().map(|f| f.to_string()).collect();
+            get_cfg_excluded_ranges(path, &features).unwrap()
+        };
+        // The cfg predicates not depending only on the features, e.g. unix, are kept
+        assert_eq!(get_ranges(&["std"]), [(6, 8), (23, 23)]);
+        assert_eq!(get_ranges(&["fast"]), [(12, 14)]);
+        assert_eq!(get_ranges(&["std", "fast"]), [(12, 14)]);
+        assert_eq!(get_ranges(&[]), [(6, 8), (12, 14), (23, 23)]);
+    }
+
+    #[test]
+    fn test_chunk_vector() {
+        let files = [
+            "./data/seahorse/src/help.rs",
+            "./data/seahorse/src/app.rs",
+            "./data/seahorse/src/lib.rs",
+            "./data/seahorse/src/flag.rs",
+            "./data/seahorse/src/command.rs",
+        ]
+        .map(String::from)
+        .to_vec();
+        // The largest files are dealt first, one per chunk
+        let chunks = chunk_vector(files.clone(), 1);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(
+            chunks[0],
+            ["./data/seahorse/src/app.rs", "./data/seahorse/src/help.rs"]
+        );
+        assert_eq!(chunks[1], ["./data/seahorse/src/flag.rs"]);
+        assert_eq!(chunks[3], ["./data/seahorse/src/lib.rs"]);
+        assert_eq!(chunk_vector(files, 2).len(), 5);
+        assert!(chunk_vector(vec![], 2).is_empty());
+    }
 }
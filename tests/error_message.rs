@@ -0,0 +1,23 @@
+use std::process::Command;
+
+#[test]
+fn test_failure_message_is_rendered() {
+    let output = Command::new(env!("CARGO_BIN_EXE_weighted-code-coverage"))
+        .args([
+            "-p",
+            "./data/seahorse/",
+            "--path_json",
+            "./data/seahorse/seahorse.json",
+            "--fail-under-coverage",
+            "99",
+        ])
+        .output()
+        .unwrap();
+    // The coverage floor has its own exit code and the error is printed with its message,
+    // with the actual and the required coverage
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr
+        .lines()
+        .any(|l| l == "Error: Project coverage 77.21% is below the minimum of 99.00%"));
+}
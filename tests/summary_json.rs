@@ -0,0 +1,29 @@
+use std::fs;
+use std::process::Command;
+
+use serde_json::Value;
+
+#[test]
+fn test_summary_json_is_the_only_output() {
+    let track = "./data/seahorse/summary_json_track.json";
+    let output = Command::new(env!("CARGO_BIN_EXE_weighted-code-coverage"))
+        .args([
+            "-p",
+            "./data/seahorse/",
+            "--path_json",
+            "./data/seahorse/seahorse.json",
+            "--summary-json",
+            "--diff-coverage",
+            "HEAD",
+            "--track",
+            track,
+            "--coverage-debt",
+        ])
+        .output()
+        .unwrap();
+    fs::remove_file(track).unwrap();
+    // The diff coverage and the track delta with the coverage debt do not print to the standard output,
+    // so it parses as a single json document
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(summary["project_coverage"].is_number());
+}